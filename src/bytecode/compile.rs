@@ -0,0 +1,269 @@
+use crate::bytecode::{Constant, Function, Object, Op};
+use crate::ir::{self, BinaryOp, Const, Inst, Intrinsic, Operand, Terminator, UnaryOp};
+use crate::lexer::lexer_ana::Span;
+use crate::parser::ast::DeviceKind;
+use crate::semantic::types::Type;
+
+/// Compiles a verified program to stack code, every IR variable gets a slot of its own.
+/// `source` is the path of the program, kept for runtime errors, and `source_len` the length of
+/// the text it was compiled from
+pub fn compile(program: &ir::Program, source: &str, source_len: usize) -> Object {
+    let mut compiler = Compiler {
+        constants: Vec::new(),
+        devices: Vec::new(),
+    };
+    // devices are numbered as the main body defines them, every other mention finds them there
+    if let Some(main) = program.functions.first() {
+        for block in &main.blocks {
+            for inst in &block.insts {
+                if let Inst::Intrinsic {
+                    intrinsic:
+                        Intrinsic::DefineMachine
+                        | Intrinsic::DefineConcentrator
+                        | Intrinsic::DefineCoaxial,
+                    args,
+                    ..
+                } = inst
+                    && let Some(Operand::Const(Const::Device(kind, name))) = args.first()
+                {
+                    compiler.device(*kind, name);
+                }
+            }
+        }
+    }
+
+    let functions = program
+        .functions
+        .iter()
+        .map(|function| compiler.function(function))
+        .collect();
+    Object {
+        source: source.to_string(),
+        source_len,
+        constants: compiler.constants,
+        devices: compiler.devices,
+        functions,
+    }
+}
+
+struct Compiler {
+    constants: Vec<Constant>,
+    devices: Vec<(DeviceKind, String)>,
+}
+
+// the code of one function as it is written, jumps still name blocks
+struct Code {
+    ops: Vec<Op>,
+    spans: Vec<Span>,
+    // (op, block) for each jump, patched once every block has an address
+    jumps: Vec<(usize, usize)>,
+    span: Span,
+    // where the block being written starts
+    block: usize,
+    // temporaries read once, they stay on the stack when read right after they are stored
+    once: Vec<bool>,
+}
+
+impl Code {
+    fn push(&mut self, op: Op) {
+        self.ops.push(op);
+        self.spans.push(self.span);
+    }
+
+    fn jump(&mut self, op: Op, block: usize) {
+        self.jumps.push((self.ops.len(), block));
+        self.push(op);
+    }
+
+    fn load(&mut self, slot: usize) {
+        if self.once[slot]
+            && self.ops.len() > self.block
+            && self.ops.last() == Some(&Op::Store(slot as u16))
+        {
+            self.ops.pop();
+            self.spans.pop();
+            return;
+        }
+        self.push(Op::Load(slot as u16));
+    }
+}
+
+impl Compiler {
+    fn function(&mut self, function: &ir::Function) -> Function {
+        let mut code = Code {
+            ops: Vec::new(),
+            spans: Vec::new(),
+            jumps: Vec::new(),
+            span: Span::new(0, 0, 1, 1),
+            block: 0,
+            once: read_once(function),
+        };
+        let mut starts = Vec::new();
+        for (index, block) in function.blocks.iter().enumerate() {
+            code.block = code.ops.len();
+            starts.push(code.block);
+            for (inst, span) in block.insts.iter().zip(&block.spans) {
+                code.span = *span;
+                self.inst(inst, &mut code);
+            }
+            // a jump to the block laid out next is left out
+            let next = index + 1;
+            match &block.terminator {
+                Terminator::Jump(target) if target.0 == next => {}
+                Terminator::Jump(target) => code.jump(Op::Jump(0), target.0),
+                Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    self.operand(condition, &mut code);
+                    code.jump(Op::JumpIfFalse(0), otherwise.0);
+                    if then.0 != next {
+                        code.jump(Op::Jump(0), then.0);
+                    }
+                }
+                Terminator::Return(None) => code.push(Op::Return),
+                Terminator::Return(Some(value)) => {
+                    self.operand(value, &mut code);
+                    code.push(Op::ReturnValue);
+                }
+                Terminator::Unreachable => code.push(Op::Unreachable),
+            }
+        }
+
+        for (at, block) in code.jumps {
+            let target = starts[block] as u32;
+            code.ops[at] = match code.ops[at] {
+                Op::Jump(_) => Op::Jump(target),
+                Op::JumpIfFalse(_) => Op::JumpIfFalse(target),
+                other => other,
+            };
+        }
+        Function {
+            name: function.name.clone(),
+            arity: function.params.len() as u16,
+            slots: (0..function.vars.len())
+                .map(|var| function.var_name(ir::Var(var)))
+                .collect(),
+            returns: function.ret != Type::Nothing,
+            code: code.ops,
+            spans: code.spans,
+        }
+    }
+
+    fn inst(&mut self, inst: &Inst, code: &mut Code) {
+        match inst {
+            Inst::Copy { value, .. } => self.operand(value, code),
+            Inst::Unary { op, operand, .. } => {
+                self.operand(operand, code);
+                code.push(match op {
+                    UnaryOp::Neg => Op::Neg,
+                    UnaryOp::Not => Op::Not,
+                });
+            }
+            Inst::Binary {
+                op, left, right, ..
+            } => {
+                self.operand(left, code);
+                self.operand(right, code);
+                code.push(binary(*op));
+            }
+            Inst::Call { function, args, .. } => {
+                for arg in args {
+                    self.operand(arg, code);
+                }
+                code.push(Op::Call(function.0 as u16));
+            }
+            Inst::Intrinsic {
+                intrinsic, args, ..
+            } => {
+                for arg in args {
+                    self.operand(arg, code);
+                }
+                code.push(match intrinsic {
+                    Intrinsic::Write => Op::Write(args.len() as u8),
+                    other => Op::Intrinsic(*other),
+                });
+            }
+        }
+        if let Some(dest) = inst.dest() {
+            code.push(Op::Store(dest.0 as u16));
+        }
+    }
+
+    fn operand(&mut self, operand: &Operand, code: &mut Code) {
+        let constant = match operand {
+            Operand::Var(var) => return code.load(var.0),
+            Operand::Const(Const::Num(value)) => Constant::Num(*value),
+            Operand::Const(Const::Text(text)) => Constant::Text(text.clone()),
+            Operand::Const(Const::Bool(value)) => Constant::Bool(*value),
+            Operand::Const(Const::Device(kind, name)) => Constant::Device(self.device(*kind, name)),
+        };
+        // each constant is in the pool once
+        let index = match self.constants.iter().position(|found| *found == constant) {
+            Some(index) => index,
+            None => {
+                self.constants.push(constant);
+                self.constants.len() - 1
+            }
+        };
+        code.push(Op::Const(index as u32));
+    }
+
+    fn device(&mut self, kind: DeviceKind, name: &str) -> u32 {
+        let index = match self
+            .devices
+            .iter()
+            .position(|(_, found)| found.as_str() == name)
+        {
+            Some(index) => index,
+            None => {
+                self.devices.push((kind, name.to_string()));
+                self.devices.len() - 1
+            }
+        };
+        index as u32
+    }
+}
+
+// the temporaries read by exactly one instruction or terminator
+fn read_once(function: &ir::Function) -> Vec<bool> {
+    let mut reads = vec![0; function.vars.len()];
+    for block in &function.blocks {
+        let condition = match &block.terminator {
+            Terminator::Branch { condition, .. } => Some(condition),
+            Terminator::Return(value) => value.as_ref(),
+            _ => None,
+        };
+        let operands = block.insts.iter().flat_map(|inst| inst.operands());
+        for operand in operands.chain(condition) {
+            if let Operand::Var(var) = operand {
+                reads[var.0] += 1;
+            }
+        }
+    }
+    reads
+        .iter()
+        .zip(&function.vars)
+        .map(|(reads, var)| *reads == 1 && var.is_temp())
+        .collect()
+}
+
+fn binary(op: BinaryOp) -> Op {
+    match op {
+        BinaryOp::Add => Op::Add,
+        BinaryOp::Sub => Op::Sub,
+        BinaryOp::Mul => Op::Mul,
+        BinaryOp::Div => Op::Div,
+        BinaryOp::Mod => Op::Mod,
+        BinaryOp::Pow => Op::Pow,
+        BinaryOp::Concat => Op::Concat,
+        BinaryOp::Eq => Op::Eq,
+        BinaryOp::Ne => Op::Ne,
+        BinaryOp::Lt => Op::Lt,
+        BinaryOp::Gt => Op::Gt,
+        BinaryOp::Le => Op::Le,
+        BinaryOp::Ge => Op::Ge,
+        BinaryOp::Range => Op::Range,
+    }
+}
@@ -0,0 +1,127 @@
+use crate::bytecode::{Constant, Function, Object, Op, VERSION};
+use crate::parser::ast::DeviceKind;
+use crate::util::document;
+use std::fmt::Write;
+
+/// A listing of an object file: its constants, its devices and the code of every function.
+/// With the source at hand each source line is shown above the ops compiled from it
+pub fn disassemble(object: &Object, source: Option<&str>) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "; kindred object version {}, compiled from {}",
+        VERSION, object.source
+    );
+
+    let _ = writeln!(out, "\nconstants:");
+    for (index, constant) in object.constants.iter().enumerate() {
+        let _ = writeln!(out, "    #{:<4} {}", index, show_constant(object, constant));
+    }
+    let _ = writeln!(out, "\ndevices:");
+    for (index, (kind, name)) in object.devices.iter().enumerate() {
+        let kind = match kind {
+            DeviceKind::Machine => "maquina",
+            DeviceKind::Concentrator => "concentrador",
+            DeviceKind::Coaxial => "coaxial",
+        };
+        let _ = writeln!(out, "    d{:<4} {} {}", index, kind, name);
+    }
+
+    for function in &object.functions {
+        let _ = writeln!(out);
+        write_function(object, function, source, &mut out);
+    }
+    out
+}
+
+// fn suma(a, b) -> value, 4 slots
+fn write_function(object: &Object, function: &Function, source: Option<&str>, out: &mut String) {
+    let params = &function.slots[..function.arity as usize];
+    let _ = write!(out, "fn {}({})", function.name, params.join(", "));
+    if function.returns {
+        let _ = write!(out, " -> value");
+    }
+    let _ = writeln!(out, ", {} slots", function.slots.len());
+
+    let mut line = 0;
+    for (offset, (op, span)) in function.code.iter().zip(&function.spans).enumerate() {
+        if span.line != line {
+            line = span.line;
+            match source.and_then(|source| source.lines().nth(line - 1)) {
+                Some(text) => {
+                    let _ = writeln!(out, "  ; {}: {}", line, text.trim());
+                }
+                None => {
+                    let _ = writeln!(out, "  ; line {}", line);
+                }
+            }
+        }
+
+        let (text, note) = match *op {
+            Op::Const(constant) => (
+                format!("const #{}", constant),
+                show_constant(object, &object.constants[constant as usize]),
+            ),
+            Op::Load(slot) => (
+                format!("load {}", slot),
+                function.slots[slot as usize].clone(),
+            ),
+            Op::Store(slot) => (
+                format!("store {}", slot),
+                function.slots[slot as usize].clone(),
+            ),
+            Op::Jump(target) => (format!("jmp {:04}", target), String::new()),
+            Op::JumpIfFalse(target) => (format!("jmpf {:04}", target), String::new()),
+            Op::Call(callee) => (
+                format!("call {}", callee),
+                object.functions[callee as usize].name.clone(),
+            ),
+            Op::Intrinsic(intrinsic) => (intrinsic.to_string(), String::new()),
+            Op::Write(count) => (format!("write {}", count), String::new()),
+            other => (mnemonic(other).to_string(), String::new()),
+        };
+        if note.is_empty() {
+            let _ = writeln!(out, "    {:04}  {}", offset, text);
+        } else {
+            let _ = writeln!(out, "    {:04}  {:<24} ; {}", offset, text, note);
+        }
+    }
+}
+
+fn show_constant(object: &Object, constant: &Constant) -> String {
+    match constant {
+        Constant::Num(value) => format!("num {}", value),
+        Constant::Text(text) => {
+            let mut quoted = "texto ".to_string();
+            document::write_string(text, &mut quoted);
+            quoted
+        }
+        Constant::Bool(value) => format!("bool {}", value),
+        Constant::Device(device) => format!("@{}", object.devices[*device as usize].1),
+    }
+}
+
+fn mnemonic(op: Op) -> &'static str {
+    match op {
+        Op::Neg => "neg",
+        Op::Not => "not",
+        Op::Add => "add",
+        Op::Sub => "sub",
+        Op::Mul => "mul",
+        Op::Div => "div",
+        Op::Mod => "mod",
+        Op::Pow => "pow",
+        Op::Concat => "concat",
+        Op::Eq => "eq",
+        Op::Ne => "ne",
+        Op::Lt => "lt",
+        Op::Gt => "gt",
+        Op::Le => "le",
+        Op::Ge => "ge",
+        Op::Range => "range",
+        Op::Return => "ret",
+        Op::ReturnValue => "retv",
+        Op::Unreachable => "unreachable",
+        _ => "?",
+    }
+}
@@ -0,0 +1,684 @@
+pub mod compile;
+pub mod disasm;
+pub mod vm;
+
+use crate::ir::{Field, Intrinsic};
+use crate::lexer::lexer_ana::Span;
+use crate::parser::ast::{DeviceKind, Direction};
+use std::error::Error;
+use std::fmt;
+
+/// First bytes of every object file
+pub const MAGIC: &[u8; 4] = b"KNOB";
+
+/// Bumped whenever the layout of the file or the meaning of an opcode changes, older files are
+/// rejected rather than misread
+pub const VERSION: u16 = 1;
+
+/// A compiled program, what `make` writes to make.ob
+#[derive(Debug, Clone, PartialEq)]
+pub struct Object {
+    // the program it was compiled from, runtime errors quote its lines when it is still there
+    pub source: String,
+    // its length in bytes when it was compiled, no span in the line tables reaches past it
+    pub source_len: usize,
+    pub constants: Vec<Constant>,
+    // every `define`d device in declaration order, what `for m in maquinas` walks
+    pub devices: Vec<(DeviceKind, String)>,
+    // the main body comes first
+    pub functions: Vec<Function>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Num(f64),
+    Text(String),
+    Bool(bool),
+    // index into `Object::devices`
+    Device(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    // `f` or `modulo::f`
+    pub name: String,
+    // parameters are the first slots
+    pub arity: u16,
+    // the name of each local slot, temporaries are `%n` as in the IR dump
+    pub slots: Vec<String>,
+    pub returns: bool,
+    pub code: Vec<Op>,
+    // where each op came from, one per op
+    pub spans: Vec<Span>,
+}
+
+/// One instruction of the stack machine. Jumps name the index of an op in the same function
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Const(u32),
+    Load(u16),
+    Store(u16),
+    Neg,
+    Not,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    Concat,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Range,
+    Jump(u32),
+    JumpIfFalse(u32),
+    // arguments are on the stack, the result is pushed when the function returns one
+    Call(u16),
+    Return,
+    ReturnValue,
+    // network commands and queries, `stack_effect` says how many values they take
+    Intrinsic(Intrinsic),
+    // escribe with this many values
+    Write(u8),
+    Unreachable,
+}
+
+impl Op {
+    /// How many values the op takes off the stack and how many it pushes, calls aside
+    pub fn stack_effect(self) -> (usize, usize) {
+        match self {
+            Op::Const(_) | Op::Load(_) => (0, 1),
+            Op::Store(_) | Op::JumpIfFalse(_) | Op::ReturnValue => (1, 0),
+            Op::Neg | Op::Not => (1, 1),
+            Op::Jump(_) | Op::Return | Op::Unreachable | Op::Call(_) => (0, 0),
+            Op::Intrinsic(intrinsic) => intrinsic_effect(intrinsic),
+            Op::Write(count) => (count as usize, 0),
+            _ => (2, 1),
+        }
+    }
+}
+
+// (arguments, results) of an intrinsic, write has its own op
+fn intrinsic_effect(intrinsic: Intrinsic) -> (usize, usize) {
+    match intrinsic {
+        Intrinsic::DefineMachine => (1, 0),
+        Intrinsic::DefineConcentrator | Intrinsic::DefineCoaxial => (2, 0),
+        Intrinsic::Place | Intrinsic::PlaceCoaxial(_) => (3, 0),
+        Intrinsic::ConnectPort | Intrinsic::AttachCoax => (3, 0),
+        Intrinsic::PlaceNextTo(_) | Intrinsic::AttachSegment => (2, 0),
+        Intrinsic::AssignPort | Intrinsic::AssignCoax => (2, 0),
+        Intrinsic::QueryPorts | Intrinsic::Field(_) => (1, 1),
+        Intrinsic::RangeStart | Intrinsic::RangeEnd => (1, 1),
+        Intrinsic::QueryReaches | Intrinsic::QueryHops | Intrinsic::Index => (2, 1),
+        Intrinsic::QueryLayout | Intrinsic::Devices(_) => (0, 1),
+        Intrinsic::Write => (0, 0),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    // doesn't start with the magic bytes
+    NotAnObject,
+    // written by a kindred with another layout
+    Version(u16),
+    Truncated,
+    Malformed(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::NotAnObject => write!(f, "not a kindred object file"),
+            DecodeError::Version(found) => write!(
+                f,
+                "the object file is version {}, this kindred runs version {}, make it again",
+                found, VERSION
+            ),
+            DecodeError::Truncated => write!(f, "the object file ends too early"),
+            DecodeError::Malformed(message) => write!(f, "broken object file, {}", message),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+// opcodes, the bytes on disk. Never renumbered, new ones go at the end and bump VERSION
+const CONST: u8 = 0x01;
+const LOAD: u8 = 0x02;
+const STORE: u8 = 0x03;
+const NEG: u8 = 0x10;
+const NOT: u8 = 0x11;
+const ADD: u8 = 0x12;
+const SUB: u8 = 0x13;
+const MUL: u8 = 0x14;
+const DIV: u8 = 0x15;
+const MOD: u8 = 0x16;
+const POW: u8 = 0x17;
+const CONCAT: u8 = 0x18;
+const EQ: u8 = 0x19;
+const NE: u8 = 0x1a;
+const LT: u8 = 0x1b;
+const GT: u8 = 0x1c;
+const LE: u8 = 0x1d;
+const GE: u8 = 0x1e;
+const RANGE: u8 = 0x1f;
+const JUMP: u8 = 0x20;
+const JUMP_IF_FALSE: u8 = 0x21;
+const CALL: u8 = 0x22;
+const RETURN: u8 = 0x23;
+const RETURN_VALUE: u8 = 0x24;
+const INTRINSIC: u8 = 0x30;
+const WRITE: u8 = 0x31;
+const UNREACHABLE: u8 = 0xff;
+
+// the ops without operands, in both directions
+const SIMPLE: [(u8, Op); 19] = [
+    (NEG, Op::Neg),
+    (NOT, Op::Not),
+    (ADD, Op::Add),
+    (SUB, Op::Sub),
+    (MUL, Op::Mul),
+    (DIV, Op::Div),
+    (MOD, Op::Mod),
+    (POW, Op::Pow),
+    (CONCAT, Op::Concat),
+    (EQ, Op::Eq),
+    (NE, Op::Ne),
+    (LT, Op::Lt),
+    (GT, Op::Gt),
+    (LE, Op::Le),
+    (GE, Op::Ge),
+    (RANGE, Op::Range),
+    (RETURN, Op::Return),
+    (RETURN_VALUE, Op::ReturnValue),
+    (UNREACHABLE, Op::Unreachable),
+];
+
+const FIELDS: [Field; 10] = [
+    Field::Pos,
+    Field::Present,
+    Field::Complete,
+    Field::Available,
+    Field::Ports,
+    Field::Length,
+    Field::X,
+    Field::Y,
+    Field::Number,
+    Field::Machine,
+];
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+];
+const KINDS: [DeviceKind; 3] = [
+    DeviceKind::Machine,
+    DeviceKind::Concentrator,
+    DeviceKind::Coaxial,
+];
+
+fn position<T: PartialEq>(items: &[T], item: &T) -> u8 {
+    items
+        .iter()
+        .position(|found| found == item)
+        .expect("every variant is listed") as u8
+}
+
+// an intrinsic is a code and an immediate, the direction, field or device kind
+fn intrinsic_code(intrinsic: Intrinsic) -> (u8, u8) {
+    match intrinsic {
+        Intrinsic::DefineMachine => (0, 0),
+        Intrinsic::DefineConcentrator => (1, 0),
+        Intrinsic::DefineCoaxial => (2, 0),
+        Intrinsic::Place => (3, 0),
+        Intrinsic::PlaceNextTo(direction) => (4, position(&DIRECTIONS, &direction)),
+        Intrinsic::PlaceCoaxial(direction) => (5, position(&DIRECTIONS, &direction)),
+        Intrinsic::AttachSegment => (6, 0),
+        Intrinsic::ConnectPort => (7, 0),
+        Intrinsic::AssignPort => (8, 0),
+        Intrinsic::AttachCoax => (9, 0),
+        Intrinsic::AssignCoax => (10, 0),
+        Intrinsic::QueryPorts => (11, 0),
+        Intrinsic::QueryReaches => (12, 0),
+        Intrinsic::QueryHops => (13, 0),
+        Intrinsic::QueryLayout => (14, 0),
+        Intrinsic::Field(field) => (15, position(&FIELDS, &field)),
+        Intrinsic::Index => (16, 0),
+        Intrinsic::RangeStart => (17, 0),
+        Intrinsic::RangeEnd => (18, 0),
+        Intrinsic::Devices(kind) => (19, position(&KINDS, &kind)),
+        Intrinsic::Write => (20, 0),
+    }
+}
+
+fn intrinsic_from_code(code: u8, immediate: u8) -> Option<Intrinsic> {
+    let direction = DIRECTIONS.get(immediate as usize).copied();
+    let intrinsic = match code {
+        0 => Intrinsic::DefineMachine,
+        1 => Intrinsic::DefineConcentrator,
+        2 => Intrinsic::DefineCoaxial,
+        3 => Intrinsic::Place,
+        4 => Intrinsic::PlaceNextTo(direction?),
+        5 => Intrinsic::PlaceCoaxial(direction?),
+        6 => Intrinsic::AttachSegment,
+        7 => Intrinsic::ConnectPort,
+        8 => Intrinsic::AssignPort,
+        9 => Intrinsic::AttachCoax,
+        10 => Intrinsic::AssignCoax,
+        11 => Intrinsic::QueryPorts,
+        12 => Intrinsic::QueryReaches,
+        13 => Intrinsic::QueryHops,
+        14 => Intrinsic::QueryLayout,
+        15 => Intrinsic::Field(*FIELDS.get(immediate as usize)?),
+        16 => Intrinsic::Index,
+        17 => Intrinsic::RangeStart,
+        18 => Intrinsic::RangeEnd,
+        19 => Intrinsic::Devices(*KINDS.get(immediate as usize)?),
+        20 => Intrinsic::Write,
+        _ => return None,
+    };
+    Some(intrinsic)
+}
+
+impl Object {
+    /// The file layout: magic, version, source path, constants, devices, then per function its
+    /// header, its code and a line table with an entry wherever the source position changes
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Writer(Vec::new());
+        out.0.extend_from_slice(MAGIC);
+        out.u16(VERSION);
+        out.string(&self.source);
+        out.u32(self.source_len as u32);
+
+        out.u32(self.constants.len() as u32);
+        for constant in &self.constants {
+            match constant {
+                Constant::Num(value) => {
+                    out.u8(0);
+                    out.0.extend_from_slice(&value.to_le_bytes());
+                }
+                Constant::Text(text) => {
+                    out.u8(1);
+                    out.string(text);
+                }
+                Constant::Bool(value) => {
+                    out.u8(2);
+                    out.u8(*value as u8);
+                }
+                Constant::Device(device) => {
+                    out.u8(3);
+                    out.u32(*device);
+                }
+            }
+        }
+
+        out.u32(self.devices.len() as u32);
+        for (kind, name) in &self.devices {
+            out.u8(position(&KINDS, kind));
+            out.string(name);
+        }
+
+        out.u32(self.functions.len() as u32);
+        for function in &self.functions {
+            out.string(&function.name);
+            out.u16(function.arity);
+            out.u8(function.returns as u8);
+            out.u32(function.slots.len() as u32);
+            for slot in &function.slots {
+                out.string(slot);
+            }
+            out.u32(function.code.len() as u32);
+            for op in &function.code {
+                out.op(*op);
+            }
+            let mut lines = Vec::new();
+            for (offset, span) in function.spans.iter().enumerate() {
+                if offset == 0 || function.spans[offset - 1] != *span {
+                    lines.push((offset, span));
+                }
+            }
+            out.u32(lines.len() as u32);
+            for (offset, span) in lines {
+                out.u32(offset as u32);
+                for part in [span.start, span.end, span.line, span.column] {
+                    out.u32(part as u32);
+                }
+            }
+        }
+        out.0
+    }
+
+    /// Reads an object file back, refusing one of another version or one whose code would
+    /// jump, load or call outside of what it has
+    pub fn decode(bytes: &[u8]) -> Result<Object, DecodeError> {
+        if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(DecodeError::NotAnObject);
+        }
+        let mut input = Reader {
+            bytes,
+            at: MAGIC.len(),
+        };
+        let version = input.u16()?;
+        if version != VERSION {
+            return Err(DecodeError::Version(version));
+        }
+        let source = input.string()?;
+        let source_len = input.u32()? as usize;
+
+        let mut constants = Vec::new();
+        for _ in 0..input.u32()? {
+            let constant = match input.u8()? {
+                0 => Constant::Num(f64::from_le_bytes(input.array()?)),
+                1 => Constant::Text(input.string()?),
+                2 => Constant::Bool(input.u8()? != 0),
+                3 => Constant::Device(input.u32()?),
+                tag => return Err(malformed(format!("unknown constant tag {}", tag))),
+            };
+            constants.push(constant);
+        }
+
+        let mut devices = Vec::new();
+        for _ in 0..input.u32()? {
+            let kind = input.u8()?;
+            let kind = *KINDS
+                .get(kind as usize)
+                .ok_or_else(|| malformed(format!("unknown device kind {}", kind)))?;
+            devices.push((kind, input.string()?));
+        }
+
+        let mut functions = Vec::new();
+        for _ in 0..input.u32()? {
+            let name = input.string()?;
+            let arity = input.u16()?;
+            let returns = input.u8()? != 0;
+            let mut slots = Vec::new();
+            for _ in 0..input.u32()? {
+                slots.push(input.string()?);
+            }
+            let mut code = Vec::new();
+            for _ in 0..input.u32()? {
+                code.push(input.op()?);
+            }
+            let mut spans: Vec<Span> = Vec::new();
+            for _ in 0..input.u32()? {
+                let offset = input.u32()? as usize;
+                let mut parts = [0; 4];
+                for part in &mut parts {
+                    *part = input.u32()? as usize;
+                }
+                // entries go in order from op 0 and end inside the source, lines and columns count from 1
+                let [start, end, line, column] = parts;
+                let in_order = match spans.len() {
+                    0 => offset == 0,
+                    len => offset >= len && offset < code.len(),
+                };
+                if !in_order || end < start || end > source_len || line == 0 || column == 0 {
+                    return Err(malformed(format!("broken line entry for op {}", offset)));
+                }
+                // the previous entry holds until this one
+                let last = spans.last().copied();
+                spans.extend(last.into_iter().cycle().take(offset - spans.len()));
+                spans.push(Span::new(start, end, line, column));
+            }
+            if spans.is_empty() && !code.is_empty() {
+                return Err(malformed(format!("'{}' has no line table", name)));
+            }
+            let last = spans.last().copied();
+            spans.extend(last.into_iter().cycle().take(code.len() - spans.len()));
+            functions.push(Function {
+                name,
+                arity,
+                slots,
+                returns,
+                code,
+                spans,
+            });
+        }
+        if input.at != bytes.len() {
+            return Err(malformed("bytes left after the last function".to_string()));
+        }
+
+        let object = Object {
+            source,
+            source_len,
+            constants,
+            devices,
+            functions,
+        };
+        object.check()?;
+        Ok(object)
+    }
+
+    // everything an op names exists, the VM indexes freely
+    fn check(&self) -> Result<(), DecodeError> {
+        if self.functions.first().is_none_or(|main| main.arity != 0) {
+            return Err(malformed("the first function must be inicio".to_string()));
+        }
+        for constant in &self.constants {
+            if let Constant::Device(device) = constant
+                && *device as usize >= self.devices.len()
+            {
+                return Err(malformed(format!("no device {}", device)));
+            }
+        }
+        for function in &self.functions {
+            let broken = |message: String| malformed(format!("{}: {}", function.name, message));
+            if function.arity as usize > function.slots.len() {
+                return Err(broken("more parameters than slots".to_string()));
+            }
+            for (offset, op) in function.code.iter().enumerate() {
+                let fits = match *op {
+                    Op::Const(constant) => (constant as usize) < self.constants.len(),
+                    Op::Load(slot) | Op::Store(slot) => (slot as usize) < function.slots.len(),
+                    Op::Jump(target) | Op::JumpIfFalse(target) => {
+                        (target as usize) < function.code.len()
+                    }
+                    Op::Call(callee) => (callee as usize) < self.functions.len(),
+                    _ => true,
+                };
+                if !fits {
+                    return Err(broken(format!(
+                        "op {} names something that isn't there",
+                        offset
+                    )));
+                }
+            }
+            self.check_stack(function).map_err(broken)?;
+        }
+        Ok(())
+    }
+
+    // every path into an op leaves the stack as deep, deep enough for what the op takes, and
+    // ends in a return
+    fn check_stack(&self, function: &Function) -> Result<(), String> {
+        let mut depths: Vec<Option<usize>> = vec![None; function.code.len()];
+        let mut work = vec![(0, 0)];
+        while let Some((offset, depth)) = work.pop() {
+            let Some(op) = function.code.get(offset) else {
+                return Err("the code runs off its end".to_string());
+            };
+            match depths[offset] {
+                Some(known) if known == depth => continue,
+                Some(known) => {
+                    return Err(format!(
+                        "op {} is reached with {} and with {} values on the stack",
+                        offset, known, depth
+                    ));
+                }
+                None => depths[offset] = Some(depth),
+            }
+            let (takes, pushes) = match *op {
+                Op::Call(callee) => {
+                    let callee = &self.functions[callee as usize];
+                    (callee.arity as usize, callee.returns as usize)
+                }
+                op => op.stack_effect(),
+            };
+            if depth < takes {
+                return Err(format!(
+                    "op {} takes {} values from a stack of {}",
+                    offset, takes, depth
+                ));
+            }
+            let depth = depth - takes + pushes;
+            match *op {
+                Op::Jump(target) => work.push((target as usize, depth)),
+                Op::JumpIfFalse(target) => {
+                    work.push((target as usize, depth));
+                    work.push((offset + 1, depth));
+                }
+                Op::Return | Op::ReturnValue if (*op == Op::ReturnValue) != function.returns => {
+                    return Err(format!("op {} returns the wrong way", offset));
+                }
+                Op::Return | Op::ReturnValue | Op::Unreachable => {}
+                _ => work.push((offset + 1, depth)),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn malformed(message: String) -> DecodeError {
+    DecodeError::Malformed(message)
+}
+
+// little endian throughout
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn string(&mut self, text: &str) {
+        self.u32(text.len() as u32);
+        self.0.extend_from_slice(text.as_bytes());
+    }
+
+    fn op(&mut self, op: Op) {
+        match op {
+            Op::Const(constant) => {
+                self.u8(CONST);
+                self.u32(constant);
+            }
+            Op::Load(slot) => {
+                self.u8(LOAD);
+                self.u16(slot);
+            }
+            Op::Store(slot) => {
+                self.u8(STORE);
+                self.u16(slot);
+            }
+            Op::Jump(target) => {
+                self.u8(JUMP);
+                self.u32(target);
+            }
+            Op::JumpIfFalse(target) => {
+                self.u8(JUMP_IF_FALSE);
+                self.u32(target);
+            }
+            Op::Call(function) => {
+                self.u8(CALL);
+                self.u16(function);
+            }
+            Op::Intrinsic(intrinsic) => {
+                let (code, immediate) = intrinsic_code(intrinsic);
+                self.u8(INTRINSIC);
+                self.u8(code);
+                self.u8(immediate);
+            }
+            Op::Write(count) => {
+                self.u8(WRITE);
+                self.u8(count);
+            }
+            simple => {
+                let (code, _) = SIMPLE
+                    .iter()
+                    .find(|(_, op)| *op == simple)
+                    .expect("ops with operands are written above");
+                self.u8(*code);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let bytes = self
+            .bytes
+            .get(self.at..self.at + N)
+            .ok_or(DecodeError::Truncated)?;
+        self.at += N;
+        Ok(bytes.try_into().expect("the slice is N long"))
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn string(&mut self) -> Result<String, DecodeError> {
+        let len = self.u32()? as usize;
+        let bytes = self
+            .bytes
+            .get(self.at..self.at + len)
+            .ok_or(DecodeError::Truncated)?;
+        self.at += len;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| malformed("text that isn't UTF-8".to_string()))
+    }
+
+    fn op(&mut self) -> Result<Op, DecodeError> {
+        let op = match self.u8()? {
+            CONST => Op::Const(self.u32()?),
+            LOAD => Op::Load(self.u16()?),
+            STORE => Op::Store(self.u16()?),
+            JUMP => Op::Jump(self.u32()?),
+            JUMP_IF_FALSE => Op::JumpIfFalse(self.u32()?),
+            CALL => Op::Call(self.u16()?),
+            INTRINSIC => {
+                let (code, immediate) = (self.u8()?, self.u8()?);
+                match intrinsic_from_code(code, immediate) {
+                    Some(Intrinsic::Write) | None => {
+                        return Err(malformed(format!(
+                            "unknown intrinsic {}.{}",
+                            code, immediate
+                        )));
+                    }
+                    Some(intrinsic) => Op::Intrinsic(intrinsic),
+                }
+            }
+            WRITE => Op::Write(self.u8()?),
+            code => match SIMPLE.iter().find(|(simple, _)| *simple == code) {
+                Some((_, op)) => *op,
+                None => return Err(malformed(format!("unknown opcode 0x{:02x}", code))),
+            },
+        };
+        Ok(op)
+    }
+}
@@ -0,0 +1,292 @@
+use crate::bytecode::{Constant, Object, Op};
+use crate::interpreter::eval::{MAX_CALL_DEPTH, RuntimeError};
+use crate::interpreter::ops;
+use crate::interpreter::value::Value;
+use crate::ir::Intrinsic;
+use crate::lexer::lexer_ana::Span;
+use crate::network::topology::Topology;
+use crate::parser::ast::{BinaryOp, DeviceKind, NetworkCommand, UnaryOp};
+use crate::util::interner::{Interner, Symbol};
+use std::io::Write;
+
+/// Runs an object file, `escribe` writes to `out`. Gives back the network the program built
+pub fn run(object: &Object, out: &mut dyn Write) -> Result<Topology, RuntimeError> {
+    let mut interner = Interner::new();
+    let devices = object
+        .devices
+        .iter()
+        .map(|(_, name)| interner.intern(name))
+        .collect();
+    let mut vm = Vm {
+        object,
+        interner,
+        devices,
+        topology: Topology::new(),
+        stack: Vec::new(),
+        frames: Vec::new(),
+        out,
+    };
+    vm.run()?;
+    Ok(vm.topology)
+}
+
+// one active call, its slots start at `base` on the stack
+struct Frame {
+    function: usize,
+    pc: usize,
+    base: usize,
+    call_span: Span,
+}
+
+struct Vm<'a> {
+    object: &'a Object,
+    interner: Interner,
+    // the symbol of each device of the object
+    devices: Vec<Symbol>,
+    topology: Topology,
+    stack: Vec<Value>,
+    // the main body is the bottom frame
+    frames: Vec<Frame>,
+    out: &'a mut dyn Write,
+}
+
+impl Vm<'_> {
+    fn run(&mut self) -> Result<(), RuntimeError> {
+        let object = self.object;
+        self.enter(0, Span::new(0, 0, 1, 1));
+        loop {
+            let frame = self.frames.last_mut().expect("the main body has a frame");
+            let function = &object.functions[frame.function];
+            let op = function.code[frame.pc];
+            let span = function.spans[frame.pc];
+            let base = frame.base;
+            frame.pc += 1;
+
+            match op {
+                Op::Const(constant) => {
+                    let value = self.constant(&object.constants[constant as usize]);
+                    self.stack.push(value);
+                }
+                Op::Load(slot) => self.stack.push(self.stack[base + slot as usize].clone()),
+                Op::Store(slot) => {
+                    let value = self.pop();
+                    self.stack[base + slot as usize] = value;
+                }
+                Op::Neg | Op::Not => {
+                    let op = if op == Op::Neg {
+                        UnaryOp::Negate
+                    } else {
+                        UnaryOp::Not
+                    };
+                    let value = self.pop();
+                    let value =
+                        ops::unary(op, value).map_err(|message| self.error(message, span))?;
+                    self.stack.push(value);
+                }
+                Op::Jump(target) => self.jump(target),
+                Op::JumpIfFalse(target) => match self.pop() {
+                    Value::Bool(true) => {}
+                    Value::Bool(false) => self.jump(target),
+                    other => return Err(self.error(ops::mismatch("bool", &other), span)),
+                },
+                Op::Call(callee) => {
+                    // the recursion is stopped as deep as the interpreter stops it
+                    if self.frames.len() > MAX_CALL_DEPTH {
+                        return Err(self.error(
+                            format!("too many nested calls, more than {}", MAX_CALL_DEPTH),
+                            span,
+                        ));
+                    }
+                    self.enter(callee as usize, span);
+                }
+                Op::Return | Op::ReturnValue => {
+                    let value = (op == Op::ReturnValue).then(|| self.pop());
+                    let frame = self.frames.pop().expect("a frame is returning");
+                    self.stack.truncate(frame.base);
+                    if self.frames.is_empty() {
+                        return Ok(());
+                    }
+                    self.stack.extend(value);
+                }
+                Op::Intrinsic(intrinsic) => {
+                    let (takes, _) = op.stack_effect();
+                    let args = self.stack.split_off(self.stack.len() - takes);
+                    let value = self
+                        .intrinsic(intrinsic, args)
+                        .map_err(|message| self.error(message, span))?;
+                    self.stack.extend(value);
+                }
+                Op::Write(count) => {
+                    let args = self.stack.split_off(self.stack.len() - count as usize);
+                    let parts: Vec<String> =
+                        args.iter().map(|arg| arg.show(&self.interner)).collect();
+                    writeln!(self.out, "{}", parts.join(" "))
+                        .map_err(|error| self.error(error.to_string(), span))?;
+                }
+                Op::Unreachable => {
+                    return Err(self.error(
+                        format!("'{}' ended without returning a value", function.name),
+                        span,
+                    ));
+                }
+                binary => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let value = ops::binary(binary_op(binary), left, right)
+                        .map_err(|message| self.error(message, span))?;
+                    self.stack.push(value);
+                }
+            }
+        }
+    }
+
+    // the arguments already on the stack become the first slots
+    fn enter(&mut self, function: usize, call_span: Span) {
+        let callee = &self.object.functions[function];
+        let base = self.stack.len() - callee.arity as usize;
+        self.stack.resize(base + callee.slots.len(), Value::Nothing);
+        self.frames.push(Frame {
+            function,
+            pc: 0,
+            base,
+            call_span,
+        });
+    }
+
+    fn jump(&mut self, target: u32) {
+        let frame = self.frames.last_mut().expect("the main body has a frame");
+        frame.pc = target as usize;
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack
+            .pop()
+            .expect("the decoder checked the stack never runs dry")
+    }
+
+    fn constant(&self, constant: &Constant) -> Value {
+        match constant {
+            Constant::Num(value) => Value::Num(*value),
+            Constant::Text(text) => Value::Text(text.clone()),
+            Constant::Bool(value) => Value::Bool(*value),
+            Constant::Device(device) => self.device(*device as usize),
+        }
+    }
+
+    fn device(&self, device: usize) -> Value {
+        let kind = self.object.devices[device].0;
+        Value::Device(kind, self.devices[device])
+    }
+
+    fn intrinsic(
+        &mut self,
+        intrinsic: Intrinsic,
+        args: Vec<Value>,
+    ) -> Result<Option<Value>, String> {
+        let (command, direction) = match intrinsic {
+            Intrinsic::DefineMachine | Intrinsic::DefineConcentrator | Intrinsic::DefineCoaxial => {
+                let kind = match intrinsic {
+                    Intrinsic::DefineMachine => DeviceKind::Machine,
+                    Intrinsic::DefineConcentrator => DeviceKind::Concentrator,
+                    _ => DeviceKind::Coaxial,
+                };
+                let Value::Device(_, name) = args[0] else {
+                    return Err(ops::mismatch("a device", &args[0]));
+                };
+                let size = args.get(1).cloned().map(ops::num).transpose()?;
+                let name = self.interner.resolve(name).to_string();
+                ops::define(&mut self.topology, kind, &name, size)
+                    .map_err(|error| error.message)?;
+                return Ok(None);
+            }
+            Intrinsic::Place => (NetworkCommand::Place, None),
+            Intrinsic::PlaceNextTo(direction) => (NetworkCommand::Place, Some(direction)),
+            Intrinsic::PlaceCoaxial(direction) => (NetworkCommand::PlaceCoaxial, Some(direction)),
+            Intrinsic::AttachSegment => (NetworkCommand::PlaceCoaxialConcentrator, None),
+            Intrinsic::ConnectPort => (NetworkCommand::ConnectPort, None),
+            Intrinsic::AssignPort => (NetworkCommand::AssignPort, None),
+            Intrinsic::AttachCoax => (NetworkCommand::MachineCoaxial, None),
+            Intrinsic::AssignCoax => (NetworkCommand::AssignMachineCoaxial, None),
+            query => return self.query(query, args).map(Some),
+        };
+        ops::network(
+            &mut self.topology,
+            &self.interner,
+            command,
+            direction,
+            &args,
+        )
+        .map_err(|error| error.message)?;
+        Ok(None)
+    }
+
+    fn query(&self, query: Intrinsic, mut args: Vec<Value>) -> Result<Value, String> {
+        let value = match query {
+            Intrinsic::QueryPorts => ops::ports(&self.topology, &self.interner, &args[0])?,
+            Intrinsic::QueryReaches | Intrinsic::QueryHops => {
+                let reaches = query == Intrinsic::QueryReaches;
+                ops::route(&self.topology, &self.interner, reaches, &args[0], &args[1])
+                    .map_err(|error| error.message)?
+            }
+            Intrinsic::QueryLayout => ops::layout(&self.topology),
+            Intrinsic::Field(field) => {
+                ops::member(&self.topology, &self.interner, args.remove(0), field.name())?
+            }
+            Intrinsic::Index => {
+                let index = args.pop().expect("index takes 2 values");
+                ops::index(args.remove(0), index)?
+            }
+            Intrinsic::RangeStart | Intrinsic::RangeEnd => match args[0] {
+                Value::Range(start, _) if query == Intrinsic::RangeStart => Value::Num(start),
+                Value::Range(_, end) => Value::Num(end),
+                ref other => return Err(ops::mismatch("rango", other)),
+            },
+            Intrinsic::Devices(kind) => Value::List(
+                (0..self.object.devices.len())
+                    .filter(|device| self.object.devices[*device].0 == kind)
+                    .map(|device| self.device(device))
+                    .collect(),
+            ),
+            other => unreachable!("{} is not a query", other),
+        };
+        Ok(value)
+    }
+
+    fn error(&self, message: String, span: Span) -> RuntimeError {
+        let trace = self
+            .frames
+            .iter()
+            .skip(1)
+            .rev()
+            .map(|frame| {
+                let name = self.object.functions[frame.function].name.clone();
+                (name, frame.call_span)
+            })
+            .collect();
+        RuntimeError {
+            message,
+            span,
+            trace,
+        }
+    }
+}
+
+// the interpreter's operator for a typed op, `concat` is `+` on text
+fn binary_op(op: Op) -> BinaryOp {
+    match op {
+        Op::Add | Op::Concat => BinaryOp::Add,
+        Op::Sub => BinaryOp::Subtract,
+        Op::Mul => BinaryOp::Multiply,
+        Op::Div => BinaryOp::Divide,
+        Op::Mod => BinaryOp::Modulo,
+        Op::Pow => BinaryOp::Power,
+        Op::Eq => BinaryOp::Equal,
+        Op::Ne => BinaryOp::NotEqual,
+        Op::Lt => BinaryOp::Less,
+        Op::Gt => BinaryOp::Greater,
+        Op::Le => BinaryOp::LessEq,
+        Op::Ge => BinaryOp::GreaterEq,
+        Op::Range => BinaryOp::Range,
+        other => unreachable!("{:?} is not a binary op", other),
+    }
+}
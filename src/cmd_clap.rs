@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[command(author,version,about = "Kindred commands", long_about = None)]
@@ -9,14 +9,109 @@ pub struct Cli {
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
+    /// Checks main.kin and compiles it to make.ob
     Make {
         #[arg(short, long, default_value = "Release")]
         mode: String,
+        #[command(flatten)]
+        check: CheckArgs,
+        /// Parse with the LL(1) table built from the grammar file instead of the hand written parser
+        #[arg(long)]
+        table_parser: bool,
+        /// Also write the network the program builds, json or yaml, or the lowered program, ir
+        #[arg(long)]
+        emit: Option<String>,
     },
-    // Run {
-    //     #[arg(short, long)]
-    //     target: Option<String>,
-    // },
-
+    /// Checks the language grammar is LL(1)
+    Grammar {
+        #[command(subcommand)]
+        action: GrammarAction,
+    },
+    /// Checks a program and runs it, a .ob file runs on the bytecode VM
+    Run {
+        /// The program, or an object file written by `make`
+        #[arg(default_value = "main.kin")]
+        file: String,
+        #[command(flatten)]
+        check: CheckArgs,
+    },
+    /// Runs a program and reports how its network is wired
+    Analyze {
+        /// The program
+        #[arg(default_value = "main.kin")]
+        file: String,
+        #[command(flatten)]
+        check: CheckArgs,
+    },
+    /// Runs a program and draws the grid it leaves behind
+    Show {
+        /// The program
+        #[arg(default_value = "main.kin")]
+        file: String,
+        #[command(flatten)]
+        check: CheckArgs,
+    },
+    /// Runs a program and sends the frames of a scenario over the network it builds
+    Simulate {
+        /// The program
+        #[arg(default_value = "main.kin")]
+        file: String,
+        /// JSON or YAML list of frames, who sends to whom and when
+        #[arg(short, long)]
+        scenario: String,
+        #[command(flatten)]
+        check: CheckArgs,
+    },
+    /// Writes a program that builds the network described by a topology file
+    Decompile {
+        /// main.json or main.yaml, as written by `make --emit`
+        file: String,
+        /// Where the program goes, printed when not given
+        #[arg(short, long)]
+        output: Option<String>,
+        /// The one keyword profile the program is written in
+        #[arg(short, long, default_value = "school")]
+        keywords: String,
+    },
+    /// Lists the bytecode of an object file
+    Disasm {
+        /// The object file
+        #[arg(default_value = "make.ob")]
+        file: String,
+    },
+    /// Deletes make.ob
     Clean,
 }
+
+// how every command that reads a program lexes it and checks its cabling
+#[derive(Args, Debug)]
+pub struct CheckArgs {
+    /// Keyword profiles, e.g. `en` or `es,school`
+    #[arg(short, long, default_value = "school")]
+    pub keywords: String,
+    /// Accept keywords written in any letter case
+    #[arg(long)]
+    pub ignore_keyword_case: bool,
+    /// Lexical errors reported per file, 0 reports all of them
+    #[arg(long, default_value_t = 100)]
+    pub max_lex_errors: usize,
+    /// Coaxial standard the cabling is checked against, 10base2 or 10base5
+    #[arg(long, default_value = "10base2")]
+    pub coaxial: String,
+    /// Broken coaxial rules stop the build instead of warning
+    #[arg(long)]
+    pub coaxial_errors: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum GrammarAction {
+    /// Checks the grammar has no LL(1) conflicts and still matches the parser
+    Check {
+        /// The grammar file
+        #[arg(short, long, default_value = crate::parser::grammar::GRAMMAR_PATH)]
+        file: String,
+        /// Print FIRST and FOLLOW of every nonterminal
+        #[arg(short, long)]
+        verbose: bool,
+    },
+}
@@ -1,15 +1,104 @@
 // use crate::lexer::lexer_ana;
+use crate::lexer::lexer_ana::LexerOptions;
 use crate::parser::parser_ana;
+use crate::bytecode;
+use crate::interpreter;
+use crate::ir;
+use crate::network::export;
+use crate::network::rules::CoaxialRules;
+use crate::network::topology::Topology;
+use crate::semantic;
+use std::error::Error;
+use std::fs;
 // use crate::util::symboltable::SymbolTable;
 
 // use std::error::Error;   
 
-pub fn compile(mode: &str) {
+/// Where `make` writes the compiled program and `clean` deletes it from
+pub const OBJECT_PATH: &str = "make.ob";
+
+/// What `make --emit` writes besides checking the program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    // the topology the program builds, main.json or main.yaml
+    Json,
+    Yaml,
+    // the lowered program, main.ir
+    Ir,
+}
+
+impl Emit {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Ok(Emit::Json),
+            "yaml" | "yml" => Ok(Emit::Yaml),
+            "ir" => Ok(Emit::Ir),
+            _ => Err(format!("unknown output '{}', expected json, yaml or ir", name)),
+        }
+    }
+}
+
+/// Checks main.kin and writes make.ob, a failed build leaves no make.ob behind
+pub fn compile(
+    mode: &str,
+    options: LexerOptions,
+    table_parser: bool,
+    rules: &CoaxialRules,
+    emit: Option<Emit>,
+) -> Result<(), Box<dyn Error>> {
     println!("compiling in mode: {}", mode);
-    
-    match parser_ana::parser() {
-        Ok(_) => println!("successful compilation "),
-        Err(e) => eprintln!(" error: {}", e),
+    // an object from an earlier build would run a program that no longer compiles
+    match fs::remove_file(OBJECT_PATH) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => return Err(error.into()),
+        _ => {}
+    }
+
+    let parsed = parser_ana::parser(options, table_parser)?;
+    let reported = semantic::semantic(&parsed, rules)?;
+    // the network only exists once the program has run, then its cabling is checked again
+    let topology = interpreter::topology(&parsed)?;
+    semantic::check_network(&parsed, &topology, rules, &reported)?;
+    if let Some(emit) = emit {
+        write_output(&parsed, &topology, emit)?;
     }
+    // last, so nothing that fails after it leaves an object behind
+    write_object(&parsed)?;
+    println!("successful compilation ");
+    Ok(())
+}
+
+// the program compiled for the VM, what `run make.ob` runs
+fn write_object(parsed: &parser_ana::Parsed) -> Result<(), Box<dyn Error>> {
+    let object = bytecode::compile::compile(&lower(parsed)?, "main.kin", parsed.source.len());
+    fs::write(OBJECT_PATH, object.encode())?;
+    println!("wrote {}", OBJECT_PATH);
+    Ok(())
 }
 
+fn write_output(
+    parsed: &parser_ana::Parsed,
+    topology: &Topology,
+    emit: Emit,
+) -> Result<(), Box<dyn Error>> {
+    let (path, text) = match emit {
+        Emit::Ir => ("main.ir", lower(parsed)?.to_string()),
+        Emit::Json => ("main.json", export::to_json(topology)),
+        Emit::Yaml => ("main.yaml", export::to_yaml(topology)),
+    };
+    fs::write(path, text)?;
+    println!("wrote {}", path);
+    Ok(())
+}
+
+/// Lowers a checked program to the IR and verifies it
+pub fn lower(parsed: &parser_ana::Parsed) -> Result<ir::Program, Box<dyn Error>> {
+    let program = ir::lower::lower(&parsed.program, &parsed.interner, &parsed.profiles)?;
+    let errors = ir::verify::verify(&program);
+    if !errors.is_empty() {
+        for error in &errors {
+            eprintln!("   {}", error);
+        }
+        return Err(format!("the lowered program is broken, {} errors", errors.len()).into());
+    }
+    Ok(program)
+}
@@ -0,0 +1,479 @@
+use crate::interpreter::ops;
+use crate::interpreter::value::Value;
+use crate::lexer::keywords::{KEYWORDS, KeywordProfile};
+use crate::lexer::lexer_ana::{Span, TokenType};
+use crate::network::topology::Topology;
+use crate::parser::ast::{
+    AssignOp, BinaryOp, Block, Decl, Expr, ExprKind, FnDecl, Iterable, Program, Stmt, StmtKind,
+};
+use crate::semantic::checker::show_span;
+use crate::util::interner::{Interner, Symbol};
+use std::collections::HashMap;
+use std::io::Write;
+
+// each call nests a few Rust frames, 100 fits a 2 MiB thread stack even in debug builds
+pub const MAX_CALL_DEPTH: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    pub message: String,
+    pub span: Span,
+    // the calls active when it happened, innermost first: (function, call site)
+    pub trace: Vec<(String, Span)>,
+}
+
+impl RuntimeError {
+    pub fn display(&self, source: &str) {
+        eprintln!(
+            "\n Runtime error on line {}:{}",
+            self.span.line, self.span.column
+        );
+        eprintln!("     {}", self.message);
+
+        show_span(source, self.span);
+
+        eprintln!();
+        for line in self.trace_lines() {
+            eprintln!("   {}", line);
+        }
+    }
+
+    /// The stack trace, `in f called at 3:5` per call, `in inicio` last
+    pub fn trace_lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .trace
+            .iter()
+            .map(|(name, span)| format!("in {} called at {}:{}", name, span.line, span.column))
+            .collect();
+        lines.push("in inicio".to_string());
+        lines
+    }
+}
+
+pub(super) type Result<T> = std::result::Result<T, RuntimeError>;
+
+// one active call
+struct Frame {
+    // `f` or `modulo::f`
+    name: String,
+    // unqualified calls look here first
+    module: Option<Symbol>,
+    call_span: Span,
+    // innermost block last
+    scopes: Vec<HashMap<Symbol, Value>>,
+}
+
+// how a statement ended
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+/// Tree walking interpreter, runs a checked program statement by statement
+pub struct Interpreter<'a> {
+    pub(super) interner: &'a Interner,
+    profiles: &'a [KeywordProfile],
+    functions: HashMap<(Option<Symbol>, Symbol), &'a FnDecl>,
+    devices: HashMap<Symbol, Value>,
+    // in declaration order, what `for m in maquinas` walks
+    declared: Vec<Value>,
+    // what the network commands have built so far
+    pub(super) topology: Topology,
+    // the main body is the bottom frame
+    frames: Vec<Frame>,
+    out: &'a mut dyn Write,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(
+        program: &'a Program,
+        interner: &'a Interner,
+        profiles: &'a [KeywordProfile],
+        out: &'a mut dyn Write,
+    ) -> Self {
+        let mut functions = HashMap::new();
+        let mut devices = HashMap::new();
+        let mut declared = Vec::new();
+        for decl in &program.declarations {
+            match decl {
+                Decl::Define(define) => {
+                    for item in &define.items {
+                        let device = Value::Device(define.kind, item.name);
+                        devices.insert(item.name, device.clone());
+                        declared.push(device);
+                    }
+                }
+                Decl::Function(function) => {
+                    functions.insert((None, function.name), function);
+                }
+                Decl::Module(module) => {
+                    for function in &module.functions {
+                        functions.insert((Some(module.name), function.name), function);
+                    }
+                }
+                Decl::Error(_) => {}
+            }
+        }
+
+        Interpreter {
+            interner,
+            profiles,
+            functions,
+            devices,
+            declared,
+            topology: Topology::new(),
+            frames: Vec::new(),
+            out,
+        }
+    }
+
+    pub fn run(&mut self, program: &Program) -> Result<()> {
+        self.frames.push(Frame {
+            name: "inicio".to_string(),
+            module: None,
+            call_span: program.body.span,
+            scopes: Vec::new(),
+        });
+        let flow = self
+            .build_topology(program)
+            .and_then(|_| self.exec_block(&program.body, HashMap::new()));
+        self.frames.clear();
+        flow.map(|_| ())
+    }
+
+    /// The network as the program left it
+    pub fn topology(&self) -> &Topology {
+        &self.topology
+    }
+
+    pub(super) fn name(&self, symbol: Symbol) -> &str {
+        self.interner.resolve(symbol)
+    }
+
+    pub(super) fn keyword(&self, symbol: Symbol) -> Option<&'static TokenType> {
+        let keyword = KEYWORDS.get(symbol.index())?;
+        keyword
+            .profiles
+            .iter()
+            .any(|profile| self.profiles.contains(profile))
+            .then_some(&keyword.token)
+    }
+
+    pub(super) fn error(&self, message: String, span: Span) -> RuntimeError {
+        let trace = self
+            .frames
+            .iter()
+            .skip(1)
+            .rev()
+            .map(|frame| (frame.name.clone(), frame.call_span))
+            .collect();
+        RuntimeError {
+            message,
+            span,
+            trace,
+        }
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("the main body has a frame")
+    }
+
+    fn exec_block(&mut self, block: &Block, scope: HashMap<Symbol, Value>) -> Result<Flow> {
+        self.frame().scopes.push(scope);
+        let mut flow = Ok(Flow::Normal);
+        for stmt in &block.statements {
+            flow = self.exec_stmt(stmt);
+            if !matches!(flow, Ok(Flow::Normal)) {
+                break;
+            }
+        }
+        self.frame().scopes.pop();
+        flow
+    }
+
+    fn exec_stmt(&mut self, stmt: &Stmt) -> Result<Flow> {
+        match &stmt.kind {
+            StmtKind::Let { name, value, .. } => {
+                let value = self.eval(value)?;
+                let scope = self.frame().scopes.last_mut();
+                scope
+                    .expect("statements run inside a block")
+                    .insert(*name, value);
+            }
+            StmtKind::Assign { target, op, value } => self.assign(target, *op, value)?,
+            StmtKind::Expr(expr) => {
+                self.eval(expr)?;
+            }
+            StmtKind::Write(args) => {
+                let mut parts = Vec::new();
+                for arg in args {
+                    parts.push(self.eval(arg)?.show(self.interner));
+                }
+                writeln!(self.out, "{}", parts.join(" "))
+                    .map_err(|error| self.error(error.to_string(), stmt.span))?;
+            }
+            StmtKind::If {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                if self.condition(condition)? {
+                    return self.exec_block(then_block, HashMap::new());
+                }
+                if let Some(else_block) = else_block {
+                    return self.exec_block(else_block, HashMap::new());
+                }
+            }
+            StmtKind::While { condition, body } => {
+                while self.condition(condition)? {
+                    match self.exec_block(body, HashMap::new())? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                }
+            }
+            StmtKind::For {
+                var,
+                iterable,
+                body,
+                ..
+            } => {
+                for value in self.iterate(iterable)? {
+                    match self.exec_block(body, HashMap::from([(*var, value)]))? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                }
+            }
+            StmtKind::Break => return Ok(Flow::Break),
+            StmtKind::Continue => return Ok(Flow::Continue),
+            StmtKind::Return(value) => {
+                let value = match value {
+                    Some(value) => self.eval(value)?,
+                    None => Value::Nothing,
+                };
+                return Ok(Flow::Return(value));
+            }
+            StmtKind::Network { command, args } => self.network(*command, args, stmt.span)?,
+            StmtKind::Error => {
+                return Err(self.error("cannot run a statement with errors".to_string(), stmt.span));
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    // the values a `for` takes, a range counts up by one and stops before its end
+    fn iterate(&mut self, iterable: &Iterable) -> Result<Box<dyn Iterator<Item = Value>>> {
+        let expr = match iterable {
+            Iterable::Devices(kind, _) => {
+                let devices: Vec<Value> = self
+                    .declared
+                    .iter()
+                    .filter(|device| matches!(device, Value::Device(device_kind, _) if device_kind == kind))
+                    .cloned()
+                    .collect();
+                return Ok(Box::new(devices.into_iter()));
+            }
+            Iterable::Expr(expr) => expr,
+        };
+        match self.eval(expr)? {
+            Value::List(items) => Ok(Box::new(items.into_iter())),
+            Value::Range(start, end) => {
+                let count = (end - start).ceil().max(0.0) as u64;
+                Ok(Box::new(
+                    (0..count).map(move |step| Value::Num(start + step as f64)),
+                ))
+            }
+            other => Err(self.error(
+                format!(
+                    "cannot loop over {}, expected a range or a list",
+                    other.type_name()
+                ),
+                expr.span,
+            )),
+        }
+    }
+
+    fn condition(&mut self, condition: &Expr) -> Result<bool> {
+        match self.eval(condition)? {
+            Value::Bool(value) => Ok(value),
+            other => Err(self.mismatch("bool", &other, condition.span)),
+        }
+    }
+
+    fn assign(&mut self, target: &Expr, op: AssignOp, value: &Expr) -> Result<()> {
+        let ExprKind::Identifier(name) = target.kind else {
+            return Err(self.error("cannot assign to this".to_string(), target.span));
+        };
+        let span = target.span.to(value.span);
+        let mut value = self.eval(value)?;
+        // `x += 1` runs like `x = x + 1`
+        if let Some(binary) = op.binary_op() {
+            let current = self.eval(target)?;
+            value = self.binary(binary, current, value, span)?;
+        }
+
+        for scope in self.frame().scopes.iter_mut().rev() {
+            if let Some(slot) = scope.get_mut(&name) {
+                *slot = value;
+                return Ok(());
+            }
+        }
+        Err(self.error(
+            format!("unknown variable '{}'", self.name(name)),
+            target.span,
+        ))
+    }
+
+    pub(super) fn mismatch(&self, expected: &str, found: &Value, span: Span) -> RuntimeError {
+        self.error(ops::mismatch(expected, found), span)
+    }
+
+    pub(super) fn num(&self, value: Value, span: Span) -> Result<f64> {
+        ops::num(value).map_err(|message| self.error(message, span))
+    }
+
+    pub fn eval(&mut self, expr: &Expr) -> Result<Value> {
+        match &expr.kind {
+            ExprKind::Integer(value) => Ok(Value::Num(*value as f64)),
+            ExprKind::Float(value) => Ok(Value::Num(*value)),
+            ExprKind::String(text) => Ok(Value::Text(text.clone())),
+            ExprKind::Identifier(name) => self.variable(*name, expr.span),
+            ExprKind::Path { .. } => Err(self.error(
+                "a function is only called, use it with ( )".to_string(),
+                expr.span,
+            )),
+            ExprKind::Unary { op, operand } => {
+                let value = self.eval(operand)?;
+                ops::unary(*op, value).map_err(|message| self.error(message, operand.span))
+            }
+            ExprKind::Binary { op, left, right } => {
+                let left_value = self.eval(left)?;
+                // && and || only look at the right side when they need it
+                match (op, &left_value) {
+                    (BinaryOp::And, Value::Bool(false)) => return Ok(Value::Bool(false)),
+                    (BinaryOp::Or, Value::Bool(true)) => return Ok(Value::Bool(true)),
+                    _ => {}
+                }
+                let right_value = self.eval(right)?;
+                self.binary(*op, left_value, right_value, expr.span)
+            }
+            ExprKind::Call { callee, args } => self.call(callee, args, expr.span),
+            ExprKind::Member {
+                object,
+                member,
+                member_span,
+            } => {
+                let value = self.eval(object)?;
+                self.member(value, *member, *member_span)
+            }
+            ExprKind::Index { object, index } => {
+                let list = self.eval(object)?;
+                let position = self.eval(index)?;
+                self.index(list, position, index.span)
+            }
+            ExprKind::Error => Err(self.error(
+                "cannot run an expression with errors".to_string(),
+                expr.span,
+            )),
+        }
+    }
+
+    fn variable(&self, name: Symbol, span: Span) -> Result<Value> {
+        let frame = self.frames.last().expect("the main body has a frame");
+        for scope in frame.scopes.iter().rev() {
+            if let Some(value) = scope.get(&name) {
+                return Ok(value.clone());
+            }
+        }
+        match self.devices.get(&name) {
+            Some(device) => Ok(device.clone()),
+            None => Err(self.error(format!("unknown name '{}'", self.name(name)), span)),
+        }
+    }
+
+    fn binary(&self, op: BinaryOp, left: Value, right: Value, span: Span) -> Result<Value> {
+        ops::binary(op, left, right).map_err(|message| self.error(message, span))
+    }
+
+    // finds what a callee names, a module's own functions first when called from inside it
+    fn resolve(&self, callee: &Expr) -> Result<(Option<Symbol>, &'a FnDecl)> {
+        let (module, name) = match callee.kind {
+            ExprKind::Identifier(name) => {
+                let current = self.frames.last().and_then(|frame| frame.module);
+                let module =
+                    current.filter(|module| self.functions.contains_key(&(Some(*module), name)));
+                (module, name)
+            }
+            ExprKind::Path { module, name, .. } => (Some(module), name),
+            _ => return Err(self.error("this is not a function".to_string(), callee.span)),
+        };
+        match self.functions.get(&(module, name)) {
+            Some(function) => Ok((module, *function)),
+            None => Err(self.error(
+                format!("unknown function '{}'", self.name(name)),
+                callee.span,
+            )),
+        }
+    }
+
+    fn call(&mut self, callee: &Expr, args: &[Expr], span: Span) -> Result<Value> {
+        // builtin queries go first, as in the checker
+        if let ExprKind::Identifier(name) = callee.kind
+            && let Some(query) = self.keyword(name)
+        {
+            return self.query(query, args, span);
+        }
+        let (module, function) = self.resolve(callee)?;
+        let name = match module {
+            Some(module) => format!("{}::{}", self.name(module), self.name(function.name)),
+            None => self.name(function.name).to_string(),
+        };
+
+        if args.len() != function.params.len() {
+            let plural = if function.params.len() == 1 { "" } else { "s" };
+            return Err(self.error(
+                format!(
+                    "'{}' takes {} argument{}, found {}",
+                    name,
+                    function.params.len(),
+                    plural,
+                    args.len()
+                ),
+                span,
+            ));
+        }
+        // the recursion is stopped before it exhausts the stack
+        if self.frames.len() > MAX_CALL_DEPTH {
+            return Err(self.error(
+                format!("too many nested calls, more than {}", MAX_CALL_DEPTH),
+                span,
+            ));
+        }
+
+        let mut scope = HashMap::new();
+        for (param, arg) in function.params.iter().zip(args) {
+            let value = self.eval(arg)?;
+            scope.insert(param.name, value);
+        }
+
+        self.frames.push(Frame {
+            name,
+            module,
+            call_span: span,
+            scopes: Vec::new(),
+        });
+        let flow = self.exec_block(&function.body, scope);
+        self.frames.pop();
+
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            // the checker keeps break and continue inside a loop of the function
+            Flow::Normal | Flow::Break | Flow::Continue => Ok(Value::Nothing),
+        }
+    }
+}
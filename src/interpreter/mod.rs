@@ -0,0 +1,49 @@
+pub mod eval;
+mod network;
+pub(crate) mod ops;
+pub mod value;
+
+use crate::network::topology::Topology;
+use crate::parser::parser_ana::Parsed;
+use std::error::Error;
+use std::io::{self, Write};
+
+/// Runs a checked program and gives back what `escribe` printed with the network it built. The
+/// output is held back for the caller to show, a runtime error shows it before the error
+pub fn run(parsed: &Parsed) -> Result<(Vec<u8>, Topology), Box<dyn Error>> {
+    let mut output = Vec::new();
+    let mut interpreter = eval::Interpreter::new(
+        &parsed.program,
+        &parsed.interner,
+        &parsed.profiles,
+        &mut output,
+    );
+
+    let result = interpreter.run(&parsed.program);
+    let topology = interpreter.topology().clone();
+    if let Err(error) = result {
+        io::stdout().write_all(&output)?;
+        error.display(&parsed.source);
+        return Err("runtime error".into());
+    }
+
+    Ok((output, topology))
+}
+
+/// Runs a checked program without printing anything and returns the network it built
+pub fn topology(parsed: &Parsed) -> Result<Topology, Box<dyn Error>> {
+    let mut sink = io::sink();
+    let mut interpreter = eval::Interpreter::new(
+        &parsed.program,
+        &parsed.interner,
+        &parsed.profiles,
+        &mut sink,
+    );
+
+    if let Err(error) = interpreter.run(&parsed.program) {
+        error.display(&parsed.source);
+        return Err("runtime error".into());
+    }
+
+    Ok(interpreter.topology().clone())
+}
@@ -0,0 +1,145 @@
+use crate::interpreter::eval::{Interpreter, Result, RuntimeError};
+use crate::interpreter::ops::{self, OpError};
+use crate::interpreter::value::Value;
+use crate::lexer::keywords::{self, KeywordProfile};
+use crate::lexer::lexer_ana::{Span, TokenType};
+use crate::parser::ast::{Decl, Expr, NetworkArg, NetworkCommand, Program};
+use crate::util::interner::Symbol;
+
+impl Interpreter<'_> {
+    // every `define` becomes a device of the topology, sizes are evaluated in order
+    pub(super) fn build_topology(&mut self, program: &Program) -> Result<()> {
+        for decl in &program.declarations {
+            let Decl::Define(define) = decl else {
+                continue;
+            };
+            for item in &define.items {
+                let size = match &item.size {
+                    Some(size) => {
+                        let value = self.eval(size)?;
+                        Some(self.num(value, size.span)?)
+                    }
+                    None => None,
+                };
+                let name = self.name(item.name).to_string();
+                let spans: Vec<Span> = item.size.iter().map(|size| size.span).collect();
+                ops::define(&mut self.topology, define.kind, &name, size)
+                    .map_err(|error| self.op_error(error, &spans, item.span))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn network(
+        &mut self,
+        command: NetworkCommand,
+        args: &[NetworkArg],
+        span: Span,
+    ) -> Result<()> {
+        let mut values = Vec::new();
+        let mut spans = Vec::new();
+        let mut direction = None;
+        for arg in args {
+            match arg {
+                NetworkArg::Expr(expr) => {
+                    values.push(self.eval(expr)?);
+                    spans.push(expr.span);
+                }
+                NetworkArg::Direction(_, span) if direction.is_some() => {
+                    return Err(self.error("expected one direction at most".to_string(), *span));
+                }
+                NetworkArg::Direction(found, _) => direction = Some(*found),
+            }
+        }
+
+        ops::network(
+            &mut self.topology,
+            self.interner,
+            command,
+            direction,
+            &values,
+        )
+        .map_err(|error| self.op_error(error, &spans, span))
+    }
+
+    /// `pos(m1)`, `disponibles(c1)`, ...
+    pub(super) fn query(&mut self, query: &TokenType, args: &[Expr], span: Span) -> Result<Value> {
+        let name = keywords::spelling(query, &[KeywordProfile::School]).unwrap_or("?");
+        if matches!(query, TokenType::Alcanza_ | TokenType::Saltos_) {
+            return self.route(query, name, args, span);
+        }
+        // plano() draws the grid as it is right now
+        if *query == TokenType::Plano_ {
+            if !args.is_empty() {
+                return Err(self.error(
+                    format!("'{}' takes no arguments, found {}", name, args.len()),
+                    span,
+                ));
+            }
+            return Ok(ops::layout(&self.topology));
+        }
+        let [arg] = args else {
+            return Err(self.error(
+                format!("'{}' takes 1 argument, found {}", name, args.len()),
+                span,
+            ));
+        };
+        let value = self.eval(arg)?;
+        // as a call `puertos` counts the ports, as a member it lists them
+        if *query == TokenType::Puertos_ {
+            return ops::ports(&self.topology, self.interner, &value)
+                .map_err(|message| self.error(message, arg.span));
+        }
+        self.member_named(value, name, arg.span)
+    }
+
+    /// `alcanza(m1, m2)` and `saltos(m1, m2)`, over the network as it is wired right now
+    fn route(&mut self, query: &TokenType, name: &str, args: &[Expr], span: Span) -> Result<Value> {
+        let [from, to] = args else {
+            return Err(self.error(
+                format!("'{}' takes 2 arguments, found {}", name, args.len()),
+                span,
+            ));
+        };
+        let from_value = self.eval(from)?;
+        let to_value = self.eval(to)?;
+        let reaches = *query == TokenType::Alcanza_;
+        ops::route(
+            &self.topology,
+            self.interner,
+            reaches,
+            &from_value,
+            &to_value,
+        )
+        .map_err(|error| self.op_error(error, &[from.span, to.span], span))
+    }
+
+    pub(super) fn member(&self, object: Value, member: Symbol, span: Span) -> Result<Value> {
+        // members spelled by keywords are named by their school spelling
+        let name = match self.keyword(member) {
+            Some(token) => keywords::spelling(token, &[KeywordProfile::School])
+                .unwrap_or_else(|| self.name(member)),
+            None => self.name(member),
+        };
+        self.member_named(object, name, span)
+    }
+
+    fn member_named(&self, object: Value, name: &str, span: Span) -> Result<Value> {
+        ops::member(&self.topology, self.interner, object, name)
+            .map_err(|message| self.error(message, span))
+    }
+
+    /// `lista[i]`, counting from 0
+    pub(super) fn index(&self, list: Value, index: Value, span: Span) -> Result<Value> {
+        ops::index(list, index).map_err(|message| self.error(message, span))
+    }
+
+    // the argument to blame when there is one, the whole command otherwise
+    fn op_error(&self, error: OpError, spans: &[Span], span: Span) -> RuntimeError {
+        let span = error
+            .arg
+            .and_then(|arg| spans.get(arg).copied())
+            .unwrap_or(span);
+        self.error(error.message, span)
+    }
+}
@@ -0,0 +1,400 @@
+use crate::interpreter::value::Value;
+use crate::network::analysis::Graph;
+use crate::network::device::{ConcentratorId, DeviceId, MachineId, PortState, Position, SegmentId};
+use crate::network::render;
+use crate::network::topology::{NetworkError, Topology};
+use crate::network::{DEFAULT_COAXIAL_LENGTH, DEFAULT_PORTS};
+use crate::parser::ast::{BinaryOp, DeviceKind, Direction, NetworkCommand, UnaryOp};
+use crate::util::interner::Interner;
+
+// what the interpreter and the bytecode VM both do to values and to the network, each puts its
+// own position on the errors
+
+/// An operation that failed, `arg` is the argument to blame when it is one of them
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct OpError {
+    pub(crate) message: String,
+    pub(crate) arg: Option<usize>,
+}
+
+impl OpError {
+    fn new(message: String) -> Self {
+        OpError { message, arg: None }
+    }
+
+    fn at(arg: usize) -> impl Fn(String) -> OpError {
+        move |message| OpError {
+            message,
+            arg: Some(arg),
+        }
+    }
+}
+
+impl From<NetworkError> for OpError {
+    fn from(error: NetworkError) -> Self {
+        OpError::new(error.to_string())
+    }
+}
+
+pub(crate) fn mismatch(expected: &str, found: &Value) -> String {
+    format!("expected {}, found {}", expected, found.type_name())
+}
+
+pub(crate) fn num(value: Value) -> Result<f64, String> {
+    match value {
+        Value::Num(value) => Ok(value),
+        other => Err(mismatch("num", &other)),
+    }
+}
+
+// port numbers, port counts and list indexes
+pub(crate) fn count(value: f64) -> Result<usize, String> {
+    if value.fract() != 0.0 || !(0.0..=u32::MAX as f64).contains(&value) {
+        return Err(format!(
+            "expected a whole number from 0 up, found {}",
+            value
+        ));
+    }
+    Ok(value as usize)
+}
+
+// grid coordinates are whole numbers
+fn whole(value: &Value) -> Result<i64, String> {
+    let value = num(value.clone())?;
+    if value.fract() != 0.0 || !value.is_finite() {
+        return Err(format!("expected a whole number, found {}", value));
+    }
+    Ok(value as i64)
+}
+
+pub(crate) fn unary(op: UnaryOp, value: Value) -> Result<Value, String> {
+    match (op, value) {
+        (UnaryOp::Negate, Value::Num(value)) => Ok(Value::Num(-value)),
+        (UnaryOp::Not, Value::Bool(value)) => Ok(Value::Bool(!value)),
+        (UnaryOp::Negate, other) => Err(mismatch("num", &other)),
+        (UnaryOp::Not, other) => Err(mismatch("bool", &other)),
+    }
+}
+
+pub(crate) fn binary(op: BinaryOp, left: Value, right: Value) -> Result<Value, String> {
+    let value = match (op, left, right) {
+        (BinaryOp::Equal, left, right) => Value::Bool(left == right),
+        (BinaryOp::NotEqual, left, right) => Value::Bool(left != right),
+        (BinaryOp::Add, Value::Text(left), Value::Text(right)) => Value::Text(left + &right),
+        (BinaryOp::And | BinaryOp::Or, Value::Bool(_), Value::Bool(right)) => {
+            // the left side already decided when it could
+            Value::Bool(right)
+        }
+        (BinaryOp::And | BinaryOp::Or, left, right) => {
+            let wrong = if matches!(left, Value::Bool(_)) {
+                right
+            } else {
+                left
+            };
+            return Err(mismatch("bool", &wrong));
+        }
+        (op, left, right) => {
+            let left = num(left)?;
+            let right = num(right)?;
+            match op {
+                BinaryOp::Add => Value::Num(left + right),
+                BinaryOp::Subtract => Value::Num(left - right),
+                BinaryOp::Multiply => Value::Num(left * right),
+                BinaryOp::Divide | BinaryOp::Modulo if right == 0.0 => {
+                    let what = if op == BinaryOp::Divide {
+                        "division"
+                    } else {
+                        "modulo"
+                    };
+                    return Err(format!("{} by zero", what));
+                }
+                BinaryOp::Divide => Value::Num(left / right),
+                BinaryOp::Modulo => Value::Num(left % right),
+                BinaryOp::Power => Value::Num(left.powf(right)),
+                BinaryOp::Less => Value::Bool(left < right),
+                BinaryOp::Greater => Value::Bool(left > right),
+                BinaryOp::LessEq => Value::Bool(left <= right),
+                BinaryOp::GreaterEq => Value::Bool(left >= right),
+                BinaryOp::Range => Value::Range(left, right),
+                _ => unreachable!("handled above"),
+            }
+        }
+    };
+    Ok(value)
+}
+
+/// Adds a `define`d device, `size` is the port count of a concentrator or the length of a
+/// coaxial segment and is argument 0
+pub(crate) fn define(
+    topology: &mut Topology,
+    kind: DeviceKind,
+    name: &str,
+    size: Option<f64>,
+) -> Result<(), OpError> {
+    match kind {
+        DeviceKind::Machine => topology.add_machine(name).map(|_| ())?,
+        DeviceKind::Concentrator => {
+            let ports = match size {
+                Some(ports) => count(ports).map_err(OpError::at(0))?,
+                None => DEFAULT_PORTS,
+            };
+            topology.add_concentrator(name, ports).map(|_| ())?
+        }
+        DeviceKind::Coaxial => {
+            let length = size.unwrap_or(DEFAULT_COAXIAL_LENGTH);
+            topology.add_coaxial(name, length).map(|_| ())?
+        }
+    }
+    Ok(())
+}
+
+/// Runs a network command, `args` are the values between the parentheses without the direction
+pub(crate) fn network(
+    topology: &mut Topology,
+    interner: &Interner,
+    command: NetworkCommand,
+    direction: Option<Direction>,
+    args: &[Value],
+) -> Result<(), OpError> {
+    match (command, direction, args) {
+        // coloca(m1, derecha, c1), next to a device already placed
+        (NetworkCommand::Place, Some(direction), [device, anchor]) => {
+            let device = self::device(topology, interner, device).map_err(OpError::at(0))?;
+            let anchor = self::device(topology, interner, anchor).map_err(OpError::at(1))?;
+            topology.place_next_to(device, direction, anchor)?;
+        }
+        (NetworkCommand::Place, None, [device, x, y]) => {
+            let x = whole(x).map_err(OpError::at(1))?;
+            let y = whole(y).map_err(OpError::at(2))?;
+            let device = self::device(topology, interner, device).map_err(OpError::at(0))?;
+            topology.place(device, Position::new(x, y))?;
+        }
+        (NetworkCommand::PlaceCoaxial, Some(direction), [segment, x, y]) => {
+            let x = whole(x).map_err(OpError::at(1))?;
+            let y = whole(y).map_err(OpError::at(2))?;
+            let segment = self::segment(topology, interner, segment).map_err(OpError::at(0))?;
+            topology.place_coaxial(segment, Position::new(x, y), direction)?;
+        }
+        (NetworkCommand::PlaceCoaxialConcentrator, None, [segment, concentrator]) => {
+            let segment = self::segment(topology, interner, segment).map_err(OpError::at(0))?;
+            let concentrator =
+                self::concentrator(topology, interner, concentrator).map_err(OpError::at(1))?;
+            topology.attach_segment(segment, concentrator)?;
+        }
+        (NetworkCommand::ConnectPort, None, [machine, concentrator, port]) => {
+            let machine = self::machine(topology, interner, machine).map_err(OpError::at(0))?;
+            let concentrator =
+                self::concentrator(topology, interner, concentrator).map_err(OpError::at(1))?;
+            let port = num(port.clone()).and_then(count).map_err(OpError::at(2))?;
+            topology.connect_port(machine, concentrator, port)?;
+        }
+        (NetworkCommand::AssignPort, None, [machine, concentrator]) => {
+            let machine = self::machine(topology, interner, machine).map_err(OpError::at(0))?;
+            let concentrator =
+                self::concentrator(topology, interner, concentrator).map_err(OpError::at(1))?;
+            topology.assign_port(machine, concentrator)?;
+        }
+        (NetworkCommand::MachineCoaxial, None, [machine, segment, offset]) => {
+            let machine = self::machine(topology, interner, machine).map_err(OpError::at(0))?;
+            let segment = self::segment(topology, interner, segment).map_err(OpError::at(1))?;
+            let offset = num(offset.clone()).map_err(OpError::at(2))?;
+            topology.attach_coaxial(machine, segment, offset)?;
+        }
+        (NetworkCommand::AssignMachineCoaxial, None, [machine, segment]) => {
+            let machine = self::machine(topology, interner, machine).map_err(OpError::at(0))?;
+            let segment = self::segment(topology, interner, segment).map_err(OpError::at(1))?;
+            topology.assign_coaxial(machine, segment)?;
+        }
+        _ => {
+            return Err(OpError::new(
+                "wrong arguments for the network command".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// `puertos(c1)`, how many ports the concentrator has
+pub(crate) fn ports(
+    topology: &Topology,
+    interner: &Interner,
+    concentrator: &Value,
+) -> Result<Value, String> {
+    let concentrator = self::concentrator(topology, interner, concentrator)?;
+    let ports = topology.concentrator(concentrator).ports.len();
+    Ok(Value::Num(ports as f64))
+}
+
+/// `alcanza(m1, m2)` and `saltos(m1, m2)`, over the network as it is wired right now
+pub(crate) fn route(
+    topology: &Topology,
+    interner: &Interner,
+    reaches: bool,
+    from: &Value,
+    to: &Value,
+) -> Result<Value, OpError> {
+    let from = device(topology, interner, from).map_err(OpError::at(0))?;
+    let to = device(topology, interner, to).map_err(OpError::at(1))?;
+    match (reaches, Graph::new(topology).hops(from, to)) {
+        (true, hops) => Ok(Value::Bool(hops.is_some())),
+        (false, Some(hops)) => Ok(Value::Num(hops as f64)),
+        (false, None) => Err(OpError::new(format!(
+            "'{}' cannot reach '{}', check with alcanza first",
+            topology.name(from),
+            topology.name(to)
+        ))),
+    }
+}
+
+/// plano(), the grid as it is right now
+pub(crate) fn layout(topology: &Topology) -> Value {
+    Value::Text(render::render(topology).trim_end().to_string())
+}
+
+/// A member named by its school spelling, `pos`, `puertos`, `x`, ...
+pub(crate) fn member(
+    topology: &Topology,
+    interner: &Interner,
+    object: Value,
+    name: &str,
+) -> Result<Value, String> {
+    let value = match (&object, name) {
+        (Value::Position(position), "x") => Value::Num(position.x as f64),
+        (Value::Position(position), "y") => Value::Num(position.y as f64),
+        (Value::List(items), "longitud") => Value::Num(items.len() as f64),
+        (Value::Port(_, number), "numero") => Value::Num(*number as f64),
+        (Value::Port(concentrator, number), "presente" | "maquina") => {
+            let hub = Value::Device(DeviceKind::Concentrator, *concentrator);
+            let DeviceId::Concentrator(id) = device(topology, interner, &hub)? else {
+                return Err(mismatch("concentrador", &object));
+            };
+            let state = topology
+                .port(id, *number)
+                .map_err(|error| error.to_string())?;
+            match (state, name) {
+                (state, "presente") => Value::Bool(state != PortState::Free),
+                (PortState::Connected(machine), _) => machine_value(topology, interner, machine),
+                (PortState::Free, _) => {
+                    return Err(format!(
+                        "port {} of '{}' has no machine",
+                        number,
+                        interner.resolve(*concentrator)
+                    ));
+                }
+            }
+        }
+        (Value::Device(_, symbol), _) => {
+            let id = device(topology, interner, &object)?;
+            device_member(topology, id, name, |number| Value::Port(*symbol, number))?
+        }
+        _ => {
+            return Err(format!("{} has no member '{}'", object.type_name(), name));
+        }
+    };
+    Ok(value)
+}
+
+fn device_member(
+    topology: &Topology,
+    device: DeviceId,
+    name: &str,
+    port: impl Fn(usize) -> Value,
+) -> Result<Value, String> {
+    let value = match (device, name) {
+        (_, "pos") => match topology.position(device) {
+            Some(position) => Value::Position(position),
+            None => {
+                let name = topology.name(device).to_string();
+                return Err(NetworkError::NotPlaced(name).to_string());
+            }
+        },
+        (DeviceId::Machine(id), "presente") => Value::Bool(topology.machine(id).link.is_some()),
+        // a hub or cable is in the network once something is plugged into it
+        (DeviceId::Concentrator(id), "presente") => {
+            let hub = topology.concentrator(id);
+            Value::Bool(hub.available() < hub.ports.len() || !topology.segments_of(id).is_empty())
+        }
+        (DeviceId::Coaxial(id), "presente") => {
+            let cable = topology.segment(id);
+            Value::Bool(!cable.taps.is_empty() || !cable.concentrators.is_empty())
+        }
+        (DeviceId::Concentrator(id), "completo") => {
+            Value::Bool(topology.concentrator(id).is_complete())
+        }
+        (DeviceId::Coaxial(id), "completo") => Value::Bool(topology.segment(id).is_complete()),
+        (DeviceId::Concentrator(id), "disponibles") => {
+            Value::Num(topology.concentrator(id).available() as f64)
+        }
+        (DeviceId::Concentrator(id), "puertos") => {
+            let ports = topology.concentrator(id).ports.len();
+            Value::List((1..=ports).map(port).collect())
+        }
+        (DeviceId::Coaxial(id), "longitud") => Value::Num(topology.segment(id).length),
+        _ => {
+            let kind = match device {
+                DeviceId::Machine(_) => "maquina",
+                DeviceId::Concentrator(_) => "concentrador",
+                DeviceId::Coaxial(_) => "coaxial",
+            };
+            return Err(format!("{} has no member '{}'", kind, name));
+        }
+    };
+    Ok(value)
+}
+
+/// `lista[i]`, counting from 0
+pub(crate) fn index(list: Value, index: Value) -> Result<Value, String> {
+    let Value::List(items) = list else {
+        return Err(format!("{} cannot be indexed", list.type_name()));
+    };
+    let position = count(num(index)?)?;
+    items.get(position).cloned().ok_or_else(|| {
+        format!(
+            "index {} is out of range, the list has {} items",
+            position,
+            items.len()
+        )
+    })
+}
+
+fn machine_value(topology: &Topology, interner: &Interner, machine: MachineId) -> Value {
+    let name = &topology.machine(machine).name;
+    match interner.get(name) {
+        Some(symbol) => Value::Device(DeviceKind::Machine, symbol),
+        None => Value::Text(name.clone()),
+    }
+}
+
+fn device(topology: &Topology, interner: &Interner, value: &Value) -> Result<DeviceId, String> {
+    match value {
+        Value::Device(_, name) => topology
+            .device(interner.resolve(*name))
+            .map_err(|error| error.to_string()),
+        other => Err(mismatch("a device", other)),
+    }
+}
+
+fn machine(topology: &Topology, interner: &Interner, value: &Value) -> Result<MachineId, String> {
+    match device(topology, interner, value)? {
+        DeviceId::Machine(id) => Ok(id),
+        _ => Err(mismatch("maquina", value)),
+    }
+}
+
+fn concentrator(
+    topology: &Topology,
+    interner: &Interner,
+    value: &Value,
+) -> Result<ConcentratorId, String> {
+    match device(topology, interner, value)? {
+        DeviceId::Concentrator(id) => Ok(id),
+        _ => Err(mismatch("concentrador", value)),
+    }
+}
+
+fn segment(topology: &Topology, interner: &Interner, value: &Value) -> Result<SegmentId, String> {
+    match device(topology, interner, value)? {
+        DeviceId::Coaxial(id) => Ok(id),
+        _ => Err(mismatch("coaxial", value)),
+    }
+}
@@ -0,0 +1,70 @@
+use crate::network::device::Position;
+use crate::parser::ast::DeviceKind;
+use crate::util::interner::{Interner, Symbol};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Num(f64),
+    Text(String),
+    Bool(bool),
+    // a defined machine, concentrator or coaxial segment
+    Device(DeviceKind, Symbol),
+    // a..b
+    Range(f64, f64),
+    Position(Position),
+    // port `n` (from 1) of a concentrator
+    Port(Symbol, usize),
+    // c1.puertos
+    List(Vec<Value>),
+    // what a `fn` without `->` returns
+    Nothing,
+}
+
+impl Value {
+    /// Name of the value's type, as the checker spells it
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Num(_) => "num",
+            Value::Text(_) => "texto",
+            Value::Bool(_) => "bool",
+            Value::Device(DeviceKind::Machine, _) => "maquina",
+            Value::Device(DeviceKind::Concentrator, _) => "concentrador",
+            Value::Device(DeviceKind::Coaxial, _) => "coaxial",
+            Value::Range(..) => "rango",
+            Value::Position(_) => "posicion",
+            Value::Port(..) => "puerto",
+            Value::List(_) => "lista",
+            Value::Nothing => "nothing",
+        }
+    }
+
+    /// How `escribe` prints the value, devices by their name
+    pub fn show(&self, interner: &Interner) -> String {
+        match self {
+            Value::Num(value) => show_num(*value),
+            Value::Text(text) => text.clone(),
+            Value::Bool(true) => "verdadero".to_string(),
+            Value::Bool(false) => "falso".to_string(),
+            Value::Device(_, name) => interner.resolve(*name).to_string(),
+            Value::Range(start, end) => format!("{}..{}", show_num(*start), show_num(*end)),
+            Value::Position(position) => position.to_string(),
+            Value::Port(concentrator, number) => {
+                format!("{}:{}", interner.resolve(*concentrator), number)
+            }
+            Value::List(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.show(interner)).collect();
+                format!("[{}]", items.join(", "))
+            }
+            Value::Nothing => "nothing".to_string(),
+        }
+    }
+}
+
+// whole numbers print without a fraction, 4 rather than 4.0
+fn show_num(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{}", value)
+    }
+}
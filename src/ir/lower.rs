@@ -0,0 +1,914 @@
+use crate::ir::{
+    BinaryOp, Block, BlockId, Const, Field, FuncId, Function, Inst, Intrinsic, Operand, Program,
+    Terminator, UnaryOp, Var, VarInfo, device_type,
+};
+use crate::lexer::keywords::{self, KEYWORDS, KeywordProfile};
+use crate::lexer::lexer_ana::{Span, TokenType};
+use crate::network::{DEFAULT_COAXIAL_LENGTH, DEFAULT_PORTS};
+use crate::parser::ast::{
+    self, AssignOp, Decl, DeviceKind, Expr, ExprKind, FnDecl, Iterable, NetworkArg, NetworkCommand,
+    Stmt, StmtKind,
+};
+use crate::semantic::checker::Checker;
+use crate::semantic::types::Type;
+use crate::util::interner::{Interner, Symbol};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LowerError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl LowerError {
+    fn new(message: impl Into<String>, span: Span) -> Self {
+        LowerError {
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+impl fmt::Display for LowerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}:{}",
+            self.message, self.span.line, self.span.column
+        )
+    }
+}
+
+impl Error for LowerError {}
+
+type Result<T> = std::result::Result<T, LowerError>;
+
+/// Lowers a program to the IR, type checking it first. The main body is the first function and
+/// starts by defining every device
+pub fn lower(
+    program: &ast::Program,
+    interner: &Interner,
+    profiles: &[KeywordProfile],
+) -> Result<Program> {
+    let mut checker = Checker::new(interner, profiles);
+    checker.check(program);
+    if let Some(error) = checker.errors().first() {
+        return Err(LowerError::new(
+            format!("cannot lower a program with type errors: {}", error.message),
+            error.span,
+        ));
+    }
+
+    let mut lowering = Lowering {
+        interner,
+        profiles,
+        checker: &checker,
+        functions: HashMap::new(),
+        names: vec!["inicio".to_string()],
+        devices: HashMap::new(),
+        module: None,
+        vars: Vec::new(),
+        taken: HashSet::new(),
+        blocks: Vec::new(),
+        current: None,
+        scopes: Vec::new(),
+        loops: Vec::new(),
+    };
+    // every function gets its id first, calls can go either way
+    let mut declared: Vec<(Option<Symbol>, &FnDecl)> = Vec::new();
+    for decl in &program.declarations {
+        match decl {
+            Decl::Define(define) => {
+                for item in &define.items {
+                    lowering.devices.insert(item.name, define.kind);
+                }
+            }
+            Decl::Function(function) => declared.push((None, function)),
+            Decl::Module(module) => declared.extend(
+                module
+                    .functions
+                    .iter()
+                    .map(|function| (Some(module.name), function)),
+            ),
+            Decl::Error(span) => {
+                return Err(LowerError::new(
+                    "cannot lower a declaration with errors",
+                    *span,
+                ));
+            }
+        }
+    }
+    for (module, function) in &declared {
+        let id = FuncId(lowering.names.len());
+        lowering.functions.insert((*module, function.name), id);
+        let name = match module {
+            Some(module) => format!(
+                "{}::{}",
+                interner.resolve(*module),
+                interner.resolve(function.name)
+            ),
+            None => interner.resolve(function.name).to_string(),
+        };
+        lowering.names.push(name);
+    }
+
+    let mut functions = vec![lowering.main(program)?];
+    for (module, function) in declared {
+        functions.push(lowering.function(function, module)?);
+    }
+    Ok(Program { functions })
+}
+
+struct Lowering<'a> {
+    interner: &'a Interner,
+    profiles: &'a [KeywordProfile],
+    checker: &'a Checker<'a>,
+    functions: HashMap<(Option<Symbol>, Symbol), FuncId>,
+    names: Vec<String>,
+    devices: HashMap<Symbol, DeviceKind>,
+
+    // the function being lowered
+    module: Option<Symbol>,
+    vars: Vec<VarInfo>,
+    // names given to vars so far, shadowed ones get `x.1`
+    taken: HashSet<String>,
+    blocks: Vec<(Vec<Inst>, Vec<Span>, Option<Terminator>)>,
+    // None right after a terminator, whatever follows is dead and gets a block of its own
+    current: Option<BlockId>,
+    scopes: Vec<HashMap<Symbol, Var>>,
+    // (continue, break) targets, innermost last
+    loops: Vec<(BlockId, BlockId)>,
+}
+
+impl Lowering<'_> {
+    fn main(&mut self, program: &ast::Program) -> Result<Function> {
+        self.start(None);
+        // devices are defined in declaration order before anything runs, as the interpreter does
+        for decl in &program.declarations {
+            let Decl::Define(define) = decl else {
+                continue;
+            };
+            for item in &define.items {
+                let device = Operand::Const(Const::Device(
+                    define.kind,
+                    self.interner.resolve(item.name).to_string(),
+                ));
+                let (size, span) = match &item.size {
+                    Some(size) => (Some(self.value(size)?), size.span),
+                    None => (None, item.span),
+                };
+                let (intrinsic, default) = match define.kind {
+                    DeviceKind::Machine => (Intrinsic::DefineMachine, None),
+                    DeviceKind::Concentrator => {
+                        (Intrinsic::DefineConcentrator, Some(DEFAULT_PORTS as f64))
+                    }
+                    DeviceKind::Coaxial => (Intrinsic::DefineCoaxial, Some(DEFAULT_COAXIAL_LENGTH)),
+                };
+                let mut args = vec![device];
+                args.extend(size.or(default.map(|size| Operand::Const(Const::Num(size)))));
+                self.intrinsic(None, intrinsic, args, span);
+            }
+        }
+        self.block(&program.body, Vec::new())?;
+        Ok(self.finish(FuncId(0), Vec::new(), Type::Nothing))
+    }
+
+    fn function(&mut self, function: &FnDecl, module: Option<Symbol>) -> Result<Function> {
+        self.start(module);
+        let signature = self
+            .checker
+            .signature(module, function.name)
+            .expect("every function was checked");
+        let ret = signature.ret.clone();
+        let mut params = Vec::new();
+        let mut bindings = Vec::new();
+        for (param, (ty, _)) in function.params.iter().zip(&signature.params) {
+            let var = self.named(self.interner.resolve(param.name), ty.clone());
+            params.push(var);
+            bindings.push((param.name, var));
+        }
+        self.block(&function.body, bindings)?;
+        Ok(self.finish(self.functions[&(module, function.name)], params, ret))
+    }
+
+    fn start(&mut self, module: Option<Symbol>) {
+        self.module = module;
+        self.vars.clear();
+        self.taken.clear();
+        self.blocks.clear();
+        self.current = None;
+        self.scopes.clear();
+        self.loops.clear();
+    }
+
+    // closes the last block and drops the blocks nothing jumps to
+    fn finish(&mut self, id: FuncId, params: Vec<Var>, ret: Type) -> Function {
+        if self.current.is_some() {
+            let end = if ret == Type::Nothing {
+                Terminator::Return(None)
+            } else {
+                Terminator::Unreachable
+            };
+            self.terminate(end);
+        }
+
+        // blocks nothing jumps to are dropped, the rest laid out in reverse postorder so a
+        // block comes after the ones that lead to it, loops aside
+        let mut order = Vec::new();
+        let mut visited = vec![false; self.blocks.len()];
+        let mut stack = vec![(0, false)];
+        while let Some((block, done)) = stack.pop() {
+            if done {
+                order.push(block);
+                continue;
+            }
+            if visited[block] {
+                continue;
+            }
+            visited[block] = true;
+            stack.push((block, true));
+            if let Some(terminator) = &self.blocks[block].2 {
+                // the last target is walked first, which puts the first right after the block
+                for target in terminator.targets() {
+                    stack.push((target.0, false));
+                }
+            }
+        }
+        order.reverse();
+        let mut renumbered = vec![BlockId(0); self.blocks.len()];
+        for (index, block) in order.iter().enumerate() {
+            renumbered[*block] = BlockId(index);
+        }
+
+        let mut built: Vec<_> = std::mem::take(&mut self.blocks)
+            .into_iter()
+            .map(Some)
+            .collect();
+        let blocks = order
+            .iter()
+            .map(|block| {
+                let (insts, spans, terminator) =
+                    built[*block].take().expect("each block is laid out once");
+                let terminator = match terminator.unwrap_or(Terminator::Unreachable) {
+                    Terminator::Jump(target) => Terminator::Jump(renumbered[target.0]),
+                    Terminator::Branch {
+                        condition,
+                        then,
+                        otherwise,
+                    } => Terminator::Branch {
+                        condition,
+                        then: renumbered[then.0],
+                        otherwise: renumbered[otherwise.0],
+                    },
+                    other => other,
+                };
+                Block {
+                    insts,
+                    spans,
+                    terminator,
+                }
+            })
+            .collect();
+
+        Function {
+            name: self.names[id.0].clone(),
+            params,
+            ret,
+            vars: std::mem::take(&mut self.vars),
+            blocks,
+        }
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push((Vec::new(), Vec::new(), None));
+        BlockId(self.blocks.len() - 1)
+    }
+
+    fn open(&mut self) -> BlockId {
+        match self.current {
+            Some(block) => block,
+            None => {
+                let block = self.new_block();
+                self.current = Some(block);
+                block
+            }
+        }
+    }
+
+    fn switch_to(&mut self, block: BlockId) {
+        self.current = Some(block);
+    }
+
+    // `span` is what a runtime error in the instruction points at
+    fn emit(&mut self, inst: Inst, span: Span) {
+        let block = self.open();
+        self.blocks[block.0].0.push(inst);
+        self.blocks[block.0].1.push(span);
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        let block = self.open();
+        self.blocks[block.0].2 = Some(terminator);
+        self.current = None;
+    }
+
+    fn temp(&mut self, ty: Type) -> Var {
+        self.vars.push(VarInfo { name: None, ty });
+        Var(self.vars.len() - 1)
+    }
+
+    fn named(&mut self, base: &str, ty: Type) -> Var {
+        let mut name = base.to_string();
+        let mut count = 0;
+        while self.taken.contains(&name) {
+            count += 1;
+            name = format!("{}.{}", base, count);
+        }
+        self.taken.insert(name.clone());
+        self.vars.push(VarInfo {
+            name: Some(name),
+            ty,
+        });
+        Var(self.vars.len() - 1)
+    }
+
+    fn intrinsic(
+        &mut self,
+        dest: Option<Var>,
+        intrinsic: Intrinsic,
+        args: Vec<Operand>,
+        span: Span,
+    ) {
+        self.emit(
+            Inst::Intrinsic {
+                dest,
+                intrinsic,
+                args,
+            },
+            span,
+        );
+    }
+
+    // what the checker worked out for the expression or name at `span`
+    fn ty(&self, span: Span) -> Result<Type> {
+        match self.checker.type_at(span) {
+            Some(Type::Error) | None => Err(LowerError::new("no type for this", span)),
+            Some(ty) => Ok(ty.clone()),
+        }
+    }
+
+    fn keyword(&self, symbol: Symbol) -> Option<&'static TokenType> {
+        let keyword = KEYWORDS.get(symbol.index())?;
+        keyword
+            .profiles
+            .iter()
+            .any(|profile| self.profiles.contains(profile))
+            .then_some(&keyword.token)
+    }
+
+    fn block(&mut self, block: &ast::Block, bindings: Vec<(Symbol, Var)>) -> Result<()> {
+        self.scopes.push(bindings.into_iter().collect());
+        for stmt in &block.statements {
+            self.stmt(stmt)?;
+        }
+        self.scopes.pop();
+        Ok(())
+    }
+
+    fn local(&self, name: Symbol) -> Option<Var> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name).copied())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<()> {
+        match &stmt.kind {
+            StmtKind::Let {
+                name,
+                name_span,
+                value,
+                ..
+            } => {
+                let value = self.value(value)?;
+                let var = self.named(self.interner.resolve(*name), self.ty(*name_span)?);
+                self.emit(Inst::Copy { dest: var, value }, stmt.span);
+                self.scopes
+                    .last_mut()
+                    .expect("statements are lowered inside a block")
+                    .insert(*name, var);
+            }
+            StmtKind::Assign { target, op, value } => self.assign(target, *op, value)?,
+            StmtKind::Expr(expr) => {
+                self.expr(expr)?;
+            }
+            StmtKind::Write(args) => {
+                let mut values = Vec::new();
+                for arg in args {
+                    values.push(self.value(arg)?);
+                }
+                self.intrinsic(None, Intrinsic::Write, values, stmt.span);
+            }
+            StmtKind::If {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                let condition = self.value(condition)?;
+                let then = self.new_block();
+                let join = self.new_block();
+                let otherwise = match else_block {
+                    Some(_) => self.new_block(),
+                    None => join,
+                };
+                self.terminate(Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                });
+                self.switch_to(then);
+                self.block(then_block, Vec::new())?;
+                self.terminate(Terminator::Jump(join));
+                if let Some(else_block) = else_block {
+                    self.switch_to(otherwise);
+                    self.block(else_block, Vec::new())?;
+                    self.terminate(Terminator::Jump(join));
+                }
+                self.switch_to(join);
+            }
+            StmtKind::While { condition, body } => {
+                let head = self.new_block();
+                let inside = self.new_block();
+                let exit = self.new_block();
+                self.terminate(Terminator::Jump(head));
+                self.switch_to(head);
+                let condition = self.value(condition)?;
+                self.terminate(Terminator::Branch {
+                    condition,
+                    then: inside,
+                    otherwise: exit,
+                });
+                self.switch_to(inside);
+                self.loops.push((head, exit));
+                self.block(body, Vec::new())?;
+                self.loops.pop();
+                self.terminate(Terminator::Jump(head));
+                self.switch_to(exit);
+            }
+            StmtKind::For {
+                var,
+                var_span,
+                iterable,
+                body,
+            } => self.for_loop(*var, *var_span, iterable, body, stmt.span)?,
+            StmtKind::Break | StmtKind::Continue => {
+                let Some(&(next, exit)) = self.loops.last() else {
+                    return Err(LowerError::new(
+                        "break or continue outside a loop",
+                        stmt.span,
+                    ));
+                };
+                let target = match stmt.kind {
+                    StmtKind::Break => exit,
+                    _ => next,
+                };
+                self.terminate(Terminator::Jump(target));
+            }
+            StmtKind::Return(value) => {
+                let value = match value {
+                    Some(value) => Some(self.value(value)?),
+                    None => None,
+                };
+                self.terminate(Terminator::Return(value));
+            }
+            StmtKind::Network { command, args } => self.network(*command, args, stmt.span)?,
+            StmtKind::Error => {
+                return Err(LowerError::new(
+                    "cannot lower a statement with errors",
+                    stmt.span,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn assign(&mut self, target: &Expr, op: AssignOp, value: &Expr) -> Result<()> {
+        let var = match target.kind {
+            ExprKind::Identifier(name) => self.local(name),
+            _ => None,
+        };
+        let Some(var) = var else {
+            return Err(LowerError::new("cannot assign to this", target.span));
+        };
+        let span = target.span.to(value.span);
+        let value = self.value(value)?;
+        // `x += 1` is `x = add x, 1`
+        match op.binary_op() {
+            Some(binary) => {
+                let ty = self.vars[var.0].ty.clone();
+                let op = binary_op(binary, &ty).expect("compound assignments are arithmetic");
+                self.emit(
+                    Inst::Binary {
+                        dest: var,
+                        op,
+                        left: Operand::Var(var),
+                        right: value,
+                    },
+                    span,
+                );
+            }
+            None => self.emit(Inst::Copy { dest: var, value }, span),
+        }
+        Ok(())
+    }
+
+    // a counter `_i` walks the range or the list, the loop variable is fresh every time round
+    fn for_loop(
+        &mut self,
+        var: Symbol,
+        var_span: Span,
+        iterable: &Iterable,
+        body: &ast::Block,
+        span: Span,
+    ) -> Result<()> {
+        let (items, ty) = match iterable {
+            Iterable::Devices(kind, _) => {
+                let ty = Type::List(Box::new(device_type(*kind)));
+                let list = self.temp(ty.clone());
+                self.intrinsic(Some(list), Intrinsic::Devices(*kind), Vec::new(), span);
+                (Operand::Var(list), ty)
+            }
+            Iterable::Expr(expr) => (self.value(expr)?, self.ty(expr.span)?),
+        };
+
+        let range = ty == Type::Range;
+        let bounds = if range {
+            let start = self.temp(Type::Num);
+            self.intrinsic(
+                Some(start),
+                Intrinsic::RangeStart,
+                vec![items.clone()],
+                span,
+            );
+            let end = self.temp(Type::Num);
+            self.intrinsic(Some(end), Intrinsic::RangeEnd, vec![items.clone()], span);
+            (Operand::Var(start), Operand::Var(end))
+        } else {
+            let count = self.temp(Type::Num);
+            self.intrinsic(
+                Some(count),
+                Intrinsic::Field(Field::Length),
+                vec![items.clone()],
+                span,
+            );
+            (Operand::Const(Const::Num(0.0)), Operand::Var(count))
+        };
+        let counter = self.named("_i", Type::Num);
+        self.emit(
+            Inst::Copy {
+                dest: counter,
+                value: Operand::Const(Const::Num(0.0)),
+            },
+            span,
+        );
+
+        let head = self.new_block();
+        let inside = self.new_block();
+        let step = self.new_block();
+        let exit = self.new_block();
+        self.terminate(Terminator::Jump(head));
+
+        // ranges count from their start, lists from 0
+        self.switch_to(head);
+        let at = if range {
+            let at = self.temp(Type::Num);
+            self.emit(
+                Inst::Binary {
+                    dest: at,
+                    op: BinaryOp::Add,
+                    left: bounds.0,
+                    right: Operand::Var(counter),
+                },
+                span,
+            );
+            Operand::Var(at)
+        } else {
+            Operand::Var(counter)
+        };
+        let more = self.temp(Type::Bool);
+        self.emit(
+            Inst::Binary {
+                dest: more,
+                op: BinaryOp::Lt,
+                left: at.clone(),
+                right: bounds.1,
+            },
+            span,
+        );
+        self.terminate(Terminator::Branch {
+            condition: Operand::Var(more),
+            then: inside,
+            otherwise: exit,
+        });
+
+        self.switch_to(inside);
+        let element = self.named(self.interner.resolve(var), self.ty(var_span)?);
+        if range {
+            self.emit(
+                Inst::Copy {
+                    dest: element,
+                    value: at,
+                },
+                span,
+            );
+        } else {
+            self.intrinsic(Some(element), Intrinsic::Index, vec![items, at], span);
+        }
+        self.loops.push((step, exit));
+        self.block(body, vec![(var, element)])?;
+        self.loops.pop();
+        self.terminate(Terminator::Jump(step));
+
+        self.switch_to(step);
+        self.emit(
+            Inst::Binary {
+                dest: counter,
+                op: BinaryOp::Add,
+                left: Operand::Var(counter),
+                right: Operand::Const(Const::Num(1.0)),
+            },
+            span,
+        );
+        self.terminate(Terminator::Jump(head));
+        self.switch_to(exit);
+        Ok(())
+    }
+
+    fn network(&mut self, command: NetworkCommand, args: &[NetworkArg], span: Span) -> Result<()> {
+        let mut values = Vec::new();
+        let mut direction = None;
+        for arg in args {
+            match arg {
+                NetworkArg::Expr(expr) => values.push(self.value(expr)?),
+                NetworkArg::Direction(found, _) => direction = Some(*found),
+            }
+        }
+        let intrinsic = match (command, direction) {
+            // coloca(m1, derecha, c1)
+            (NetworkCommand::Place, Some(direction)) => Intrinsic::PlaceNextTo(direction),
+            (NetworkCommand::Place, None) => Intrinsic::Place,
+            (NetworkCommand::PlaceCoaxial, Some(direction)) => Intrinsic::PlaceCoaxial(direction),
+            (NetworkCommand::PlaceCoaxialConcentrator, None) => Intrinsic::AttachSegment,
+            (NetworkCommand::ConnectPort, None) => Intrinsic::ConnectPort,
+            (NetworkCommand::AssignPort, None) => Intrinsic::AssignPort,
+            (NetworkCommand::MachineCoaxial, None) => Intrinsic::AttachCoax,
+            (NetworkCommand::AssignMachineCoaxial, None) => Intrinsic::AssignCoax,
+            _ => {
+                return Err(LowerError::new(
+                    "wrong arguments for the network command",
+                    span,
+                ));
+            }
+        };
+        self.intrinsic(None, intrinsic, values, span);
+        Ok(())
+    }
+
+    fn value(&mut self, expr: &Expr) -> Result<Operand> {
+        self.expr(expr)?
+            .ok_or_else(|| LowerError::new("expected a value, found nothing", expr.span))
+    }
+
+    // None for calls to functions that return nothing
+    fn expr(&mut self, expr: &Expr) -> Result<Option<Operand>> {
+        let operand = match &expr.kind {
+            ExprKind::Integer(value) => Operand::Const(Const::Num(*value as f64)),
+            ExprKind::Float(value) => Operand::Const(Const::Num(*value)),
+            ExprKind::String(text) => Operand::Const(Const::Text(text.clone())),
+            ExprKind::Identifier(name) => match (self.local(*name), self.devices.get(name)) {
+                (Some(var), _) => Operand::Var(var),
+                (None, Some(kind)) => Operand::Const(Const::Device(
+                    *kind,
+                    self.interner.resolve(*name).to_string(),
+                )),
+                (None, None) => {
+                    return Err(LowerError::new(
+                        format!("unknown name '{}'", self.interner.resolve(*name)),
+                        expr.span,
+                    ));
+                }
+            },
+            ExprKind::Path { .. } => {
+                return Err(LowerError::new(
+                    "a function is only called, use it with ( )",
+                    expr.span,
+                ));
+            }
+            ExprKind::Unary { op, operand } => {
+                let span = operand.span;
+                let operand = self.value(operand)?;
+                let dest = self.temp(self.ty(expr.span)?);
+                let op = match op {
+                    ast::UnaryOp::Negate => UnaryOp::Neg,
+                    ast::UnaryOp::Not => UnaryOp::Not,
+                };
+                self.emit(Inst::Unary { dest, op, operand }, span);
+                Operand::Var(dest)
+            }
+            ExprKind::Binary {
+                op: op @ (ast::BinaryOp::And | ast::BinaryOp::Or),
+                left,
+                right,
+            } => self.short_circuit(*op, left, right)?,
+            ExprKind::Binary { op, left, right } => {
+                let left_ty = self.ty(left.span)?;
+                let left = self.value(left)?;
+                let right = self.value(right)?;
+                let dest = self.temp(self.ty(expr.span)?);
+                let op = binary_op(*op, &left_ty).expect("&& and || are branches");
+                self.emit(
+                    Inst::Binary {
+                        dest,
+                        op,
+                        left,
+                        right,
+                    },
+                    expr.span,
+                );
+                Operand::Var(dest)
+            }
+            ExprKind::Member {
+                object,
+                member,
+                member_span,
+            } => {
+                let name = match self.keyword(*member) {
+                    Some(token) => keywords::spelling(token, &[KeywordProfile::School])
+                        .unwrap_or_else(|| self.interner.resolve(*member)),
+                    None => self.interner.resolve(*member),
+                };
+                let Some(field) = Field::from_name(name) else {
+                    return Err(LowerError::new(
+                        format!("no member '{}'", name),
+                        *member_span,
+                    ));
+                };
+                let object = self.value(object)?;
+                let dest = self.temp(self.ty(expr.span)?);
+                self.intrinsic(
+                    Some(dest),
+                    Intrinsic::Field(field),
+                    vec![object],
+                    *member_span,
+                );
+                Operand::Var(dest)
+            }
+            ExprKind::Index { object, index } => {
+                let span = index.span;
+                let object = self.value(object)?;
+                let index = self.value(index)?;
+                let dest = self.temp(self.ty(expr.span)?);
+                self.intrinsic(Some(dest), Intrinsic::Index, vec![object, index], span);
+                Operand::Var(dest)
+            }
+            ExprKind::Call { callee, args } => return self.call(expr, callee, args),
+            ExprKind::Error => {
+                return Err(LowerError::new(
+                    "cannot lower an expression with errors",
+                    expr.span,
+                ));
+            }
+        };
+        Ok(Some(operand))
+    }
+
+    // `a && b` only looks at `b` when `a` is true, the result is a variable both paths set
+    fn short_circuit(&mut self, op: ast::BinaryOp, left: &Expr, right: &Expr) -> Result<Operand> {
+        let base = if op == ast::BinaryOp::And {
+            "_and"
+        } else {
+            "_or"
+        };
+        let result = self.named(base, Type::Bool);
+        let (left_span, right_span) = (left.span, right.span);
+        let left = self.value(left)?;
+        self.emit(
+            Inst::Copy {
+                dest: result,
+                value: left.clone(),
+            },
+            left_span,
+        );
+        let rest = self.new_block();
+        let join = self.new_block();
+        let (then, otherwise) = if op == ast::BinaryOp::And {
+            (rest, join)
+        } else {
+            (join, rest)
+        };
+        self.terminate(Terminator::Branch {
+            condition: left,
+            then,
+            otherwise,
+        });
+        self.switch_to(rest);
+        let right = self.value(right)?;
+        self.emit(
+            Inst::Copy {
+                dest: result,
+                value: right,
+            },
+            right_span,
+        );
+        self.terminate(Terminator::Jump(join));
+        self.switch_to(join);
+        Ok(Operand::Var(result))
+    }
+
+    fn call(&mut self, expr: &Expr, callee: &Expr, args: &[Expr]) -> Result<Option<Operand>> {
+        let ty = self.ty(expr.span)?;
+        // builtin queries go first, as in the checker and the interpreter
+        if let ExprKind::Identifier(name) = callee.kind
+            && let Some(query) = self.keyword(name)
+        {
+            let intrinsic = match query {
+                TokenType::Pos_ => Intrinsic::Field(Field::Pos),
+                TokenType::Presente_ => Intrinsic::Field(Field::Present),
+                TokenType::Completo_ => Intrinsic::Field(Field::Complete),
+                TokenType::Disponibles_ => Intrinsic::Field(Field::Available),
+                TokenType::Longitud_ => Intrinsic::Field(Field::Length),
+                TokenType::Puertos_ => Intrinsic::QueryPorts,
+                TokenType::Alcanza_ => Intrinsic::QueryReaches,
+                TokenType::Saltos_ => Intrinsic::QueryHops,
+                TokenType::Plano_ => Intrinsic::QueryLayout,
+                _ => return Err(LowerError::new("this is not a function", callee.span)),
+            };
+            // the interpreter blames the argument of the one argument queries
+            let span = match args {
+                [arg] => arg.span,
+                _ => expr.span,
+            };
+            let args = self.values(args)?;
+            let dest = self.temp(ty);
+            self.intrinsic(Some(dest), intrinsic, args, span);
+            return Ok(Some(Operand::Var(dest)));
+        }
+
+        // a module's own functions first when called from inside it
+        let key = match callee.kind {
+            ExprKind::Identifier(name) => {
+                let module = self
+                    .module
+                    .filter(|module| self.functions.contains_key(&(Some(*module), name)));
+                (module, name)
+            }
+            ExprKind::Path { module, name, .. } => (Some(module), name),
+            _ => return Err(LowerError::new("this is not a function", callee.span)),
+        };
+        let Some(&function) = self.functions.get(&key) else {
+            return Err(LowerError::new(
+                format!("unknown function '{}'", self.interner.resolve(key.1)),
+                callee.span,
+            ));
+        };
+        let args = self.values(args)?;
+        let dest = (ty != Type::Nothing).then(|| self.temp(ty));
+        self.emit(
+            Inst::Call {
+                dest,
+                function,
+                args,
+            },
+            expr.span,
+        );
+        Ok(dest.map(Operand::Var))
+    }
+
+    fn values(&mut self, exprs: &[Expr]) -> Result<Vec<Operand>> {
+        exprs.iter().map(|expr| self.value(expr)).collect()
+    }
+}
+
+// the typed operation for `op` on a left side of type `left`
+fn binary_op(op: ast::BinaryOp, left: &Type) -> Option<BinaryOp> {
+    let op = match op {
+        ast::BinaryOp::Add if *left == Type::Text => BinaryOp::Concat,
+        ast::BinaryOp::Add => BinaryOp::Add,
+        ast::BinaryOp::Subtract => BinaryOp::Sub,
+        ast::BinaryOp::Multiply => BinaryOp::Mul,
+        ast::BinaryOp::Divide => BinaryOp::Div,
+        ast::BinaryOp::Modulo => BinaryOp::Mod,
+        ast::BinaryOp::Power => BinaryOp::Pow,
+        ast::BinaryOp::Equal => BinaryOp::Eq,
+        ast::BinaryOp::NotEqual => BinaryOp::Ne,
+        ast::BinaryOp::Less => BinaryOp::Lt,
+        ast::BinaryOp::Greater => BinaryOp::Gt,
+        ast::BinaryOp::LessEq => BinaryOp::Le,
+        ast::BinaryOp::GreaterEq => BinaryOp::Ge,
+        ast::BinaryOp::Range => BinaryOp::Range,
+        ast::BinaryOp::And | ast::BinaryOp::Or => return None,
+    };
+    Some(op)
+}
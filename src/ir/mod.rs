@@ -0,0 +1,519 @@
+pub mod lower;
+pub mod verify;
+
+use crate::lexer::lexer_ana::Span;
+use crate::parser::ast::{DeviceKind, Direction};
+use crate::semantic::types::Type;
+use crate::util::document;
+use std::fmt;
+
+/// A lowered program, one function per `fn` plus the main body
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    // the main body comes first, named `inicio`
+    pub functions: Vec<Function>,
+}
+
+/// Index into `Program::functions`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FuncId(pub usize);
+
+/// Index into `Function::vars`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Var(pub usize);
+
+/// Index into `Function::blocks`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    // `f` or `modulo::f`
+    pub name: String,
+    // the first vars, in order
+    pub params: Vec<Var>,
+    pub ret: Type,
+    pub vars: Vec<VarInfo>,
+    // the entry block is the first
+    pub blocks: Vec<Block>,
+}
+
+impl Function {
+    pub fn var(&self, var: Var) -> &VarInfo {
+        &self.vars[var.0]
+    }
+
+    /// How the dump names a var, temporaries are `%n` counting temporaries only
+    pub fn var_name(&self, var: Var) -> String {
+        match &self.var(var).name {
+            Some(name) => name.clone(),
+            None => {
+                let temps = self.vars[..var.0]
+                    .iter()
+                    .filter(|var| var.is_temp())
+                    .count();
+                format!("%{}", temps)
+            }
+        }
+    }
+
+    pub fn is_entry(&self) -> bool {
+        self.name == "inicio"
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarInfo {
+    // None for temporaries, which are assigned once. Named ones are variables of the program or
+    // of the lowering (`_i`, `_and`) and may be assigned again
+    pub name: Option<String>,
+    pub ty: Type,
+}
+
+impl VarInfo {
+    pub fn is_temp(&self) -> bool {
+        self.name.is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub insts: Vec<Inst>,
+    // where each instruction came from, for runtime errors
+    pub spans: Vec<Span>,
+    pub terminator: Terminator,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Var(Var),
+    Const(Const),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Const {
+    Num(f64),
+    Text(String),
+    Bool(bool),
+    // a defined device, by name
+    Device(DeviceKind, String),
+}
+
+impl Const {
+    pub fn ty(&self) -> Type {
+        match self {
+            Const::Num(_) => Type::Num,
+            Const::Text(_) => Type::Text,
+            Const::Bool(_) => Type::Bool,
+            Const::Device(kind, _) => device_type(*kind),
+        }
+    }
+}
+
+pub fn device_type(kind: DeviceKind) -> Type {
+    match kind {
+        DeviceKind::Machine => Type::Machine,
+        DeviceKind::Concentrator => Type::Concentrator,
+        DeviceKind::Coaxial => Type::Coaxial,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inst {
+    // dest = value
+    Copy {
+        dest: Var,
+        value: Operand,
+    },
+    Unary {
+        dest: Var,
+        op: UnaryOp,
+        operand: Operand,
+    },
+    Binary {
+        dest: Var,
+        op: BinaryOp,
+        left: Operand,
+        right: Operand,
+    },
+    // functions returning nothing have no dest
+    Call {
+        dest: Option<Var>,
+        function: FuncId,
+        args: Vec<Operand>,
+    },
+    Intrinsic {
+        dest: Option<Var>,
+        intrinsic: Intrinsic,
+        args: Vec<Operand>,
+    },
+}
+
+impl Inst {
+    pub fn dest(&self) -> Option<Var> {
+        match self {
+            Inst::Copy { dest, .. } | Inst::Unary { dest, .. } | Inst::Binary { dest, .. } => {
+                Some(*dest)
+            }
+            Inst::Call { dest, .. } | Inst::Intrinsic { dest, .. } => *dest,
+        }
+    }
+
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Inst::Copy { value, .. } => vec![value],
+            Inst::Unary { operand, .. } => vec![operand],
+            Inst::Binary { left, right, .. } => vec![left, right],
+            Inst::Call { args, .. } | Inst::Intrinsic { args, .. } => args.iter().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+// typed, `+` on text is `concat`. `&&` and `||` are branches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    Concat,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Range,
+}
+
+/// What the runtime does for the program, the network commands, the queries and the bits of
+/// the language that aren't arithmetic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Intrinsic {
+    // `define`, before the main body: name and port count or length
+    DefineMachine,
+    DefineConcentrator,
+    DefineCoaxial,
+    // coloca(m1, 2, 3) and coloca(m1, derecha, c1)
+    Place,
+    PlaceNextTo(Direction),
+    PlaceCoaxial(Direction),
+    // colocaCoaxialConcentrador
+    AttachSegment,
+    ConnectPort,
+    AssignPort,
+    // maquinaCoaxial, asignaMaquinaCoaxial
+    AttachCoax,
+    AssignCoax,
+    // puertos(c1), the port count, `c1.puertos` is the field
+    QueryPorts,
+    QueryReaches,
+    QueryHops,
+    QueryLayout,
+    // `x.pos`, `pos(x)` and the other queries that read the same
+    Field(Field),
+    // list[i]
+    Index,
+    RangeStart,
+    RangeEnd,
+    // every declared device of a kind, in declaration order
+    Devices(DeviceKind),
+    // escribe, any number of values
+    Write,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Pos,
+    Present,
+    Complete,
+    Available,
+    Ports,
+    Length,
+    X,
+    Y,
+    Number,
+    Machine,
+}
+
+impl Field {
+    /// The field a member or query spelled in the school profile reads
+    pub fn from_name(name: &str) -> Option<Field> {
+        let field = match name {
+            "pos" => Field::Pos,
+            "presente" => Field::Present,
+            "completo" => Field::Complete,
+            "disponibles" => Field::Available,
+            "puertos" => Field::Ports,
+            "longitud" => Field::Length,
+            "x" => Field::X,
+            "y" => Field::Y,
+            "numero" => Field::Number,
+            "maquina" => Field::Machine,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    /// The school spelling, as the interpreter names members
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Pos => "pos",
+            Field::Present => "presente",
+            Field::Complete => "completo",
+            Field::Available => "disponibles",
+            Field::Ports => "puertos",
+            Field::Length => "longitud",
+            Field::X => "x",
+            Field::Y => "y",
+            Field::Number => "numero",
+            Field::Machine => "maquina",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch {
+        condition: Operand,
+        then: BlockId,
+        otherwise: BlockId,
+    },
+    Return(Option<Operand>),
+    // the end of a function that returns on every path
+    Unreachable,
+}
+
+impl Terminator {
+    pub fn targets(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch {
+                then, otherwise, ..
+            } => vec![*then, *otherwise],
+            Terminator::Return(_) | Terminator::Unreachable => vec![],
+        }
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, function) in self.functions.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write_function(self, function, f)?;
+        }
+        Ok(())
+    }
+}
+
+// fn area(w: num, h: num) -> num {, the variables, then the blocks
+fn write_function(program: &Program, function: &Function, f: &mut fmt::Formatter) -> fmt::Result {
+    let params: Vec<String> = function
+        .params
+        .iter()
+        .map(|param| format!("{}: {}", VarName(function, *param), function.var(*param).ty))
+        .collect();
+    write!(f, "fn {}({})", function.name, params.join(", "))?;
+    if function.ret != Type::Nothing {
+        write!(f, " -> {}", function.ret)?;
+    }
+    writeln!(f, " {{")?;
+
+    for (index, var) in function.vars.iter().enumerate() {
+        if var.name.is_some() && !function.params.contains(&Var(index)) {
+            writeln!(f, "    var {}: {}", VarName(function, Var(index)), var.ty)?;
+        }
+    }
+    for (index, block) in function.blocks.iter().enumerate() {
+        writeln!(f, "b{}:", index)?;
+        for inst in &block.insts {
+            write!(f, "    ")?;
+            write_inst(program, function, inst, f)?;
+            writeln!(f)?;
+        }
+        write!(f, "    ")?;
+        match &block.terminator {
+            Terminator::Jump(target) => writeln!(f, "jmp b{}", target.0)?,
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => writeln!(
+                f,
+                "br {}, b{}, b{}",
+                Show(function, condition),
+                then.0,
+                otherwise.0
+            )?,
+            Terminator::Return(None) => writeln!(f, "ret")?,
+            Terminator::Return(Some(value)) => writeln!(f, "ret {}", Show(function, value))?,
+            Terminator::Unreachable => writeln!(f, "unreachable")?,
+        }
+    }
+    writeln!(f, "}}")
+}
+
+fn write_inst(
+    program: &Program,
+    function: &Function,
+    inst: &Inst,
+    f: &mut fmt::Formatter,
+) -> fmt::Result {
+    // temporaries get their type where they are defined, variables are listed up top
+    if let Some(dest) = inst.dest() {
+        write!(f, "{}", VarName(function, dest))?;
+        if function.var(dest).is_temp() {
+            write!(f, ": {}", function.var(dest).ty)?;
+        }
+        write!(f, " = ")?;
+    }
+    let operands = |args: &[Operand]| -> String {
+        args.iter()
+            .map(|arg| Show(function, arg).to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    match inst {
+        Inst::Copy { value, .. } => write!(f, "{}", Show(function, value)),
+        Inst::Unary { op, operand, .. } => {
+            let op = match op {
+                UnaryOp::Neg => "neg",
+                UnaryOp::Not => "not",
+            };
+            write!(f, "{} {}", op, Show(function, operand))
+        }
+        Inst::Binary {
+            op, left, right, ..
+        } => write!(
+            f,
+            "{} {}, {}",
+            op.mnemonic(),
+            Show(function, left),
+            Show(function, right)
+        ),
+        Inst::Call {
+            function: callee,
+            args,
+            ..
+        } => write!(
+            f,
+            "call {}({})",
+            program.functions[callee.0].name,
+            operands(args)
+        ),
+        Inst::Intrinsic {
+            intrinsic, args, ..
+        } if args.is_empty() => write!(f, "{}", intrinsic),
+        Inst::Intrinsic {
+            intrinsic, args, ..
+        } => write!(f, "{} {}", intrinsic, operands(args)),
+    }
+}
+
+impl BinaryOp {
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "mul",
+            BinaryOp::Div => "div",
+            BinaryOp::Mod => "mod",
+            BinaryOp::Pow => "pow",
+            BinaryOp::Concat => "concat",
+            BinaryOp::Eq => "eq",
+            BinaryOp::Ne => "ne",
+            BinaryOp::Lt => "lt",
+            BinaryOp::Gt => "gt",
+            BinaryOp::Le => "le",
+            BinaryOp::Ge => "ge",
+            BinaryOp::Range => "range",
+        }
+    }
+}
+
+// immediates go after a dot, `place_next_to.right` or `field.pos`
+impl fmt::Display for Intrinsic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Intrinsic::DefineMachine => "define_machine",
+            Intrinsic::DefineConcentrator => "define_concentrator",
+            Intrinsic::DefineCoaxial => "define_coax",
+            Intrinsic::Place => "place",
+            Intrinsic::PlaceNextTo(direction) => {
+                return write!(f, "place_next_to.{}", direction_name(*direction));
+            }
+            Intrinsic::PlaceCoaxial(direction) => {
+                return write!(f, "place_coax.{}", direction_name(*direction));
+            }
+            Intrinsic::AttachSegment => "attach_segment",
+            Intrinsic::ConnectPort => "connect_port",
+            Intrinsic::AssignPort => "assign_port",
+            Intrinsic::AttachCoax => "attach_coax",
+            Intrinsic::AssignCoax => "assign_coax",
+            Intrinsic::QueryPorts => "query_ports",
+            Intrinsic::QueryReaches => "query_reaches",
+            Intrinsic::QueryHops => "query_hops",
+            Intrinsic::QueryLayout => "query_layout",
+            Intrinsic::Field(field) => return write!(f, "field.{}", field.name()),
+            Intrinsic::Index => "index",
+            Intrinsic::RangeStart => "range_start",
+            Intrinsic::RangeEnd => "range_end",
+            Intrinsic::Devices(kind) => {
+                let kind = match kind {
+                    DeviceKind::Machine => "machines",
+                    DeviceKind::Concentrator => "concentrators",
+                    DeviceKind::Coaxial => "coaxial",
+                };
+                return write!(f, "devices.{}", kind);
+            }
+            Intrinsic::Write => "write",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "up",
+        Direction::Down => "down",
+        Direction::Left => "left",
+        Direction::Right => "right",
+    }
+}
+
+struct VarName<'a>(&'a Function, Var);
+
+impl fmt::Display for VarName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.var_name(self.1))
+    }
+}
+
+struct Show<'a>(&'a Function, &'a Operand);
+
+impl fmt::Display for Show<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.1 {
+            Operand::Var(var) => write!(f, "{}", VarName(self.0, *var)),
+            Operand::Const(Const::Num(value)) => write!(f, "{}", value),
+            Operand::Const(Const::Text(text)) => {
+                let mut quoted = String::new();
+                document::write_string(text, &mut quoted);
+                write!(f, "{}", quoted)
+            }
+            Operand::Const(Const::Bool(value)) => write!(f, "{}", value),
+            Operand::Const(Const::Device(_, name)) => write!(f, "@{}", name),
+        }
+    }
+}
@@ -0,0 +1,466 @@
+use crate::ir::{
+    BinaryOp, BlockId, Field, Function, Inst, Intrinsic, Operand, Program, Terminator, UnaryOp,
+    Var, device_type,
+};
+use crate::semantic::types::Type;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyError {
+    pub function: String,
+    // None for a problem with the function as a whole
+    pub block: Option<BlockId>,
+    pub message: String,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.block {
+            Some(block) => write!(f, "{}, b{}: {}", self.function, block.0, self.message),
+            None => write!(f, "{}: {}", self.function, self.message),
+        }
+    }
+}
+
+impl Error for VerifyError {}
+
+/// Checks the invariants later passes rely on: blocks end in a terminator that jumps to blocks
+/// that exist, every block is reachable, temporaries are assigned once, nothing is read before
+/// it is assigned on every path and each operation gets the types it works on
+pub fn verify(program: &Program) -> Vec<VerifyError> {
+    let mut errors = Vec::new();
+    match program.functions.first() {
+        Some(main) if main.is_entry() && main.params.is_empty() && main.ret == Type::Nothing => {}
+        Some(main) => errors.push(VerifyError {
+            function: main.name.clone(),
+            block: None,
+            message: "the first function must be inicio, taking and returning nothing".to_string(),
+        }),
+        None => errors.push(VerifyError {
+            function: "inicio".to_string(),
+            block: None,
+            message: "the program has no functions".to_string(),
+        }),
+    }
+    for function in &program.functions {
+        let mut verifier = Verifier {
+            program,
+            function,
+            block: None,
+            errors: Vec::new(),
+        };
+        verifier.verify();
+        errors.extend(verifier.errors);
+    }
+    errors
+}
+
+struct Verifier<'a> {
+    program: &'a Program,
+    function: &'a Function,
+    // the block being looked at
+    block: Option<BlockId>,
+    errors: Vec<VerifyError>,
+}
+
+impl Verifier<'_> {
+    fn error(&mut self, message: String) {
+        self.errors.push(VerifyError {
+            function: self.function.name.clone(),
+            block: self.block,
+            message,
+        });
+    }
+
+    fn verify(&mut self) {
+        let function = self.function;
+        if function.blocks.is_empty() {
+            self.error("has no blocks".to_string());
+            return;
+        }
+        for (index, var) in function.vars.iter().enumerate() {
+            if matches!(var.ty, Type::Nothing | Type::Error) {
+                let name = function.var_name(Var(index));
+                self.error(format!("{} is {}, not a value", name, var.ty));
+            }
+        }
+        for (index, param) in function.params.iter().enumerate() {
+            match function.vars.get(param.0) {
+                Some(var) if !var.is_temp() => {}
+                _ => self.error(format!("parameter {} is not a named variable", index + 1)),
+            }
+        }
+        // the rest indexes vars and blocks freely
+        if !self.structure() {
+            return;
+        }
+
+        for (index, block) in function.blocks.iter().enumerate() {
+            self.block = Some(BlockId(index));
+            for inst in &block.insts {
+                self.inst(inst);
+            }
+            self.terminator(&block.terminator);
+        }
+        self.block = None;
+        self.assignments();
+    }
+
+    // every var and block named exists and every block is reachable
+    fn structure(&mut self) -> bool {
+        let function = self.function;
+        let before = self.errors.len();
+        let var_exists = |operand: &Operand| match operand {
+            Operand::Var(var) => var.0 < function.vars.len(),
+            Operand::Const(_) => true,
+        };
+        for (index, block) in function.blocks.iter().enumerate() {
+            self.block = Some(BlockId(index));
+            if block.spans.len() != block.insts.len() {
+                self.error(format!(
+                    "has {} instructions but {} spans",
+                    block.insts.len(),
+                    block.spans.len()
+                ));
+            }
+            for inst in &block.insts {
+                let dest_exists = inst.dest().is_none_or(|dest| dest.0 < function.vars.len());
+                if !dest_exists || !inst.operands().into_iter().all(var_exists) {
+                    self.error("uses a variable that doesn't exist".to_string());
+                }
+            }
+            let condition_exists = match &block.terminator {
+                Terminator::Branch { condition, .. } => var_exists(condition),
+                Terminator::Return(Some(value)) => var_exists(value),
+                _ => true,
+            };
+            if !condition_exists {
+                self.error("uses a variable that doesn't exist".to_string());
+            }
+            for target in block.terminator.targets() {
+                if target.0 >= function.blocks.len() {
+                    self.error(format!("jumps to b{}, which doesn't exist", target.0));
+                }
+            }
+        }
+        self.block = None;
+        if self.errors.len() > before {
+            return false;
+        }
+
+        let reachable = reachable(function);
+        for (index, reachable) in reachable.iter().enumerate() {
+            if !reachable {
+                self.block = Some(BlockId(index));
+                self.error("is unreachable".to_string());
+            }
+        }
+        self.block = None;
+        self.errors.len() == before
+    }
+
+    fn ty(&self, operand: &Operand) -> Type {
+        match operand {
+            Operand::Var(var) => self.function.var(*var).ty.clone(),
+            Operand::Const(constant) => constant.ty(),
+        }
+    }
+
+    fn name(&self, var: Var) -> String {
+        self.function.var_name(var)
+    }
+
+    fn expect(&mut self, what: &str, found: &Type, expected: &Type) {
+        if found != expected {
+            self.error(format!("{} is {}, expected {}", what, found, expected));
+        }
+    }
+
+    fn inst(&mut self, inst: &Inst) {
+        let dest = inst.dest().map(|dest| self.function.var(dest).ty.clone());
+        match inst {
+            Inst::Copy { dest: var, value } => {
+                let found = self.ty(value);
+                let what = format!("the value copied to {}", self.name(*var));
+                self.expect(&what, &found, dest.as_ref().expect("copies have a dest"));
+            }
+            Inst::Unary { op, operand, .. } => {
+                let ty = match op {
+                    UnaryOp::Neg => Type::Num,
+                    UnaryOp::Not => Type::Bool,
+                };
+                let found = self.ty(operand);
+                self.expect("the operand", &found, &ty);
+                self.expect("the result", dest.as_ref().expect("has a dest"), &ty);
+            }
+            Inst::Binary {
+                op, left, right, ..
+            } => {
+                let (left, right) = (self.ty(left), self.ty(right));
+                let (operands, result) = match op {
+                    BinaryOp::Add
+                    | BinaryOp::Sub
+                    | BinaryOp::Mul
+                    | BinaryOp::Div
+                    | BinaryOp::Mod
+                    | BinaryOp::Pow => (Type::Num, Type::Num),
+                    BinaryOp::Concat => (Type::Text, Type::Text),
+                    BinaryOp::Lt | BinaryOp::Gt | BinaryOp::Le | BinaryOp::Ge => {
+                        (Type::Num, Type::Bool)
+                    }
+                    BinaryOp::Range => (Type::Num, Type::Range),
+                    // any two values of one type compare
+                    BinaryOp::Eq | BinaryOp::Ne => (left.clone(), Type::Bool),
+                };
+                let what = format!("the left side of {}", op.mnemonic());
+                self.expect(&what, &left, &operands);
+                let what = format!("the right side of {}", op.mnemonic());
+                self.expect(&what, &right, &operands);
+                self.expect("the result", dest.as_ref().expect("has a dest"), &result);
+            }
+            Inst::Call { function, args, .. } => {
+                let Some(callee) = self.program.functions.get(function.0) else {
+                    self.error(format!(
+                        "calls function {}, which doesn't exist",
+                        function.0
+                    ));
+                    return;
+                };
+                if args.len() != callee.params.len() {
+                    self.error(format!(
+                        "{} takes {} arguments, found {}",
+                        callee.name,
+                        callee.params.len(),
+                        args.len()
+                    ));
+                    return;
+                }
+                for (index, (arg, param)) in args.iter().zip(&callee.params).enumerate() {
+                    let found = self.ty(arg);
+                    let what = format!("argument {} of {}", index + 1, callee.name);
+                    self.expect(&what, &found, &callee.var(*param).ty);
+                }
+                match dest {
+                    Some(_) if callee.ret == Type::Nothing => {
+                        self.error(format!("{} returns nothing to assign", callee.name));
+                    }
+                    Some(dest) => self.expect("the result", &dest, &callee.ret),
+                    None => {}
+                }
+            }
+            Inst::Intrinsic {
+                intrinsic, args, ..
+            } => {
+                let args: Vec<Type> = args.iter().map(|arg| self.ty(arg)).collect();
+                match intrinsic_type(*intrinsic, &args) {
+                    Err(message) => self.error(format!("{}: {}", intrinsic, message)),
+                    Ok(None) if dest.is_some() => {
+                        self.error(format!("{} gives nothing to assign", intrinsic));
+                    }
+                    Ok(Some(result)) => {
+                        if let Some(dest) = dest {
+                            self.expect("the result", &dest, &result);
+                        }
+                    }
+                    Ok(None) => {}
+                }
+            }
+        }
+    }
+
+    fn terminator(&mut self, terminator: &Terminator) {
+        match terminator {
+            Terminator::Branch { condition, .. } => {
+                let found = self.ty(condition);
+                self.expect("the condition", &found, &Type::Bool);
+            }
+            Terminator::Return(None) if self.function.ret != Type::Nothing => {
+                self.error(format!("returns nothing, expected {}", self.function.ret));
+            }
+            Terminator::Return(Some(value)) => {
+                let found = self.ty(value);
+                let expected = self.function.ret.clone();
+                self.expect("the returned value", &found, &expected);
+            }
+            Terminator::Jump(_) | Terminator::Return(None) | Terminator::Unreachable => {}
+        }
+    }
+
+    // temporaries are assigned once, and whatever is read was assigned on every path to it
+    fn assignments(&mut self) {
+        let function = self.function;
+        let vars = function.vars.len();
+        let mut defined = vec![0; vars];
+        for block in &function.blocks {
+            for inst in &block.insts {
+                if let Some(dest) = inst.dest() {
+                    defined[dest.0] += 1;
+                }
+            }
+        }
+        for param in &function.params {
+            defined[param.0] += 1;
+        }
+        for (index, count) in defined.iter().enumerate() {
+            if function.vars[index].is_temp() && *count > 1 {
+                let name = function.var_name(Var(index));
+                self.error(format!("{} is assigned {} times", name, count));
+            }
+        }
+
+        // what is assigned on entry to each block, worked out until nothing changes
+        let mut entry: Vec<Vec<bool>> = vec![vec![true; vars]; function.blocks.len()];
+        entry[0] = vec![false; vars];
+        for param in &function.params {
+            entry[0][param.0] = true;
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, block) in function.blocks.iter().enumerate() {
+                let mut assigned = entry[index].clone();
+                for inst in &block.insts {
+                    if let Some(dest) = inst.dest() {
+                        assigned[dest.0] = true;
+                    }
+                }
+                for target in block.terminator.targets() {
+                    for (var, assigned) in assigned.iter().enumerate() {
+                        if !assigned && entry[target.0][var] {
+                            entry[target.0][var] = false;
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        for (index, block) in function.blocks.iter().enumerate() {
+            self.block = Some(BlockId(index));
+            let mut assigned = entry[index].clone();
+            for inst in &block.insts {
+                for operand in inst.operands() {
+                    self.assigned(operand, &assigned);
+                }
+                if let Some(dest) = inst.dest() {
+                    assigned[dest.0] = true;
+                }
+            }
+            match &block.terminator {
+                Terminator::Branch { condition, .. } => self.assigned(condition, &assigned),
+                Terminator::Return(Some(value)) => self.assigned(value, &assigned),
+                _ => {}
+            }
+        }
+        self.block = None;
+    }
+
+    fn assigned(&mut self, operand: &Operand, assigned: &[bool]) {
+        if let Operand::Var(var) = operand
+            && !assigned[var.0]
+        {
+            self.error(format!("{} is read before it is assigned", self.name(*var)));
+        }
+    }
+}
+
+fn reachable(function: &Function) -> Vec<bool> {
+    let mut reachable = vec![false; function.blocks.len()];
+    let mut stack = vec![0];
+    while let Some(block) = stack.pop() {
+        if reachable[block] {
+            continue;
+        }
+        reachable[block] = true;
+        let targets = function.blocks[block].terminator.targets();
+        stack.extend(targets.iter().map(|target| target.0));
+    }
+    reachable
+}
+
+/// What an intrinsic gives back for arguments of these types, None when it gives nothing
+pub fn intrinsic_type(intrinsic: Intrinsic, args: &[Type]) -> Result<Option<Type>, String> {
+    let device = [Type::Machine, Type::Concentrator, Type::Coaxial];
+    let (params, result): (Vec<&[Type]>, Option<Type>) = match intrinsic {
+        Intrinsic::DefineMachine => (vec![&[Type::Machine]], None),
+        Intrinsic::DefineConcentrator => (vec![&[Type::Concentrator], &[Type::Num]], None),
+        Intrinsic::DefineCoaxial => (vec![&[Type::Coaxial], &[Type::Num]], None),
+        Intrinsic::Place => (vec![&device[..2], &[Type::Num], &[Type::Num]], None),
+        Intrinsic::PlaceNextTo(_) => (vec![&device[..2], &device], None),
+        Intrinsic::PlaceCoaxial(_) => (vec![&[Type::Coaxial], &[Type::Num], &[Type::Num]], None),
+        Intrinsic::AttachSegment => (vec![&[Type::Coaxial], &[Type::Concentrator]], None),
+        Intrinsic::ConnectPort => (
+            vec![&[Type::Machine], &[Type::Concentrator], &[Type::Num]],
+            None,
+        ),
+        Intrinsic::AssignPort => (vec![&[Type::Machine], &[Type::Concentrator]], None),
+        Intrinsic::AttachCoax => (vec![&[Type::Machine], &[Type::Coaxial], &[Type::Num]], None),
+        Intrinsic::AssignCoax => (vec![&[Type::Machine], &[Type::Coaxial]], None),
+        Intrinsic::QueryPorts => (vec![&[Type::Concentrator]], Some(Type::Num)),
+        Intrinsic::QueryReaches => (vec![&device, &device], Some(Type::Bool)),
+        Intrinsic::QueryHops => (vec![&device, &device], Some(Type::Num)),
+        Intrinsic::QueryLayout => (vec![], Some(Type::Text)),
+        Intrinsic::RangeStart | Intrinsic::RangeEnd => (vec![&[Type::Range]], Some(Type::Num)),
+        Intrinsic::Devices(kind) => (vec![], Some(Type::List(Box::new(device_type(kind))))),
+        Intrinsic::Write => {
+            return match args.iter().position(|arg| *arg == Type::Nothing) {
+                Some(index) => Err(format!("argument {} is nothing", index + 1)),
+                None => Ok(None),
+            };
+        }
+        Intrinsic::Index => {
+            return match args {
+                [Type::List(element), Type::Num] => Ok(Some(*element.clone())),
+                [list, index] => Err(format!("cannot index {} with {}", list, index)),
+                _ => Err(format!("takes 2 arguments, found {}", args.len())),
+            };
+        }
+        Intrinsic::Field(field) => {
+            let [object] = args else {
+                return Err(format!("takes 1 argument, found {}", args.len()));
+            };
+            return field_type(object, field)
+                .map(Some)
+                .ok_or_else(|| format!("{} has no field {}", object, field.name()));
+        }
+    };
+
+    if args.len() != params.len() {
+        return Err(format!(
+            "takes {} arguments, found {}",
+            params.len(),
+            args.len()
+        ));
+    }
+    for (index, (arg, accepted)) in args.iter().zip(&params).enumerate() {
+        if !accepted.contains(arg) {
+            let names: Vec<String> = accepted.iter().map(Type::to_string).collect();
+            return Err(format!(
+                "argument {} is {}, expected {}",
+                index + 1,
+                arg,
+                names.join(" or ")
+            ));
+        }
+    }
+    Ok(result)
+}
+
+// members and the queries that read like them, `presente(c1)` works on any device
+fn field_type(object: &Type, field: Field) -> Option<Type> {
+    let ty = match (object, field) {
+        (device, Field::Pos) if device.is_device() => Type::Position,
+        (device, Field::Present) if device.is_device() => Type::Bool,
+        (Type::Concentrator | Type::Coaxial, Field::Complete) => Type::Bool,
+        (Type::Concentrator, Field::Available) => Type::Num,
+        (Type::Concentrator, Field::Ports) => Type::List(Box::new(Type::Port)),
+        (Type::Coaxial | Type::List(_), Field::Length) => Type::Num,
+        (Type::Position, Field::X | Field::Y) => Type::Num,
+        (Type::Port, Field::Number) => Type::Num,
+        (Type::Port, Field::Present) => Type::Bool,
+        (Type::Port, Field::Machine) => Type::Machine,
+        _ => return None,
+    };
+    Some(ty)
+}
@@ -224,7 +224,6 @@ impl Automata {
 impl<'a> DfaRunner<'a> {
     pub fn new(automata: &'a Automata) -> Self {
         let current_state = automata.initial_state().to_string();
-        println!("DFA Runner started on state: {}", current_state);
 
         DfaRunner {
             automata,
@@ -237,22 +236,14 @@ impl<'a> DfaRunner<'a> {
             Some(next_state) => {
                 self.current_state = next_state.clone();
 
-                let result = if self.automata.is_final_state(&self.current_state) {
+                if self.automata.is_final_state(&self.current_state) {
                     TransitionResult::Accepted
                 } else {
                     TransitionResult::Continue
-                };
-                println!(
-                    "  '{}' -> State: {} ({:?})",
-                    character, self.current_state, result
-                );
-                result
+                }
             }
 
-            None => {
-                println!("  '{}' -> FAILED (no transition)", character);
-                TransitionResult::Reject
-            }
+            None => TransitionResult::Reject,
         }
     }
 
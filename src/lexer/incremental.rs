@@ -0,0 +1,162 @@
+use crate::lexer::lexer_ana::{Lexer, Span, Token, TokenType};
+use std::{borrow::Cow, error::Error, ops::Range};
+
+/// `range` of the old source was replaced by `replacement`
+#[derive(Debug, Clone)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+#[derive(Debug)]
+pub struct Relexed<'src> {
+    pub tokens: Vec<Token<'src>>,
+    /// Indices of the old tokens that were thrown away
+    pub removed: Range<usize>,
+    /// Indices of the freshly lexed tokens in `tokens`
+    pub changed: Range<usize>,
+}
+
+impl<'src> Lexer<'src> {
+    /// Relexes only the part of the file touched by `edit`.
+    ///
+    /// `self` must be a fresh lexer over the source with the edit already applied, built
+    /// with the interner that produced `old_tokens`. Lexing stops as soon as a new token
+    /// lines up with an old one, the rest of the old stream is reused with shifted spans.
+    pub fn relex(
+        &mut self,
+        old_tokens: &[Token<'_>],
+        edit: &TextEdit,
+    ) -> Result<Relexed<'src>, Box<dyn Error>> {
+        let source = self.source();
+        let edit_end = edit.range.start + edit.replacement.len();
+        if edit.range.start > edit.range.end
+            || source.get(edit.range.start..edit_end) != Some(edit.replacement.as_str())
+        {
+            return Err("edit does not match the new source".into());
+        }
+
+        // no token spans a newline, so the start of the edited line is a safe restart
+        // point. When only header lines come before it the edit may have changed the
+        // keyword pragma, and with it how every later word lexes, so the whole file is redone
+        let line_start = source[..edit.range.start].rfind('\n').map_or(0, |i| i + 1);
+        let whole_file = line_start <= header_end(source);
+        let restart = if whole_file { 0 } else { line_start };
+
+        let first = old_tokens.partition_point(|token| token.span.start < restart);
+        let line = match first.checked_sub(1) {
+            Some(previous) => {
+                let previous = &old_tokens[previous].span;
+                previous.line + source[previous.end..restart].matches('\n').count()
+            }
+            None => 1 + source[..restart].matches('\n').count(),
+        };
+        self.restart_at(restart, line);
+
+        let shifted = |start: usize| start + edit_end - edit.range.end;
+        let mut fresh = Vec::new();
+        let mut old_index = first;
+        let resync = loop {
+            let token = self.next_token();
+
+            if !whole_file && token.span.start >= edit_end {
+                // skip old tokens inside the edit or already behind the new one
+                while old_tokens.get(old_index).is_some_and(|old| {
+                    old.span.start < edit.range.end || shifted(old.span.start) < token.span.start
+                }) {
+                    old_index += 1;
+                }
+                if let Some(old) = old_tokens.get(old_index)
+                    && shifted(old.span.start) == token.span.start
+                    && same_token(old, &token)
+                {
+                    break Some((old_index, token.span));
+                }
+            }
+
+            let at_end = token.type_ == TokenType::EndOfFile;
+            fresh.push(token);
+            if at_end {
+                break None;
+            }
+        };
+
+        let mut tokens: Vec<Token<'src>> = old_tokens[..first]
+            .iter()
+            .map(|token| rebase(token, source, token.span))
+            .collect();
+        let changed = tokens.len()..tokens.len() + fresh.len();
+        tokens.extend(fresh);
+
+        let removed = match resync {
+            Some((index, new_span)) => {
+                let old_line = old_tokens[index].span.line;
+                let line_shift = new_span.line as isize - old_line as isize;
+                let column_shift =
+                    new_span.column as isize - old_tokens[index].span.column as isize;
+
+                tokens.extend(old_tokens[index..].iter().map(|token| {
+                    let span = token.span;
+                    // only the rest of the resync line moves sideways
+                    let column = if span.line == old_line {
+                        span.column.saturating_add_signed(column_shift)
+                    } else {
+                        span.column
+                    };
+                    let span = Span::new(
+                        shifted(span.start),
+                        shifted(span.end),
+                        span.line.saturating_add_signed(line_shift),
+                        column,
+                    );
+                    rebase(token, source, span)
+                }));
+                first..index
+            }
+            None => first..old_tokens.len(),
+        };
+
+        Ok(Relexed {
+            tokens,
+            removed,
+            changed,
+        })
+    }
+}
+
+fn same_token(old: &Token<'_>, new: &Token<'_>) -> bool {
+    old.type_ == new.type_
+        && old.span.end - old.span.start == new.span.end - new.span.start
+        && old.lexeme == new.lexeme
+}
+
+// points the lexeme of an unchanged token into the new source
+fn rebase<'src>(token: &Token<'_>, source: &'src str, span: Span) -> Token<'src> {
+    let lexeme = match &token.lexeme {
+        Cow::Owned(text) => Cow::Owned(text.clone()),
+        Cow::Borrowed(_) if token.type_ == TokenType::StringLiteral => {
+            Cow::Borrowed(&source[span.start + 1..span.end - 1])
+        }
+        Cow::Borrowed(_) => Cow::Borrowed(&source[span.start..span.end]),
+    };
+
+    Token {
+        type_: token.type_.clone(),
+        lexeme,
+        span,
+        symbol: token.symbol,
+    }
+}
+
+// end of the leading blank and comment lines, where a `//@keywords` pragma can appear
+fn header_end(source: &str) -> usize {
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if !trimmed.is_empty() && !trimmed.starts_with("//") {
+            break;
+        }
+        offset += line.len();
+    }
+    offset
+}
@@ -0,0 +1,226 @@
+use crate::lexer::lexer_ana::TokenType;
+use std::{fmt, str::FromStr};
+
+use KeywordProfile::{En, Es, School};
+
+/// Which keyword spellings the lexer recognizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeywordProfile {
+    /// English spellings, network statements included (`place`, `connectPort`, ...)
+    En,
+    /// Spanish spellings, including `mientras`, `para`, `regresa`
+    Es,
+    /// The mixed set used by the course material, the default
+    School,
+}
+
+impl KeywordProfile {
+    pub const ALL: [KeywordProfile; 3] = [En, Es, School];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            En => "en",
+            Es => "es",
+            School => "school",
+        }
+    }
+
+    /// Parses a comma separated list like `en,school`
+    pub fn parse_list(list: &str) -> Result<Vec<KeywordProfile>, String> {
+        let profiles = list
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if profiles.is_empty() {
+            return Err("no keyword profile given".to_string());
+        }
+        Ok(profiles)
+    }
+}
+
+impl fmt::Display for KeywordProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for KeywordProfile {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        KeywordProfile::ALL
+            .into_iter()
+            .find(|profile| profile.name() == name)
+            .ok_or_else(|| {
+                format!(
+                    "unknown keyword profile '{}' (expected one of: en, es, school)",
+                    name
+                )
+            })
+    }
+}
+
+pub struct Keyword {
+    pub spelling: &'static str,
+    pub token: TokenType,
+    pub profiles: &'static [KeywordProfile],
+}
+
+const fn kw(
+    spelling: &'static str,
+    token: TokenType,
+    profiles: &'static [KeywordProfile],
+) -> Keyword {
+    Keyword {
+        spelling,
+        token,
+        profiles,
+    }
+}
+
+// every spelling of every profile, pre-interned in this order so a keyword
+// candidate is any symbol below KEYWORDS.len()
+pub const KEYWORDS: &[Keyword] = &[
+    kw("let", TokenType::KeywordLet, &[En, School]),
+    kw("if", TokenType::KeywordIf, &[En, School]),
+    kw("else", TokenType::KeywordElse, &[En, School]),
+    kw("for", TokenType::KeywordFor, &[En, School]),
+    kw("while", TokenType::KeywordWhile, &[En, School]),
+    kw("return", TokenType::KeywordReturn, &[En, School]),
+    kw("fn", TokenType::KeywordFn, &[En, School]),
+    kw("in", TokenType::KeywordIn, &[En, School]),
+    kw("break", TokenType::KeywordBreak, &[En, School]),
+    kw("continue", TokenType::KeywordContinue, &[En, School]),
+    kw("define", TokenType::Define_, &[En, Es, School]),
+    kw("programa", TokenType::Programa_, &[Es, School]),
+    kw("inicio", TokenType::Inicio_, &[Es, School]),
+    kw("fin", TokenType::Fin_, &[Es, School]),
+    kw("modulo", TokenType::Modulo_, &[Es, School]),
+    kw("maquinas", TokenType::Maquinas_, &[Es, School]),
+    kw("concentradores", TokenType::Concentradores_, &[Es, School]),
+    kw("coaxial", TokenType::Coaxial_, &[En, Es, School]),
+    kw("coloca", TokenType::Coloca_, &[Es, School]),
+    kw("colocaCoaxial", TokenType::ColocaCoaxial_, &[Es, School]),
+    kw(
+        "colocaCoaxialConcentrador",
+        TokenType::ColocaCoaxialConcentrador_,
+        &[Es, School],
+    ),
+    kw(
+        "uneMaquinaPuerto",
+        TokenType::UneMaquinaPuerto_,
+        &[Es, School],
+    ),
+    kw("asignaPuerto", TokenType::AsignaPuerto_, &[Es, School]),
+    kw("maquinaCoaxial", TokenType::MaquinaCoaxial_, &[Es, School]),
+    kw(
+        "asignaMaquinaCoaxial",
+        TokenType::AsignaMaquinaCoaxial_,
+        &[Es, School],
+    ),
+    kw("escribe", TokenType::Escribe_, &[Es, School]),
+    kw("si", TokenType::Si_, &[Es, School]),
+    kw("sino", TokenType::Sino_, &[Es, School]),
+    kw("arriba", TokenType::Arriba_, &[Es, School]),
+    kw("abajo", TokenType::Abajo_, &[Es, School]),
+    kw("izquierda", TokenType::Izquierda_, &[Es, School]),
+    kw("derecha", TokenType::Derecha_, &[Es, School]),
+    kw("puertos", TokenType::Puertos_, &[Es, School]),
+    kw("disponibles", TokenType::Disponibles_, &[Es, School]),
+    kw("presente", TokenType::Presente_, &[Es, School]),
+    kw("longitud", TokenType::Longitud_, &[Es, School]),
+    kw("completo", TokenType::Completo_, &[Es, School]),
+    kw("num", TokenType::Num_, &[En, Es, School]),
+    kw("maquina", TokenType::Maquina_, &[Es, School]),
+    kw("pos", TokenType::Pos_, &[En, Es, School]),
+    kw("alcanza", TokenType::Alcanza_, &[Es, School]),
+    kw("saltos", TokenType::Saltos_, &[Es, School]),
+    kw("plano", TokenType::Plano_, &[Es, School]),
+    // spanish spellings for the general purpose keywords
+    kw("sea", TokenType::KeywordLet, &[Es]),
+    kw("para", TokenType::KeywordFor, &[Es]),
+    kw("mientras", TokenType::KeywordWhile, &[Es]),
+    kw("regresa", TokenType::KeywordReturn, &[Es]),
+    kw("funcion", TokenType::KeywordFn, &[Es]),
+    kw("en", TokenType::KeywordIn, &[Es]),
+    kw("rompe", TokenType::KeywordBreak, &[Es]),
+    kw("continua", TokenType::KeywordContinue, &[Es]),
+    // english aliases for the school keywords
+    kw("program", TokenType::Programa_, &[En]),
+    kw("begin", TokenType::Inicio_, &[En]),
+    kw("end", TokenType::Fin_, &[En]),
+    kw("module", TokenType::Modulo_, &[En]),
+    kw("machines", TokenType::Maquinas_, &[En]),
+    kw("concentrators", TokenType::Concentradores_, &[En]),
+    kw("place", TokenType::Coloca_, &[En]),
+    kw("placeCoaxial", TokenType::ColocaCoaxial_, &[En]),
+    kw(
+        "placeCoaxialConcentrator",
+        TokenType::ColocaCoaxialConcentrador_,
+        &[En],
+    ),
+    kw("connectPort", TokenType::UneMaquinaPuerto_, &[En]),
+    kw("assignPort", TokenType::AsignaPuerto_, &[En]),
+    kw("machineCoaxial", TokenType::MaquinaCoaxial_, &[En]),
+    kw(
+        "assignMachineCoaxial",
+        TokenType::AsignaMaquinaCoaxial_,
+        &[En],
+    ),
+    kw("write", TokenType::Escribe_, &[En]),
+    kw("up", TokenType::Arriba_, &[En]),
+    kw("down", TokenType::Abajo_, &[En]),
+    kw("left", TokenType::Izquierda_, &[En]),
+    kw("right", TokenType::Derecha_, &[En]),
+    kw("ports", TokenType::Puertos_, &[En]),
+    kw("available", TokenType::Disponibles_, &[En]),
+    kw("present", TokenType::Presente_, &[En]),
+    kw("length", TokenType::Longitud_, &[En]),
+    kw("complete", TokenType::Completo_, &[En]),
+    kw("machine", TokenType::Maquina_, &[En]),
+    kw("reaches", TokenType::Alcanza_, &[En]),
+    kw("hops", TokenType::Saltos_, &[En]),
+    kw("layout", TokenType::Plano_, &[En]),
+];
+
+/// Token type of every keyword spelling active in `profiles`, indexed like `KEYWORDS`
+pub fn keyword_types(profiles: &[KeywordProfile]) -> Vec<Option<TokenType>> {
+    KEYWORDS
+        .iter()
+        .map(|keyword| {
+            keyword
+                .profiles
+                .iter()
+                .any(|profile| profiles.contains(profile))
+                .then(|| keyword.token.clone())
+        })
+        .collect()
+}
+
+/// Spelling of `token` in the first of `profiles` that has one
+pub fn spelling(token: &TokenType, profiles: &[KeywordProfile]) -> Option<&'static str> {
+    profiles.iter().find_map(|profile| {
+        KEYWORDS
+            .iter()
+            .find(|keyword| keyword.token == *token && keyword.profiles.contains(profile))
+            .map(|keyword| keyword.spelling)
+    })
+}
+
+/// Reads a `//@keywords en,school` pragma from the comment header of a file
+pub fn pragma_profiles(source: &str) -> Option<Result<Vec<KeywordProfile>, String>> {
+    for line in source.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let comment = line.strip_prefix("//")?;
+        if let Some(list) = comment.trim_start().strip_prefix("@keywords") {
+            return Some(KeywordProfile::parse_list(list));
+        }
+    }
+    None
+}
@@ -1,8 +1,15 @@
-use std::{collections::HashMap, error::Error, fmt, fs};
-// use crate::lexer::helper::lexer_helper;
 use crate::lexer::automaton::{Automata, DfaRunner, TransitionResult};
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use crate::lexer::helper::lexer_helper;
+use crate::lexer::keywords::{self, KEYWORDS, KeywordProfile};
+use crate::util::interner::{Interner, Symbol};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt, fs,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Span {
     pub start: usize,
     pub end: usize,
@@ -19,6 +26,14 @@ impl Span {
             column,
         }
     }
+
+    /// Span from the start of `self` to the end of `end`
+    pub fn to(self, end: Span) -> Span {
+        Span {
+            end: end.end.max(self.end),
+            ..self
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -27,6 +42,7 @@ pub enum ErrorType {
     UnterminatedString,
     InvalidNumber,
     InvalidEscape,
+    InvalidPragma,
     Unknown,
 }
 
@@ -60,6 +76,52 @@ impl LexerError {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct LexerWarning {
+    pub message: String,
+    pub span: Span,
+    // canonical keyword spelling to use instead
+    pub suggestion: Option<&'static str>,
+    // the identifier a "did you mean" hint is about
+    pub name: Option<Symbol>,
+}
+
+impl LexerWarning {
+    pub fn new(message: String, span: Span, suggestion: Option<&'static str>) -> Self {
+        LexerWarning {
+            message,
+            span,
+            suggestion,
+            name: None,
+        }
+    }
+
+    pub fn display(&self, source: &str) {
+        eprintln!(
+            "\n Lexical warning on line {}:{}",
+            self.span.line, self.span.column
+        );
+        eprintln!("     {}", self.message);
+
+        if let Some(line_text) = source.lines().nth(self.span.line - 1) {
+            eprintln!("\n   {}", line_text);
+            eprintln!("   {}^", " ".repeat(self.span.column - 1));
+        }
+        if let Some(suggestion) = self.suggestion {
+            eprintln!("   help: did you mean '{}'?", suggestion);
+        }
+    }
+}
+
+// how an identifier relates to the active keywords, computed once per symbol
+#[derive(Debug, Clone, Copy)]
+enum KeywordHint {
+    // same spelling as KEYWORDS[index] up to letter case
+    CaseMismatch(usize),
+    // a few edits away from KEYWORDS[index]
+    Close(usize),
+}
+
 // const FILE_PATH: &str = "main.kin";
 
 #[derive(Debug, Clone, PartialEq)]
@@ -71,6 +133,9 @@ pub enum TokenType {
     KeywordWhile,
     KeywordReturn,
     KeywordFn,
+    KeywordIn,
+    KeywordBreak,
+    KeywordContinue,
 
     Identifier,
     Integer,
@@ -88,6 +153,13 @@ pub enum TokenType {
     OperatorGreater,   // >
     OperatorLessEq,    // <=
     OperatorGreaterEq, // >=
+    OperatorModulo,    // %
+    OperatorPower,     // **
+
+    OperatorPlusAssign,     // +=
+    OperatorMinusAssign,    // -=
+    OperatorMultiplyAssign, // *=
+    OperatorDivideAssign,   // /=
 
     ParenthesisLeft,  // (
     ParenthesisRight, // )
@@ -98,6 +170,10 @@ pub enum TokenType {
     Semicolon, // ;
     Comma,     // ,
     Dot,
+    DotDot,      // ..
+    Colon,       // :
+    DoubleColon, // ::
+    Arrow,       // ->
     Or,
     Not,
     And,
@@ -136,6 +212,9 @@ pub enum TokenType {
     Num_,
     Maquina_,
     Pos_,
+    Alcanza_,
+    Saltos_,
+    Plano_,
 }
 
 impl fmt::Display for TokenType {
@@ -144,135 +223,257 @@ impl fmt::Display for TokenType {
     }
 }
 
+// every operator and punctuation spelling, longer spellings first so the scanner
+// always takes the longest match ("**" before "*", "<=" before "<")
+pub const PUNCTUATION: &[(&str, TokenType)] = &[
+    ("**", TokenType::OperatorPower),
+    ("+=", TokenType::OperatorPlusAssign),
+    ("-=", TokenType::OperatorMinusAssign),
+    ("*=", TokenType::OperatorMultiplyAssign),
+    ("/=", TokenType::OperatorDivideAssign),
+    ("==", TokenType::OperatorEqual),
+    ("!=", TokenType::OperatorNotEqual),
+    ("<>", TokenType::OperatorNotEqual),
+    ("<=", TokenType::OperatorLessEq),
+    (">=", TokenType::OperatorGreaterEq),
+    ("->", TokenType::Arrow),
+    ("::", TokenType::DoubleColon),
+    ("..", TokenType::DotDot),
+    ("&&", TokenType::And),
+    ("||", TokenType::Or),
+    ("(", TokenType::ParenthesisLeft),
+    (")", TokenType::ParenthesisRight),
+    ("{", TokenType::BraceLeft),
+    ("}", TokenType::BraceRight),
+    ("[", TokenType::BracketLeft),
+    ("]", TokenType::BracketRight),
+    (";", TokenType::Semicolon),
+    (",", TokenType::Comma),
+    (".", TokenType::Dot),
+    (":", TokenType::Colon),
+    ("+", TokenType::OperatorPlus),
+    ("-", TokenType::OperatorMinus),
+    ("*", TokenType::OperatorMultiply),
+    ("/", TokenType::OperatorDivide),
+    ("%", TokenType::OperatorModulo),
+    ("=", TokenType::OperatorAssign),
+    ("!", TokenType::Not),
+    ("<", TokenType::OperatorLess),
+    (">", TokenType::OperatorGreater),
+];
+
 #[derive(Debug, Clone)]
-pub struct Token {
+pub struct Token<'src> {
     pub type_: TokenType,
-    pub lexeme: String,
+    // borrowed straight from the source, owned only for strings with escapes
+    pub lexeme: Cow<'src, str>,
     pub span: Span,
+    // set for identifiers and keywords
+    pub symbol: Option<Symbol>,
 }
 
-impl Token {
-    pub fn new(type_: TokenType, lexeme: String, span: Span) -> Self {
+impl<'src> Token<'src> {
+    pub fn new(type_: TokenType, lexeme: Cow<'src, str>, span: Span) -> Self {
         Token {
             type_,
             lexeme,
             span,
+            symbol: None,
+        }
+    }
+
+    pub fn word(type_: TokenType, lexeme: &'src str, symbol: Symbol, span: Span) -> Self {
+        Token {
+            type_,
+            lexeme: Cow::Borrowed(lexeme),
+            span,
+            symbol: Some(symbol),
         }
     }
 
     pub fn eof(position: usize, line: usize, column: usize) -> Self {
         Token {
             type_: TokenType::EndOfFile,
-            lexeme: String::new(),
+            lexeme: Cow::Borrowed(""),
             span: Span::new(position, position, line, column),
+            symbol: None,
         }
     }
 
-    pub fn error(lexeme: String, span: Span) -> Self {
+    pub fn error(lexeme: &'src str, span: Span) -> Self {
         Token {
             type_: TokenType::Unknown,
-            lexeme,
+            lexeme: Cow::Borrowed(lexeme),
             span,
+            symbol: None,
+        }
+    }
+
+    /// Detaches the token from the source buffer
+    pub fn into_owned(self) -> Token<'static> {
+        Token {
+            type_: self.type_,
+            lexeme: Cow::Owned(self.lexeme.into_owned()),
+            span: self.span,
+            symbol: self.symbol,
         }
     }
 }
 
-pub struct Lexer {
-    source: Vec<char>,
-    source_string: String,
+#[derive(Debug, Clone)]
+pub struct LexerOptions {
+    // a `//@keywords` pragma in the file takes precedence over these
+    pub profiles: Vec<KeywordProfile>,
+    // `ColocaCoaxial` still lexes as a keyword, with a warning
+    pub case_insensitive_keywords: bool,
+    // errors past this many are counted but not kept, None keeps them all
+    pub max_errors: Option<usize>,
+}
+
+impl Default for LexerOptions {
+    fn default() -> Self {
+        LexerOptions {
+            profiles: vec![KeywordProfile::School],
+            case_insensitive_keywords: false,
+            max_errors: Some(100),
+        }
+    }
+}
+
+pub struct Lexer<'src> {
+    source: &'src str,
+    // byte offset into `source`, always on a char boundary
     current_index: usize,
     line: usize,
     column: usize,
 
-    peeked_token: Option<Token>,
+    peeked_token: Option<Token<'src>>,
+    finished: bool,
 
     identifier_dfa: Automata,
     integer_dfa: Automata,
     float_dfa: Automata,
 
-    keywords: HashMap<String, TokenType>,
+    interner: Interner,
+    // indexed by symbol, None for spellings outside the active profiles
+    keyword_types: Vec<Option<TokenType>>,
+    profiles: Vec<KeywordProfile>,
+    case_insensitive_keywords: bool,
+    keyword_hints: HashMap<Symbol, Option<KeywordHint>>,
+    suggested: HashSet<Symbol>,
     errors: Vec<LexerError>,
+    max_errors: Option<usize>,
+    suppressed_errors: usize,
+    warnings: Vec<LexerWarning>,
 }
 
-impl Lexer {
-    pub fn from_file(file_path: &str) -> Result<Self, Box<dyn Error>> {
-        let source_code = fs::read_to_string(file_path)?;
-        Self::new(&source_code)
+impl<'src> Lexer<'src> {
+    pub fn new(source_code: &'src str) -> Result<Self, Box<dyn Error>> {
+        Self::with_options(source_code, LexerOptions::default())
     }
 
-    pub fn new(source_code: &str) -> Result<Self, Box<dyn Error>> {
+    pub fn with_options(
+        source_code: &'src str,
+        options: LexerOptions,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::with_interner(source_code, Self::keyword_interner(), options)
+    }
+
+    /// Fresh interner holding every keyword spelling
+    pub fn keyword_interner() -> Interner {
+        let words: Vec<&str> = KEYWORDS.iter().map(|keyword| keyword.spelling).collect();
+        Interner::with_words(&words)
+    }
+
+    /// Lexes with an interner shared with earlier files, it must come from `keyword_interner`
+    pub fn with_interner(
+        source_code: &'src str,
+        interner: Interner,
+        options: LexerOptions,
+    ) -> Result<Self, Box<dyn Error>> {
+        let seeded = KEYWORDS.iter().enumerate().all(|(index, keyword)| {
+            interner
+                .get(keyword.spelling)
+                .is_some_and(|symbol| symbol.index() == index)
+        });
+        if !seeded {
+            return Err("interner was not seeded with the keyword table".into());
+        }
+
+        let mut errors = Vec::new();
+        let profiles = match keywords::pragma_profiles(source_code) {
+            Some(Ok(profiles)) => profiles,
+            Some(Err(message)) => {
+                errors.push(LexerError::new(
+                    message,
+                    Span::new(0, 0, 1, 1),
+                    ErrorType::InvalidPragma,
+                ));
+                options.profiles
+            }
+            None => options.profiles,
+        };
+
         let identifier_dfa = Self::load_identifier_dfa()?;
         let integer_dfa = Self::load_integer_dfa()?;
         let float_dfa = Self::load_float_dfa()?;
-        let mut keywords = HashMap::new();
-
-        keywords.insert("let".to_string(), TokenType::KeywordLet);
-        keywords.insert("if".to_string(), TokenType::KeywordIf);
-        keywords.insert("else".to_string(), TokenType::KeywordElse);
-        keywords.insert("for".to_string(), TokenType::KeywordFor);
-        keywords.insert("while".to_string(), TokenType::KeywordWhile);
-        keywords.insert("return".to_string(), TokenType::KeywordReturn);
-        keywords.insert("fn".to_string(), TokenType::KeywordFn);
-        keywords.insert("define".to_string(), TokenType::Define_);
-        keywords.insert("programa".to_string(), TokenType::Programa_);
-        keywords.insert("inicio".to_string(), TokenType::Inicio_);
-        keywords.insert("fin".to_string(), TokenType::Fin_);
-        keywords.insert("modulo".to_string(), TokenType::Modulo_);
-        keywords.insert("maquinas".to_string(), TokenType::Maquinas_);
-        keywords.insert("concentradores".to_string(), TokenType::Concentradores_);
-        keywords.insert("coaxial".to_string(), TokenType::Coaxial_);
-        keywords.insert("coloca".to_string(), TokenType::Coloca_);
-        keywords.insert("colocaCoaxial".to_string(), TokenType::ColocaCoaxial_);
-        keywords.insert(
-            "colocaCoaxialConcentrador".to_string(),
-            TokenType::ColocaCoaxialConcentrador_,
-        );
-        keywords.insert("uneMaquinaPuerto".to_string(), TokenType::UneMaquinaPuerto_);
-        keywords.insert("asignaPuerto".to_string(), TokenType::AsignaPuerto_);
-        keywords.insert("maquinaCoaxial".to_string(), TokenType::MaquinaCoaxial_);
-        keywords.insert(
-            "asignaMaquinaCoaxial".to_string(),
-            TokenType::AsignaMaquinaCoaxial_,
-        );
-        keywords.insert("escribe".to_string(), TokenType::Escribe_);
-        keywords.insert("si".to_string(), TokenType::Si_);
-        keywords.insert("sino".to_string(), TokenType::Sino_);
-        keywords.insert("arriba".to_string(), TokenType::Arriba_);
-        keywords.insert("abajo".to_string(), TokenType::Abajo_);
-        keywords.insert("izquierda".to_string(), TokenType::Izquierda_);
-        keywords.insert("derecha".to_string(), TokenType::Derecha_);
-        keywords.insert("puertos".to_string(), TokenType::Puertos_);
-        keywords.insert("disponibles".to_string(), TokenType::Disponibles_);
-        keywords.insert("presente".to_string(), TokenType::Presente_);
-        keywords.insert("longitud".to_string(), TokenType::Longitud_);
-        keywords.insert("completo".to_string(), TokenType::Completo_);
-        keywords.insert("num".to_string(), TokenType::Num_);
-        keywords.insert("maquina".to_string(), TokenType::Maquina_);
-        keywords.insert("pos".to_string(), TokenType::Pos_);
-
         Ok(Lexer {
-            source: source_code.chars().collect(),
-            source_string: source_code.to_string(),
+            source: source_code,
             current_index: 0,
             line: 1,
             column: 1,
             peeked_token: None,
+            finished: false,
             identifier_dfa,
             integer_dfa,
             float_dfa,
-            keywords,
-            errors: Vec::new(),
+            interner,
+            keyword_types: keywords::keyword_types(&profiles),
+            profiles,
+            case_insensitive_keywords: options.case_insensitive_keywords,
+            keyword_hints: HashMap::new(),
+            suggested: HashSet::new(),
+            errors,
+            max_errors: options.max_errors,
+            suppressed_errors: 0,
+            warnings: Vec::new(),
         })
     }
 
-    pub fn peek(&mut self) -> &Token {
+    pub fn source(&self) -> &'src str {
+        self.source
+    }
+
+    // moves the cursor to a known line start, used when relexing part of a file
+    pub(crate) fn restart_at(&mut self, index: usize, line: usize) {
+        self.current_index = index;
+        self.line = line;
+        self.column = 1;
+        self.peeked_token = None;
+        self.finished = false;
+    }
+
+    /// Keyword profiles in effect, after the file pragma
+    pub fn profiles(&self) -> &[KeywordProfile] {
+        &self.profiles
+    }
+
+    pub fn interner(&self) -> &Interner {
+        &self.interner
+    }
+
+    pub fn into_interner(self) -> Interner {
+        self.interner
+    }
+
+    pub fn peek(&mut self) -> &Token<'src> {
         if self.peeked_token.is_none() {
             self.peeked_token = Some(self.scan_token());
         }
         self.peeked_token.as_ref().unwrap()
     }
 
-    pub fn next_token(&mut self) -> Token {
+    pub fn next_token(&mut self) -> Token<'src> {
         if let Some(token) = self.peeked_token.take() {
             return token;
         }
@@ -280,7 +481,12 @@ impl Lexer {
     }
 
     pub fn has_errors(&self) -> bool {
-        !self.errors.is_empty()
+        self.error_count() > 0
+    }
+
+    /// Every lexical error seen so far, including the ones past the cap
+    pub fn error_count(&self) -> usize {
+        self.errors.len() + self.suppressed_errors
     }
 
     pub fn get_errors(&self) -> &[LexerError] {
@@ -289,11 +495,38 @@ impl Lexer {
 
     pub fn print_errors(&self) {
         for error in &self.errors {
-            error.display(&self.source_string);
+            error.display(self.source);
+        }
+        if self.suppressed_errors > 0 {
+            eprintln!(
+                "\n ... and {} more lexical errors not shown",
+                self.suppressed_errors
+            );
+        }
+    }
+
+    pub fn has_warnings(&self) -> bool {
+        !self.warnings.is_empty()
+    }
+
+    pub fn get_warnings(&self) -> &[LexerWarning] {
+        &self.warnings
+    }
+
+    /// Drops the keyword hints about names the program declares, `maquina1` or `fine` are
+    /// meant as they are written. Names nothing declares keep their hint
+    pub fn forget_declared(&mut self, declared: &HashSet<Symbol>) {
+        self.warnings
+            .retain(|warning| !warning.name.is_some_and(|name| declared.contains(&name)));
+    }
+
+    pub fn print_warnings(&self) {
+        for warning in &self.warnings {
+            warning.display(self.source);
         }
     }
 
-    fn scan_token(&mut self) -> Token {
+    fn scan_token(&mut self) -> Token<'src> {
         self.skip_whitespace_and_comments();
 
         if self.is_at_end() {
@@ -304,243 +537,15 @@ impl Lexer {
         let start_line = self.line;
         let start_column = self.column;
 
-        match self.peek_char() {
-            '(' => {
-                self.advance();
-                self.make_token(
-                    TokenType::ParenthesisLeft,
-                    "(",
-                    start_index,
-                    start_line,
-                    start_column,
-                )
-            }
-            ')' => {
-                self.advance();
-                self.make_token(
-                    TokenType::ParenthesisRight,
-                    ")",
-                    start_index,
-                    start_line,
-                    start_column,
-                )
-            }
-            '{' => {
-                self.advance();
-                self.make_token(
-                    TokenType::BraceLeft,
-                    "{",
-                    start_index,
-                    start_line,
-                    start_column,
-                )
-            }
-            '}' => {
-                self.advance();
-                self.make_token(
-                    TokenType::BraceRight,
-                    "}",
-                    start_index,
-                    start_line,
-                    start_column,
-                )
-            }
-            '[' => {
-                self.advance();
-                self.make_token(
-                    TokenType::BracketLeft,
-                    "[",
-                    start_index,
-                    start_line,
-                    start_column,
-                )
-            }
-            ']' => {
-                self.advance();
-                self.make_token(
-                    TokenType::BracketRight,
-                    "]",
-                    start_index,
-                    start_line,
-                    start_column,
-                )
-            }
-            '&' => {
-                self.advance();
-                if !self.is_at_end() && self.peek_char() == '&' {
-                    self.advance();
-                    self.make_token(TokenType::And, "&&", start_index, start_line, start_column)
-                } else {
-                    self.report_error(
-                        "Expected '&&'".to_string(),
-                        start_index,
-                        start_line,
-                        start_column,
-                        ErrorType::UnexpectedCharacter,
-                    )
-                }
-            }
-            ';' => {
-                self.advance();
-                self.make_token(
-                    TokenType::Semicolon,
-                    ";",
-                    start_index,
-                    start_line,
-                    start_column,
-                )
-            }
-            ',' => {
-                self.advance();
-                self.make_token(TokenType::Comma, ",", start_index, start_line, start_column)
-            }
-            '.' => {
-                self.advance();
-                self.make_token(TokenType::Dot, ".", start_index, start_line, start_column)
-            }
-            '|' => {
-                self.advance();
-                if !self.is_at_end() && self.peek_char() == '|' {
-                    self.advance();
-                    self.make_token(TokenType::Or, "||", start_index, start_line, start_column)
-                } else {
-                    self.report_error(
-                        "Expected '||'".to_string(),
-                        start_index,
-                        start_line,
-                        start_column,
-                        ErrorType::UnexpectedCharacter,
-                    )
-                }
-            }
-            '+' => {
-                self.advance();
-                self.make_token(
-                    TokenType::OperatorPlus,
-                    "+",
-                    start_index,
-                    start_line,
-                    start_column,
-                )
-            }
-            '-' => {
-                self.advance();
-                self.make_token(
-                    TokenType::OperatorMinus,
-                    "-",
-                    start_index,
-                    start_line,
-                    start_column,
-                )
-            }
-            '*' => {
-                self.advance();
-                self.make_token(
-                    TokenType::OperatorMultiply,
-                    "*",
-                    start_index,
-                    start_line,
-                    start_column,
-                )
-            }
-            '/' => {
-                self.advance();
-                self.make_token(
-                    TokenType::OperatorDivide,
-                    "/",
-                    start_index,
-                    start_line,
-                    start_column,
-                )
-            }
-            // Compound operators
-            '=' => {
-                self.advance();
-                if !self.is_at_end() && self.peek_char() == '=' {
-                    self.advance();
-                    self.make_token(
-                        TokenType::OperatorEqual,
-                        "==",
-                        start_index,
-                        start_line,
-                        start_column,
-                    )
-                } else {
-                    self.make_token(
-                        TokenType::OperatorAssign,
-                        "=",
-                        start_index,
-                        start_line,
-                        start_column,
-                    )
-                }
-            }
-            '!' => {
-                self.advance();
-                if !self.is_at_end() && self.peek_char() == '=' {
-                    self.advance();
-                    self.make_token(
-                        TokenType::OperatorNotEqual,
-                        "!=",
-                        start_index,
-                        start_line,
-                        start_column,
-                    )
-                } else {
-                    self.make_token(TokenType::Not, "!", start_index, start_line, start_column)
-                }
-            }
-            '<' => {
-                self.advance();
-                if !self.is_at_end() && self.peek_char() == '=' {
-                    self.advance();
-                    self.make_token(
-                        TokenType::OperatorLessEq,
-                        "<=",
-                        start_index,
-                        start_line,
-                        start_column,
-                    )
-                } else if !self.is_at_end() && self.peek_char() == '>' {
-                    self.advance();
-                    self.make_token(
-                        TokenType::OperatorNotEqual,
-                        "<>",
-                        start_index,
-                        start_line,
-                        start_column,
-                    )
-                } else {
-                    self.make_token(
-                        TokenType::OperatorLess,
-                        "<",
-                        start_index,
-                        start_line,
-                        start_column,
-                    )
-                }
-            }
-            '>' => {
+        if let Some((text, type_)) = self.match_punctuation() {
+            // punctuation is ascii, one byte per char
+            for _ in 0..text.len() {
                 self.advance();
-                if !self.is_at_end() && self.peek_char() == '=' {
-                    self.advance();
-                    self.make_token(
-                        TokenType::OperatorGreaterEq,
-                        ">=",
-                        start_index,
-                        start_line,
-                        start_column,
-                    )
-                } else {
-                    self.make_token(
-                        TokenType::OperatorGreater,
-                        ">",
-                        start_index,
-                        start_line,
-                        start_column,
-                    )
-                }
             }
+            return self.make_token(type_.clone(), start_index, start_line, start_column);
+        }
+
+        match self.peek_char() {
             // Strings
             '"' => self.scan_string(start_index, start_line, start_column),
             // Numbers
@@ -552,6 +557,7 @@ impl Lexer {
                 {
                     token
                 } else {
+                    self.advance();
                     self.report_error(
                         "Invalid number".to_string(),
                         start_index,
@@ -562,32 +568,64 @@ impl Lexer {
                 }
             }
             // Identifiers & keywords
-            c if c.is_alphabetic() || c == '_' => {
+            c if c.is_ascii_alphabetic() || c == '_' => {
                 if let Some(token) =
                     self.try_match_identifier(start_index, start_line, start_column)
                 {
                     token
                 } else {
-                    self.report_error(
-                        format!("Invalid identifier: '{}'", c),
-                        start_index,
-                        start_line,
-                        start_column,
-                        ErrorType::UnexpectedCharacter,
-                    )
+                    self.scan_invalid_run(start_index, start_line, start_column)
                 }
             }
-            // Unknown character
-            c => {
-                self.advance();
-                self.report_error(
-                    format!("Unknown character: '{}'", c),
-                    start_index,
-                    start_line,
-                    start_column,
-                    ErrorType::UnexpectedCharacter,
-                )
-            }
+            // Unknown characters, including a lone '&' or '|'
+            _ => self.scan_invalid_run(start_index, start_line, start_column),
+        }
+    }
+
+    // consumes the offending char and every following char that can't start a token,
+    // so a run of garbage becomes a single Unknown token and a single diagnostic
+    fn scan_invalid_run(
+        &mut self,
+        start_index: usize,
+        start_line: usize,
+        start_column: usize,
+    ) -> Token<'src> {
+        self.advance();
+        while !self.is_at_end() && !self.at_token_start() {
+            self.advance();
+        }
+
+        let text = &self.source[start_index..self.current_index];
+        let message = match text {
+            "&" => "Expected '&&'".to_string(),
+            "|" => "Expected '||'".to_string(),
+            _ if text.chars().nth(1).is_none() => format!("Unknown character: '{}'", text),
+            _ => format!("Unknown characters: '{}'", text),
+        };
+        self.report_error(
+            message,
+            start_index,
+            start_line,
+            start_column,
+            ErrorType::UnexpectedCharacter,
+        )
+    }
+
+    fn match_punctuation(&self) -> Option<&'static (&'static str, TokenType)> {
+        let rest = &self.source[self.current_index..];
+        PUNCTUATION.iter().find(|(text, _)| rest.starts_with(text))
+    }
+
+    // whether scanning from the cursor yields something other than an Unknown token
+    fn at_token_start(&self) -> bool {
+        if self.match_punctuation().is_some() {
+            return true;
+        }
+        match self.peek_char() {
+            ' ' | '\t' | '\r' | '\n' | '"' => true,
+            c if c.is_ascii_digit() => true,
+            // the identifier DFA decides, it rejects a leading '_'
+            _ => self.longest_match(&self.identifier_dfa).is_some(),
         }
     }
 
@@ -604,9 +642,7 @@ impl Lexer {
                 }
                 '/' => {
                     // Peek to see if it's a comment
-                    if self.current_index + 1 < self.source.len()
-                        && self.source[self.current_index + 1] == '/'
-                    {
+                    if self.source[self.current_index..].starts_with("//") {
                         // Line comment
                         while !self.is_at_end() && self.peek_char() != '\n' {
                             self.advance();
@@ -620,13 +656,23 @@ impl Lexer {
         }
     }
 
-    fn scan_string(&mut self, start_index: usize, start_line: usize, start_column: usize) -> Token {
+    fn scan_string(
+        &mut self,
+        start_index: usize,
+        start_line: usize,
+        start_column: usize,
+    ) -> Token<'src> {
         self.advance(); // Consume "
 
-        let mut value = String::new();
+        let content_start = self.current_index;
+        // only allocated once an escape sequence shows up
+        let mut unescaped: Option<String> = None;
 
         while !self.is_at_end() && self.peek_char() != '"' {
             if self.peek_char() == '\\' {
+                let value = unescaped.get_or_insert_with(|| {
+                    self.source[content_start..self.current_index].to_string()
+                });
                 self.advance();
 
                 if self.is_at_end() {
@@ -638,6 +684,16 @@ impl Lexer {
                         ErrorType::UnterminatedString,
                     );
                 }
+                // an escape does not carry the string onto the next line either
+                if self.peek_char() == '\n' {
+                    return self.report_error(
+                        "Unterminated string before newline".to_string(),
+                        start_index,
+                        start_line,
+                        start_column,
+                        ErrorType::UnterminatedString,
+                    );
+                }
 
                 // Handle escape sequences
                 match self.peek_char() {
@@ -648,11 +704,11 @@ impl Lexer {
                     '"' => value.push('"'),
                     '0' => value.push('\0'),
                     c => {
-                        self.errors.push(LexerError::new(
+                        self.push_error(LexerError::new(
                             format!("Invalid escape sequence '\\{}'", c),
                             Span::new(
                                 self.current_index - 1,
-                                self.current_index + 1,
+                                self.current_index + c.len_utf8(),
                                 self.line,
                                 self.column - 1,
                             ),
@@ -672,7 +728,10 @@ impl Lexer {
                     ErrorType::UnterminatedString,
                 );
             } else {
-                value.push(self.advance());
+                let c = self.advance();
+                if let Some(value) = unescaped.as_mut() {
+                    value.push(c);
+                }
             }
         }
 
@@ -686,11 +745,16 @@ impl Lexer {
             );
         }
 
+        let lexeme = match unescaped {
+            Some(value) => Cow::Owned(value),
+            None => Cow::Borrowed(&self.source[content_start..self.current_index]),
+        };
+
         self.advance(); // Consume "
 
         Token::new(
             TokenType::StringLiteral,
-            value,
+            lexeme,
             Span::new(start_index, self.current_index, start_line, start_column),
         )
     }
@@ -698,14 +762,13 @@ impl Lexer {
     fn make_token(
         &self,
         type_: TokenType,
-        lexeme: &str,
         start_index: usize,
         start_line: usize,
         start_column: usize,
-    ) -> Token {
+    ) -> Token<'src> {
         Token::new(
             type_,
-            lexeme.to_string(),
+            Cow::Borrowed(&self.source[start_index..self.current_index]),
             Span::new(start_index, self.current_index, start_line, start_column),
         )
     }
@@ -717,162 +780,180 @@ impl Lexer {
         start_line: usize,
         start_column: usize,
         error_type: ErrorType,
-    ) -> Token {
+    ) -> Token<'src> {
         let span = Span::new(start_index, self.current_index, start_line, start_column);
 
-        self.errors
-            .push(LexerError::new(message.clone(), span, error_type));
+        self.push_error(LexerError::new(message, span, error_type));
+
+        Token::error(&self.source[start_index..self.current_index], span)
+    }
 
-        Token::error(message, span)
+    fn push_error(&mut self, error: LexerError) {
+        match self.max_errors {
+            Some(max) if self.errors.len() >= max => self.suppressed_errors += 1,
+            _ => self.errors.push(error),
+        }
     }
 
     fn advance(&mut self) -> char {
-        let c = self.source[self.current_index];
-        self.current_index += 1;
+        let c = self.peek_char();
+        self.current_index += c.len_utf8();
         self.column += 1;
         c
     }
 
     fn peek_char(&self) -> char {
-        if self.is_at_end() {
-            '\0'
-        } else {
-            self.source[self.current_index]
-        }
+        self.source[self.current_index..]
+            .chars()
+            .next()
+            .unwrap_or('\0')
     }
 
     fn is_at_end(&self) -> bool {
         self.current_index >= self.source.len()
     }
 
-    fn try_match_identifier(
-        &mut self,
-        start_index: usize,
-        start_line: usize,
-        start_column: usize,
-    ) -> Option<Token> {
-        let save_index = self.current_index;
-
-        let identifier_dfa = self.identifier_dfa.clone();
-        let mut runner = DfaRunner::new(&identifier_dfa);
+    /// Runs `dfa` from the cursor and returns the end of the longest accepted prefix
+    fn longest_match(&self, dfa: &Automata) -> Option<usize> {
+        let mut runner = DfaRunner::new(dfa);
         let mut last_accept_index = None;
 
-        while !self.is_at_end() {
-            let c = self.peek_char();
-            let result = runner.transition(c);
-
-            match result {
+        for (offset, c) in self.source[self.current_index..].char_indices() {
+            match runner.transition(c) {
                 TransitionResult::Reject => break,
                 TransitionResult::Accepted => {
-                    self.advance();
-                    last_accept_index = Some(self.current_index);
-                }
-                TransitionResult::Continue => {
-                    self.advance();
+                    last_accept_index = Some(self.current_index + offset + c.len_utf8());
                 }
+                TransitionResult::Continue => {}
             }
         }
 
-        if let Some(end_index) = last_accept_index {
-            let lexeme: String = self.source[start_index..end_index].iter().collect();
-
-            let token_type = self
-                .keywords
-                .get(&lexeme)
-                .cloned()
-                .unwrap_or(TokenType::Identifier);
+        last_accept_index
+    }
 
-            return Some(Token::new(
-                token_type,
-                lexeme,
-                Span::new(start_index, end_index, start_line, start_column),
-            ));
+    // numbers and identifiers never span lines, so the column just follows the chars
+    fn advance_to(&mut self, end_index: usize) {
+        while self.current_index < end_index {
+            self.advance();
         }
-
-        self.current_index = save_index;
-        None
     }
 
-    fn try_match_integer(
+    fn try_match_identifier(
         &mut self,
         start_index: usize,
         start_line: usize,
         start_column: usize,
-    ) -> Option<Token> {
-        let save_index = self.current_index;
-
-        let integer_dfa = self.integer_dfa.clone();
-        let mut runner = DfaRunner::new(&integer_dfa);
-        let mut last_accept_index = None;
+    ) -> Option<Token<'src>> {
+        let end_index = self.longest_match(&self.identifier_dfa)?;
+        self.advance_to(end_index);
+
+        let lexeme = &self.source[start_index..end_index];
+        let span = Span::new(start_index, end_index, start_line, start_column);
+        let symbol = self.interner.intern(lexeme);
+        if let Some(Some(keyword)) = self.keyword_types.get(symbol.index()) {
+            return Some(Token::word(keyword.clone(), lexeme, symbol, span));
+        }
 
-        while !self.is_at_end() {
-            let c = self.peek_char();
-            let result = runner.transition(c);
+        let hint = self.keyword_hint(symbol, lexeme);
+        if let Some(KeywordHint::CaseMismatch(index)) = hint
+            && self.case_insensitive_keywords
+        {
+            let keyword = &KEYWORDS[index];
+            self.warnings.push(LexerWarning::new(
+                format!(
+                    "Keyword '{}' should be written '{}'",
+                    lexeme, keyword.spelling
+                ),
+                span,
+                Some(keyword.spelling),
+            ));
+            let canonical = self.interner.intern(keyword.spelling);
+            return Some(Token::word(keyword.token.clone(), lexeme, canonical, span));
+        }
 
-            match result {
-                TransitionResult::Reject => break,
-                TransitionResult::Accepted => {
-                    self.advance();
-                    last_accept_index = Some(self.current_index);
-                }
-                TransitionResult::Continue => {
-                    self.advance();
-                }
-            }
+        // once per name is enough to point at the typo
+        if let Some(KeywordHint::CaseMismatch(index) | KeywordHint::Close(index)) = hint
+            && self.suggested.insert(symbol)
+        {
+            let mut warning = LexerWarning::new(
+                format!("'{}' is an identifier, not a keyword", lexeme),
+                span,
+                Some(KEYWORDS[index].spelling),
+            );
+            warning.name = Some(symbol);
+            self.warnings.push(warning);
         }
 
-        if let Some(end_index) = last_accept_index {
-            let lexeme: String = self.source[start_index..end_index].iter().collect();
-            return Some(Token::new(
-                TokenType::Integer,
-                lexeme,
-                Span::new(start_index, end_index, start_line, start_column),
-            ));
+        Some(Token::word(TokenType::Identifier, lexeme, symbol, span))
+    }
+
+    fn keyword_hint(&mut self, symbol: Symbol, lexeme: &str) -> Option<KeywordHint> {
+        if let Some(hint) = self.keyword_hints.get(&symbol) {
+            return *hint;
         }
 
-        self.current_index = save_index;
-        None
+        let active = || {
+            KEYWORDS
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| self.keyword_types[*index].is_some())
+        };
+        let hint = active()
+            .find(|(_, keyword)| keyword.spelling.eq_ignore_ascii_case(lexeme))
+            .map(|(index, _)| KeywordHint::CaseMismatch(index))
+            .or_else(|| {
+                let folded = lexeme.to_ascii_lowercase();
+                let threshold = lexer_helper::typo_threshold(folded.chars().count());
+                if threshold == 0 {
+                    return None;
+                }
+                active()
+                    .map(|(index, keyword)| {
+                        let distance = lexer_helper::edit_distance(
+                            &folded,
+                            &keyword.spelling.to_ascii_lowercase(),
+                        );
+                        (distance, index)
+                    })
+                    .filter(|(distance, _)| *distance <= threshold)
+                    .min()
+                    .map(|(_, index)| KeywordHint::Close(index))
+            });
+
+        self.keyword_hints.insert(symbol, hint);
+        hint
     }
 
-    fn try_match_float(
+    fn try_match_integer(
         &mut self,
         start_index: usize,
         start_line: usize,
         start_column: usize,
-    ) -> Option<Token> {
-        let save_index = self.current_index;
-
-        let float_dfa = self.float_dfa.clone();
-        let mut runner = DfaRunner::new(&float_dfa);
-        let mut last_accept_index = None;
-
-        while !self.is_at_end() {
-            let c = self.peek_char();
-            let result = runner.transition(c);
-
-            match result {
-                TransitionResult::Reject => break,
-                TransitionResult::Accepted => {
-                    self.advance();
-                    last_accept_index = Some(self.current_index);
-                }
-                TransitionResult::Continue => {
-                    self.advance();
-                }
-            }
-        }
-
-        if let Some(end_index) = last_accept_index {
-            let lexeme: String = self.source[start_index..end_index].iter().collect();
-            return Some(Token::new(
-                TokenType::Float,
-                lexeme,
-                Span::new(start_index, end_index, start_line, start_column),
-            ));
-        }
+    ) -> Option<Token<'src>> {
+        let end_index = self.longest_match(&self.integer_dfa)?;
+        self.advance_to(end_index);
+
+        Some(Token::new(
+            TokenType::Integer,
+            Cow::Borrowed(&self.source[start_index..end_index]),
+            Span::new(start_index, end_index, start_line, start_column),
+        ))
+    }
 
-        self.current_index = save_index;
-        None
+    fn try_match_float(
+        &mut self,
+        start_index: usize,
+        start_line: usize,
+        start_column: usize,
+    ) -> Option<Token<'src>> {
+        let end_index = self.longest_match(&self.float_dfa)?;
+        self.advance_to(end_index);
+
+        Some(Token::new(
+            TokenType::Float,
+            Cow::Borrowed(&self.source[start_index..end_index]),
+            Span::new(start_index, end_index, start_line, start_column),
+        ))
     }
 
     fn load_identifier_dfa() -> Result<Automata, Box<dyn Error>> {
@@ -897,6 +978,22 @@ impl Lexer {
     }
 }
 
+impl<'src> Iterator for Lexer<'src> {
+    type Item = Token<'src>;
+
+    // yields the EndOfFile token once, then stops
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let token = self.next_token();
+        if token.type_ == TokenType::EndOfFile {
+            self.finished = true;
+        }
+        Some(token)
+    }
+}
+
 // let tokens: Vec<Token> = vec![
 //     Token { type_: TokenType::KeywordIf, lexeme: "if".to_string(), line: 1 },
 //     Token { type_: TokenType::ParenthesisLeft, lexeme: "(".to_string(), line: 1 },
@@ -1,4 +1,6 @@
 pub mod automaton;
+pub mod incremental;
+pub mod keywords;
 pub mod lexer_ana;
 pub mod helper;
 
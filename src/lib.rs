@@ -1,32 +1,166 @@
+pub mod bytecode;
 pub mod cmd_clap;
 pub mod compiler;
+pub mod interpreter;
+pub mod ir;
 pub mod lexer;
+pub mod network;
 pub mod parser;
+pub mod semantic;
 pub mod util;
 
-use cmd_clap::{Cli, Commands};
+use cmd_clap::{CheckArgs, Cli, Commands, GrammarAction};
+use compiler::Emit;
+use lexer::keywords::KeywordProfile;
+use lexer::lexer_ana::LexerOptions;
+use network::rules::{CoaxialRules, Severity};
+use network::simulation::Scenario;
 use std::error::Error;
 use std::fs;
+use std::io::{self, Write};
 
 
 //cmd to create the executable (run the compiler basically)
-fn make_cmd(mode: &str) -> Result<(), Box<dyn Error>> {
+fn make_cmd(
+    mode: &str,
+    options: LexerOptions,
+    table_parser: bool,
+    rules: &CoaxialRules,
+    emit: Option<Emit>,
+) -> Result<(), Box<dyn Error>> {
     // let main_path = "src/main.kin";
-    compiler::compile(mode);
+    compiler::compile(mode, options, table_parser, rules, emit)
+}
+
+//cmd to check and run a program, or run one `make` compiled
+fn run_cmd(file: &str, options: LexerOptions, rules: &CoaxialRules) -> Result<(), Box<dyn Error>> {
+    if file.ends_with(".ob") {
+        return run_object(file);
+    }
+    let parsed = parser::parser_ana::parse_file(file, options, false)?;
+    let reported = semantic::semantic(&parsed, rules)?;
+    let (output, topology) = interpreter::run(&parsed)?;
+    // what the program printed only shows once the network it built passes
+    semantic::check_network(&parsed, &topology, rules, &reported)?;
+    io::stdout().write_all(&output)?;
+    Ok(())
+}
+
+// checks and runs a program for the network it builds, the cabling is checked again once built
+fn built_network(
+    file: &str,
+    options: LexerOptions,
+    rules: &CoaxialRules,
+) -> Result<network::topology::Topology, Box<dyn Error>> {
+    let parsed = parser::parser_ana::parse_file(file, options, false)?;
+    let reported = semantic::semantic(&parsed, rules)?;
+    let topology = interpreter::topology(&parsed)?;
+    semantic::check_network(&parsed, &topology, rules, &reported)?;
+    Ok(topology)
+}
+
+// runs make.ob on the VM, errors quote the program it was compiled from when it is still there
+fn run_object(file: &str) -> Result<(), Box<dyn Error>> {
+    let object = bytecode::Object::decode(&fs::read(file)?)?;
+    let mut stdout = std::io::stdout().lock();
+    if let Err(error) = bytecode::vm::run(&object, &mut stdout) {
+        error.display(&fs::read_to_string(&object.source).unwrap_or_default());
+        return Err("runtime error".into());
+    }
+    Ok(())
+}
+
+//cmd to list the code of an object file
+fn disasm_cmd(file: &str) -> Result<(), Box<dyn Error>> {
+    let object = bytecode::Object::decode(&fs::read(file)?)?;
+    let source = fs::read_to_string(&object.source).ok();
+    print!(
+        "{}",
+        bytecode::disasm::disassemble(&object, source.as_deref())
+    );
+    Ok(())
+}
+
+//cmd to report reachability, isolated machines and loops of the network a program builds
+fn analyze_cmd(
+    file: &str,
+    options: LexerOptions,
+    rules: &CoaxialRules,
+) -> Result<(), Box<dyn Error>> {
+    let topology = built_network(file, options, rules)?;
+    print!("{}", network::analysis::report(&topology));
+    Ok(())
+}
+
+//cmd to draw the grid of the network a program builds
+fn show_cmd(file: &str, options: LexerOptions, rules: &CoaxialRules) -> Result<(), Box<dyn Error>> {
+    let topology = built_network(file, options, rules)?;
+    print!("{}", network::render::render(&topology));
+    Ok(())
+}
 
+//cmd to send the frames of a scenario over the network a program builds
+fn simulate_cmd(
+    file: &str,
+    scenario: &str,
+    options: LexerOptions,
+    rules: &CoaxialRules,
+) -> Result<(), Box<dyn Error>> {
+    let topology = built_network(file, options, rules)?;
+    let text = fs::read_to_string(scenario)?;
+    let scenario = if scenario.ends_with(".yaml") || scenario.ends_with(".yml") {
+        Scenario::from_yaml(&text, &topology)?
+    } else {
+        Scenario::from_json(&text, &topology)?
+    };
+    let simulation = network::simulation::simulate(&topology, &scenario);
+    print!(
+        "{}",
+        network::simulation::report(&topology, &scenario, &simulation)
+    );
     Ok(())
 }
 
-// fn run_cmd(target: Option<&str>) -> Result<(), Box<dyn Error>> {
-//     let t = target.unwrap_or("default");
-//     println!("Here will be the running of the executable");
-//     Ok(())
-// }
+//cmd to turn a topology file back into a program
+fn decompile_cmd(
+    file: &str,
+    output: Option<&str>,
+    profile: KeywordProfile,
+) -> Result<(), Box<dyn Error>> {
+    let text = fs::read_to_string(file)?;
+    let topology = if file.ends_with(".yaml") || file.ends_with(".yml") {
+        network::export::from_yaml(&text)?
+    } else {
+        network::export::from_json(&text)?
+    };
+    let program = network::decompile::decompile(&topology, profile)?;
+    match output {
+        Some(path) => {
+            fs::write(path, program)?;
+            println!("wrote {}", path);
+        }
+        None => print!("{}", program),
+    }
+    Ok(())
+}
+
+// `--keywords en --ignore-keyword-case --coaxial 10base5 --coaxial-errors`
+fn check_options(args: &CheckArgs) -> Result<(LexerOptions, CoaxialRules), Box<dyn Error>> {
+    let options = LexerOptions {
+        profiles: KeywordProfile::parse_list(&args.keywords)?,
+        case_insensitive_keywords: args.ignore_keyword_case,
+        max_errors: (args.max_lex_errors > 0).then_some(args.max_lex_errors),
+    };
+    let mut rules = CoaxialRules::parse(&args.coaxial)?;
+    if args.coaxial_errors {
+        rules.severity = Severity::Error;
+    }
+    Ok((options, rules))
+}
 
 //cmd to clean the exutable
 fn clean_cmd() -> Result<(), Box<dyn Error>> {
-    let path = "make.ob";
-    fs::remove_file(path)?;
+    fs::remove_file(compiler::OBJECT_PATH)?;
     println!("Executable deleted");
     Ok(())
 }
@@ -35,8 +169,41 @@ fn clean_cmd() -> Result<(), Box<dyn Error>> {
 pub fn execute(cli: Cli) -> Result<(), Box<dyn Error>> {
     println!("running");
     match cli.command {
-        Commands::Make { mode } => make_cmd(&mode),
-        // Commands::Run { target } => run_cmd(target.as_deref()),
+        Commands::Make {
+            mode,
+            check,
+            table_parser,
+            emit,
+        } => {
+            let (options, rules) = check_options(&check)?;
+            let emit = emit.as_deref().map(Emit::parse).transpose()?;
+            make_cmd(&mode, options, table_parser, &rules, emit)
+        }
+        Commands::Grammar {
+            action: GrammarAction::Check { file, verbose },
+        } => parser::ll1::check(&file, verbose),
+        Commands::Run { file, check } => {
+            let (options, rules) = check_options(&check)?;
+            run_cmd(&file, options, &rules)
+        }
+        Commands::Analyze { file, check } => {
+            let (options, rules) = check_options(&check)?;
+            analyze_cmd(&file, options, &rules)
+        }
+        Commands::Show { file, check } => {
+            let (options, rules) = check_options(&check)?;
+            show_cmd(&file, options, &rules)
+        }
+        Commands::Simulate { file, scenario, check } => {
+            let (options, rules) = check_options(&check)?;
+            simulate_cmd(&file, &scenario, options, &rules)
+        }
+        Commands::Decompile {
+            file,
+            output,
+            keywords,
+        } => decompile_cmd(&file, output.as_deref(), keywords.parse()?),
+        Commands::Disasm { file } => disasm_cmd(&file),
         Commands::Clean => clean_cmd(),
     }
 }
@@ -0,0 +1,219 @@
+use crate::network::device::{DeviceId, Link, MachineId, SegmentId};
+use crate::network::topology::Topology;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::Write;
+
+/// The devices of a topology and the links between them: machine to port or cable, and cable to
+/// concentrator
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graph {
+    // every device, linked or not, neighbours sorted
+    neighbours: BTreeMap<DeviceId, Vec<DeviceId>>,
+    links: Vec<(DeviceId, DeviceId)>,
+}
+
+impl Graph {
+    pub fn new(topology: &Topology) -> Self {
+        let mut links = Vec::new();
+        for (index, machine) in topology.machines.iter().enumerate() {
+            let from = DeviceId::Machine(MachineId(index));
+            match machine.link {
+                Some(Link::Port(concentrator, _)) => {
+                    links.push((from, DeviceId::Concentrator(concentrator)))
+                }
+                Some(Link::Coaxial(segment, _)) => links.push((from, DeviceId::Coaxial(segment))),
+                None => {}
+            }
+        }
+        for (index, segment) in topology.segments.iter().enumerate() {
+            let from = DeviceId::Coaxial(SegmentId(index));
+            for concentrator in &segment.concentrators {
+                links.push((from, DeviceId::Concentrator(*concentrator)));
+            }
+        }
+
+        let mut neighbours: BTreeMap<DeviceId, Vec<DeviceId>> = topology
+            .devices()
+            .map(|device| (device, Vec::new()))
+            .collect();
+        for (a, b) in &links {
+            neighbours.entry(*a).or_default().push(*b);
+            neighbours.entry(*b).or_default().push(*a);
+        }
+        for list in neighbours.values_mut() {
+            list.sort();
+        }
+        Graph { neighbours, links }
+    }
+
+    pub fn neighbours(&self, device: DeviceId) -> &[DeviceId] {
+        self.neighbours
+            .get(&device)
+            .map_or(&[], |list| list.as_slice())
+    }
+
+    pub fn links(&self) -> &[(DeviceId, DeviceId)] {
+        &self.links
+    }
+
+    /// Shortest way from one device to another, both ends included
+    pub fn path(&self, from: DeviceId, to: DeviceId) -> Option<Vec<DeviceId>> {
+        let mut came_from = BTreeMap::from([(from, from)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(device) = queue.pop_front() {
+            if device == to {
+                let mut path = vec![to];
+                let mut step = to;
+                while step != from {
+                    step = came_from[&step];
+                    path.push(step);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.neighbours(device) {
+                if !came_from.contains_key(next) {
+                    came_from.insert(*next, device);
+                    queue.push_back(*next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Links crossed on the shortest way, `m1 - c1 - m2` is 2 hops
+    pub fn hops(&self, from: DeviceId, to: DeviceId) -> Option<usize> {
+        self.path(from, to).map(|path| path.len() - 1)
+    }
+
+    pub fn reaches(&self, from: DeviceId, to: DeviceId) -> bool {
+        self.path(from, to).is_some()
+    }
+
+    /// Devices wired together, each group sorted with machines first
+    pub fn components(&self) -> Vec<Vec<DeviceId>> {
+        let mut seen = BTreeSet::new();
+        let mut components = Vec::new();
+        for start in self.neighbours.keys() {
+            if seen.contains(start) {
+                continue;
+            }
+            let mut component = Vec::new();
+            let mut stack = vec![*start];
+            seen.insert(*start);
+            while let Some(device) = stack.pop() {
+                component.push(device);
+                for next in self.neighbours(device) {
+                    if seen.insert(*next) {
+                        stack.push(*next);
+                    }
+                }
+            }
+            component.sort();
+            components.push(component);
+        }
+        components
+    }
+
+    /// Machines that reach no other machine
+    pub fn isolated(&self) -> Vec<DeviceId> {
+        self.components()
+            .into_iter()
+            .filter(|component| machines(component).count() == 1)
+            .flat_map(|component| machines(&component).collect::<Vec<_>>())
+            .collect()
+    }
+
+    /// Links that close a loop, the second way between two devices already wired together
+    pub fn loops(&self) -> Vec<(DeviceId, DeviceId)> {
+        // union find over the devices, in link order
+        let mut parent: BTreeMap<DeviceId, DeviceId> = BTreeMap::new();
+        fn root(parent: &mut BTreeMap<DeviceId, DeviceId>, device: DeviceId) -> DeviceId {
+            let up = *parent.entry(device).or_insert(device);
+            if up == device {
+                return device;
+            }
+            let top = root(parent, up);
+            parent.insert(device, top);
+            top
+        }
+
+        let mut closing = Vec::new();
+        for (a, b) in &self.links {
+            let (root_a, root_b) = (root(&mut parent, *a), root(&mut parent, *b));
+            if root_a == root_b {
+                closing.push((*a, *b));
+            } else {
+                parent.insert(root_a, root_b);
+            }
+        }
+        closing
+    }
+}
+
+fn machines(devices: &[DeviceId]) -> impl Iterator<Item = DeviceId> + '_ {
+    devices
+        .iter()
+        .copied()
+        .filter(|device| matches!(device, DeviceId::Machine(_)))
+}
+
+/// What `kindred analyze` prints
+pub fn report(topology: &Topology) -> String {
+    let graph = Graph::new(topology);
+    let name = |device: DeviceId| topology.name(device);
+    let names = |devices: &[DeviceId]| {
+        devices
+            .iter()
+            .map(|device| name(*device))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut out = String::new();
+
+    let _ = writeln!(
+        out,
+        "{} machines, {} concentrators, {} coaxial segments",
+        topology.machines.len(),
+        topology.concentrators.len(),
+        topology.segments.len()
+    );
+
+    let _ = writeln!(out, "\ngroups:");
+    for (index, component) in graph.components().iter().enumerate() {
+        let _ = writeln!(out, "  {}: {}", index + 1, names(component));
+    }
+
+    let _ = writeln!(out, "\nreachable:");
+    let all: Vec<DeviceId> = topology.devices().collect();
+    for from in machines(&all) {
+        let reached: Vec<String> = machines(&all)
+            .filter(|to| *to != from)
+            .filter_map(|to| {
+                let hops = graph.hops(from, to)?;
+                Some(format!("{} ({} hops)", name(to), hops))
+            })
+            .collect();
+        if !reached.is_empty() {
+            let _ = writeln!(out, "  {}: {}", name(from), reached.join(", "));
+        }
+    }
+
+    let isolated = graph.isolated();
+    if isolated.is_empty() {
+        let _ = writeln!(out, "\nisolated: none");
+    } else {
+        let _ = writeln!(out, "\nisolated: {}", names(&isolated));
+    }
+
+    let loops = graph.loops();
+    if loops.is_empty() {
+        let _ = writeln!(out, "loops: none");
+    } else {
+        let _ = writeln!(out, "loops:");
+        for (a, b) in loops {
+            let _ = writeln!(out, "  {} - {} closes a loop", name(a), name(b));
+        }
+    }
+    out
+}
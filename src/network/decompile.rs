@@ -0,0 +1,244 @@
+use crate::lexer::keywords::{self, KEYWORDS, KeywordProfile};
+use crate::lexer::lexer_ana::TokenType;
+use crate::network::device::{DeviceId, Link};
+use crate::network::topology::Topology;
+use crate::network::{DEFAULT_COAXIAL_LENGTH, DEFAULT_PORTS, GRID_HEIGHT, GRID_WIDTH};
+use crate::parser::ast::Direction;
+use std::error::Error;
+use std::fmt;
+
+// define lines wrap past this column
+const LINE_WIDTH: usize = 80;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecompileError {
+    // programs always get the default grid
+    Grid {
+        width: i64,
+        height: i64,
+    },
+    // not something the lexer reads as an identifier
+    BadName(String),
+    Keyword {
+        name: String,
+        profile: KeywordProfile,
+    },
+}
+
+impl fmt::Display for DecompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecompileError::Grid { width, height } => write!(
+                f,
+                "the grid is {}x{}, a program can only build on the {}x{} one",
+                width, height, GRID_WIDTH, GRID_HEIGHT
+            ),
+            DecompileError::BadName(name) => write!(
+                f,
+                "'{}' can't be a device name, names are a letter followed by letters and digits",
+                name
+            ),
+            DecompileError::Keyword { name, profile } => {
+                write!(f, "'{}' is a keyword in the {} profile", name, profile)
+            }
+        }
+    }
+}
+
+impl Error for DecompileError {}
+
+/// Writes a program that builds `topology` when run, with the keywords of `profile`
+pub fn decompile(topology: &Topology, profile: KeywordProfile) -> Result<String, DecompileError> {
+    let (width, height) = (topology.layout.width, topology.layout.height);
+    if (width, height) != (GRID_WIDTH, GRID_HEIGHT) {
+        return Err(DecompileError::Grid { width, height });
+    }
+    for device in topology.devices() {
+        check_name(topology.name(device), profile)?;
+    }
+
+    let word = |token: TokenType| keywords::spelling(&token, &[profile]).unwrap_or("?");
+    // the pragma makes the file read the same whatever --keywords says
+    let mut out = format!("//@keywords {}\n\n", profile);
+
+    let machines = topology
+        .machines
+        .iter()
+        .map(|machine| machine.name.clone())
+        .collect();
+    define(word(TokenType::Maquinas_), machines, &mut out);
+    let concentrators = topology
+        .concentrators
+        .iter()
+        .map(|concentrator| match concentrator.ports.len() {
+            DEFAULT_PORTS => concentrator.name.clone(),
+            ports => format!("{} = {}", concentrator.name, ports),
+        })
+        .collect();
+    define(word(TokenType::Concentradores_), concentrators, &mut out);
+    let segments = topology
+        .segments
+        .iter()
+        .map(|segment| {
+            if segment.length == DEFAULT_COAXIAL_LENGTH {
+                segment.name.clone()
+            } else {
+                format!("{} = {}", segment.name, segment.length)
+            }
+        })
+        .collect();
+    define(word(TokenType::Coaxial_), segments, &mut out);
+
+    out.push('\n');
+    out.push_str(word(TokenType::Inicio_));
+    out.push('\n');
+
+    let mut placements = Vec::new();
+    for device in topology.devices() {
+        let Some(at) = topology.position(device) else {
+            continue;
+        };
+        let name = topology.name(device);
+        placements.push(match device {
+            DeviceId::Coaxial(id) => {
+                let direction = topology.segment(id).direction.unwrap_or(Direction::Right);
+                format!(
+                    "{}({}, {}, {}, {});",
+                    word(TokenType::ColocaCoaxial_),
+                    name,
+                    at.x,
+                    at.y,
+                    word(direction_token(direction))
+                )
+            }
+            _ => format!(
+                "{}({}, {}, {});",
+                word(TokenType::Coloca_),
+                name,
+                at.x,
+                at.y
+            ),
+        });
+    }
+
+    // cables into concentrators in the order they were plugged in
+    let backbones = topology
+        .segments
+        .iter()
+        .flat_map(|segment| {
+            segment.concentrators.iter().map(|concentrator| {
+                format!(
+                    "{}({}, {});",
+                    word(TokenType::ColocaCoaxialConcentrador_),
+                    segment.name,
+                    topology.concentrator(*concentrator).name
+                )
+            })
+        })
+        .collect();
+
+    let ports = topology
+        .machines
+        .iter()
+        .filter_map(|machine| match machine.link {
+            Some(Link::Port(concentrator, port)) => Some(format!(
+                "{}({}, {}, {});",
+                word(TokenType::UneMaquinaPuerto_),
+                machine.name,
+                topology.concentrator(concentrator).name,
+                port
+            )),
+            _ => None,
+        })
+        .collect();
+
+    // taps in cable order, a tap right after the previous one doesn't need its offset spelled out
+    let mut taps = Vec::new();
+    for segment in &topology.segments {
+        let mut next = 0.0;
+        for tap in &segment.taps {
+            let machine = &topology.machine(tap.machine).name;
+            taps.push(if tap.offset == next {
+                format!(
+                    "{}({}, {});",
+                    word(TokenType::AsignaMaquinaCoaxial_),
+                    machine,
+                    segment.name
+                )
+            } else {
+                format!(
+                    "{}({}, {}, {});",
+                    word(TokenType::MaquinaCoaxial_),
+                    machine,
+                    segment.name,
+                    tap.offset
+                )
+            });
+            next = tap.offset + 1.0;
+        }
+    }
+
+    let groups: [Vec<String>; 4] = [placements, backbones, ports, taps];
+    for (index, group) in groups.iter().filter(|group| !group.is_empty()).enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        for statement in group {
+            out.push_str("    ");
+            out.push_str(statement);
+            out.push('\n');
+        }
+    }
+
+    out.push_str(word(TokenType::Fin_));
+    out.push('\n');
+    Ok(out)
+}
+
+// `define maquinas m1, m2;`, wrapped when it gets long
+fn define(device: &str, items: Vec<String>, out: &mut String) {
+    if items.is_empty() {
+        return;
+    }
+    let mut line = format!("define {} ", device);
+    for (index, item) in items.iter().enumerate() {
+        let separator = if index + 1 < items.len() { "," } else { ";" };
+        if index > 0 && line.len() + item.len() + 2 > LINE_WIDTH {
+            out.push_str(line.trim_end());
+            out.push('\n');
+            line = "    ".to_string();
+        }
+        line.push_str(item);
+        line.push_str(separator);
+        line.push(' ');
+    }
+    out.push_str(line.trim_end());
+    out.push('\n');
+}
+
+fn check_name(name: &str, profile: KeywordProfile) -> Result<(), DecompileError> {
+    let identifier = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric());
+    if !identifier {
+        return Err(DecompileError::BadName(name.to_string()));
+    }
+    if KEYWORDS
+        .iter()
+        .any(|keyword| keyword.spelling == name && keyword.profiles.contains(&profile))
+    {
+        return Err(DecompileError::Keyword {
+            name: name.to_string(),
+            profile,
+        });
+    }
+    Ok(())
+}
+
+fn direction_token(direction: Direction) -> TokenType {
+    match direction {
+        Direction::Up => TokenType::Arriba_,
+        Direction::Down => TokenType::Abajo_,
+        Direction::Left => TokenType::Izquierda_,
+        Direction::Right => TokenType::Derecha_,
+    }
+}
@@ -0,0 +1,134 @@
+use crate::parser::ast::Direction;
+use std::fmt;
+
+/// A cell of the layout grid, `y` grows downwards so `arriba` is `y - 1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Position {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Position {
+    pub fn new(x: i64, y: i64) -> Self {
+        Position { x, y }
+    }
+
+    /// The position `distance` cells away towards `direction`, far off the grid it stops at
+    /// the edge of `i64`
+    pub fn step(self, direction: Direction, distance: i64) -> Position {
+        match direction {
+            Direction::Up => Position::new(self.x, self.y.saturating_sub(distance)),
+            Direction::Down => Position::new(self.x, self.y.saturating_add(distance)),
+            Direction::Left => Position::new(self.x.saturating_sub(distance), self.y),
+            Direction::Right => Position::new(self.x.saturating_add(distance), self.y),
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+// indexes into the topology's device lists, only valid for the topology that handed them out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MachineId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConcentratorId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SegmentId(pub usize);
+
+/// Any placed or named device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DeviceId {
+    Machine(MachineId),
+    Concentrator(ConcentratorId),
+    Coaxial(SegmentId),
+}
+
+/// What a machine's network card is plugged into
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Link {
+    // ports are numbered from 1
+    Port(ConcentratorId, usize),
+    // a tap `offset` units along the segment
+    Coaxial(SegmentId, f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Machine {
+    pub name: String,
+    pub position: Option<Position>,
+    pub link: Option<Link>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortState {
+    Free,
+    Connected(MachineId),
+}
+
+/// A hub, every frame on one port is repeated on all the others
+#[derive(Debug, Clone, PartialEq)]
+pub struct Concentrator {
+    pub name: String,
+    pub position: Option<Position>,
+    // port n is ports[n - 1]
+    pub ports: Vec<PortState>,
+}
+
+impl Concentrator {
+    pub fn available(&self) -> usize {
+        self.ports
+            .iter()
+            .filter(|port| **port == PortState::Free)
+            .count()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.available() == 0
+    }
+
+    /// Number of the first free port
+    pub fn first_free(&self) -> Option<usize> {
+        self.ports
+            .iter()
+            .position(|port| *port == PortState::Free)
+            .map(|index| index + 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tap {
+    pub machine: MachineId,
+    pub offset: f64,
+}
+
+/// A shared coaxial cable, machines tap into it along its length
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoaxialSegment {
+    pub name: String,
+    pub length: f64,
+    // where the cable starts and which way it runs
+    pub position: Option<Position>,
+    pub direction: Option<Direction>,
+    // sorted by offset
+    pub taps: Vec<Tap>,
+    // a cable can run between several concentrators, as a backbone
+    pub concentrators: Vec<ConcentratorId>,
+}
+
+impl CoaxialSegment {
+    /// Where asignaMaquinaCoaxial puts the next machine, one unit past the last tap
+    pub fn next_offset(&self) -> f64 {
+        self.taps.last().map_or(0.0, |tap| tap.offset + 1.0)
+    }
+
+    /// No room left for another tap
+    pub fn is_complete(&self) -> bool {
+        self.next_offset() > self.length
+    }
+}
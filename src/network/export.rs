@@ -0,0 +1,364 @@
+use crate::network::device::{DeviceId, Link, MachineId, Position, SegmentId};
+use crate::network::topology::{NetworkError, Topology};
+use crate::parser::ast::Direction;
+use crate::util::document::{self, Node};
+use crate::util::yaml;
+use std::error::Error;
+use std::fmt;
+
+/// Written at the top of every export, bumped when the layout of the document changes.
+/// The layout is described in `topology.schema.json` next to this file
+pub const SCHEMA: &str = "kindred-topology/1";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportError {
+    // not valid JSON or YAML
+    Syntax(String),
+    // a field missing or of the wrong kind, `path` like `machines[2].link.port`
+    Shape { path: String, message: String },
+    // the document describes a network the model rejects, e.g. two machines on a port
+    Network(NetworkError),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::Syntax(message) => write!(f, "{}", message),
+            ImportError::Shape { path, message } => write!(f, "{}: {}", path, message),
+            ImportError::Network(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for ImportError {}
+
+impl From<NetworkError> for ImportError {
+    fn from(error: NetworkError) -> Self {
+        ImportError::Network(error)
+    }
+}
+
+type Result<T> = std::result::Result<T, ImportError>;
+
+pub fn to_json(topology: &Topology) -> String {
+    document::to_json(&to_node(topology))
+}
+
+pub fn to_yaml(topology: &Topology) -> String {
+    yaml::to_yaml(&to_node(topology))
+}
+
+pub fn from_json(text: &str) -> Result<Topology> {
+    from_node(&document::parse_json(text).map_err(ImportError::Syntax)?)
+}
+
+pub fn from_yaml(text: &str) -> Result<Topology> {
+    from_node(&yaml::parse_yaml(text).map_err(ImportError::Syntax)?)
+}
+
+/// Devices in declaration order, each machine with what it is plugged into
+pub fn to_node(topology: &Topology) -> Node {
+    let machines = topology
+        .machines
+        .iter()
+        .map(|machine| {
+            let link = match machine.link {
+                Some(Link::Port(concentrator, port)) => object(vec![
+                    (
+                        "concentrator",
+                        text(&topology.concentrator(concentrator).name),
+                    ),
+                    ("port", Node::Num(port as f64)),
+                ]),
+                Some(Link::Coaxial(segment, offset)) => object(vec![
+                    ("coaxial", text(&topology.segment(segment).name)),
+                    ("offset", Node::Num(offset)),
+                ]),
+                None => Node::Null,
+            };
+            object(vec![
+                ("name", text(&machine.name)),
+                ("position", position(machine.position)),
+                ("link", link),
+            ])
+        })
+        .collect();
+
+    let concentrators = topology
+        .concentrators
+        .iter()
+        .map(|concentrator| {
+            object(vec![
+                ("name", text(&concentrator.name)),
+                ("position", position(concentrator.position)),
+                ("ports", Node::Num(concentrator.ports.len() as f64)),
+            ])
+        })
+        .collect();
+
+    let segments = topology
+        .segments
+        .iter()
+        .map(|segment| {
+            let concentrators = segment
+                .concentrators
+                .iter()
+                .map(|id| text(&topology.concentrator(*id).name))
+                .collect();
+            object(vec![
+                ("name", text(&segment.name)),
+                ("length", Node::Num(segment.length)),
+                ("position", position(segment.position)),
+                (
+                    "direction",
+                    segment
+                        .direction
+                        .map_or(Node::Null, |direction| text(direction_name(direction))),
+                ),
+                ("concentrators", Node::List(concentrators)),
+            ])
+        })
+        .collect();
+
+    object(vec![
+        ("schema", text(SCHEMA)),
+        (
+            "grid",
+            object(vec![
+                ("width", Node::Num(topology.layout.width as f64)),
+                ("height", Node::Num(topology.layout.height as f64)),
+            ]),
+        ),
+        ("machines", Node::List(machines)),
+        ("concentrators", Node::List(concentrators)),
+        ("coaxial", Node::List(segments)),
+    ])
+}
+
+/// Rebuilds a topology with the same commands a program uses, so an inconsistent document is
+/// rejected the way the program would have been
+pub fn from_node(node: &Node) -> Result<Topology> {
+    let root = Field::root(node);
+    let schema = root.get("schema")?.str()?;
+    if schema != SCHEMA {
+        return Err(root
+            .get("schema")?
+            .error(format!("expected schema '{}', found '{}'", SCHEMA, schema)));
+    }
+    let grid = root.get("grid")?;
+    let mut topology =
+        Topology::with_grid(grid.get("width")?.whole()?, grid.get("height")?.whole()?);
+
+    let machines = root.get("machines")?.list()?;
+    let concentrators = root.get("concentrators")?.list()?;
+    let segments = root.get("coaxial")?.list()?;
+
+    for machine in &machines {
+        topology.add_machine(machine.get("name")?.str()?)?;
+    }
+    for concentrator in &concentrators {
+        let ports = concentrator.get("ports")?.whole()?;
+        topology.add_concentrator(concentrator.get("name")?.str()?, ports as usize)?;
+    }
+    for segment in &segments {
+        let length = segment.get("length")?.num()?;
+        topology.add_coaxial(segment.get("name")?.str()?, length)?;
+    }
+
+    // positions first, the same cells are free whatever order they are taken in
+    for (device, field) in topology.devices().collect::<Vec<_>>().into_iter().zip(
+        machines
+            .iter()
+            .chain(concentrators.iter())
+            .chain(segments.iter()),
+    ) {
+        let Some(at) = field.get("position")?.position()? else {
+            continue;
+        };
+        match device {
+            DeviceId::Coaxial(id) => {
+                let direction = field.get("direction")?;
+                let direction = match direction.node {
+                    Node::Null => Direction::Right,
+                    _ => parse_direction(&direction)?,
+                };
+                topology.place_coaxial(id, at, direction)?;
+            }
+            _ => topology.place(device, at)?,
+        }
+    }
+
+    for (index, segment) in segments.iter().enumerate() {
+        let id = SegmentId(index);
+        for concentrator in segment.get("concentrators")?.list()? {
+            let concentrator = topology.concentrator_id(concentrator.str()?)?;
+            topology.attach_segment(id, concentrator)?;
+        }
+    }
+
+    for (index, machine) in machines.iter().enumerate() {
+        let id = MachineId(index);
+        let link = machine.get("link")?;
+        if link.node == &Node::Null {
+            continue;
+        }
+        if link.node.get("concentrator").is_some() {
+            let concentrator = topology.concentrator_id(link.get("concentrator")?.str()?)?;
+            let port = link.get("port")?.whole()?;
+            topology.connect_port(id, concentrator, port as usize)?;
+        } else {
+            let segment = topology.segment_id(link.get("coaxial")?.str()?)?;
+            topology.attach_coaxial(id, segment, link.get("offset")?.num()?)?;
+        }
+    }
+
+    Ok(topology)
+}
+
+fn object(fields: Vec<(&str, Node)>) -> Node {
+    Node::Object(
+        fields
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+fn text(value: &str) -> Node {
+    Node::Str(value.to_string())
+}
+
+fn position(position: Option<Position>) -> Node {
+    match position {
+        Some(position) => object(vec![
+            ("x", Node::Num(position.x as f64)),
+            ("y", Node::Num(position.y as f64)),
+        ]),
+        None => Node::Null,
+    }
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "up",
+        Direction::Down => "down",
+        Direction::Left => "left",
+        Direction::Right => "right",
+    }
+}
+
+fn parse_direction(field: &Field) -> Result<Direction> {
+    match field.str()? {
+        "up" => Ok(Direction::Up),
+        "down" => Ok(Direction::Down),
+        "left" => Ok(Direction::Left),
+        "right" => Ok(Direction::Right),
+        other => Err(field.error(format!(
+            "expected up, down, left or right, found '{}'",
+            other
+        ))),
+    }
+}
+
+// a node and where it sits in the document, for errors
+pub(crate) struct Field<'a> {
+    pub(crate) node: &'a Node,
+    path: String,
+}
+
+impl<'a> Field<'a> {
+    pub(crate) fn root(node: &'a Node) -> Self {
+        Field {
+            node,
+            path: "document".to_string(),
+        }
+    }
+
+    pub(crate) fn error(&self, message: String) -> ImportError {
+        ImportError::Shape {
+            path: self.path.clone(),
+            message,
+        }
+    }
+
+    fn mismatch(&self, expected: &str) -> ImportError {
+        self.error(format!("expected {}, found {}", expected, self.node.kind()))
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Result<Field<'a>> {
+        if !matches!(self.node, Node::Object(_)) {
+            return Err(self.mismatch("an object"));
+        }
+        let path = match self.path.as_str() {
+            "document" => key.to_string(),
+            parent => format!("{}.{}", parent, key),
+        };
+        match self.node.get(key) {
+            Some(node) => Ok(Field { node, path }),
+            None => Err(ImportError::Shape {
+                path,
+                message: "missing".to_string(),
+            }),
+        }
+    }
+
+    // None when the key is missing or null
+    pub(crate) fn optional(&self, key: &str) -> Result<Option<Field<'a>>> {
+        match self.node.get(key) {
+            None | Some(Node::Null) => Ok(None),
+            Some(_) => self.get(key).map(Some),
+        }
+    }
+
+    pub(crate) fn list(&self) -> Result<Vec<Field<'a>>> {
+        let Node::List(items) = self.node else {
+            return Err(self.mismatch("a list"));
+        };
+        Ok(items
+            .iter()
+            .enumerate()
+            .map(|(index, node)| Field {
+                node,
+                path: format!("{}[{}]", self.path, index),
+            })
+            .collect())
+    }
+
+    pub(crate) fn str(&self) -> Result<&'a str> {
+        match self.node {
+            Node::Str(text) => Ok(text),
+            _ => Err(self.mismatch("a string")),
+        }
+    }
+
+    pub(crate) fn num(&self) -> Result<f64> {
+        match self.node {
+            Node::Num(value) => Ok(*value),
+            _ => Err(self.mismatch("a number")),
+        }
+    }
+
+    pub(crate) fn whole(&self) -> Result<i64> {
+        let value = self.num()?;
+        if value.fract() != 0.0 || value < 0.0 || value > u32::MAX as f64 {
+            return Err(self.error(format!("expected a whole number, found {}", value)));
+        }
+        Ok(value as i64)
+    }
+
+    fn position(&self) -> Result<Option<Position>> {
+        if self.node == &Node::Null {
+            return Ok(None);
+        }
+        // coordinates may be negative in a hand written document, the grid rejects them
+        let coordinate = |name: &str| -> Result<i64> {
+            let field = self.get(name)?;
+            let value = field.num()?;
+            if value.fract() != 0.0 || value.abs() > u32::MAX as f64 {
+                return Err(field.error(format!("expected a whole number, found {}", value)));
+            }
+            Ok(value as i64)
+        };
+        Ok(Some(Position::new(coordinate("x")?, coordinate("y")?)))
+    }
+}
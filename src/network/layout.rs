@@ -0,0 +1,80 @@
+use crate::network::device::{DeviceId, Position};
+use crate::network::{GRID_HEIGHT, GRID_WIDTH};
+use crate::parser::ast::Direction;
+use std::collections::BTreeMap;
+
+/// The grid devices are placed on and which device covers each cell
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    pub width: i64,
+    pub height: i64,
+    // sorted so walking the layout always gives the same order
+    cells: BTreeMap<Position, DeviceId>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::new(GRID_WIDTH, GRID_HEIGHT)
+    }
+}
+
+impl Layout {
+    pub fn new(width: i64, height: i64) -> Self {
+        Layout {
+            width,
+            height,
+            cells: BTreeMap::new(),
+        }
+    }
+
+    /// Cells go from (0, 0) to (width - 1, height - 1)
+    pub fn contains(&self, position: Position) -> bool {
+        (0..self.width).contains(&position.x) && (0..self.height).contains(&position.y)
+    }
+
+    pub fn at(&self, position: Position) -> Option<DeviceId> {
+        self.cells.get(&position).copied()
+    }
+
+    /// Every covered cell, by column and then row
+    pub fn cells(&self) -> impl Iterator<Item = (Position, DeviceId)> + '_ {
+        self.cells
+            .iter()
+            .map(|(position, device)| (*position, *device))
+    }
+
+    /// Cells covered by `device`
+    pub fn covered_by(&self, device: DeviceId) -> impl Iterator<Item = Position> + '_ {
+        self.cells()
+            .filter(move |(_, other)| *other == device)
+            .map(|(position, _)| position)
+    }
+
+    /// First cell of `cells` outside the grid or covered by another device
+    pub fn conflict(
+        &self,
+        device: DeviceId,
+        cells: &[Position],
+    ) -> Option<(Position, Option<DeviceId>)> {
+        cells.iter().find_map(|cell| match self.at(*cell) {
+            _ if !self.contains(*cell) => Some((*cell, None)),
+            Some(other) if other != device => Some((*cell, Some(other))),
+            _ => None,
+        })
+    }
+
+    // moves the device, whatever it covered before is freed
+    pub(super) fn cover(&mut self, device: DeviceId, cells: &[Position]) {
+        self.cells.retain(|_, other| *other != device);
+        for cell in cells {
+            self.cells.insert(*cell, device);
+        }
+    }
+}
+
+/// Cells a coaxial cable covers, one for every whole unit of its length so each tap has a cell
+pub fn coaxial_cells(start: Position, direction: Direction, length: f64) -> Vec<Position> {
+    (0..=length as i64)
+        .map(|distance| start.step(direction, distance))
+        .collect()
+}
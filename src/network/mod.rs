@@ -0,0 +1,17 @@
+pub mod analysis;
+pub mod decompile;
+pub mod device;
+pub mod export;
+pub mod layout;
+pub mod render;
+pub mod rules;
+pub mod simulation;
+pub mod topology;
+
+// what an undecorated `define` gets
+pub const DEFAULT_PORTS: usize = 8;
+pub const DEFAULT_COAXIAL_LENGTH: f64 = 185.0;
+
+// big enough for a default coaxial segment laid out in a straight line
+pub const GRID_WIDTH: i64 = 200;
+pub const GRID_HEIGHT: i64 = 200;
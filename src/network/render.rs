@@ -0,0 +1,300 @@
+use crate::network::device::{DeviceId, Link, Position};
+use crate::network::topology::Topology;
+use crate::parser::ast::Direction;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+// longer stretches of bare cable are drawn shortened
+const ELIDE_AFTER: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cell {
+    Device(DeviceId),
+    Cable { vertical: bool, tap: bool },
+}
+
+impl Cell {
+    fn is_box(self) -> bool {
+        matches!(self, Cell::Device(_))
+    }
+
+    // bare cable running along a row (or a column) can be shortened
+    fn elidable(self, vertical: bool) -> bool {
+        matches!(self, Cell::Cable { vertical: v, tap: false } if v == vertical)
+    }
+}
+
+// a column (or row) of the drawing, a grid coordinate or a run of shortened ones
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Line {
+    At(i64),
+    Elided(i64, i64),
+}
+
+/// Draws the placed devices as they sit on the grid with box drawing characters: machines in
+/// single boxes with the port they use, concentrators in double boxes and coaxial cable as thick
+/// lines with a branch at every tap. What is plugged where and what isn't placed follows
+pub fn render(topology: &Topology) -> String {
+    let cells = cells(topology);
+    let mut out = String::new();
+    match (bounds(&cells, |at| at.x), bounds(&cells, |at| at.y)) {
+        (Some((left, right)), Some((top, bottom))) => {
+            let _ = writeln!(
+                out,
+                "grid {} to {}",
+                Position::new(left, top),
+                Position::new(right, bottom)
+            );
+            let columns = lines(left, right, |x| {
+                (top..=bottom).all(|y| {
+                    cells
+                        .get(&Position::new(x, y))
+                        .is_none_or(|cell| cell.elidable(false))
+                })
+            });
+            let rows = lines(top, bottom, |y| {
+                (left..=right).all(|x| {
+                    cells
+                        .get(&Position::new(x, y))
+                        .is_none_or(|cell| cell.elidable(true))
+                })
+            });
+            draw(topology, &cells, &columns, &rows, &mut out);
+        }
+        _ => out.push_str("nothing is placed on the grid\n"),
+    }
+
+    let mut links = Vec::new();
+    for machine in &topology.machines {
+        if let Some(link) = machine.link {
+            links.push(format!(
+                "{} - {}",
+                machine.name,
+                topology.describe_link(link)
+            ));
+        }
+    }
+    for segment in &topology.segments {
+        for concentrator in &segment.concentrators {
+            links.push(format!(
+                "{} - '{}'",
+                segment.name,
+                topology.concentrator(*concentrator).name
+            ));
+        }
+    }
+    if !links.is_empty() {
+        out.push_str("\nlinks:\n");
+        for link in links {
+            let _ = writeln!(out, "  {}", link);
+        }
+    }
+
+    let unplaced: Vec<&str> = topology
+        .devices()
+        .filter(|device| topology.position(*device).is_none())
+        .map(|device| topology.name(device))
+        .collect();
+    if !unplaced.is_empty() {
+        let _ = writeln!(out, "\nnot placed: {}", unplaced.join(", "));
+    }
+    out
+}
+
+fn cells(topology: &Topology) -> BTreeMap<Position, Cell> {
+    topology
+        .layout
+        .cells()
+        .map(|(at, device)| {
+            let cell = match device {
+                DeviceId::Coaxial(id) => {
+                    let segment = topology.segment(id);
+                    let start = segment.position.unwrap_or(at);
+                    // cells along the cable are a metre apart, taps go on the cell of their metre
+                    let index = (at.x - start.x).abs() + (at.y - start.y).abs();
+                    Cell::Cable {
+                        vertical: matches!(
+                            segment.direction,
+                            Some(Direction::Up | Direction::Down)
+                        ),
+                        tap: segment
+                            .taps
+                            .iter()
+                            .any(|tap| tap.offset.floor() as i64 == index),
+                    }
+                }
+                device => Cell::Device(device),
+            };
+            (at, cell)
+        })
+        .collect()
+}
+
+fn bounds(cells: &BTreeMap<Position, Cell>, axis: fn(&Position) -> i64) -> Option<(i64, i64)> {
+    let min = cells.keys().map(axis).min()?;
+    let max = cells.keys().map(axis).max()?;
+    Some((min, max))
+}
+
+// every coordinate from `first` to `last`, runs of elidable ones shortened to their ends
+fn lines(first: i64, last: i64, elidable: impl Fn(i64) -> bool) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut at = first;
+    while at <= last {
+        let mut end = at;
+        while end < last && elidable(end + 1) && elidable(at) {
+            end += 1;
+        }
+        if elidable(at) && (end - at) as usize >= ELIDE_AFTER {
+            lines.push(Line::At(at));
+            lines.push(Line::Elided(at + 1, end - 1));
+            lines.push(Line::At(end));
+        } else {
+            lines.extend((at..=end).map(Line::At));
+        }
+        at = end + 1;
+    }
+    lines
+}
+
+fn draw(
+    topology: &Topology,
+    cells: &BTreeMap<Position, Cell>,
+    columns: &[Line],
+    rows: &[Line],
+    out: &mut String,
+) {
+    let boxes: BTreeSet<Position> = cells
+        .iter()
+        .filter(|(_, cell)| cell.is_box())
+        .map(|(at, _)| *at)
+        .collect();
+
+    // a column is as wide as its widest box, bare cable and empty columns take one character
+    let widths: Vec<usize> = columns
+        .iter()
+        .map(|column| match column {
+            Line::At(x) => boxes
+                .iter()
+                .filter(|at| at.x == *x)
+                .filter_map(|at| match cells[at] {
+                    Cell::Device(device) => Some(topology.name(device).chars().count() + 2),
+                    _ => None,
+                })
+                .max()
+                .unwrap_or(1),
+            Line::Elided(..) => 1,
+        })
+        .collect();
+
+    for row in rows {
+        let height = match row {
+            Line::At(y) if boxes.iter().any(|at| at.y == *y) => 3,
+            _ => 1,
+        };
+        let mut text = vec![String::new(); height];
+        for (column, width) in columns.iter().zip(&widths) {
+            let block = match (column, row) {
+                (Line::At(x), Line::At(y)) => match cells.get(&Position::new(*x, *y)) {
+                    Some(Cell::Device(device)) => device_box(topology, *device, *width),
+                    Some(Cell::Cable { vertical, tap }) => cable(*vertical, *tap, *width, height),
+                    None => vec![" ".repeat(*width); height],
+                },
+                // a shortened stretch shows dashes where the cable runs through it
+                (Line::Elided(from, to), Line::At(y)) => {
+                    let runs = (*from..=*to).any(|x| cells.contains_key(&Position::new(x, *y)));
+                    let mut block = vec![" ".to_string(); height];
+                    if runs {
+                        block[height / 2] = "┄".to_string();
+                    }
+                    block
+                }
+                (Line::At(x), Line::Elided(from, to)) => {
+                    let runs = (*from..=*to).any(|y| cells.contains_key(&Position::new(*x, y)));
+                    let middle = width / 2;
+                    vec![
+                        format!(
+                            "{}{}{}",
+                            " ".repeat(middle),
+                            if runs { "┆" } else { " " },
+                            " ".repeat(width - middle - 1)
+                        );
+                        height
+                    ]
+                }
+                (Line::Elided(..), Line::Elided(..)) => vec![" ".to_string(); height],
+            };
+            for (line, part) in text.iter_mut().zip(block) {
+                line.push_str(&part);
+            }
+        }
+        for line in text {
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+    }
+}
+
+// three lines `width` wide, machines show their port number in the bottom edge
+fn device_box(topology: &Topology, device: DeviceId, width: usize) -> Vec<String> {
+    let name = topology.name(device);
+    let inner = width - 2;
+    let (top, side, bottom, edge) = match device {
+        DeviceId::Concentrator(_) => (["╔", "╗"], "║", ["╚", "╝"], "═"),
+        _ => (["┌", "┐"], "│", ["└", "┘"], "─"),
+    };
+    let port = match device {
+        DeviceId::Machine(id) => match topology.machine(id).link {
+            Some(Link::Port(_, port)) => port.to_string(),
+            _ => String::new(),
+        },
+        _ => String::new(),
+    };
+    vec![
+        format!("{}{}{}", top[0], edge.repeat(inner), top[1]),
+        format!("{}{}{}", side, centered(name, inner, " "), side),
+        format!("{}{}{}", bottom[0], centered(&port, inner, edge), bottom[1]),
+    ]
+}
+
+fn cable(vertical: bool, tap: bool, width: usize, height: usize) -> Vec<String> {
+    let middle = width / 2;
+    (0..height)
+        .map(|line| {
+            if vertical {
+                let mark = if tap && line == height / 2 {
+                    "┣"
+                } else {
+                    "┃"
+                };
+                format!(
+                    "{}{}{}",
+                    " ".repeat(middle),
+                    mark,
+                    " ".repeat(width - middle - 1)
+                )
+            } else if line == height / 2 {
+                let mark = if tap { "┳" } else { "━" };
+                format!(
+                    "{}{}{}",
+                    "━".repeat(middle),
+                    mark,
+                    "━".repeat(width - middle - 1)
+                )
+            } else {
+                " ".repeat(width)
+            }
+        })
+        .collect()
+}
+
+fn centered(text: &str, width: usize, fill: &str) -> String {
+    let length = text.chars().count().min(width);
+    let left = (width - length) / 2;
+    format!(
+        "{}{}{}",
+        fill.repeat(left),
+        text,
+        fill.repeat(width - length - left)
+    )
+}
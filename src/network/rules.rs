@@ -0,0 +1,168 @@
+use crate::network::device::{CoaxialSegment, SegmentId};
+use crate::network::topology::Topology;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// Which limit of the standard a problem breaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    Length,
+    Taps,
+    TapSpacing,
+    Ends,
+}
+
+/// A broken rule, the message names the cable and the limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub rule: Rule,
+    pub message: String,
+}
+
+/// Physical limits of a coaxial Ethernet, lengths in metres like `define coaxial x1 = 185`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoaxialRules {
+    pub standard: &'static str,
+    pub max_length: f64,
+    pub min_tap_spacing: f64,
+    pub max_taps: usize,
+    // both ends of the cable need a terminator, or a concentrator that terminates it
+    pub max_concentrators: usize,
+    pub severity: Severity,
+}
+
+impl Default for CoaxialRules {
+    fn default() -> Self {
+        CoaxialRules::THIN
+    }
+}
+
+impl CoaxialRules {
+    /// 10BASE2, thin coaxial with BNC T connectors
+    pub const THIN: CoaxialRules = CoaxialRules {
+        standard: "10BASE2",
+        max_length: 185.0,
+        min_tap_spacing: 0.5,
+        max_taps: 30,
+        max_concentrators: 2,
+        severity: Severity::Warning,
+    };
+
+    /// 10BASE5, thick coaxial with vampire taps on the 2.5 m marks
+    pub const THICK: CoaxialRules = CoaxialRules {
+        standard: "10BASE5",
+        max_length: 500.0,
+        min_tap_spacing: 2.5,
+        max_taps: 100,
+        max_concentrators: 2,
+        severity: Severity::Warning,
+    };
+
+    /// `10base2` or `10base5`, any letter case
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "10base2" | "thin" => Ok(CoaxialRules::THIN),
+            "10base5" | "thick" => Ok(CoaxialRules::THICK),
+            _ => Err(format!(
+                "unknown coaxial standard '{}', expected 10base2 or 10base5",
+                name
+            )),
+        }
+    }
+
+    pub fn check_length(&self, segment: &str, length: f64) -> Option<Problem> {
+        (length > self.max_length).then(|| Problem {
+            rule: Rule::Length,
+            message: format!(
+                "coaxial '{}' is {} m long, {} segments go up to {} m",
+                segment,
+                metres(length),
+                self.standard,
+                self.max_length
+            ),
+        })
+    }
+
+    /// Problems with tapping `segment` at `offset`, before the tap is made
+    pub fn check_tap(&self, segment: &CoaxialSegment, offset: f64) -> Option<Problem> {
+        if segment.taps.len() >= self.max_taps {
+            return Some(Problem {
+                rule: Rule::Taps,
+                message: format!(
+                    "coaxial '{}' already has {} machines, {} allows {}",
+                    segment.name,
+                    segment.taps.len(),
+                    self.standard,
+                    self.max_taps
+                ),
+            });
+        }
+        let nearest = segment
+            .taps
+            .iter()
+            .map(|tap| (tap.offset - offset).abs())
+            .filter(|distance| *distance > 0.0)
+            .fold(f64::INFINITY, f64::min);
+        (nearest < self.min_tap_spacing).then(|| Problem {
+            rule: Rule::TapSpacing,
+            message: format!(
+                "tap on coaxial '{}' at {} is {} m from the next one, {} needs {} m",
+                segment.name,
+                metres(offset),
+                metres(nearest),
+                self.standard,
+                self.min_tap_spacing
+            ),
+        })
+    }
+
+    /// Problems with plugging `segment` into one more concentrator
+    pub fn check_attach(&self, segment: &CoaxialSegment) -> Option<Problem> {
+        (segment.concentrators.len() >= self.max_concentrators).then(|| Problem {
+            rule: Rule::Ends,
+            message: format!(
+                "coaxial '{}' has only {} ends and they are taken, the cable can't be terminated",
+                segment.name, self.max_concentrators
+            ),
+        })
+    }
+
+    /// Every broken rule of a finished topology
+    pub fn check(&self, topology: &Topology) -> Vec<(SegmentId, Problem)> {
+        let mut problems = Vec::new();
+        for (index, segment) in topology.segments.iter().enumerate() {
+            let id = SegmentId(index);
+            if let Some(problem) = self.check_length(&segment.name, segment.length) {
+                problems.push((id, problem));
+            }
+            // replay the taps in order so each is checked against the ones before it
+            let mut so_far = CoaxialSegment {
+                taps: Vec::new(),
+                concentrators: Vec::new(),
+                ..segment.clone()
+            };
+            for tap in &segment.taps {
+                if let Some(problem) = self.check_tap(&so_far, tap.offset) {
+                    problems.push((id, problem));
+                }
+                so_far.taps.push(*tap);
+            }
+            for concentrator in &segment.concentrators {
+                if let Some(problem) = self.check_attach(&so_far) {
+                    problems.push((id, problem));
+                }
+                so_far.concentrators.push(*concentrator);
+            }
+        }
+        problems
+    }
+}
+
+// centimetres are as precise as cabling gets, 0.2 rather than 0.19999999999999996
+fn metres(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
@@ -0,0 +1,586 @@
+use crate::network::device::{Link, MachineId};
+use crate::network::export::{Field, ImportError};
+use crate::network::topology::Topology;
+use crate::util::document::{self, Node};
+use crate::util::yaml;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::fmt::Write;
+
+// signals cross coaxial cable at about two thirds of the speed of light
+const NS_PER_METRE: f64 = 5.0;
+// a concentrator repeats what it hears out of every other port after this long, in ns
+const REPEATER_DELAY: u64 = 500;
+// CSMA/CD timings, in bit times
+const SLOT_BITS: u64 = 512;
+const GAP_BITS: u64 = 96;
+const JAM_BITS: u64 = 32;
+const PREAMBLE_BITS: u64 = 64;
+const MAX_ATTEMPTS: usize = 16;
+// backoff stops growing after this many collisions
+const BACKOFF_LIMIT: usize = 10;
+
+/// Smallest and biggest Ethernet frames in bytes, shorter ones are padded
+pub const MIN_FRAME: usize = 64;
+pub const MAX_FRAME: usize = 1518;
+
+/// Frames to send over a topology, times in nanoseconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    // Mb/s
+    pub bitrate: f64,
+    // for the backoff after a collision, the same seed gives the same run
+    pub seed: u64,
+    pub frames: Vec<Frame>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub from: MachineId,
+    pub to: MachineId,
+    // when the machine wants to send it
+    pub at: u64,
+    pub bytes: usize,
+}
+
+impl Scenario {
+    pub fn from_json(text: &str, topology: &Topology) -> Result<Self, ImportError> {
+        Scenario::from_node(
+            &document::parse_json(text).map_err(ImportError::Syntax)?,
+            topology,
+        )
+    }
+
+    pub fn from_yaml(text: &str, topology: &Topology) -> Result<Self, ImportError> {
+        Scenario::from_node(
+            &yaml::parse_yaml(text).map_err(ImportError::Syntax)?,
+            topology,
+        )
+    }
+
+    /// `bitrate` in Mb/s and `seed` are optional, each frame has `from`, `to`, `at` in
+    /// microseconds and optionally `bytes`
+    pub fn from_node(node: &Node, topology: &Topology) -> Result<Self, ImportError> {
+        let root = Field::root(node);
+        let bitrate = match root.optional("bitrate")? {
+            Some(field) if field.num()? <= 0.0 => {
+                return Err(field.error("expected a bitrate above 0".to_string()));
+            }
+            Some(field) => field.num()?,
+            None => 10.0,
+        };
+        let seed = match root.optional("seed")? {
+            Some(field) => field.whole()? as u64,
+            None => 1,
+        };
+
+        let mut frames = Vec::new();
+        for field in root.get("frames")?.list()? {
+            let from = topology.machine_id(field.get("from")?.str()?)?;
+            let to = topology.machine_id(field.get("to")?.str()?)?;
+            if from == to {
+                return Err(field.error("a machine can't send a frame to itself".to_string()));
+            }
+            let at = field.get("at")?;
+            if at.num()? < 0.0 {
+                return Err(at.error("expected a time from 0 on".to_string()));
+            }
+            let bytes = match field.optional("bytes")? {
+                Some(bytes) if bytes.whole()? as usize > MAX_FRAME => {
+                    return Err(bytes.error(format!("frames go up to {} bytes", MAX_FRAME)));
+                }
+                Some(bytes) => bytes.whole()? as usize,
+                None => MIN_FRAME,
+            };
+            frames.push(Frame {
+                from,
+                to,
+                at: (at.num()? * 1000.0).round() as u64,
+                bytes,
+            });
+        }
+        Ok(Scenario {
+            bitrate,
+            seed,
+            frames,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    // when it reached the other machine and how long after it was queued
+    Delivered { at: u64, latency: u64 },
+    Dropped(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameReport {
+    pub collisions: usize,
+    pub outcome: Outcome,
+}
+
+/// What happened to every frame of a scenario, in the order of the scenario
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simulation {
+    pub timeline: Vec<(u64, String)>,
+    pub frames: Vec<FrameReport>,
+}
+
+/// Nanoseconds a signal takes from one machine to another, None when they don't share a cable
+/// or a concentrator somewhere along the way. Concentrators repeat every frame out of all their
+/// ports, so every machine reached this way hears every frame
+pub fn delays(topology: &Topology) -> Vec<Vec<Option<u64>>> {
+    // machines first, then concentrators
+    let machines = topology.machines.len();
+    let mut edges = vec![Vec::new(); machines + topology.concentrators.len()];
+    let mut link = |a: usize, b: usize, delay: u64| {
+        edges[a].push((b, delay));
+        edges[b].push((a, delay));
+    };
+    for (index, machine) in topology.machines.iter().enumerate() {
+        // patch cables are too short to matter
+        if let Some(Link::Port(concentrator, _)) = machine.link {
+            link(index, machines + concentrator.0, 0);
+        }
+    }
+    for segment in &topology.segments {
+        let mut points: Vec<(usize, f64)> = segment
+            .taps
+            .iter()
+            .map(|tap| (tap.machine.0, tap.offset))
+            .collect();
+        // the first concentrator sits at the start of the cable, the others at its far end
+        for (index, concentrator) in segment.concentrators.iter().enumerate() {
+            let offset = if index == 0 { 0.0 } else { segment.length };
+            points.push((machines + concentrator.0, offset));
+        }
+        for (index, (a, at)) in points.iter().enumerate() {
+            for (b, bt) in &points[index + 1..] {
+                link(*a, *b, ((at - bt).abs() * NS_PER_METRE).round() as u64);
+            }
+        }
+    }
+
+    (0..machines)
+        .map(|from| {
+            let mut best: Vec<Option<u64>> = vec![None; edges.len()];
+            let mut queue = BinaryHeap::new();
+            best[from] = Some(0);
+            queue.push(Reverse((0, from)));
+            while let Some(Reverse((delay, node))) = queue.pop() {
+                if best[node].is_some_and(|known| known < delay) {
+                    continue;
+                }
+                for (next, cost) in &edges[node] {
+                    let repeat = if *next >= machines { REPEATER_DELAY } else { 0 };
+                    let total = delay + cost + repeat;
+                    if best[*next].is_none_or(|known| total < known) {
+                        best[*next] = Some(total);
+                        queue.push(Reverse((total, *next)));
+                    }
+                }
+            }
+            best.truncate(machines);
+            best
+        })
+        .collect()
+}
+
+/// Runs the frames of `scenario` over `topology` with CSMA/CD: machines listen before sending,
+/// wait for a busy medium to go quiet and back off after a collision
+pub fn simulate(topology: &Topology, scenario: &Scenario) -> Simulation {
+    let mut stations: Vec<Station> = (0..topology.machines.len())
+        .map(|_| Station::default())
+        .collect();
+    let mut order: Vec<usize> = (0..scenario.frames.len()).collect();
+    order.sort_by_key(|index| scenario.frames[*index].at);
+    for index in order {
+        stations[scenario.frames[index].from.0]
+            .pending
+            .push_back(index);
+    }
+
+    let mut simulator = Simulator {
+        topology,
+        scenario,
+        delays: delays(topology),
+        bit: 1000.0 / scenario.bitrate,
+        events: BinaryHeap::new(),
+        sequence: 0,
+        stations,
+        transmissions: Vec::new(),
+        frames: vec![None; scenario.frames.len()],
+        collisions: vec![0; scenario.frames.len()],
+        timeline: Vec::new(),
+        random: if scenario.seed == 0 { 1 } else { scenario.seed },
+    };
+    for station in 0..simulator.stations.len() {
+        if let Some(&frame) = simulator.stations[station].pending.front() {
+            simulator.schedule(scenario.frames[frame].at, Event::Ready(station));
+        }
+    }
+    while let Some(Reverse((time, _, event))) = simulator.events.pop() {
+        match event {
+            Event::Ready(station) => simulator.ready(time, station),
+            Event::Detect(transmission) => simulator.detect(time, transmission),
+            Event::End(transmission) => simulator.end(time, transmission),
+            Event::Deliver(transmission) => simulator.deliver(time, transmission),
+        }
+    }
+
+    let frames = simulator
+        .frames
+        .into_iter()
+        .zip(simulator.collisions)
+        .map(|(outcome, collisions)| FrameReport {
+            collisions,
+            outcome: outcome.unwrap_or_else(|| Outcome::Dropped("never sent".to_string())),
+        })
+        .collect();
+    Simulation {
+        timeline: simulator.timeline,
+        frames,
+    }
+}
+
+/// The timeline, every frame and the totals
+pub fn report(topology: &Topology, scenario: &Scenario, simulation: &Simulation) -> String {
+    let mut out = String::from("timeline:\n");
+    for (time, line) in &simulation.timeline {
+        let _ = writeln!(out, "  {:>10} us  {}", micros(*time), line);
+    }
+
+    out.push_str("\nframes:\n");
+    let mut latencies = Vec::new();
+    for (index, (frame, result)) in scenario.frames.iter().zip(&simulation.frames).enumerate() {
+        let outcome = match &result.outcome {
+            Outcome::Delivered { latency, .. } => {
+                latencies.push(*latency);
+                format!("delivered in {} us", micros(*latency))
+            }
+            Outcome::Dropped(reason) => format!("dropped, {}", reason),
+        };
+        let collisions = match result.collisions {
+            0 => String::new(),
+            1 => ", 1 collision".to_string(),
+            n => format!(", {} collisions", n),
+        };
+        let _ = writeln!(
+            out,
+            "  {} {} -> {}: {}{}",
+            index + 1,
+            topology.machine(frame.from).name,
+            topology.machine(frame.to).name,
+            outcome,
+            collisions
+        );
+    }
+
+    let sent = simulation.frames.len();
+    let collided = simulation
+        .frames
+        .iter()
+        .filter(|frame| frame.collisions > 0)
+        .count();
+    let collisions: usize = simulation.frames.iter().map(|frame| frame.collisions).sum();
+    let _ = writeln!(
+        out,
+        "\n{} frames, {} delivered, {} dropped, {} collided",
+        sent,
+        latencies.len(),
+        sent - latencies.len(),
+        collided
+    );
+    let _ = writeln!(out, "collisions: {}", collisions);
+    match (latencies.iter().min(), latencies.iter().max()) {
+        (Some(min), Some(max)) => {
+            let average = latencies.iter().sum::<u64>() / latencies.len() as u64;
+            let _ = writeln!(
+                out,
+                "latency: min {} us, average {} us, max {} us",
+                micros(*min),
+                micros(average),
+                micros(*max)
+            );
+        }
+        _ => out.push_str("latency: nothing delivered\n"),
+    }
+    out
+}
+
+fn micros(nanos: u64) -> String {
+    format!("{}.{:03}", nanos / 1000, nanos % 1000)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Event {
+    // the station's next frame goes out if the medium is quiet
+    Ready(usize),
+    // the sender hears another signal while transmitting
+    Detect(usize),
+    End(usize),
+    // the last bit reaches the receiver
+    Deliver(usize),
+}
+
+#[derive(Debug, Default)]
+struct Station {
+    // frame indexes, the first one is the one being sent
+    pending: VecDeque<usize>,
+    attempts: usize,
+}
+
+struct Transmission {
+    station: usize,
+    frame: usize,
+    start: u64,
+    // moves up to the end of the jam when a collision is detected
+    end: u64,
+    detect: Option<u64>,
+    aborted: bool,
+    // hit by a collision the sender never heard
+    late: bool,
+}
+
+struct Simulator<'a> {
+    topology: &'a Topology,
+    scenario: &'a Scenario,
+    delays: Vec<Vec<Option<u64>>>,
+    // nanoseconds per bit
+    bit: f64,
+    // the sequence number keeps events at the same time in the order they were scheduled
+    events: BinaryHeap<Reverse<(u64, u64, Event)>>,
+    sequence: u64,
+    stations: Vec<Station>,
+    transmissions: Vec<Transmission>,
+    frames: Vec<Option<Outcome>>,
+    collisions: Vec<usize>,
+    timeline: Vec<(u64, String)>,
+    random: u64,
+}
+
+impl Simulator<'_> {
+    fn schedule(&mut self, time: u64, event: Event) {
+        self.sequence += 1;
+        self.events.push(Reverse((time, self.sequence, event)));
+    }
+
+    fn log(&mut self, time: u64, line: String) {
+        self.timeline.push((time, line));
+    }
+
+    fn bits(&self, bits: u64) -> u64 {
+        (bits as f64 * self.bit).round() as u64
+    }
+
+    fn name(&self, station: usize) -> &str {
+        &self.topology.machines[station].name
+    }
+
+    // xorshift, good enough to pick a backoff
+    fn random(&mut self, below: u64) -> u64 {
+        let mut x = self.random;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.random = x;
+        x % below
+    }
+
+    fn ready(&mut self, time: u64, station: usize) {
+        let Some(&frame) = self.stations[station].pending.front() else {
+            return;
+        };
+        let to = self.scenario.frames[frame].to.0;
+        if self.topology.machines[station].link.is_none() {
+            let reason = format!("'{}' is not connected to anything", self.name(station));
+            return self.drop_frame(time, station, reason);
+        }
+        if self.delays[station][to].is_none() {
+            let reason = format!("'{}' cannot reach '{}'", self.name(station), self.name(to));
+            return self.drop_frame(time, station, reason);
+        }
+
+        // what this station hears right now
+        let mut busy_until = None;
+        let mut quiet_since = None;
+        for transmission in &self.transmissions {
+            let Some(delay) = self.delays[transmission.station][station] else {
+                continue;
+            };
+            let (arrives, leaves) = (transmission.start + delay, transmission.end + delay);
+            if arrives <= time && time < leaves {
+                busy_until = busy_until.max(Some(leaves));
+            } else if leaves <= time {
+                quiet_since = quiet_since.max(Some(leaves));
+            }
+        }
+        let gap = self.bits(GAP_BITS);
+        if let Some(until) = busy_until {
+            let line = format!(
+                "{} defers frame {}, the medium is busy",
+                self.name(station),
+                frame + 1
+            );
+            self.log(time, line);
+            return self.schedule(until + gap, Event::Ready(station));
+        }
+        if let Some(since) = quiet_since
+            && time < since + gap
+        {
+            return self.schedule(since + gap, Event::Ready(station));
+        }
+
+        let bytes = self.scenario.frames[frame].bytes.max(MIN_FRAME) as u64;
+        let end = time + self.bits(bytes * 8 + PREAMBLE_BITS);
+        let id = self.transmissions.len();
+        let attempt = match self.stations[station].attempts {
+            0 => String::new(),
+            n => format!(", attempt {}", n + 1),
+        };
+        let line = format!(
+            "{} starts sending frame {} to {}{}",
+            self.name(station),
+            frame + 1,
+            self.name(to),
+            attempt
+        );
+        self.log(time, line);
+
+        // anyone already sending whose signal hasn't got here yet collides with this one
+        let mut detect: Option<u64> = None;
+        let mut heard = Vec::new();
+        for (index, other) in self.transmissions.iter_mut().enumerate() {
+            let Some(delay) = self.delays[other.station][station] else {
+                continue;
+            };
+            if other.start + delay <= time {
+                continue;
+            }
+            let heard_here = other.start + delay;
+            detect = Some(detect.map_or(heard_here, |known| known.min(heard_here)));
+            let heard_there = time + delay;
+            if other.aborted {
+                continue;
+            }
+            if heard_there >= other.end {
+                other.late = true;
+            } else if other.detect.is_none_or(|known| heard_there < known) {
+                other.detect = Some(heard_there);
+                heard.push((heard_there, index));
+            }
+        }
+        for (at, index) in heard {
+            self.schedule(at, Event::Detect(index));
+        }
+        let mut transmission = Transmission {
+            station,
+            frame,
+            start: time,
+            end,
+            detect: None,
+            aborted: false,
+            late: false,
+        };
+        match detect {
+            Some(at) if at < end => {
+                transmission.detect = Some(at);
+                self.schedule(at, Event::Detect(id));
+            }
+            Some(_) => transmission.late = true,
+            None => {}
+        }
+        self.transmissions.push(transmission);
+        self.schedule(end, Event::End(id));
+    }
+
+    fn detect(&mut self, time: u64, id: usize) {
+        let jam = self.bits(JAM_BITS);
+        let transmission = &mut self.transmissions[id];
+        if transmission.aborted || transmission.detect != Some(time) {
+            return;
+        }
+        transmission.aborted = true;
+        transmission.end = time + jam;
+        let (station, frame, end) = (transmission.station, transmission.frame, transmission.end);
+        self.collisions[frame] += 1;
+        self.stations[station].attempts += 1;
+
+        let attempts = self.stations[station].attempts;
+        if attempts >= MAX_ATTEMPTS {
+            let reason = format!("gave up after {} collisions", attempts);
+            return self.drop_frame(time, station, reason);
+        }
+        let slots = self.random(1 << attempts.min(BACKOFF_LIMIT));
+        let line = format!(
+            "{} detects a collision on frame {}, jams and backs off {} slot{}",
+            self.name(station),
+            frame + 1,
+            slots,
+            if slots == 1 { "" } else { "s" }
+        );
+        self.log(time, line);
+        let wait = self.bits(slots * SLOT_BITS);
+        self.schedule(end + wait, Event::Ready(station));
+    }
+
+    fn end(&mut self, time: u64, id: usize) {
+        let transmission = &self.transmissions[id];
+        if transmission.aborted {
+            return;
+        }
+        let (station, frame) = (transmission.station, transmission.frame);
+        if transmission.late {
+            self.collisions[frame] += 1;
+            let reason = "lost to a collision the sender didn't hear".to_string();
+            return self.drop_frame(time, station, reason);
+        }
+        let to = self.scenario.frames[frame].to.0;
+        if let Some(delay) = self.delays[station][to] {
+            self.schedule(time + delay, Event::Deliver(id));
+        }
+        self.next_frame(time, station);
+    }
+
+    fn deliver(&mut self, time: u64, id: usize) {
+        let frame = self.transmissions[id].frame;
+        let latency = time - self.scenario.frames[frame].at;
+        let line = format!(
+            "{} receives frame {} from {}",
+            self.name(self.scenario.frames[frame].to.0),
+            frame + 1,
+            self.name(self.transmissions[id].station)
+        );
+        self.log(time, line);
+        self.frames[frame] = Some(Outcome::Delivered { at: time, latency });
+    }
+
+    fn drop_frame(&mut self, time: u64, station: usize, reason: String) {
+        let Some(&frame) = self.stations[station].pending.front() else {
+            return;
+        };
+        let line = format!(
+            "{} drops frame {}, {}",
+            self.name(station),
+            frame + 1,
+            reason
+        );
+        self.log(time, line);
+        self.frames[frame] = Some(Outcome::Dropped(reason));
+        self.next_frame(time, station);
+    }
+
+    // done with the frame at the front, the next one waits for the gap
+    fn next_frame(&mut self, time: u64, station: usize) {
+        let state = &mut self.stations[station];
+        state.pending.pop_front();
+        state.attempts = 0;
+        if let Some(&next) = state.pending.front() {
+            let at = self.scenario.frames[next]
+                .at
+                .max(time + self.bits(GAP_BITS));
+            self.schedule(at, Event::Ready(station));
+        }
+    }
+}
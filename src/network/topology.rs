@@ -0,0 +1,568 @@
+use crate::network::device::{
+    CoaxialSegment, Concentrator, ConcentratorId, DeviceId, Link, Machine, MachineId, PortState,
+    Position, SegmentId, Tap,
+};
+use crate::network::layout::{Layout, coaxial_cells};
+use crate::parser::ast::Direction;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetworkError {
+    DuplicateName(String),
+    UnknownDevice(String),
+    // a name used as the wrong kind of device, e.g. a machine where a concentrator goes
+    WrongKind {
+        name: String,
+        expected: &'static str,
+    },
+    NoPorts(String),
+    InvalidLength {
+        segment: String,
+        length: f64,
+    },
+    PortOutOfRange {
+        concentrator: String,
+        port: usize,
+        ports: usize,
+    },
+    PortTaken {
+        concentrator: String,
+        port: usize,
+        machine: String,
+    },
+    NoFreePort(String),
+    AlreadyConnected {
+        machine: String,
+        link: String,
+    },
+    OffsetOutOfRange {
+        segment: String,
+        offset: f64,
+        length: f64,
+    },
+    TapTaken {
+        segment: String,
+        offset: f64,
+        machine: String,
+    },
+    SegmentFull(String),
+    SegmentAttached {
+        segment: String,
+        concentrator: String,
+    },
+    NotPlaced(String),
+    OutOfBounds {
+        device: String,
+        position: Position,
+        width: i64,
+        height: i64,
+    },
+    Overlap {
+        device: String,
+        position: Position,
+        occupant: String,
+    },
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetworkError::DuplicateName(name) => write!(f, "'{}' is already defined", name),
+            NetworkError::UnknownDevice(name) => write!(f, "unknown device '{}'", name),
+            NetworkError::WrongKind { name, expected } => {
+                write!(f, "'{}' is not a {}", name, expected)
+            }
+            NetworkError::NoPorts(name) => {
+                write!(f, "concentrator '{}' needs at least one port", name)
+            }
+            NetworkError::InvalidLength { segment, length } => write!(
+                f,
+                "coaxial '{}' needs a positive length, found {}",
+                segment, length
+            ),
+            NetworkError::PortOutOfRange {
+                concentrator,
+                port,
+                ports,
+            } => write!(
+                f,
+                "concentrator '{}' has ports 1 to {}, found port {}",
+                concentrator, ports, port
+            ),
+            NetworkError::PortTaken {
+                concentrator,
+                port,
+                machine,
+            } => write!(
+                f,
+                "port {} of '{}' is already taken by '{}'",
+                port, concentrator, machine
+            ),
+            NetworkError::NoFreePort(name) => {
+                write!(f, "concentrator '{}' has no free port", name)
+            }
+            NetworkError::AlreadyConnected { machine, link } => {
+                write!(f, "machine '{}' is already connected to {}", machine, link)
+            }
+            NetworkError::OffsetOutOfRange {
+                segment,
+                offset,
+                length,
+            } => write!(
+                f,
+                "coaxial '{}' is {} long, found a tap at {}",
+                segment, length, offset
+            ),
+            NetworkError::TapTaken {
+                segment,
+                offset,
+                machine,
+            } => write!(
+                f,
+                "'{}' already taps coaxial '{}' at {}",
+                machine, segment, offset
+            ),
+            NetworkError::SegmentFull(name) => {
+                write!(f, "coaxial '{}' has no room for another machine", name)
+            }
+            NetworkError::SegmentAttached {
+                segment,
+                concentrator,
+            } => write!(
+                f,
+                "coaxial '{}' is already attached to '{}'",
+                segment, concentrator
+            ),
+            NetworkError::NotPlaced(name) => write!(f, "'{}' has not been placed yet", name),
+            NetworkError::OutOfBounds {
+                device,
+                position,
+                width,
+                height,
+            } => write!(
+                f,
+                "'{}' would leave the grid at {}, it goes from (0, 0) to ({}, {})",
+                device,
+                position,
+                width - 1,
+                height - 1
+            ),
+            NetworkError::Overlap {
+                device,
+                position,
+                occupant,
+            } => write!(
+                f,
+                "'{}' would overlap '{}' at {}",
+                device, occupant, position
+            ),
+        }
+    }
+}
+
+impl Error for NetworkError {}
+
+type Result<T> = std::result::Result<T, NetworkError>;
+
+/// Every device of a network and how they are wired, built up by the network commands
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Topology {
+    pub machines: Vec<Machine>,
+    pub concentrators: Vec<Concentrator>,
+    pub segments: Vec<CoaxialSegment>,
+    pub layout: Layout,
+    names: HashMap<String, DeviceId>,
+}
+
+impl Topology {
+    pub fn new() -> Self {
+        Topology::default()
+    }
+
+    /// A topology on a grid other than the default one
+    pub fn with_grid(width: i64, height: i64) -> Self {
+        Topology {
+            layout: Layout::new(width, height),
+            ..Topology::default()
+        }
+    }
+
+    fn declare(&mut self, name: &str, id: DeviceId) -> Result<()> {
+        if self.names.contains_key(name) {
+            return Err(NetworkError::DuplicateName(name.to_string()));
+        }
+        self.names.insert(name.to_string(), id);
+        Ok(())
+    }
+
+    pub fn add_machine(&mut self, name: &str) -> Result<MachineId> {
+        let id = MachineId(self.machines.len());
+        self.declare(name, DeviceId::Machine(id))?;
+        self.machines.push(Machine {
+            name: name.to_string(),
+            position: None,
+            link: None,
+        });
+        Ok(id)
+    }
+
+    pub fn add_concentrator(&mut self, name: &str, ports: usize) -> Result<ConcentratorId> {
+        if ports == 0 {
+            return Err(NetworkError::NoPorts(name.to_string()));
+        }
+        let id = ConcentratorId(self.concentrators.len());
+        self.declare(name, DeviceId::Concentrator(id))?;
+        self.concentrators.push(Concentrator {
+            name: name.to_string(),
+            position: None,
+            ports: vec![PortState::Free; ports],
+        });
+        Ok(id)
+    }
+
+    pub fn add_coaxial(&mut self, name: &str, length: f64) -> Result<SegmentId> {
+        if length.is_nan() || length <= 0.0 {
+            return Err(NetworkError::InvalidLength {
+                segment: name.to_string(),
+                length,
+            });
+        }
+        let id = SegmentId(self.segments.len());
+        self.declare(name, DeviceId::Coaxial(id))?;
+        self.segments.push(CoaxialSegment {
+            name: name.to_string(),
+            length,
+            position: None,
+            direction: None,
+            taps: Vec::new(),
+            concentrators: Vec::new(),
+        });
+        Ok(id)
+    }
+
+    pub fn device(&self, name: &str) -> Result<DeviceId> {
+        self.names
+            .get(name)
+            .copied()
+            .ok_or_else(|| NetworkError::UnknownDevice(name.to_string()))
+    }
+
+    pub fn machine_id(&self, name: &str) -> Result<MachineId> {
+        match self.device(name)? {
+            DeviceId::Machine(id) => Ok(id),
+            _ => Err(wrong_kind(name, "machine")),
+        }
+    }
+
+    pub fn concentrator_id(&self, name: &str) -> Result<ConcentratorId> {
+        match self.device(name)? {
+            DeviceId::Concentrator(id) => Ok(id),
+            _ => Err(wrong_kind(name, "concentrator")),
+        }
+    }
+
+    pub fn segment_id(&self, name: &str) -> Result<SegmentId> {
+        match self.device(name)? {
+            DeviceId::Coaxial(id) => Ok(id),
+            _ => Err(wrong_kind(name, "coaxial segment")),
+        }
+    }
+
+    pub fn machine(&self, id: MachineId) -> &Machine {
+        &self.machines[id.0]
+    }
+
+    pub fn concentrator(&self, id: ConcentratorId) -> &Concentrator {
+        &self.concentrators[id.0]
+    }
+
+    pub fn segment(&self, id: SegmentId) -> &CoaxialSegment {
+        &self.segments[id.0]
+    }
+
+    /// Segments plugged into a concentrator with colocaCoaxialConcentrador
+    pub fn segments_of(&self, concentrator: ConcentratorId) -> Vec<SegmentId> {
+        (0..self.segments.len())
+            .map(SegmentId)
+            .filter(|id| self.segment(*id).concentrators.contains(&concentrator))
+            .collect()
+    }
+
+    pub fn name(&self, device: DeviceId) -> &str {
+        match device {
+            DeviceId::Machine(id) => &self.machine(id).name,
+            DeviceId::Concentrator(id) => &self.concentrator(id).name,
+            DeviceId::Coaxial(id) => &self.segment(id).name,
+        }
+    }
+
+    pub fn position(&self, device: DeviceId) -> Option<Position> {
+        match device {
+            DeviceId::Machine(id) => self.machine(id).position,
+            DeviceId::Concentrator(id) => self.concentrator(id).position,
+            DeviceId::Coaxial(id) => self.segment(id).position,
+        }
+    }
+
+    /// Devices in declaration order, machines first
+    pub fn devices(&self) -> impl Iterator<Item = DeviceId> + '_ {
+        let machines = (0..self.machines.len()).map(|index| DeviceId::Machine(MachineId(index)));
+        let concentrators = (0..self.concentrators.len())
+            .map(|index| DeviceId::Concentrator(ConcentratorId(index)));
+        let segments = (0..self.segments.len()).map(|index| DeviceId::Coaxial(SegmentId(index)));
+        machines.chain(concentrators).chain(segments)
+    }
+
+    /// Cells `device` would cover placed at `position`, a coaxial keeps the way it runs
+    pub fn cells(&self, device: DeviceId, position: Position) -> Result<Vec<Position>> {
+        match device {
+            DeviceId::Coaxial(id) => {
+                let segment = self.segment(id);
+                let direction = segment.direction.unwrap_or(Direction::Right);
+                self.cable_cells(device, position, direction, segment.length)
+            }
+            _ => Ok(vec![position]),
+        }
+    }
+
+    // both ends of the cable are checked first, a cable longer than the grid never gets a
+    // cell for each unit of its length
+    fn cable_cells(
+        &self,
+        device: DeviceId,
+        start: Position,
+        direction: Direction,
+        length: f64,
+    ) -> Result<Vec<Position>> {
+        let end = start.step(direction, length as i64);
+        if let Some(cell) = [start, end]
+            .into_iter()
+            .find(|cell| !self.layout.contains(*cell))
+        {
+            return Err(self.out_of_bounds(device, cell));
+        }
+        Ok(coaxial_cells(start, direction, length))
+    }
+
+    /// `coloca(m1, 2, 3)`, placing a device again moves it
+    pub fn place(&mut self, device: DeviceId, position: Position) -> Result<()> {
+        let cells = self.cells(device, position)?;
+        self.check_cells(device, &cells)?;
+        self.layout.cover(device, &cells);
+        match device {
+            DeviceId::Machine(id) => self.machines[id.0].position = Some(position),
+            DeviceId::Concentrator(id) => self.concentrators[id.0].position = Some(position),
+            DeviceId::Coaxial(id) => {
+                let segment = &mut self.segments[id.0];
+                segment.position = Some(position);
+                segment.direction.get_or_insert(Direction::Right);
+            }
+        }
+        Ok(())
+    }
+
+    /// `coloca(m1, derecha, c1)`, the nearest cell towards `direction` from `anchor` where the
+    /// device fits. Returns where it went
+    pub fn place_next_to(
+        &mut self,
+        device: DeviceId,
+        direction: Direction,
+        anchor: DeviceId,
+    ) -> Result<Position> {
+        let Some(mut position) = self.position(anchor) else {
+            return Err(NetworkError::NotPlaced(self.name(anchor).to_string()));
+        };
+        loop {
+            position = position.step(direction, 1);
+            let cells = self.cells(device, position)?;
+            match self.layout.conflict(device, &cells) {
+                // skip over whatever is in the way
+                Some((_, Some(_))) => continue,
+                Some((cell, None)) => return Err(self.out_of_bounds(device, cell)),
+                None => break,
+            }
+        }
+        self.place(device, position)?;
+        Ok(position)
+    }
+
+    /// `colocaCoaxial(x1, 0, 0, derecha)`, where the cable starts and which way it runs
+    pub fn place_coaxial(
+        &mut self,
+        segment: SegmentId,
+        start: Position,
+        direction: Direction,
+    ) -> Result<()> {
+        let device = DeviceId::Coaxial(segment);
+        let length = self.segment(segment).length;
+        let cells = self.cable_cells(device, start, direction, length)?;
+        self.check_cells(device, &cells)?;
+        self.layout.cover(device, &cells);
+        let segment = &mut self.segments[segment.0];
+        segment.position = Some(start);
+        segment.direction = Some(direction);
+        Ok(())
+    }
+
+    fn check_cells(&self, device: DeviceId, cells: &[Position]) -> Result<()> {
+        match self.layout.conflict(device, cells) {
+            Some((cell, Some(occupant))) => Err(NetworkError::Overlap {
+                device: self.name(device).to_string(),
+                position: cell,
+                occupant: self.name(occupant).to_string(),
+            }),
+            Some((cell, None)) => Err(self.out_of_bounds(device, cell)),
+            None => Ok(()),
+        }
+    }
+
+    fn out_of_bounds(&self, device: DeviceId, position: Position) -> NetworkError {
+        NetworkError::OutOfBounds {
+            device: self.name(device).to_string(),
+            position,
+            width: self.layout.width,
+            height: self.layout.height,
+        }
+    }
+
+    /// `colocaCoaxialConcentrador(x1, c1)`
+    pub fn attach_segment(
+        &mut self,
+        segment: SegmentId,
+        concentrator: ConcentratorId,
+    ) -> Result<()> {
+        if self.segment(segment).concentrators.contains(&concentrator) {
+            return Err(NetworkError::SegmentAttached {
+                segment: self.segment(segment).name.clone(),
+                concentrator: self.concentrator(concentrator).name.clone(),
+            });
+        }
+        self.segments[segment.0].concentrators.push(concentrator);
+        Ok(())
+    }
+
+    /// State of port `port` (from 1) of a concentrator
+    pub fn port(&self, concentrator: ConcentratorId, port: usize) -> Result<PortState> {
+        let hub = self.concentrator(concentrator);
+        match port.checked_sub(1).and_then(|index| hub.ports.get(index)) {
+            Some(state) => Ok(*state),
+            None => Err(NetworkError::PortOutOfRange {
+                concentrator: hub.name.clone(),
+                port,
+                ports: hub.ports.len(),
+            }),
+        }
+    }
+
+    /// `uneMaquinaPuerto(m1, c1, 2)`
+    pub fn connect_port(
+        &mut self,
+        machine: MachineId,
+        concentrator: ConcentratorId,
+        port: usize,
+    ) -> Result<()> {
+        if let PortState::Connected(taken) = self.port(concentrator, port)? {
+            return Err(NetworkError::PortTaken {
+                concentrator: self.concentrator(concentrator).name.clone(),
+                port,
+                machine: self.machine(taken).name.clone(),
+            });
+        }
+        self.check_unconnected(machine)?;
+        self.concentrators[concentrator.0].ports[port - 1] = PortState::Connected(machine);
+        self.machines[machine.0].link = Some(Link::Port(concentrator, port));
+        Ok(())
+    }
+
+    /// `asignaPuerto(m1, c1)`, takes the first free port and returns its number
+    pub fn assign_port(
+        &mut self,
+        machine: MachineId,
+        concentrator: ConcentratorId,
+    ) -> Result<usize> {
+        let hub = self.concentrator(concentrator);
+        let port = hub
+            .first_free()
+            .ok_or_else(|| NetworkError::NoFreePort(hub.name.clone()))?;
+        self.connect_port(machine, concentrator, port)?;
+        Ok(port)
+    }
+
+    /// `maquinaCoaxial(m1, x1, 10)`
+    pub fn attach_coaxial(
+        &mut self,
+        machine: MachineId,
+        segment: SegmentId,
+        offset: f64,
+    ) -> Result<()> {
+        let cable = self.segment(segment);
+        if !(0.0..=cable.length).contains(&offset) {
+            return Err(NetworkError::OffsetOutOfRange {
+                segment: cable.name.clone(),
+                offset,
+                length: cable.length,
+            });
+        }
+        if let Some(tap) = cable.taps.iter().find(|tap| tap.offset == offset) {
+            return Err(NetworkError::TapTaken {
+                segment: cable.name.clone(),
+                offset,
+                machine: self.machine(tap.machine).name.clone(),
+            });
+        }
+        self.check_unconnected(machine)?;
+
+        let taps = &mut self.segments[segment.0].taps;
+        let at = taps.partition_point(|tap| tap.offset < offset);
+        taps.insert(at, Tap { machine, offset });
+        self.machines[machine.0].link = Some(Link::Coaxial(segment, offset));
+        Ok(())
+    }
+
+    /// `asignaMaquinaCoaxial(m1, x1)`, taps one unit past the last machine and returns where
+    pub fn assign_coaxial(&mut self, machine: MachineId, segment: SegmentId) -> Result<f64> {
+        let cable = self.segment(segment);
+        if cable.is_complete() {
+            return Err(NetworkError::SegmentFull(cable.name.clone()));
+        }
+        let offset = cable.next_offset();
+        self.attach_coaxial(machine, segment, offset)?;
+        Ok(offset)
+    }
+
+    // a machine has a single network card
+    fn check_unconnected(&self, machine: MachineId) -> Result<()> {
+        match self.machine(machine).link {
+            Some(link) => Err(NetworkError::AlreadyConnected {
+                machine: self.machine(machine).name.clone(),
+                link: self.describe_link(link),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// `port 2 of 'c1'` or `'x1' at 10`
+    pub fn describe_link(&self, link: Link) -> String {
+        match link {
+            Link::Port(concentrator, port) => {
+                format!(
+                    "port {} of '{}'",
+                    port,
+                    self.concentrator(concentrator).name
+                )
+            }
+            Link::Coaxial(segment, offset) => {
+                format!("'{}' at {}", self.segment(segment).name, offset)
+            }
+        }
+    }
+}
+
+fn wrong_kind(name: &str, expected: &'static str) -> NetworkError {
+    NetworkError::WrongKind {
+        name: name.to_string(),
+        expected,
+    }
+}
@@ -0,0 +1,596 @@
+use crate::lexer::lexer_ana::{Span, Token, TokenType};
+use crate::parser::ast::{
+    AssignOp, Block, Decl, DefineDecl, DefineItem, DeviceKind, Direction, Expr, ExprKind, FnDecl,
+    Iterable, ModuleDecl, NetworkArg, NetworkCommand, Param, Program, Stmt, StmtKind, TypeName,
+    UnaryOp,
+};
+use crate::parser::expression::infix_binding_power;
+use crate::parser::grammar::Grammar;
+use crate::parser::parser_ana::ParseError;
+use crate::parser::table_parser::ParseTree;
+use crate::util::interner::Symbol;
+
+type Result<T> = std::result::Result<T, ParseError>;
+
+/// Semantic actions of the table driven parser: builds the same AST as the hand written
+/// parser from a parse tree, one action per nonterminal
+pub struct AstBuilder<'g> {
+    grammar: &'g Grammar,
+}
+
+fn span_of(tree: &ParseTree) -> Span {
+    match (tree.first_token(), tree.last_token()) {
+        (Some(first), Some(last)) => first.span.to(last.span),
+        _ => Span::new(0, 0, 1, 1),
+    }
+}
+
+fn symbol(token: &Token) -> Result<Symbol> {
+    token
+        .symbol
+        .ok_or_else(|| ParseError::new(format!("'{}' is not a name", token.lexeme), token.span))
+}
+
+impl<'g> AstBuilder<'g> {
+    pub fn new(grammar: &'g Grammar) -> Self {
+        AstBuilder { grammar }
+    }
+
+    fn name(&self, tree: &ParseTree) -> &str {
+        match tree {
+            ParseTree::Node { nonterminal, .. } => &self.grammar.nonterminals[*nonterminal],
+            ParseTree::Leaf(_) => "",
+        }
+    }
+
+    // a tree whose shape the actions don't know, the grammar file changed under them
+    fn shape_error(&self, tree: &ParseTree) -> ParseError {
+        let what = match tree {
+            ParseTree::Leaf(token) => format!("token '{}'", token.lexeme),
+            ParseTree::Node { .. } => format!("'{}'", self.name(tree)),
+        };
+        ParseError::new(
+            format!("no semantic action for {} in this position", what),
+            span_of(tree),
+        )
+    }
+
+    fn children<'t, 'src>(&self, tree: &'t ParseTree<'src>) -> &'t [ParseTree<'src>] {
+        match tree {
+            ParseTree::Node { children, .. } => children,
+            ParseTree::Leaf(_) => &[],
+        }
+    }
+
+    fn leaf<'t, 'src>(&self, tree: &'t ParseTree<'src>) -> Result<&'t Token<'src>> {
+        match tree {
+            ParseTree::Leaf(token) => Ok(token),
+            ParseTree::Node { .. } => Err(self.shape_error(tree)),
+        }
+    }
+
+    // right recursive lists, `items -> item items_tail` and `items_tail -> ',' item items_tail | ε`
+    fn list<'t, 'src>(&self, tree: &'t ParseTree<'src>) -> Vec<&'t ParseTree<'src>> {
+        let mut items = Vec::new();
+        let mut current = tree;
+        while let Some((last, rest)) = self.children(current).split_last() {
+            items.extend(
+                rest.iter()
+                    .filter(|child| matches!(child, ParseTree::Node { .. })),
+            );
+            match last {
+                ParseTree::Node { .. } => current = last,
+                ParseTree::Leaf(_) => break,
+            }
+        }
+        items
+    }
+
+    // program -> header declarations 'inicio' statements 'fin'
+    pub fn program(&self, tree: &ParseTree) -> Result<Program> {
+        let [header, declarations, open, statements, _close] = self.children(tree) else {
+            return Err(self.shape_error(tree));
+        };
+
+        let name = match self.children(header) {
+            [_, name, _] => {
+                let token = self.leaf(name)?;
+                Some((symbol(token)?, token.span))
+            }
+            _ => None,
+        };
+
+        let declarations = self
+            .list(declarations)
+            .into_iter()
+            .map(|declaration| self.declaration(declaration))
+            .collect::<Result<Vec<_>>>()?;
+
+        let open = self.leaf(open)?;
+        let body = Block {
+            statements: self.statements(statements)?,
+            span: open.span.to(span_of(tree)),
+        };
+
+        Ok(Program {
+            name,
+            declarations,
+            body,
+            span: span_of(tree),
+        })
+    }
+
+    fn declaration(&self, tree: &ParseTree) -> Result<Decl> {
+        match self.children(tree) {
+            [module] if self.name(module) == "module" => Ok(Decl::Module(self.module(module)?)),
+            [function] => Ok(Decl::Function(self.function(function)?)),
+            [_, device, items, _] => {
+                let kind = match self.leaf(&self.children(device)[0])?.type_ {
+                    TokenType::Maquinas_ => DeviceKind::Machine,
+                    TokenType::Concentradores_ => DeviceKind::Concentrator,
+                    TokenType::Coaxial_ => DeviceKind::Coaxial,
+                    _ => return Err(self.shape_error(device)),
+                };
+                let items = self
+                    .list(items)
+                    .into_iter()
+                    .map(|item| self.define_item(item))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Decl::Define(DefineDecl {
+                    kind,
+                    items,
+                    span: span_of(tree),
+                }))
+            }
+            _ => Err(self.shape_error(tree)),
+        }
+    }
+
+    // define_item -> IDENT define_size
+    fn define_item(&self, tree: &ParseTree) -> Result<DefineItem> {
+        let [name, size] = self.children(tree) else {
+            return Err(self.shape_error(tree));
+        };
+        let name = self.leaf(name)?;
+        let size = match self.children(size) {
+            [_, value] => Some(self.expr(value)?),
+            _ => None,
+        };
+        Ok(DefineItem {
+            name: symbol(name)?,
+            span: name.span,
+            size,
+        })
+    }
+
+    // module -> 'modulo' IDENT module_body
+    fn module(&self, tree: &ParseTree) -> Result<ModuleDecl> {
+        let [_, name, body] = self.children(tree) else {
+            return Err(self.shape_error(tree));
+        };
+        let [_, functions, _] = self.children(body) else {
+            return Err(self.shape_error(body));
+        };
+        let name = self.leaf(name)?;
+        let functions = self
+            .list(functions)
+            .into_iter()
+            .map(|function| self.function(function))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ModuleDecl {
+            name: symbol(name)?,
+            name_span: name.span,
+            functions,
+            span: span_of(tree),
+        })
+    }
+
+    // function -> 'fn' IDENT '(' params ')' return_type block
+    fn function(&self, tree: &ParseTree) -> Result<FnDecl> {
+        let [_, name, _, params, _, return_type, body] = self.children(tree) else {
+            return Err(self.shape_error(tree));
+        };
+        let name = self.leaf(name)?;
+
+        let params = self
+            .list(params)
+            .into_iter()
+            .map(|param| match self.children(param) {
+                [name, _, ty] => {
+                    let name = self.leaf(name)?;
+                    Ok(Param {
+                        name: symbol(name)?,
+                        span: name.span,
+                        ty: self.type_name(ty)?,
+                    })
+                }
+                _ => Err(self.shape_error(param)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let return_type = match self.children(return_type) {
+            [_, ty] => Some(self.type_name(ty)?),
+            _ => None,
+        };
+
+        Ok(FnDecl {
+            name: symbol(name)?,
+            name_span: name.span,
+            params,
+            return_type,
+            body: self.block(body)?,
+            span: span_of(tree),
+        })
+    }
+
+    fn type_name(&self, tree: &ParseTree) -> Result<TypeName> {
+        let token = self.leaf(&self.children(tree)[0])?;
+        Ok(TypeName {
+            name: symbol(token)?,
+            span: token.span,
+        })
+    }
+
+    // block -> '{' statements '}' | 'inicio' statements 'fin'
+    fn block(&self, tree: &ParseTree) -> Result<Block> {
+        let [_, statements, _] = self.children(tree) else {
+            return Err(self.shape_error(tree));
+        };
+        Ok(Block {
+            statements: self.statements(statements)?,
+            span: span_of(tree),
+        })
+    }
+
+    fn statements(&self, tree: &ParseTree) -> Result<Vec<Stmt>> {
+        self.list(tree)
+            .into_iter()
+            .map(|statement| self.statement(statement))
+            .collect()
+    }
+
+    fn statement(&self, tree: &ParseTree) -> Result<Stmt> {
+        let children = self.children(tree);
+        let Some(first) = children.first() else {
+            return Err(self.shape_error(tree));
+        };
+
+        let kind = match (first, children) {
+            (ParseTree::Node { .. }, [if_statement]) => self.if_statement(if_statement)?,
+            (ParseTree::Node { .. }, [command, _, args, _, _]) => {
+                let token = self.leaf(&self.children(command)[0])?;
+                let command = NetworkCommand::from_token(&token.type_)
+                    .ok_or_else(|| self.shape_error(command))?;
+                StmtKind::Network {
+                    command,
+                    args: self.network_args(args)?,
+                }
+            }
+            (ParseTree::Node { .. }, [target, assignment, _]) => {
+                self.expression_statement(target, assignment)?
+            }
+            (ParseTree::Leaf(keyword), _) => match (&keyword.type_, children) {
+                (TokenType::KeywordLet, [_, name, ty, _, value, _]) => {
+                    let name = self.leaf(name)?;
+                    let ty = match self.children(ty) {
+                        [_, ty] => Some(self.type_name(ty)?),
+                        _ => None,
+                    };
+                    StmtKind::Let {
+                        name: symbol(name)?,
+                        name_span: name.span,
+                        ty,
+                        value: self.expr(value)?,
+                    }
+                }
+                (TokenType::KeywordWhile, [_, condition, body]) => StmtKind::While {
+                    condition: self.expr(condition)?,
+                    body: self.block(body)?,
+                },
+                (TokenType::KeywordFor, [_, var, _, iterable, body]) => {
+                    let var = self.leaf(var)?;
+                    StmtKind::For {
+                        var: symbol(var)?,
+                        var_span: var.span,
+                        iterable: self.iterable(iterable)?,
+                        body: self.block(body)?,
+                    }
+                }
+                (TokenType::KeywordBreak, _) => StmtKind::Break,
+                (TokenType::KeywordContinue, _) => StmtKind::Continue,
+                (TokenType::KeywordReturn, [_, value, _]) => {
+                    let value = match self.children(value) {
+                        [value] => Some(self.expr(value)?),
+                        _ => None,
+                    };
+                    StmtKind::Return(value)
+                }
+                (TokenType::Escribe_, [_, _, args, _, _]) => StmtKind::Write(
+                    self.list(args)
+                        .into_iter()
+                        .map(|arg| self.expr(arg))
+                        .collect::<Result<Vec<_>>>()?,
+                ),
+                _ => return Err(self.shape_error(tree)),
+            },
+            _ => return Err(self.shape_error(tree)),
+        };
+        Ok(Stmt::new(kind, span_of(tree)))
+    }
+
+    // iterable -> expr | device
+    fn iterable(&self, tree: &ParseTree) -> Result<Iterable> {
+        let [child] = self.children(tree) else {
+            return Err(self.shape_error(tree));
+        };
+        if self.name(child) == "expr" {
+            return Ok(Iterable::Expr(self.expr(child)?));
+        }
+        let token = self.leaf(&self.children(child)[0])?;
+        let kind = match token.type_ {
+            TokenType::Maquinas_ => DeviceKind::Machine,
+            TokenType::Concentradores_ => DeviceKind::Concentrator,
+            TokenType::Coaxial_ => DeviceKind::Coaxial,
+            _ => return Err(self.shape_error(child)),
+        };
+        Ok(Iterable::Devices(kind, token.span))
+    }
+
+    // statement -> expr assignment ';'
+    fn expression_statement(&self, target: &ParseTree, assignment: &ParseTree) -> Result<StmtKind> {
+        let target = self.expr(target)?;
+        let [op, value] = self.children(assignment) else {
+            return Ok(StmtKind::Expr(target));
+        };
+
+        let operator = self.leaf(&self.children(op)[0])?;
+        let op = match operator.type_ {
+            TokenType::OperatorAssign => AssignOp::Assign,
+            TokenType::OperatorPlusAssign => AssignOp::AddAssign,
+            TokenType::OperatorMinusAssign => AssignOp::SubtractAssign,
+            TokenType::OperatorMultiplyAssign => AssignOp::MultiplyAssign,
+            TokenType::OperatorDivideAssign => AssignOp::DivideAssign,
+            _ => return Err(self.shape_error(op)),
+        };
+        if !matches!(
+            target.kind,
+            ExprKind::Identifier(_) | ExprKind::Member { .. } | ExprKind::Index { .. }
+        ) {
+            return Err(ParseError::new(
+                format!(
+                    "cannot assign to this expression with '{}'",
+                    operator.lexeme
+                ),
+                target.span,
+            ));
+        }
+
+        Ok(StmtKind::Assign {
+            target,
+            op,
+            value: self.expr(value)?,
+        })
+    }
+
+    fn network_args(&self, tree: &ParseTree) -> Result<Vec<NetworkArg>> {
+        self.list(tree)
+            .into_iter()
+            .map(|arg| {
+                let [inner] = self.children(arg) else {
+                    return Err(self.shape_error(arg));
+                };
+                if self.name(inner) != "direction" {
+                    return Ok(NetworkArg::Expr(self.expr(inner)?));
+                }
+                let token = self.leaf(&self.children(inner)[0])?;
+                let direction =
+                    Direction::from_token(&token.type_).ok_or_else(|| self.shape_error(inner))?;
+                Ok(NetworkArg::Direction(direction, token.span))
+            })
+            .collect()
+    }
+
+    // if_statement -> ('si' | 'if') expr block else_part
+    fn if_statement(&self, tree: &ParseTree) -> Result<StmtKind> {
+        let [_, condition, then_block, else_part] = self.children(tree) else {
+            return Err(self.shape_error(tree));
+        };
+
+        let else_block = match self.children(else_part) {
+            [_, body] => {
+                let [inner] = self.children(body) else {
+                    return Err(self.shape_error(body));
+                };
+                if self.name(inner) == "if_statement" {
+                    // sino si ... is a block holding just the nested si
+                    let span = span_of(inner);
+                    Some(Block {
+                        statements: vec![Stmt::new(self.if_statement(inner)?, span)],
+                        span,
+                    })
+                } else {
+                    Some(self.block(inner)?)
+                }
+            }
+            _ => None,
+        };
+
+        Ok(StmtKind::If {
+            condition: self.expr(condition)?,
+            then_block: self.block(then_block)?,
+            else_block,
+        })
+    }
+
+    fn expr(&self, tree: &ParseTree) -> Result<Expr> {
+        match (self.name(tree), self.children(tree)) {
+            ("primary", _) => self.primary(tree),
+            ("postfix", [primary, tail]) => self.postfix(self.expr(primary)?, tail),
+            ("unary", [operator, operand]) => {
+                let operator = self.leaf(operator)?;
+                let op = match operator.type_ {
+                    TokenType::Not => UnaryOp::Not,
+                    _ => UnaryOp::Negate,
+                };
+                let operand = self.expr(operand)?;
+                let span = operator.span.to(operand.span);
+                Ok(Expr::new(
+                    ExprKind::Unary {
+                        op,
+                        operand: Box::new(operand),
+                    },
+                    span,
+                ))
+            }
+            // expr, or_expr, ... power: an operand followed by its operator tail
+            (_, [operand, tail]) => self.binary_tail(self.expr(operand)?, tail),
+            (_, [inner]) => self.expr(inner),
+            _ => Err(self.shape_error(tree)),
+        }
+    }
+
+    // tail -> op operand tail | ε, folded to the left. `power_tail -> '**' unary` has no
+    // tail of its own, the right associativity comes from `unary`
+    fn binary_tail(&self, mut left: Expr, tail: &ParseTree) -> Result<Expr> {
+        let mut current = tail;
+        loop {
+            let (operator, operand, next) = match self.children(current) {
+                [] => return Ok(left),
+                [operator, operand] => (operator, operand, None),
+                [operator, operand, next] => (operator, operand, Some(next)),
+                _ => return Err(self.shape_error(current)),
+            };
+
+            let operator = self.leaf(operator)?;
+            let (op, _, _) =
+                infix_binding_power(&operator.type_).ok_or_else(|| self.shape_error(current))?;
+            let right = self.expr(operand)?;
+            let span = left.span.to(right.span);
+            left = Expr::new(
+                ExprKind::Binary {
+                    op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                },
+                span,
+            );
+
+            match next {
+                Some(next) => current = next,
+                None => return Ok(left),
+            }
+        }
+    }
+
+    // postfix_tail -> '(' args ')' postfix_tail | '[' expr ']' postfix_tail | '.' member postfix_tail | ε
+    fn postfix(&self, mut object: Expr, tail: &ParseTree) -> Result<Expr> {
+        let mut current = tail;
+        loop {
+            let children = self.children(current);
+            let Some(next) = children.last() else {
+                return Ok(object);
+            };
+
+            let operator = self.leaf(&children[0])?;
+            object = match (&operator.type_, children) {
+                (TokenType::ParenthesisLeft, [_, args, close, _]) => {
+                    let args = self
+                        .list(args)
+                        .into_iter()
+                        .map(|arg| self.expr(arg))
+                        .collect::<Result<Vec<_>>>()?;
+                    let span = object.span.to(self.leaf(close)?.span);
+                    Expr::new(
+                        ExprKind::Call {
+                            callee: Box::new(object),
+                            args,
+                        },
+                        span,
+                    )
+                }
+                (TokenType::BracketLeft, [_, index, close, _]) => {
+                    let index = self.expr(index)?;
+                    let span = object.span.to(self.leaf(close)?.span);
+                    Expr::new(
+                        ExprKind::Index {
+                            object: Box::new(object),
+                            index: Box::new(index),
+                        },
+                        span,
+                    )
+                }
+                (TokenType::Dot, [_, member, _]) => {
+                    // member -> IDENT | query
+                    let name = member
+                        .first_token()
+                        .ok_or_else(|| self.shape_error(member))?;
+                    let span = object.span.to(name.span);
+                    Expr::new(
+                        ExprKind::Member {
+                            object: Box::new(object),
+                            member: symbol(name)?,
+                            member_span: name.span,
+                        },
+                        span,
+                    )
+                }
+                _ => return Err(self.shape_error(current)),
+            };
+            current = next;
+        }
+    }
+
+    fn primary(&self, tree: &ParseTree) -> Result<Expr> {
+        match self.children(tree) {
+            [open, inner, close] => {
+                let mut expr = self.expr(inner)?;
+                expr.span = self.leaf(open)?.span.to(self.leaf(close)?.span);
+                Ok(expr)
+            }
+            [ParseTree::Leaf(token)] => {
+                let kind = match token.type_ {
+                    TokenType::Integer => match token.lexeme.parse::<i64>() {
+                        Ok(value) => ExprKind::Integer(value),
+                        Err(_) => {
+                            return Err(ParseError::new(
+                                format!("integer literal {} is too large", token.lexeme),
+                                token.span,
+                            ));
+                        }
+                    },
+                    TokenType::Float => {
+                        ExprKind::Float(token.lexeme.parse::<f64>().unwrap_or(f64::NAN))
+                    }
+                    TokenType::StringLiteral => ExprKind::String(token.lexeme.to_string()),
+                    _ => ExprKind::Identifier(symbol(token)?),
+                };
+                Ok(Expr::new(kind, token.span))
+            }
+            // IDENT path_tail
+            [ParseTree::Leaf(module), path_tail] => match self.children(path_tail) {
+                [_, name] => {
+                    let name = self.leaf(name)?;
+                    Ok(Expr::new(
+                        ExprKind::Path {
+                            module: symbol(module)?,
+                            module_span: module.span,
+                            name: symbol(name)?,
+                            name_span: name.span,
+                        },
+                        module.span.to(name.span),
+                    ))
+                }
+                _ => Ok(Expr::new(
+                    ExprKind::Identifier(symbol(module)?),
+                    module.span,
+                )),
+            },
+            // query -> 'pos' | ...
+            [query] => {
+                let token = self.leaf(&self.children(query)[0])?;
+                Ok(Expr::new(ExprKind::Identifier(symbol(token)?), token.span))
+            }
+            _ => Err(self.shape_error(tree)),
+        }
+    }
+}
@@ -0,0 +1,431 @@
+use crate::lexer::lexer_ana::{Span, TokenType};
+use crate::util::interner::Symbol;
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr { kind, span }
+    }
+
+    pub fn error(span: Span) -> Self {
+        Expr {
+            kind: ExprKind::Error,
+            span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Identifier(Symbol),
+    // modulo::funcion
+    Path {
+        module: Symbol,
+        module_span: Span,
+        name: Symbol,
+        name_span: Span,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    // concentrador.puertos, the member may be a keyword like `puertos`
+    Member {
+        object: Box<Expr>,
+        member: Symbol,
+        member_span: Span,
+    },
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
+    // stands in for an expression that failed to parse
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Negate,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Power,
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEq,
+    GreaterEq,
+    And,
+    Or,
+    Range,
+}
+
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnaryOp::Negate => write!(f, "-"),
+            UnaryOp::Not => write!(f, "!"),
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Modulo => "%",
+            BinaryOp::Power => "**",
+            BinaryOp::Equal => "==",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::Less => "<",
+            BinaryOp::Greater => ">",
+            BinaryOp::LessEq => "<=",
+            BinaryOp::GreaterEq => ">=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+            BinaryOp::Range => "..",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    // `programa nombre;`, optional
+    pub name: Option<(Symbol, Span)>,
+    pub declarations: Vec<Decl>,
+    pub body: Block,
+    pub span: Span,
+}
+
+impl Program {
+    /// Every name the program gives to something: devices, modules, functions, parameters,
+    /// `let` and `for` variables
+    pub fn declared_names(&self) -> HashSet<Symbol> {
+        let mut names = HashSet::new();
+        names.extend(self.name.map(|(name, _)| name));
+        for decl in &self.declarations {
+            match decl {
+                Decl::Define(define) => names.extend(define.items.iter().map(|item| item.name)),
+                Decl::Function(function) => function.declare(&mut names),
+                Decl::Module(module) => {
+                    names.insert(module.name);
+                    for function in &module.functions {
+                        function.declare(&mut names);
+                    }
+                }
+                Decl::Error(_) => {}
+            }
+        }
+        self.body.declare(&mut names);
+        names
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decl {
+    Define(DefineDecl),
+    Function(FnDecl),
+    Module(ModuleDecl),
+    Error(Span),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceKind {
+    Machine,
+    Concentrator,
+    Coaxial,
+}
+
+// define concentradores c1 = 4, c2 = 8;
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefineDecl {
+    pub kind: DeviceKind,
+    pub items: Vec<DefineItem>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefineItem {
+    pub name: Symbol,
+    pub span: Span,
+    // port count of a concentrator, length of a coaxial segment
+    pub size: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FnDecl {
+    pub name: Symbol,
+    pub name_span: Span,
+    pub params: Vec<Param>,
+    pub return_type: Option<TypeName>,
+    pub body: Block,
+    pub span: Span,
+}
+
+// modulo geometria { fn area(...) ... }, its functions are called as geometria::area(...)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleDecl {
+    pub name: Symbol,
+    pub name_span: Span,
+    pub functions: Vec<FnDecl>,
+    pub span: Span,
+}
+
+impl FnDecl {
+    fn declare(&self, names: &mut HashSet<Symbol>) {
+        names.insert(self.name);
+        names.extend(self.params.iter().map(|param| param.name));
+        self.body.declare(names);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: Symbol,
+    pub span: Span,
+    pub ty: TypeName,
+}
+
+// a type as written, resolved by the checker
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TypeName {
+    pub name: Symbol,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub statements: Vec<Stmt>,
+    pub span: Span,
+}
+
+impl Block {
+    fn declare(&self, names: &mut HashSet<Symbol>) {
+        for stmt in &self.statements {
+            match &stmt.kind {
+                StmtKind::Let { name, .. } => {
+                    names.insert(*name);
+                }
+                StmtKind::If {
+                    then_block,
+                    else_block,
+                    ..
+                } => {
+                    then_block.declare(names);
+                    if let Some(else_block) = else_block {
+                        else_block.declare(names);
+                    }
+                }
+                StmtKind::While { body, .. } => body.declare(names),
+                StmtKind::For { var, body, .. } => {
+                    names.insert(*var);
+                    body.declare(names);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Stmt { kind, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    Let {
+        name: Symbol,
+        name_span: Span,
+        ty: Option<TypeName>,
+        value: Expr,
+    },
+    Assign {
+        target: Expr,
+        op: AssignOp,
+        value: Expr,
+    },
+    Expr(Expr),
+    // escribe(a, b)
+    Write(Vec<Expr>),
+    // both `si`/`sino` and `if`/`else`
+    If {
+        condition: Expr,
+        then_block: Block,
+        else_block: Option<Block>,
+    },
+    While {
+        condition: Expr,
+        body: Block,
+    },
+    // for i in 0..10 { }, for m in maquinas { }
+    For {
+        var: Symbol,
+        var_span: Span,
+        iterable: Iterable,
+        body: Block,
+    },
+    Break,
+    Continue,
+    Return(Option<Expr>),
+    Network {
+        command: NetworkCommand,
+        args: Vec<NetworkArg>,
+    },
+    // stands in for a statement that failed to parse
+    Error,
+}
+
+// what a `for` walks over
+#[derive(Debug, Clone, PartialEq)]
+pub enum Iterable {
+    // a range or a list, `0..n` or `c1.puertos`
+    Expr(Expr),
+    // every declared device of a kind, `maquinas`
+    Devices(DeviceKind, Span),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignOp {
+    Assign,
+    AddAssign,
+    SubtractAssign,
+    MultiplyAssign,
+    DivideAssign,
+}
+
+impl AssignOp {
+    /// The arithmetic behind a compound assignment
+    pub fn binary_op(self) -> Option<BinaryOp> {
+        match self {
+            AssignOp::Assign => None,
+            AssignOp::AddAssign => Some(BinaryOp::Add),
+            AssignOp::SubtractAssign => Some(BinaryOp::Subtract),
+            AssignOp::MultiplyAssign => Some(BinaryOp::Multiply),
+            AssignOp::DivideAssign => Some(BinaryOp::Divide),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkCommand {
+    Place,
+    PlaceCoaxial,
+    PlaceCoaxialConcentrator,
+    ConnectPort,
+    AssignPort,
+    MachineCoaxial,
+    AssignMachineCoaxial,
+}
+
+impl NetworkCommand {
+    pub fn from_token(type_: &TokenType) -> Option<Self> {
+        let command = match type_ {
+            TokenType::Coloca_ => NetworkCommand::Place,
+            TokenType::ColocaCoaxial_ => NetworkCommand::PlaceCoaxial,
+            TokenType::ColocaCoaxialConcentrador_ => NetworkCommand::PlaceCoaxialConcentrator,
+            TokenType::UneMaquinaPuerto_ => NetworkCommand::ConnectPort,
+            TokenType::AsignaPuerto_ => NetworkCommand::AssignPort,
+            TokenType::MaquinaCoaxial_ => NetworkCommand::MachineCoaxial,
+            TokenType::AsignaMaquinaCoaxial_ => NetworkCommand::AssignMachineCoaxial,
+            _ => return None,
+        };
+        Some(command)
+    }
+
+    pub fn token(self) -> TokenType {
+        match self {
+            NetworkCommand::Place => TokenType::Coloca_,
+            NetworkCommand::PlaceCoaxial => TokenType::ColocaCoaxial_,
+            NetworkCommand::PlaceCoaxialConcentrator => TokenType::ColocaCoaxialConcentrador_,
+            NetworkCommand::ConnectPort => TokenType::UneMaquinaPuerto_,
+            NetworkCommand::AssignPort => TokenType::AsignaPuerto_,
+            NetworkCommand::MachineCoaxial => TokenType::MaquinaCoaxial_,
+            NetworkCommand::AssignMachineCoaxial => TokenType::AsignaMaquinaCoaxial_,
+        }
+    }
+
+    /// Number of arguments between the parentheses
+    pub fn arity(self) -> usize {
+        match self {
+            // coloca(m1, 2, 3)
+            NetworkCommand::Place => 3,
+            // colocaCoaxial(x1, 0, 0, derecha)
+            NetworkCommand::PlaceCoaxial => 4,
+            // colocaCoaxialConcentrador(x1, c1)
+            NetworkCommand::PlaceCoaxialConcentrator => 2,
+            // uneMaquinaPuerto(m1, c1, 2)
+            NetworkCommand::ConnectPort => 3,
+            // asignaPuerto(m1, c1)
+            NetworkCommand::AssignPort => 2,
+            // maquinaCoaxial(m1, x1, 10)
+            NetworkCommand::MachineCoaxial => 3,
+            // asignaMaquinaCoaxial(m1, x1)
+            NetworkCommand::AssignMachineCoaxial => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetworkArg {
+    Expr(Expr),
+    Direction(Direction, Span),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn from_token(type_: &TokenType) -> Option<Self> {
+        match type_ {
+            TokenType::Arriba_ => Some(Direction::Up),
+            TokenType::Abajo_ => Some(Direction::Down),
+            TokenType::Izquierda_ => Some(Direction::Left),
+            TokenType::Derecha_ => Some(Direction::Right),
+            _ => None,
+        }
+    }
+}
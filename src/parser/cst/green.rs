@@ -0,0 +1,153 @@
+use crate::lexer::lexer_ana::TokenType;
+use crate::parser::cst::SyntaxKind;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A token and its text, trivia included. Identical tokens are shared
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct GreenToken {
+    pub kind: SyntaxKind,
+    pub text: String,
+}
+
+/// An immutable node that only knows its kind, length and children, never where it
+/// sits in the file, so unchanged subtrees can be shared between versions of the tree
+#[derive(Debug, PartialEq, Eq)]
+pub struct GreenNode {
+    pub kind: SyntaxKind,
+    pub text_len: usize,
+    pub children: Vec<GreenElement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            GreenElement::Node(node) => node.kind,
+            GreenElement::Token(token) => token.kind,
+        }
+    }
+
+    pub fn text_len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.text_len,
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        let text_len = children.iter().map(GreenElement::text_len).sum();
+        GreenNode {
+            kind,
+            text_len,
+            children,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity(self.text_len);
+        self.write_text(&mut text);
+        text
+    }
+
+    fn write_text(&self, out: &mut String) {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => node.write_text(out),
+                GreenElement::Token(token) => out.push_str(&token.text),
+            }
+        }
+    }
+
+    /// A copy of this node with child `index` swapped, the other children are shared
+    pub fn replace_child(&self, index: usize, child: GreenElement) -> GreenNode {
+        let mut children = self.children.clone();
+        children[index] = child;
+        GreenNode::new(self.kind, children)
+    }
+}
+
+/// Builds a green tree bottom up from start/token/finish calls
+#[derive(Default)]
+pub struct GreenBuilder {
+    // children collected so far for each open node, the innermost last
+    parents: Vec<(SyntaxKind, Vec<GreenElement>)>,
+    finished: Option<Rc<GreenNode>>,
+    tokens: HashMap<(SyntaxKind, String), Rc<GreenToken>>,
+}
+
+impl GreenBuilder {
+    pub fn new() -> Self {
+        GreenBuilder::default()
+    }
+
+    pub fn start_node(&mut self, kind: SyntaxKind) {
+        self.parents.push((kind, Vec::new()));
+    }
+
+    pub fn finish_node(&mut self) {
+        let (kind, children) = self.parents.pop().expect("finish_node without start_node");
+        let node = Rc::new(GreenNode::new(kind, children));
+        match self.parents.last_mut() {
+            Some((_, siblings)) => siblings.push(GreenElement::Node(node)),
+            None => self.finished = Some(node),
+        }
+    }
+
+    pub fn token(&mut self, kind: SyntaxKind, text: &str) {
+        let token = self
+            .tokens
+            .entry((kind, text.to_string()))
+            .or_insert_with(|| {
+                Rc::new(GreenToken {
+                    kind,
+                    text: text.to_string(),
+                })
+            })
+            .clone();
+        let (_, children) = self.parents.last_mut().expect("tokens go inside a node");
+        children.push(GreenElement::Token(token));
+    }
+
+    /// Splits the text between two tokens into whitespace, comments and anything the
+    /// lexer gave up on
+    pub fn trivia(&mut self, mut text: &str) {
+        while !text.is_empty() {
+            let (kind, len) = if text.starts_with("//") {
+                (SyntaxKind::Comment, text.find('\n').unwrap_or(text.len()))
+            } else if text.starts_with(is_whitespace) {
+                let len = text.find(|c| !is_whitespace(c)).unwrap_or(text.len());
+                (SyntaxKind::Whitespace, len)
+            } else {
+                // text the lexer dropped without a token, kept so nothing is lost
+                let len = text
+                    .find(|c| is_whitespace(c) || c == '/')
+                    .filter(|len| *len > 0)
+                    .unwrap_or_else(|| text.chars().next().map_or(0, char::len_utf8));
+                (SyntaxKind::token(&TokenType::Unknown), len)
+            };
+            self.token(kind, &text[..len]);
+            text = &text[len..];
+        }
+    }
+
+    pub fn finish(self) -> Rc<GreenNode> {
+        assert!(
+            self.parents.is_empty(),
+            "unfinished nodes left in the builder"
+        );
+        self.finished
+            .expect("the builder never finished a root node")
+    }
+}
+
+fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\r' | '\n')
+}
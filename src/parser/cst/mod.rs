@@ -0,0 +1,224 @@
+pub mod green;
+pub mod red;
+pub mod reparse;
+pub mod view;
+
+use crate::lexer::lexer_ana::{Lexer, LexerOptions, Span, TokenType};
+use crate::parser::parser_ana::{ParseError, Parser};
+use green::{GreenBuilder, GreenNode};
+use red::SyntaxNode;
+use std::{error::Error, ops::Range, rc::Rc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    Token(TokenTypeKind),
+    Whitespace,
+    Comment,
+
+    Program,
+    DefineDecl,
+    DefineItem,
+    FnDecl,
+    ModuleDecl,
+    Param,
+    TypeName,
+    Block,
+    LetStmt,
+    AssignStmt,
+    ExprStmt,
+    WriteStmt,
+    IfStmt,
+    WhileStmt,
+    ForStmt,
+    BreakStmt,
+    ContinueStmt,
+    ReturnStmt,
+    NetworkStmt,
+    Literal,
+    NameRef,
+    PathExpr,
+    UnaryExpr,
+    BinaryExpr,
+    ParenExpr,
+    CallExpr,
+    IndexExpr,
+    MemberExpr,
+    // whatever a syntax error left behind, plus the tokens skipped to recover
+    Error,
+}
+
+// `TokenType` isn't Copy, kinds are compared a lot so tokens are stored by discriminant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TokenTypeKind(u16);
+
+impl SyntaxKind {
+    pub fn token(type_: &TokenType) -> Self {
+        let index = TOKEN_TYPES
+            .iter()
+            .position(|known| known == type_)
+            .expect("every token type is listed in TOKEN_TYPES");
+        SyntaxKind::Token(TokenTypeKind(index as u16))
+    }
+
+    pub fn token_type(self) -> Option<TokenType> {
+        match self {
+            SyntaxKind::Token(TokenTypeKind(index)) => Some(TOKEN_TYPES[index as usize].clone()),
+            _ => None,
+        }
+    }
+
+    pub fn is_trivia(self) -> bool {
+        matches!(self, SyntaxKind::Whitespace | SyntaxKind::Comment)
+    }
+}
+
+/// What the parser did, in order. The tree is built from these afterwards so the
+/// parser itself never deals with trivia
+#[derive(Debug, Clone)]
+pub enum Event {
+    Start(SyntaxKind),
+    Finish,
+    Token(TokenType, Span),
+}
+
+/// A lossless tree of the whole file and the syntax errors found building it
+pub struct Parse {
+    pub root: SyntaxNode,
+    pub errors: Vec<ParseError>,
+}
+
+pub fn parse(source: &str, options: LexerOptions) -> Result<Parse, Box<dyn Error>> {
+    let mut parser = Parser::new(Lexer::with_options(source, options)?);
+    parser.parse_program();
+    let green = build_tree(source, parser.events());
+    Ok(Parse {
+        root: SyntaxNode::new_root(green),
+        errors: parser.get_errors().to_vec(),
+    })
+}
+
+/// Turns parser events into a green tree, filling the gaps between tokens with trivia
+pub fn build_tree(source: &str, events: &[Event]) -> Rc<GreenNode> {
+    build(source, 0..source.len(), events)
+}
+
+// the events cover `range` of the source, a single block when reparsing
+fn build(source: &str, range: Range<usize>, events: &[Event]) -> Rc<GreenNode> {
+    let mut builder = GreenBuilder::new();
+    let mut offset = range.start;
+    let mut depth = 0;
+
+    for event in events {
+        match event {
+            Event::Start(kind) => {
+                builder.start_node(*kind);
+                depth += 1;
+            }
+            Event::Finish => {
+                // trailing trivia belongs to the root
+                if depth == 1 {
+                    builder.trivia(&source[offset..range.end]);
+                    offset = range.end;
+                }
+                builder.finish_node();
+                depth -= 1;
+            }
+            Event::Token(type_, span) => {
+                if *type_ == TokenType::EndOfFile {
+                    continue;
+                }
+                builder.trivia(&source[offset..span.start]);
+                builder.token(SyntaxKind::token(type_), &source[span.start..span.end]);
+                offset = span.end;
+            }
+        }
+    }
+
+    builder.finish()
+}
+
+// in declaration order, the index is the token's `TokenTypeKind`
+const TOKEN_TYPES: &[TokenType] = &[
+    TokenType::KeywordLet,
+    TokenType::KeywordIf,
+    TokenType::KeywordElse,
+    TokenType::KeywordFor,
+    TokenType::KeywordWhile,
+    TokenType::KeywordReturn,
+    TokenType::KeywordFn,
+    TokenType::KeywordIn,
+    TokenType::KeywordBreak,
+    TokenType::KeywordContinue,
+    TokenType::Identifier,
+    TokenType::Integer,
+    TokenType::Float,
+    TokenType::StringLiteral,
+    TokenType::OperatorPlus,
+    TokenType::OperatorMinus,
+    TokenType::OperatorMultiply,
+    TokenType::OperatorDivide,
+    TokenType::OperatorAssign,
+    TokenType::OperatorEqual,
+    TokenType::OperatorNotEqual,
+    TokenType::OperatorLess,
+    TokenType::OperatorGreater,
+    TokenType::OperatorLessEq,
+    TokenType::OperatorGreaterEq,
+    TokenType::OperatorModulo,
+    TokenType::OperatorPower,
+    TokenType::OperatorPlusAssign,
+    TokenType::OperatorMinusAssign,
+    TokenType::OperatorMultiplyAssign,
+    TokenType::OperatorDivideAssign,
+    TokenType::ParenthesisLeft,
+    TokenType::ParenthesisRight,
+    TokenType::BraceLeft,
+    TokenType::BraceRight,
+    TokenType::BracketLeft,
+    TokenType::BracketRight,
+    TokenType::Semicolon,
+    TokenType::Comma,
+    TokenType::Dot,
+    TokenType::DotDot,
+    TokenType::Colon,
+    TokenType::DoubleColon,
+    TokenType::Arrow,
+    TokenType::Or,
+    TokenType::Not,
+    TokenType::And,
+    TokenType::EndOfFile,
+    TokenType::Unknown,
+    TokenType::Programa_,
+    TokenType::Define_,
+    TokenType::Maquinas_,
+    TokenType::Concentradores_,
+    TokenType::Coaxial_,
+    TokenType::Modulo_,
+    TokenType::Inicio_,
+    TokenType::Fin_,
+    TokenType::Coloca_,
+    TokenType::ColocaCoaxial_,
+    TokenType::ColocaCoaxialConcentrador_,
+    TokenType::UneMaquinaPuerto_,
+    TokenType::AsignaPuerto_,
+    TokenType::MaquinaCoaxial_,
+    TokenType::AsignaMaquinaCoaxial_,
+    TokenType::Escribe_,
+    TokenType::Si_,
+    TokenType::Sino_,
+    TokenType::Arriba_,
+    TokenType::Abajo_,
+    TokenType::Izquierda_,
+    TokenType::Derecha_,
+    TokenType::Puertos_,
+    TokenType::Disponibles_,
+    TokenType::Presente_,
+    TokenType::Longitud_,
+    TokenType::Completo_,
+    TokenType::Num_,
+    TokenType::Maquina_,
+    TokenType::Pos_,
+    TokenType::Alcanza_,
+    TokenType::Saltos_,
+    TokenType::Plano_,
+];
@@ -0,0 +1,247 @@
+use crate::parser::cst::SyntaxKind;
+use crate::parser::cst::green::{GreenElement, GreenNode, GreenToken};
+use std::{fmt, ops::Range, rc::Rc};
+
+/// A green node seen from a position in the file. Red nodes are made on demand while
+/// walking down and know their parent and absolute offset
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    // position among the parent's children
+    index: usize,
+    offset: usize,
+}
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    parent: SyntaxNode,
+    index: usize,
+    offset: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> Self {
+        SyntaxNode(Rc::new(NodeData {
+            green,
+            parent: None,
+            index: 0,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.text_len
+    }
+
+    pub fn text(&self) -> String {
+        self.0.green.text()
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    pub fn index(&self) -> usize {
+        self.0.index
+    }
+
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode> {
+        std::iter::successors(Some(self.clone()), SyntaxNode::parent)
+    }
+
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        let mut elements = Vec::with_capacity(self.0.green.children.len());
+        for (index, child) in self.0.green.children.iter().enumerate() {
+            elements.push(match child {
+                GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: green.clone(),
+                    parent: Some(self.clone()),
+                    index,
+                    offset,
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                    green: green.clone(),
+                    parent: self.clone(),
+                    index,
+                    offset,
+                }),
+            });
+            offset += child.text_len();
+        }
+        elements
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxNode> + use<> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|element| match element {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+    }
+
+    /// Tokens that are direct children, trivia left out
+    pub fn tokens(&self) -> impl Iterator<Item = SyntaxToken> + use<> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|element| match element {
+                SyntaxElement::Token(token) if !token.kind().is_trivia() => Some(token),
+                _ => None,
+            })
+    }
+
+    /// This node and everything under it, in source order
+    pub fn descendants(&self) -> Vec<SyntaxNode> {
+        let mut nodes = vec![self.clone()];
+        for child in self.children() {
+            nodes.extend(child.descendants());
+        }
+        nodes
+    }
+
+    /// Every token under this node in source order, trivia included
+    pub fn descendant_tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        for element in self.children_with_tokens() {
+            match element {
+                SyntaxElement::Node(node) => tokens.extend(node.descendant_tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    /// The token holding byte `offset`, if any
+    pub fn token_at_offset(&self, offset: usize) -> Option<SyntaxToken> {
+        for element in self.children_with_tokens() {
+            match element {
+                SyntaxElement::Node(node) if node.text_range().contains(&offset) => {
+                    return node.token_at_offset(offset);
+                }
+                SyntaxElement::Token(token) if token.text_range().contains(&offset) => {
+                    return Some(token);
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// The deepest node whose range holds all of `range`
+    pub fn covering_node(&self, range: Range<usize>) -> SyntaxNode {
+        for child in self.children() {
+            let child_range = child.text_range();
+            if child_range.start <= range.start && range.end <= child_range.end {
+                return child.covering_node(range);
+            }
+        }
+        self.clone()
+    }
+
+    /// The root of a new tree where this node is replaced by `green`, everything off
+    /// the path to the root is shared with the old tree
+    pub fn replace_with(&self, green: Rc<GreenNode>) -> Rc<GreenNode> {
+        match self.parent() {
+            None => green,
+            Some(parent) => {
+                let parent_green = parent
+                    .green()
+                    .replace_child(self.index(), GreenElement::Node(green));
+                parent.replace_with(Rc::new(parent_green))
+            }
+        }
+    }
+}
+
+impl PartialEq for SyntaxNode {
+    // the same green node at the same place of the same tree
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0.green, &other.0.green) && self.0.offset == other.0.offset
+    }
+}
+
+impl Eq for SyntaxNode {}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}@{:?}", self.kind(), self.text_range())
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.text.len()
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?}@{:?} {:?}",
+            self.kind(),
+            self.text_range(),
+            self.text()
+        )
+    }
+}
+
+/// A node remembered by kind and range, which survives rebuilding the red tree and
+/// edits elsewhere in the file that do not shift it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SyntaxNodePtr {
+    pub kind: SyntaxKind,
+    pub range: Range<usize>,
+}
+
+impl SyntaxNodePtr {
+    pub fn new(node: &SyntaxNode) -> Self {
+        SyntaxNodePtr {
+            kind: node.kind(),
+            range: node.text_range(),
+        }
+    }
+
+    /// Finds the node again in `root`, a tree of the same or a later version
+    pub fn to_node(&self, root: &SyntaxNode) -> Option<SyntaxNode> {
+        root.covering_node(self.range.clone())
+            .ancestors()
+            .take_while(|node| node.text_range() == self.range)
+            .find(|node| node.kind() == self.kind)
+    }
+}
@@ -0,0 +1,70 @@
+use crate::lexer::incremental::TextEdit;
+use crate::lexer::lexer_ana::{Lexer, LexerOptions, TokenType};
+use crate::parser::cst::green::GreenNode;
+use crate::parser::cst::red::SyntaxNode;
+use crate::parser::cst::{Parse, SyntaxKind, build};
+use crate::parser::parser_ana::Parser;
+use std::{ops::Range, rc::Rc};
+
+/// Reparses only the innermost block around `edit` and splices it into the old tree.
+///
+/// `new_source` is the old source with the edit applied. Returns None when the edit is
+/// not inside a single block, or the block stops parsing cleanly on its own (an
+/// unbalanced brace, a syntax error, ...); the caller then parses the whole file again.
+pub fn reparse(
+    old: &Parse,
+    new_source: &str,
+    edit: &TextEdit,
+    options: LexerOptions,
+) -> Option<Parse> {
+    // errors elsewhere would keep their old spans, not worth patching up
+    if !old.errors.is_empty() {
+        return None;
+    }
+    let old_len = old.root.text_range().end;
+    let new_len = (old_len + edit.replacement.len()).checked_sub(edit.range.len())?;
+    if edit.range.end > old_len || new_len != new_source.len() {
+        return None;
+    }
+
+    // the edit must stay clear of the braces, or the block may not be a block anymore
+    let block = old
+        .root
+        .covering_node(edit.range.clone())
+        .ancestors()
+        .filter(|node| node.kind() == SyntaxKind::Block)
+        .find(|node| {
+            let range = node.text_range();
+            range.start < edit.range.start && edit.range.end < range.end
+        })?;
+
+    let old_range = block.text_range();
+    let new_end = old_range.end + edit.replacement.len() - edit.range.len();
+    let green = parse_block(new_source, old_range.start..new_end, options)?;
+
+    Some(Parse {
+        root: SyntaxNode::new_root(block.replace_with(green)),
+        errors: Vec::new(),
+    })
+}
+
+fn parse_block(source: &str, range: Range<usize>, options: LexerOptions) -> Option<Rc<GreenNode>> {
+    let mut lexer = Lexer::with_options(source, options).ok()?;
+    let line = 1 + source[..range.start].matches('\n').count();
+    lexer.restart_at(range.start, line);
+
+    let mut parser = Parser::new(lexer);
+    if !matches!(
+        parser.peek().type_,
+        TokenType::BraceLeft | TokenType::Inicio_
+    ) {
+        return None;
+    }
+    parser.parse_block();
+
+    let clean = !parser.has_errors() && !parser.lexer().has_errors();
+    if !clean || parser.last_span().end != range.end {
+        return None;
+    }
+    Some(build(source, range, parser.events()))
+}
@@ -0,0 +1,474 @@
+use crate::lexer::lexer_ana::TokenType;
+use crate::parser::ast::Direction;
+use crate::parser::cst::SyntaxKind;
+use crate::parser::cst::red::{SyntaxElement, SyntaxNode, SyntaxToken};
+
+// typed wrappers over the CST, one per node kind. They never fail, a missing piece
+// of a broken program is just None
+macro_rules! ast_node {
+    ($name:ident, $kind:ident) => {
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct $name(SyntaxNode);
+
+        impl $name {
+            pub fn cast(node: SyntaxNode) -> Option<Self> {
+                (node.kind() == SyntaxKind::$kind).then_some($name(node))
+            }
+
+            pub fn syntax(&self) -> &SyntaxNode {
+                &self.0
+            }
+        }
+    };
+}
+
+const DEVICE_KEYWORDS: &[TokenType] = &[
+    TokenType::Maquinas_,
+    TokenType::Concentradores_,
+    TokenType::Coaxial_,
+];
+
+ast_node!(Program, Program);
+ast_node!(DefineDecl, DefineDecl);
+ast_node!(DefineItem, DefineItem);
+ast_node!(FnDecl, FnDecl);
+ast_node!(ModuleDecl, ModuleDecl);
+ast_node!(Param, Param);
+ast_node!(TypeName, TypeName);
+ast_node!(Block, Block);
+ast_node!(LetStmt, LetStmt);
+ast_node!(AssignStmt, AssignStmt);
+ast_node!(ExprStmt, ExprStmt);
+ast_node!(WriteStmt, WriteStmt);
+ast_node!(IfStmt, IfStmt);
+ast_node!(WhileStmt, WhileStmt);
+ast_node!(ForStmt, ForStmt);
+ast_node!(BreakStmt, BreakStmt);
+ast_node!(ContinueStmt, ContinueStmt);
+ast_node!(ReturnStmt, ReturnStmt);
+ast_node!(NetworkStmt, NetworkStmt);
+ast_node!(Literal, Literal);
+ast_node!(NameRef, NameRef);
+ast_node!(PathExpr, PathExpr);
+ast_node!(UnaryExpr, UnaryExpr);
+ast_node!(BinaryExpr, BinaryExpr);
+ast_node!(ParenExpr, ParenExpr);
+ast_node!(CallExpr, CallExpr);
+ast_node!(IndexExpr, IndexExpr);
+ast_node!(MemberExpr, MemberExpr);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stmt {
+    Let(LetStmt),
+    Assign(AssignStmt),
+    Expr(ExprStmt),
+    Write(WriteStmt),
+    If(IfStmt),
+    While(WhileStmt),
+    For(ForStmt),
+    Break(BreakStmt),
+    Continue(ContinueStmt),
+    Return(ReturnStmt),
+    Network(NetworkStmt),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Literal(Literal),
+    Name(NameRef),
+    Path(PathExpr),
+    Unary(UnaryExpr),
+    Binary(BinaryExpr),
+    Paren(ParenExpr),
+    Call(CallExpr),
+    Index(IndexExpr),
+    Member(MemberExpr),
+}
+
+fn child<N>(node: &SyntaxNode, cast: fn(SyntaxNode) -> Option<N>) -> Option<N> {
+    node.children().find_map(cast)
+}
+
+fn children<N>(node: &SyntaxNode, cast: fn(SyntaxNode) -> Option<N>) -> Vec<N> {
+    node.children().filter_map(cast).collect()
+}
+
+fn token(node: &SyntaxNode, type_: TokenType) -> Option<SyntaxToken> {
+    let kind = SyntaxKind::token(&type_);
+    node.tokens().find(|token| token.kind() == kind)
+}
+
+fn name(node: &SyntaxNode) -> Option<SyntaxToken> {
+    token(node, TokenType::Identifier)
+}
+
+impl Program {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, TokenType::Programa_)?;
+        name(&self.0)
+    }
+
+    pub fn defines(&self) -> Vec<DefineDecl> {
+        children(&self.0, DefineDecl::cast)
+    }
+
+    pub fn functions(&self) -> Vec<FnDecl> {
+        children(&self.0, FnDecl::cast)
+    }
+
+    pub fn modules(&self) -> Vec<ModuleDecl> {
+        children(&self.0, ModuleDecl::cast)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child(&self.0, Block::cast)
+    }
+}
+
+impl DefineDecl {
+    /// 'maquinas', 'concentradores' or 'coaxial'
+    pub fn device(&self) -> Option<SyntaxToken> {
+        self.0.tokens().nth(1)
+    }
+
+    pub fn items(&self) -> Vec<DefineItem> {
+        children(&self.0, DefineItem::cast)
+    }
+}
+
+impl DefineItem {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        name(&self.0)
+    }
+
+    pub fn size(&self) -> Option<Expr> {
+        child(&self.0, Expr::cast)
+    }
+}
+
+impl ModuleDecl {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        name(&self.0)
+    }
+
+    pub fn functions(&self) -> Vec<FnDecl> {
+        children(&self.0, FnDecl::cast)
+    }
+}
+
+impl FnDecl {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        name(&self.0)
+    }
+
+    pub fn params(&self) -> Vec<Param> {
+        children(&self.0, Param::cast)
+    }
+
+    pub fn return_type(&self) -> Option<TypeName> {
+        child(&self.0, TypeName::cast)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child(&self.0, Block::cast)
+    }
+}
+
+impl Param {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        name(&self.0)
+    }
+
+    pub fn ty(&self) -> Option<TypeName> {
+        child(&self.0, TypeName::cast)
+    }
+}
+
+impl TypeName {
+    pub fn token(&self) -> Option<SyntaxToken> {
+        self.0.tokens().next()
+    }
+}
+
+impl Block {
+    pub fn statements(&self) -> Vec<Stmt> {
+        children(&self.0, Stmt::cast)
+    }
+}
+
+impl Stmt {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        let stmt = match node.kind() {
+            SyntaxKind::LetStmt => Stmt::Let(LetStmt(node)),
+            SyntaxKind::AssignStmt => Stmt::Assign(AssignStmt(node)),
+            SyntaxKind::ExprStmt => Stmt::Expr(ExprStmt(node)),
+            SyntaxKind::WriteStmt => Stmt::Write(WriteStmt(node)),
+            SyntaxKind::IfStmt => Stmt::If(IfStmt(node)),
+            SyntaxKind::WhileStmt => Stmt::While(WhileStmt(node)),
+            SyntaxKind::ForStmt => Stmt::For(ForStmt(node)),
+            SyntaxKind::BreakStmt => Stmt::Break(BreakStmt(node)),
+            SyntaxKind::ContinueStmt => Stmt::Continue(ContinueStmt(node)),
+            SyntaxKind::ReturnStmt => Stmt::Return(ReturnStmt(node)),
+            SyntaxKind::NetworkStmt => Stmt::Network(NetworkStmt(node)),
+            _ => return None,
+        };
+        Some(stmt)
+    }
+
+    pub fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Stmt::Let(stmt) => stmt.syntax(),
+            Stmt::Assign(stmt) => stmt.syntax(),
+            Stmt::Expr(stmt) => stmt.syntax(),
+            Stmt::Write(stmt) => stmt.syntax(),
+            Stmt::If(stmt) => stmt.syntax(),
+            Stmt::While(stmt) => stmt.syntax(),
+            Stmt::For(stmt) => stmt.syntax(),
+            Stmt::Break(stmt) => stmt.syntax(),
+            Stmt::Continue(stmt) => stmt.syntax(),
+            Stmt::Return(stmt) => stmt.syntax(),
+            Stmt::Network(stmt) => stmt.syntax(),
+        }
+    }
+}
+
+impl LetStmt {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        name(&self.0)
+    }
+
+    pub fn ty(&self) -> Option<TypeName> {
+        child(&self.0, TypeName::cast)
+    }
+
+    pub fn value(&self) -> Option<Expr> {
+        child(&self.0, Expr::cast)
+    }
+}
+
+impl AssignStmt {
+    pub fn target(&self) -> Option<Expr> {
+        child(&self.0, Expr::cast)
+    }
+
+    /// '=', '+=', ...
+    pub fn operator(&self) -> Option<SyntaxToken> {
+        self.0.tokens().next()
+    }
+
+    pub fn value(&self) -> Option<Expr> {
+        children(&self.0, Expr::cast).into_iter().nth(1)
+    }
+}
+
+impl ExprStmt {
+    pub fn expr(&self) -> Option<Expr> {
+        child(&self.0, Expr::cast)
+    }
+}
+
+impl WriteStmt {
+    pub fn args(&self) -> Vec<Expr> {
+        children(&self.0, Expr::cast)
+    }
+}
+
+impl IfStmt {
+    pub fn condition(&self) -> Option<Expr> {
+        child(&self.0, Expr::cast)
+    }
+
+    pub fn then_block(&self) -> Option<Block> {
+        child(&self.0, Block::cast)
+    }
+
+    /// The `sino` block, or the nested `si` of `sino si`
+    pub fn else_branch(&self) -> Option<SyntaxNode> {
+        self.0
+            .children()
+            .filter(|node| matches!(node.kind(), SyntaxKind::Block | SyntaxKind::IfStmt))
+            .nth(1)
+    }
+}
+
+impl WhileStmt {
+    pub fn condition(&self) -> Option<Expr> {
+        child(&self.0, Expr::cast)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child(&self.0, Block::cast)
+    }
+}
+
+impl ForStmt {
+    pub fn var(&self) -> Option<SyntaxToken> {
+        name(&self.0)
+    }
+
+    /// A range or list expression, None when looping over a device kind
+    pub fn iterable(&self) -> Option<Expr> {
+        child(&self.0, Expr::cast)
+    }
+
+    /// 'maquinas', 'concentradores' or 'coaxial' in `for m in maquinas`
+    pub fn devices(&self) -> Option<SyntaxToken> {
+        self.0.tokens().nth(3).filter(|token| {
+            DEVICE_KEYWORDS
+                .iter()
+                .any(|type_| token.kind() == SyntaxKind::token(type_))
+        })
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child(&self.0, Block::cast)
+    }
+}
+
+impl ReturnStmt {
+    pub fn value(&self) -> Option<Expr> {
+        child(&self.0, Expr::cast)
+    }
+}
+
+impl NetworkStmt {
+    pub fn command(&self) -> Option<SyntaxToken> {
+        self.0.tokens().next()
+    }
+
+    /// Expressions and direction keywords, in order
+    pub fn args(&self) -> Vec<NetworkArg> {
+        self.0
+            .children_with_tokens()
+            .into_iter()
+            .filter_map(|element| match element {
+                SyntaxElement::Node(node) => Expr::cast(node).map(NetworkArg::Expr),
+                SyntaxElement::Token(token) => {
+                    let type_ = token.kind().token_type()?;
+                    Direction::from_token(&type_).map(|_| NetworkArg::Direction(token))
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum NetworkArg {
+    Expr(Expr),
+    Direction(SyntaxToken),
+}
+
+impl Expr {
+    pub fn cast(node: SyntaxNode) -> Option<Self> {
+        let expr = match node.kind() {
+            SyntaxKind::Literal => Expr::Literal(Literal(node)),
+            SyntaxKind::NameRef => Expr::Name(NameRef(node)),
+            SyntaxKind::PathExpr => Expr::Path(PathExpr(node)),
+            SyntaxKind::UnaryExpr => Expr::Unary(UnaryExpr(node)),
+            SyntaxKind::BinaryExpr => Expr::Binary(BinaryExpr(node)),
+            SyntaxKind::ParenExpr => Expr::Paren(ParenExpr(node)),
+            SyntaxKind::CallExpr => Expr::Call(CallExpr(node)),
+            SyntaxKind::IndexExpr => Expr::Index(IndexExpr(node)),
+            SyntaxKind::MemberExpr => Expr::Member(MemberExpr(node)),
+            _ => return None,
+        };
+        Some(expr)
+    }
+
+    pub fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Expr::Literal(expr) => expr.syntax(),
+            Expr::Name(expr) => expr.syntax(),
+            Expr::Path(expr) => expr.syntax(),
+            Expr::Unary(expr) => expr.syntax(),
+            Expr::Binary(expr) => expr.syntax(),
+            Expr::Paren(expr) => expr.syntax(),
+            Expr::Call(expr) => expr.syntax(),
+            Expr::Index(expr) => expr.syntax(),
+            Expr::Member(expr) => expr.syntax(),
+        }
+    }
+}
+
+impl Literal {
+    pub fn token(&self) -> Option<SyntaxToken> {
+        self.0.tokens().next()
+    }
+}
+
+impl NameRef {
+    pub fn token(&self) -> Option<SyntaxToken> {
+        self.0.tokens().next()
+    }
+}
+
+impl PathExpr {
+    pub fn module(&self) -> Option<SyntaxToken> {
+        self.0.tokens().next()
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        self.0.tokens().nth(2)
+    }
+}
+
+impl UnaryExpr {
+    pub fn operator(&self) -> Option<SyntaxToken> {
+        self.0.tokens().next()
+    }
+
+    pub fn operand(&self) -> Option<Expr> {
+        child(&self.0, Expr::cast)
+    }
+}
+
+impl BinaryExpr {
+    pub fn left(&self) -> Option<Expr> {
+        child(&self.0, Expr::cast)
+    }
+
+    pub fn operator(&self) -> Option<SyntaxToken> {
+        self.0.tokens().next()
+    }
+
+    pub fn right(&self) -> Option<Expr> {
+        children(&self.0, Expr::cast).into_iter().nth(1)
+    }
+}
+
+impl ParenExpr {
+    pub fn inner(&self) -> Option<Expr> {
+        child(&self.0, Expr::cast)
+    }
+}
+
+impl CallExpr {
+    pub fn callee(&self) -> Option<Expr> {
+        child(&self.0, Expr::cast)
+    }
+
+    pub fn args(&self) -> Vec<Expr> {
+        children(&self.0, Expr::cast).into_iter().skip(1).collect()
+    }
+}
+
+impl IndexExpr {
+    pub fn object(&self) -> Option<Expr> {
+        child(&self.0, Expr::cast)
+    }
+
+    pub fn index(&self) -> Option<Expr> {
+        children(&self.0, Expr::cast).into_iter().nth(1)
+    }
+}
+
+impl MemberExpr {
+    pub fn object(&self) -> Option<Expr> {
+        child(&self.0, Expr::cast)
+    }
+
+    /// The name after the '.', which can be a keyword like `puertos`
+    pub fn member(&self) -> Option<SyntaxToken> {
+        self.0.tokens().nth(1)
+    }
+}
@@ -0,0 +1,350 @@
+use crate::lexer::lexer_ana::{Span, Token, TokenType};
+use crate::parser::ast::{BinaryOp, Expr, ExprKind, UnaryOp};
+use crate::parser::cst::SyntaxKind;
+use crate::parser::parser_ana::{Parser, describe};
+
+// binding powers, higher binds tighter. Infix operators get a (left, right) pair:
+// left < right is left associative, left > right is right associative
+const PREFIX_POWER: u8 = 15;
+const POSTFIX_POWER: u8 = 19;
+
+pub(super) fn infix_binding_power(type_: &TokenType) -> Option<(BinaryOp, u8, u8)> {
+    let (op, left, right) = match type_ {
+        TokenType::DotDot => (BinaryOp::Range, 1, 2),
+        TokenType::Or => (BinaryOp::Or, 3, 4),
+        TokenType::And => (BinaryOp::And, 5, 6),
+        TokenType::OperatorEqual => (BinaryOp::Equal, 7, 8),
+        TokenType::OperatorNotEqual => (BinaryOp::NotEqual, 7, 8),
+        TokenType::OperatorLess => (BinaryOp::Less, 9, 10),
+        TokenType::OperatorGreater => (BinaryOp::Greater, 9, 10),
+        TokenType::OperatorLessEq => (BinaryOp::LessEq, 9, 10),
+        TokenType::OperatorGreaterEq => (BinaryOp::GreaterEq, 9, 10),
+        TokenType::OperatorPlus => (BinaryOp::Add, 11, 12),
+        TokenType::OperatorMinus => (BinaryOp::Subtract, 11, 12),
+        TokenType::OperatorMultiply => (BinaryOp::Multiply, 13, 14),
+        TokenType::OperatorDivide => (BinaryOp::Divide, 13, 14),
+        TokenType::OperatorModulo => (BinaryOp::Modulo, 13, 14),
+        // above the prefix operators, so -2 ** 2 is -(2 ** 2)
+        TokenType::OperatorPower => (BinaryOp::Power, 18, 17),
+        _ => return None,
+    };
+    Some((op, left, right))
+}
+
+/// Tokens that can start an expression
+pub const EXPRESSION_FIRST: &[TokenType] = &[
+    TokenType::Integer,
+    TokenType::Float,
+    TokenType::StringLiteral,
+    TokenType::Identifier,
+    TokenType::ParenthesisLeft,
+    TokenType::OperatorMinus,
+    TokenType::Not,
+    TokenType::Pos_,
+    TokenType::Presente_,
+    TokenType::Completo_,
+    TokenType::Disponibles_,
+    TokenType::Longitud_,
+    TokenType::Puertos_,
+    TokenType::Alcanza_,
+    TokenType::Saltos_,
+    TokenType::Plano_,
+];
+
+pub fn starts_expression(type_: &TokenType) -> bool {
+    EXPRESSION_FIRST.contains(type_)
+}
+
+// domain keywords that name builtin queries, e.g. `pos(m1)` or `completo(c1)`
+fn is_query_keyword(type_: &TokenType) -> bool {
+    matches!(
+        type_,
+        TokenType::Pos_
+            | TokenType::Presente_
+            | TokenType::Completo_
+            | TokenType::Disponibles_
+            | TokenType::Longitud_
+            | TokenType::Puertos_
+            | TokenType::Alcanza_
+            | TokenType::Saltos_
+            | TokenType::Plano_
+    )
+}
+
+fn identifier(token: &Token) -> Expr {
+    match token.symbol {
+        Some(symbol) => Expr::new(ExprKind::Identifier(symbol), token.span),
+        None => Expr::error(token.span),
+    }
+}
+
+impl<'src> Parser<'src> {
+    pub fn parse_expression(&mut self) -> Expr {
+        self.parse_expression_bp(0)
+    }
+
+    fn parse_expression_bp(&mut self, min_power: u8) -> Expr {
+        let checkpoint = self.checkpoint();
+        let mut left = self.parse_prefix();
+
+        loop {
+            let type_ = self.peek().type_.clone();
+
+            if matches!(
+                type_,
+                TokenType::ParenthesisLeft | TokenType::BracketLeft | TokenType::Dot
+            ) {
+                if POSTFIX_POWER < min_power {
+                    break;
+                }
+                let marker = self.start_node_at(checkpoint);
+                left = self.parse_postfix(left);
+                let kind = match type_ {
+                    TokenType::ParenthesisLeft => SyntaxKind::CallExpr,
+                    TokenType::BracketLeft => SyntaxKind::IndexExpr,
+                    _ => SyntaxKind::MemberExpr,
+                };
+                self.finish_node(marker, kind);
+                continue;
+            }
+
+            let Some((op, left_power, right_power)) = infix_binding_power(&type_) else {
+                break;
+            };
+            if left_power < min_power {
+                break;
+            }
+
+            let marker = self.start_node_at(checkpoint);
+            let operator = self.advance();
+            let right = self.parse_operand(right_power, &operator);
+            self.finish_node(marker, SyntaxKind::BinaryExpr);
+            let span = left.span.to(right.span);
+            left = Expr::new(
+                ExprKind::Binary {
+                    op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                },
+                span,
+            );
+        }
+
+        left
+    }
+
+    // right hand side of an operator, a missing one is reported at the operator
+    fn parse_operand(&mut self, min_power: u8, operator: &Token) -> Expr {
+        if !starts_expression(&self.peek().type_) {
+            let found = describe(self.peek());
+            let expected = self.expected_list(EXPRESSION_FIRST);
+            self.error(
+                format!(
+                    "expected {} after '{}', found {}",
+                    expected, operator.lexeme, found
+                ),
+                operator.span,
+            );
+            return Expr::error(operator.span);
+        }
+        self.parse_expression_bp(min_power)
+    }
+
+    fn parse_prefix(&mut self) -> Expr {
+        let token = self.peek().clone();
+        let kind = match token.type_ {
+            TokenType::Integer | TokenType::Float | TokenType::StringLiteral => SyntaxKind::Literal,
+            TokenType::OperatorMinus | TokenType::Not => SyntaxKind::UnaryExpr,
+            TokenType::ParenthesisLeft => SyntaxKind::ParenExpr,
+            ref type_ if starts_expression(type_) => SyntaxKind::NameRef,
+            // nothing is consumed, so there is no node either
+            _ => return self.parse_prefix_inner(token),
+        };
+
+        let marker = self.start_node();
+        let expr = self.parse_prefix_inner(token);
+        let kind = match expr.kind {
+            ExprKind::Path { .. } => SyntaxKind::PathExpr,
+            _ => kind,
+        };
+        self.finish_node(marker, kind);
+        expr
+    }
+
+    fn parse_prefix_inner(&mut self, token: Token<'src>) -> Expr {
+        match token.type_ {
+            TokenType::Integer => {
+                self.advance();
+                match token.lexeme.parse::<i64>() {
+                    Ok(value) => Expr::new(ExprKind::Integer(value), token.span),
+                    Err(_) => {
+                        self.error(
+                            format!("integer literal {} is too large", token.lexeme),
+                            token.span,
+                        );
+                        Expr::error(token.span)
+                    }
+                }
+            }
+            TokenType::Float => {
+                self.advance();
+                // the float DFA only accepts digits.digits, which always parses
+                let value = token.lexeme.parse::<f64>().unwrap_or(f64::NAN);
+                Expr::new(ExprKind::Float(value), token.span)
+            }
+            TokenType::StringLiteral => {
+                self.advance();
+                Expr::new(ExprKind::String(token.lexeme.into_owned()), token.span)
+            }
+            TokenType::Identifier => {
+                self.advance();
+                match self.eat(&TokenType::DoubleColon) {
+                    Some(colons) => self.parse_path(&token, &colons),
+                    None => identifier(&token),
+                }
+            }
+            ref type_ if is_query_keyword(type_) => {
+                self.advance();
+                identifier(&token)
+            }
+            TokenType::OperatorMinus | TokenType::Not => {
+                let operator = self.advance();
+                let op = if operator.type_ == TokenType::Not {
+                    UnaryOp::Not
+                } else {
+                    UnaryOp::Negate
+                };
+                let operand = self.parse_operand(PREFIX_POWER, &operator);
+                let span = operator.span.to(operand.span);
+                Expr::new(
+                    ExprKind::Unary {
+                        op,
+                        operand: Box::new(operand),
+                    },
+                    span,
+                )
+            }
+            TokenType::ParenthesisLeft => {
+                let open = self.advance();
+                let inner = self.parse_operand(0, &open);
+                let close = self.expect_closing(&TokenType::ParenthesisRight, &open);
+                let mut expr = inner;
+                expr.span = open.span.to(close.unwrap_or(expr.span));
+                expr
+            }
+            _ => {
+                self.expected(EXPRESSION_FIRST, "");
+                Expr::error(token.span)
+            }
+        }
+    }
+
+    // the function after `modulo::`
+    fn parse_path(&mut self, module: &Token, colons: &Token) -> Expr {
+        let name = self.peek().clone();
+        if name.type_ != TokenType::Identifier {
+            self.error(
+                format!(
+                    "expected function name after '::', found {}",
+                    describe(&name)
+                ),
+                colons.span,
+            );
+            return Expr::error(module.span.to(colons.span));
+        }
+        self.advance();
+        let span = module.span.to(name.span);
+        match (module.symbol, name.symbol) {
+            (Some(module_symbol), Some(name_symbol)) => Expr::new(
+                ExprKind::Path {
+                    module: module_symbol,
+                    module_span: module.span,
+                    name: name_symbol,
+                    name_span: name.span,
+                },
+                span,
+            ),
+            _ => Expr::error(span),
+        }
+    }
+
+    fn parse_postfix(&mut self, object: Expr) -> Expr {
+        let operator = self.advance();
+
+        match operator.type_ {
+            TokenType::ParenthesisLeft => {
+                let mut args = Vec::new();
+                if !self.check(&TokenType::ParenthesisRight) {
+                    args.push(self.parse_operand(0, &operator));
+                    while let Some(comma) = self.eat(&TokenType::Comma) {
+                        args.push(self.parse_operand(0, &comma));
+                    }
+                }
+                let close = self.expect_closing(&TokenType::ParenthesisRight, &operator);
+                let span = object.span.to(close.unwrap_or(operator.span));
+                Expr::new(
+                    ExprKind::Call {
+                        callee: Box::new(object),
+                        args,
+                    },
+                    span,
+                )
+            }
+            TokenType::BracketLeft => {
+                let index = self.parse_operand(0, &operator);
+                let close = self.expect_closing(&TokenType::BracketRight, &operator);
+                let span = object.span.to(close.unwrap_or(index.span));
+                Expr::new(
+                    ExprKind::Index {
+                        object: Box::new(object),
+                        index: Box::new(index),
+                    },
+                    span,
+                )
+            }
+            _ => {
+                // member names can be keywords, `concentrador.puertos.disponibles`
+                let name = self.peek().clone();
+                let Some(member) = name.symbol else {
+                    self.error(
+                        format!("expected member name after '.', found {}", describe(&name)),
+                        operator.span,
+                    );
+                    return Expr::new(object.kind, object.span.to(operator.span));
+                };
+                self.advance();
+                let span = object.span.to(name.span);
+                Expr::new(
+                    ExprKind::Member {
+                        object: Box::new(object),
+                        member,
+                        member_span: name.span,
+                    },
+                    span,
+                )
+            }
+        }
+    }
+
+    // the closing bracket of `open`, returns its span when present
+    pub(super) fn expect_closing(&mut self, close: &TokenType, open: &Token) -> Option<Span> {
+        if let Some(token) = self.eat(close) {
+            return Some(token.span);
+        }
+
+        let closing = match close {
+            TokenType::BracketRight => "]",
+            _ => ")",
+        };
+        let found = describe(self.peek());
+        let span = self.peek().span;
+        self.error(
+            format!(
+                "expected '{}' to close '{}' from line {}:{}, found {}",
+                closing, open.lexeme, open.span.line, open.span.column, found
+            ),
+            span,
+        );
+        None
+    }
+}
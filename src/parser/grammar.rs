@@ -0,0 +1,243 @@
+use crate::lexer::keywords::{KEYWORDS, KeywordProfile};
+use crate::lexer::lexer_ana::{PUNCTUATION, TokenType};
+use crate::parser::parser_ana::token_name;
+use std::{error::Error, fmt, fs};
+
+pub const GRAMMAR_PATH: &str = "src/parser/grammar/kindred.grammar";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrammarSymbol {
+    // index into `Grammar::terminals`
+    Terminal(usize),
+    // index into `Grammar::nonterminals`
+    NonTerminal(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct Production {
+    pub lhs: usize,
+    // empty for ε
+    pub rhs: Vec<GrammarSymbol>,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Grammar {
+    pub nonterminals: Vec<String>,
+    // the first terminal is always end of file
+    pub terminals: Vec<TokenType>,
+    pub productions: Vec<Production>,
+    pub start: usize,
+}
+
+#[derive(Debug)]
+pub enum GrammarError {
+    InvalidFormat(usize, String),
+    UnknownTerminal(usize, String),
+    UndefinedNonTerminal(usize, String),
+    DuplicateRule(usize, String),
+    Empty,
+}
+
+impl fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GrammarError::InvalidFormat(line, msg) => {
+                write!(f, "line {}: Invalid Format: {}", line, msg)
+            }
+            GrammarError::UnknownTerminal(line, s) => {
+                write!(f, "line {}: '{}' is not a Kindred token", line, s)
+            }
+            GrammarError::UndefinedNonTerminal(line, s) => {
+                write!(f, "line {}: nonterminal '{}' has no rule", line, s)
+            }
+            GrammarError::DuplicateRule(line, s) => {
+                write!(f, "line {}: rule for '{}' is defined twice", line, s)
+            }
+            GrammarError::Empty => write!(f, "The grammar has no rules"),
+        }
+    }
+}
+
+impl Error for GrammarError {}
+
+impl Grammar {
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        Self::from_file(GRAMMAR_PATH)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Error loading grammar {}: {}", path, e))?;
+        Ok(Self::from_source(&content)?)
+    }
+
+    pub fn from_source(source: &str) -> Result<Self, GrammarError> {
+        let mut grammar = Grammar {
+            nonterminals: Vec::new(),
+            terminals: vec![TokenType::EndOfFile],
+            productions: Vec::new(),
+            start: 0,
+        };
+        // (line, name) of every nonterminal used, checked once all rules are known
+        let mut used = Vec::new();
+        let mut defined = Vec::new();
+        let mut current = None;
+
+        for (index, raw) in source.lines().enumerate() {
+            let line = index + 1;
+            let text = raw.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+
+            let alternative = if let Some(rest) = text.strip_prefix('|') {
+                let Some(lhs) = current else {
+                    return Err(GrammarError::InvalidFormat(
+                        line,
+                        "'|' before any rule".to_string(),
+                    ));
+                };
+                (lhs, rest)
+            } else {
+                let Some((name, rest)) = text.split_once("->") else {
+                    return Err(GrammarError::InvalidFormat(
+                        line,
+                        "expected 'name -> symbols'".to_string(),
+                    ));
+                };
+                let name = name.trim();
+                if !is_nonterminal(name) {
+                    return Err(GrammarError::InvalidFormat(
+                        line,
+                        format!("'{}' is not a valid nonterminal name", name),
+                    ));
+                }
+                let lhs = grammar.nonterminal(name);
+                if defined.contains(&lhs) {
+                    return Err(GrammarError::DuplicateRule(line, name.to_string()));
+                }
+                defined.push(lhs);
+                current = Some(lhs);
+                (lhs, rest)
+            };
+
+            let (lhs, rest) = alternative;
+            let mut rhs = Vec::new();
+            for word in rest.split_whitespace() {
+                if word == "ε" {
+                    continue;
+                }
+                let symbol = if is_nonterminal(word) {
+                    used.push((line, word.to_string()));
+                    GrammarSymbol::NonTerminal(grammar.nonterminal(word))
+                } else {
+                    let type_ = terminal(word)
+                        .ok_or_else(|| GrammarError::UnknownTerminal(line, word.to_string()))?;
+                    GrammarSymbol::Terminal(grammar.terminal(type_))
+                };
+                rhs.push(symbol);
+            }
+            grammar.productions.push(Production { lhs, rhs, line });
+        }
+
+        if grammar.productions.is_empty() {
+            return Err(GrammarError::Empty);
+        }
+        for (line, name) in used {
+            if !defined.contains(&grammar.nonterminal(&name)) {
+                return Err(GrammarError::UndefinedNonTerminal(line, name));
+            }
+        }
+        Ok(grammar)
+    }
+
+    fn nonterminal(&mut self, name: &str) -> usize {
+        match self.nonterminals.iter().position(|known| known == name) {
+            Some(index) => index,
+            None => {
+                self.nonterminals.push(name.to_string());
+                self.nonterminals.len() - 1
+            }
+        }
+    }
+
+    fn terminal(&mut self, type_: TokenType) -> usize {
+        match self.terminals.iter().position(|known| *known == type_) {
+            Some(index) => index,
+            None => {
+                self.terminals.push(type_);
+                self.terminals.len() - 1
+            }
+        }
+    }
+
+    pub fn nonterminal_index(&self, name: &str) -> Option<usize> {
+        self.nonterminals.iter().position(|known| known == name)
+    }
+
+    pub fn terminal_index(&self, type_: &TokenType) -> Option<usize> {
+        self.terminals.iter().position(|known| known == type_)
+    }
+
+    pub fn terminal_name(&self, terminal: usize) -> String {
+        let type_ = &self.terminals[terminal];
+        token_name(type_, &[KeywordProfile::School]).unwrap_or_else(|| type_.to_string())
+    }
+
+    pub fn symbol_name(&self, symbol: GrammarSymbol) -> String {
+        match symbol {
+            GrammarSymbol::Terminal(terminal) => self.terminal_name(terminal),
+            GrammarSymbol::NonTerminal(nonterminal) => self.nonterminals[nonterminal].clone(),
+        }
+    }
+
+    /// A production written back in the grammar file notation
+    pub fn production_text(&self, production: usize) -> String {
+        let production = &self.productions[production];
+        let rhs: Vec<String> = production
+            .rhs
+            .iter()
+            .map(|symbol| self.symbol_name(*symbol))
+            .collect();
+        let rhs = if rhs.is_empty() {
+            "ε".to_string()
+        } else {
+            rhs.join(" ")
+        };
+        format!("{} -> {}", self.nonterminals[production.lhs], rhs)
+    }
+}
+
+fn is_nonterminal(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_lowercase())
+        && word
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+// 'quoted' punctuation or school keyword, or a token class
+fn terminal(word: &str) -> Option<TokenType> {
+    if let Some(text) = word
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+    {
+        if let Some((_, type_)) = PUNCTUATION.iter().find(|(spelling, _)| *spelling == text) {
+            return Some(type_.clone());
+        }
+        return KEYWORDS
+            .iter()
+            .find(|keyword| {
+                keyword.spelling == text && keyword.profiles.contains(&KeywordProfile::School)
+            })
+            .map(|keyword| keyword.token.clone());
+    }
+
+    match word {
+        "IDENT" => Some(TokenType::Identifier),
+        "INTEGER" => Some(TokenType::Integer),
+        "FLOAT" => Some(TokenType::Float),
+        "STRING" => Some(TokenType::StringLiteral),
+        _ => None,
+    }
+}
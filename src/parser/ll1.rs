@@ -0,0 +1,331 @@
+use crate::lexer::lexer_ana::TokenType;
+use crate::parser::expression::EXPRESSION_FIRST;
+use crate::parser::grammar::{Grammar, GrammarSymbol};
+use crate::parser::statement::statement_first;
+use std::collections::{BTreeMap, BTreeSet};
+use std::{error::Error, fmt};
+
+// sets hold terminal indices of the grammar
+pub type TerminalSet = BTreeSet<usize>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    // two alternatives start with the same terminal
+    FirstFirst,
+    // an alternative that can be empty and another one both accept what follows
+    FirstFollow,
+}
+
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    pub nonterminal: usize,
+    pub terminal: usize,
+    // production already in the table and the one that collided with it
+    pub productions: (usize, usize),
+}
+
+pub struct Analysis<'g> {
+    pub grammar: &'g Grammar,
+    pub nullable: Vec<bool>,
+    pub first: Vec<TerminalSet>,
+    pub follow: Vec<TerminalSet>,
+    // per nonterminal, terminal -> production
+    pub table: Vec<BTreeMap<usize, usize>>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl<'g> Analysis<'g> {
+    pub fn new(grammar: &'g Grammar) -> Self {
+        let count = grammar.nonterminals.len();
+        let mut analysis = Analysis {
+            grammar,
+            nullable: vec![false; count],
+            first: vec![TerminalSet::new(); count],
+            follow: vec![TerminalSet::new(); count],
+            table: vec![BTreeMap::new(); count],
+            conflicts: Vec::new(),
+        };
+        analysis.compute_first();
+        analysis.compute_follow();
+        analysis.build_table();
+        analysis
+    }
+
+    // nullable and FIRST grow together until nothing changes
+    fn compute_first(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in &self.grammar.productions {
+                let (first, nullable) = self.first_of(&production.rhs);
+                let lhs = production.lhs;
+                if nullable && !self.nullable[lhs] {
+                    self.nullable[lhs] = true;
+                    changed = true;
+                }
+                let before = self.first[lhs].len();
+                self.first[lhs].extend(first);
+                changed |= self.first[lhs].len() != before;
+            }
+        }
+    }
+
+    fn compute_follow(&mut self) {
+        // end of file follows the whole program
+        self.follow[self.grammar.start].insert(0);
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in &self.grammar.productions {
+                for (position, symbol) in production.rhs.iter().enumerate() {
+                    let GrammarSymbol::NonTerminal(nonterminal) = *symbol else {
+                        continue;
+                    };
+                    let (mut follow, nullable) = self.first_of(&production.rhs[position + 1..]);
+                    if nullable {
+                        follow.extend(self.follow[production.lhs].iter().copied());
+                    }
+                    let before = self.follow[nonterminal].len();
+                    self.follow[nonterminal].extend(follow);
+                    changed |= self.follow[nonterminal].len() != before;
+                }
+            }
+        }
+    }
+
+    fn build_table(&mut self) {
+        for (index, production) in self.grammar.productions.iter().enumerate() {
+            let (first, nullable) = self.first_of(&production.rhs);
+            for terminal in first {
+                self.insert(production.lhs, terminal, index, ConflictKind::FirstFirst);
+            }
+            if nullable {
+                for terminal in self.follow[production.lhs].clone() {
+                    self.insert(production.lhs, terminal, index, ConflictKind::FirstFollow);
+                }
+            }
+        }
+    }
+
+    fn insert(
+        &mut self,
+        nonterminal: usize,
+        terminal: usize,
+        production: usize,
+        kind: ConflictKind,
+    ) {
+        match self.table[nonterminal].get(&terminal) {
+            Some(&existing) if existing != production => self.conflicts.push(Conflict {
+                kind,
+                nonterminal,
+                terminal,
+                productions: (existing, production),
+            }),
+            Some(_) => {}
+            None => {
+                self.table[nonterminal].insert(terminal, production);
+            }
+        }
+    }
+
+    /// FIRST of a sequence of symbols and whether the whole sequence can be empty
+    pub fn first_of(&self, symbols: &[GrammarSymbol]) -> (TerminalSet, bool) {
+        let mut first = TerminalSet::new();
+        for symbol in symbols {
+            match *symbol {
+                GrammarSymbol::Terminal(terminal) => {
+                    first.insert(terminal);
+                    return (first, false);
+                }
+                GrammarSymbol::NonTerminal(nonterminal) => {
+                    first.extend(self.first[nonterminal].iter().copied());
+                    if !self.nullable[nonterminal] {
+                        return (first, false);
+                    }
+                }
+            }
+        }
+        (first, true)
+    }
+
+    /// The production to expand `nonterminal` with when `terminal` is next
+    pub fn predict(&self, nonterminal: usize, terminal: usize) -> Option<usize> {
+        self.table[nonterminal].get(&terminal).copied()
+    }
+
+    /// Nonterminals the start symbol never reaches
+    pub fn unreachable(&self) -> Vec<usize> {
+        let mut reached = vec![false; self.grammar.nonterminals.len()];
+        let mut pending = vec![self.grammar.start];
+        reached[self.grammar.start] = true;
+
+        while let Some(nonterminal) = pending.pop() {
+            for production in &self.grammar.productions {
+                if production.lhs != nonterminal {
+                    continue;
+                }
+                for symbol in &production.rhs {
+                    if let GrammarSymbol::NonTerminal(next) = *symbol
+                        && !reached[next]
+                    {
+                        reached[next] = true;
+                        pending.push(next);
+                    }
+                }
+            }
+        }
+
+        (0..reached.len())
+            .filter(|index| !reached[*index])
+            .collect()
+    }
+
+    pub fn set_names(&self, set: &TerminalSet) -> Vec<String> {
+        set.iter()
+            .map(|terminal| self.grammar.terminal_name(*terminal))
+            .collect()
+    }
+
+    pub fn display_conflict<'a>(&'a self, conflict: &'a Conflict) -> impl fmt::Display + 'a {
+        ConflictDisplay {
+            analysis: self,
+            conflict,
+        }
+    }
+}
+
+struct ConflictDisplay<'a, 'g> {
+    analysis: &'a Analysis<'g>,
+    conflict: &'a Conflict,
+}
+
+impl fmt::Display for ConflictDisplay<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let grammar = self.analysis.grammar;
+        let conflict = self.conflict;
+        let (first, second) = conflict.productions;
+        let kind = match conflict.kind {
+            ConflictKind::FirstFirst => "FIRST/FIRST",
+            ConflictKind::FirstFollow => "FIRST/FOLLOW",
+        };
+        writeln!(
+            f,
+            "{} conflict in '{}' on {}:",
+            kind,
+            grammar.nonterminals[conflict.nonterminal],
+            grammar.terminal_name(conflict.terminal)
+        )?;
+        writeln!(
+            f,
+            "    line {}: {}",
+            grammar.productions[first].line,
+            grammar.production_text(first)
+        )?;
+        write!(
+            f,
+            "    line {}: {}",
+            grammar.productions[second].line,
+            grammar.production_text(second)
+        )
+    }
+}
+
+// FIRST sets the hand written parser hardcodes for its error messages and recovery
+fn parser_first_sets() -> Vec<(&'static str, Vec<TokenType>)> {
+    vec![
+        ("statement", statement_first()),
+        ("expr", EXPRESSION_FIRST.to_vec()),
+    ]
+}
+
+/// `kindred grammar check`: reports conflicts, unreachable rules and FIRST sets the
+/// parser disagrees with, fails when the grammar is not LL(1)
+pub fn check(path: &str, verbose: bool) -> Result<(), Box<dyn Error>> {
+    let grammar = Grammar::from_file(path)?;
+    let analysis = Analysis::new(&grammar);
+
+    println!(
+        "{}: {} nonterminals, {} terminals, {} productions",
+        path,
+        grammar.nonterminals.len(),
+        grammar.terminals.len(),
+        grammar.productions.len()
+    );
+
+    if verbose {
+        for (index, name) in grammar.nonterminals.iter().enumerate() {
+            let mut first = analysis.set_names(&analysis.first[index]);
+            if analysis.nullable[index] {
+                first.push("ε".to_string());
+            }
+            println!("\n{}", name);
+            println!("    FIRST  {{{}}}", first.join(", "));
+            println!(
+                "    FOLLOW {{{}}}",
+                analysis.set_names(&analysis.follow[index]).join(", ")
+            );
+        }
+        println!();
+    }
+
+    for nonterminal in analysis.unreachable() {
+        println!(
+            "warning: '{}' is never used from '{}'",
+            grammar.nonterminals[nonterminal], grammar.nonterminals[grammar.start]
+        );
+    }
+
+    let mut problems = 0;
+    for (name, tokens) in parser_first_sets() {
+        let Some(nonterminal) = grammar.nonterminal_index(name) else {
+            continue;
+        };
+        let mut parser_set = TerminalSet::new();
+        for type_ in &tokens {
+            match grammar.terminal_index(type_) {
+                Some(terminal) => {
+                    parser_set.insert(terminal);
+                }
+                None => {
+                    problems += 1;
+                    println!(
+                        "parser starts '{}' with {}, the grammar never uses it",
+                        name, type_
+                    );
+                }
+            }
+        }
+        let grammar_set = &analysis.first[nonterminal];
+        for terminal in grammar_set.difference(&parser_set) {
+            problems += 1;
+            println!(
+                "FIRST({}) has {} but the parser does not start '{}' with it",
+                name,
+                grammar.terminal_name(*terminal),
+                name
+            );
+        }
+        for terminal in parser_set.difference(grammar_set) {
+            problems += 1;
+            println!(
+                "the parser starts '{}' with {} but FIRST({}) does not have it",
+                name,
+                grammar.terminal_name(*terminal),
+                name
+            );
+        }
+    }
+
+    for conflict in &analysis.conflicts {
+        println!("{}", analysis.display_conflict(conflict));
+    }
+    problems += analysis.conflicts.len();
+
+    if problems > 0 {
+        return Err(format!("grammar check found {} problem(s)", problems).into());
+    }
+    println!("no LL(1) conflicts");
+    Ok(())
+}
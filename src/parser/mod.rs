@@ -1,2 +1,10 @@
+pub mod actions;
+pub mod ast;
+pub mod cst;
+pub mod expression;
+pub mod grammar;
 mod helper;
+pub mod ll1;
 pub mod parser_ana;
+pub mod statement;
+pub mod table_parser;
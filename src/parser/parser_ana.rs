@@ -1,39 +1,350 @@
-use crate::lexer::lexer_ana::{Lexer, TokenType};
+use crate::lexer::keywords::{self, KeywordProfile};
+use crate::lexer::lexer_ana::{Lexer, LexerOptions, PUNCTUATION, Span, Token, TokenType};
+use crate::parser::actions::AstBuilder;
+use crate::parser::ast::Program;
+use crate::parser::cst::{Event, SyntaxKind};
+use crate::parser::grammar::Grammar;
+use crate::parser::ll1::Analysis;
+use crate::parser::statement::{DECLARATION_FIRST, STATEMENT_KEYWORDS};
+use crate::parser::table_parser::TableParser;
+use crate::util::interner::Interner;
 use std::error::Error;
+use std::fs;
 
-pub fn parser() -> Result<(), Box<dyn Error>> {
-    let mut lexer = Lexer::from_file("main.kin")?;
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
 
-    loop {
-        let token = lexer.peek();
+impl ParseError {
+    pub fn new(message: String, span: Span) -> Self {
+        ParseError { message, span }
+    }
 
-        match token.type_ {
-            TokenType::EndOfFile => break,
-            TokenType::Unknown => {
-                // recoverable error  
-                lexer.next_token();
-            }
-            TokenType::KeywordLet => {
-                lexer.next_token();
+    pub fn display(&self, source: &str) {
+        eprintln!(
+            "\n Syntax error on line {}:{}",
+            self.span.line, self.span.column
+        );
+        eprintln!("     {}", self.message);
 
-                if lexer.peek().type_ != TokenType::Identifier {
-                    eprintln!("error: identifier expected after 'let'");
-                }
+        if let Some(line_text) = source.lines().nth(self.span.line - 1) {
+            eprintln!("\n   {}", line_text);
+            eprintln!("   {}^", " ".repeat(self.span.column - 1));
+        }
+    }
+}
+
+pub struct Parser<'src> {
+    lexer: Lexer<'src>,
+    errors: Vec<ParseError>,
+    // set by the first error of a statement, further errors are dropped until synchronize
+    panicking: bool,
+    // span of the last consumed token
+    last_span: Span,
+    // what the CST is built from, see cst::build_tree
+    events: Vec<Event>,
+}
+
+impl<'src> Parser<'src> {
+    pub fn new(lexer: Lexer<'src>) -> Self {
+        Parser {
+            lexer,
+            errors: Vec::new(),
+            panicking: false,
+            last_span: Span::new(0, 0, 1, 1),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn lexer(&self) -> &Lexer<'src> {
+        &self.lexer
+    }
+
+    pub fn lexer_mut(&mut self) -> &mut Lexer<'src> {
+        &mut self.lexer
+    }
+
+    pub fn into_lexer(self) -> Lexer<'src> {
+        self.lexer
+    }
+
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    pub fn get_errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    pub fn print_errors(&self) {
+        for error in &self.errors {
+            error.display(self.lexer.source());
+        }
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    // opens a node, its kind is decided by finish_node once the parse is known
+    pub(super) fn start_node(&mut self) -> usize {
+        self.events.push(Event::Start(SyntaxKind::Error));
+        self.events.len() - 1
+    }
+
+    pub(super) fn checkpoint(&self) -> usize {
+        self.events.len()
+    }
+
+    // opens a node around everything parsed since `checkpoint`, for `left op right`
+    pub(super) fn start_node_at(&mut self, checkpoint: usize) -> usize {
+        self.events
+            .insert(checkpoint, Event::Start(SyntaxKind::Error));
+        checkpoint
+    }
+
+    pub(super) fn finish_node(&mut self, marker: usize, kind: SyntaxKind) {
+        self.events[marker] = Event::Start(kind);
+        self.events.push(Event::Finish);
+    }
+
+    // Unknown tokens were already reported by the lexer, the grammar never sees them
+    pub(super) fn peek(&mut self) -> &Token<'src> {
+        while self.lexer.peek().type_ == TokenType::Unknown {
+            self.lexer.next_token();
+        }
+        self.lexer.peek()
+    }
+
+    pub(super) fn check(&mut self, type_: &TokenType) -> bool {
+        self.peek().type_ == *type_
+    }
+
+    pub(super) fn advance(&mut self) -> Token<'src> {
+        self.peek();
+        let token = self.lexer.next_token();
+        self.last_span = token.span;
+        self.events
+            .push(Event::Token(token.type_.clone(), token.span));
+        token
+    }
+
+    pub(super) fn eat(&mut self, type_: &TokenType) -> Option<Token<'src>> {
+        if self.check(type_) {
+            Some(self.advance())
+        } else {
+            None
+        }
+    }
+
+    pub(super) fn error(&mut self, message: String, span: Span) {
+        if self.panicking {
+            return;
+        }
+        self.panicking = true;
+        self.errors.push(ParseError::new(message, span));
+    }
+
+    pub(super) fn panicking(&self) -> bool {
+        self.panicking
+    }
 
-                lexer.next_token();
+    pub(super) fn end_panic(&mut self) {
+        self.panicking = false;
+    }
+
+    pub(super) fn last_span(&self) -> Span {
+        self.last_span
+    }
+
+    /// Reports "expected X, found Y" at the next token, `context` follows X
+    pub(super) fn expected(&mut self, expected: &[TokenType], context: &str) {
+        let list = expected_list(expected, self.lexer.profiles());
+        let token = self.peek().clone();
+        self.error(
+            format!("expected {}{}, found {}", list, context, describe(&token)),
+            token.span,
+        );
+    }
+
+    pub(super) fn expect(&mut self, type_: &TokenType, context: &str) -> Option<Token<'src>> {
+        let token = self.eat(type_);
+        if token.is_none() {
+            if *type_ == TokenType::Semicolon {
+                // a missing ';' belongs to the end of what came before, not the next line
+                let found = describe(self.peek());
+                let span = self.last_span;
+                let span = Span::new(
+                    span.end,
+                    span.end,
+                    span.line,
+                    span.column + (span.end - span.start),
+                );
+                self.error(format!("expected ';'{}, found {}", context, found), span);
+            } else {
+                self.expected(std::slice::from_ref(type_), context);
             }
-            _ => {
-                println!("Token: {:?} '{}'", token.type_, token.lexeme);
-                lexer.next_token();
+        }
+        token
+    }
+
+    pub(super) fn expected_list(&self, expected: &[TokenType]) -> String {
+        expected_list(expected, self.lexer.profiles())
+    }
+
+    // panic mode: skip to a `;` (eaten), a block end or a keyword that starts a new
+    // statement or declaration, so the next error reported is a real one. Names and
+    // numbers can start a statement too, but mostly they are the rest of the broken one
+    pub(super) fn synchronize(&mut self) {
+        loop {
+            let type_ = self.peek().type_.clone();
+            match type_ {
+                TokenType::Semicolon => {
+                    self.advance();
+                    break;
+                }
+                TokenType::BraceRight | TokenType::Fin_ | TokenType::EndOfFile => break,
+                ref type_
+                    if STATEMENT_KEYWORDS.contains(type_) || DECLARATION_FIRST.contains(type_) =>
+                {
+                    break;
+                }
+                _ => {
+                    self.advance();
+                }
             }
         }
+        self.panicking = false;
+    }
+}
+
+/// How a token is named in "found ..." messages
+pub fn describe(token: &Token) -> String {
+    match token.type_ {
+        TokenType::EndOfFile => "end of file".to_string(),
+        TokenType::StringLiteral => format!("string \"{}\"", token.lexeme),
+        _ => format!("'{}'", token.lexeme),
+    }
+}
+
+/// How a token type is named in "expected ..." messages, keywords are spelled in the
+/// active profile and left out when it has no spelling for them
+pub fn token_name(type_: &TokenType, profiles: &[KeywordProfile]) -> Option<String> {
+    let name = match type_ {
+        TokenType::Identifier => "identifier".to_string(),
+        TokenType::Integer => "integer".to_string(),
+        TokenType::Float => "float".to_string(),
+        TokenType::StringLiteral => "string".to_string(),
+        TokenType::EndOfFile => "end of file".to_string(),
+        _ => {
+            let text = PUNCTUATION
+                .iter()
+                .find(|(_, punctuation)| punctuation == type_)
+                .map(|(text, _)| *text)
+                .or_else(|| keywords::spelling(type_, profiles))?;
+            format!("'{}'", text)
+        }
+    };
+    Some(name)
+}
+
+pub fn expected_list(expected: &[TokenType], profiles: &[KeywordProfile]) -> String {
+    let names: Vec<String> = expected
+        .iter()
+        .filter_map(|type_| token_name(type_, profiles))
+        .collect();
+    match names.as_slice() {
+        [single] => single.clone(),
+        _ => format!("one of {}", names.join(", ")),
+    }
+}
+
+/// A program that parsed cleanly, with what later passes need to name things
+pub struct Parsed {
+    pub program: Program,
+    pub interner: Interner,
+    pub profiles: Vec<KeywordProfile>,
+    pub source: String,
+}
+
+pub fn parser(options: LexerOptions, table_parser: bool) -> Result<Parsed, Box<dyn Error>> {
+    parse_file("main.kin", options, table_parser)
+}
+
+pub fn parse_file(
+    path: &str,
+    options: LexerOptions,
+    table_parser: bool,
+) -> Result<Parsed, Box<dyn Error>> {
+    let source = fs::read_to_string(path)?;
+    let lexer = Lexer::with_options(&source, options)?;
+
+    let (program, lexer) = if table_parser {
+        parse_with_table(lexer)?
+    } else {
+        parse_with_hand_parser(lexer)?
+    };
+
+    Ok(Parsed {
+        program,
+        profiles: lexer.profiles().to_vec(),
+        interner: lexer.into_interner(),
+        source,
+    })
+}
+
+fn parse_with_hand_parser(lexer: Lexer) -> Result<(Program, Lexer), Box<dyn Error>> {
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    parser.lexer_mut().forget_declared(&program.declared_names());
+
+    report_lexer(parser.lexer())?;
+
+    if parser.has_errors() {
+        eprintln!("\nsyntax error {} ", parser.get_errors().len());
+        parser.print_errors();
+        return Err("syntax errors found".into());
+    }
+
+    Ok((program, parser.into_lexer()))
+}
+
+fn parse_with_table(lexer: Lexer) -> Result<(Program, Lexer), Box<dyn Error>> {
+    let grammar = Grammar::load()?;
+    let analysis = Analysis::new(&grammar);
+    if !analysis.conflicts.is_empty() {
+        return Err("the grammar is not LL(1), see `kindred grammar check`".into());
+    }
+
+    let mut parser = TableParser::new(&analysis, lexer);
+    let program = parser
+        .parse()
+        .and_then(|tree| AstBuilder::new(&grammar).program(&tree));
+    if let Ok(program) = &program {
+        parser.lexer_mut().forget_declared(&program.declared_names());
+    }
+
+    report_lexer(parser.lexer())?;
+
+    match program {
+        Ok(program) => Ok((program, parser.into_lexer())),
+        Err(error) => {
+            eprintln!("\nsyntax error 1 ");
+            error.display(parser.lexer().source());
+            Err("syntax errors found".into())
+        }
+    }
+}
+
+fn report_lexer(lexer: &Lexer) -> Result<(), Box<dyn Error>> {
+    if lexer.has_warnings() {
+        lexer.print_warnings();
     }
 
     if lexer.has_errors() {
-        eprintln!(
-            "\nlexical error {} ",
-            lexer.get_errors().len()
-        );
+        eprintln!("\nlexical error {} ", lexer.error_count());
         lexer.print_errors();
         return Err("lexical errors found".into());
     }
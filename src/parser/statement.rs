@@ -0,0 +1,704 @@
+use crate::lexer::lexer_ana::{Span, Token, TokenType};
+use crate::parser::ast::{
+    AssignOp, Block, Decl, DefineDecl, DefineItem, DeviceKind, Direction, Expr, ExprKind, FnDecl,
+    Iterable, ModuleDecl, NetworkArg, NetworkCommand, Param, Program, Stmt, StmtKind, TypeName,
+};
+use crate::parser::cst::SyntaxKind;
+use crate::parser::expression::{EXPRESSION_FIRST, starts_expression};
+use crate::parser::parser_ana::{Parser, describe};
+use crate::util::interner::Symbol;
+
+// keywords that open a statement, expressions can start one as well
+pub(super) const STATEMENT_KEYWORDS: &[TokenType] = &[
+    TokenType::KeywordLet,
+    TokenType::Si_,
+    TokenType::KeywordIf,
+    TokenType::KeywordWhile,
+    TokenType::KeywordFor,
+    TokenType::KeywordBreak,
+    TokenType::KeywordContinue,
+    TokenType::KeywordReturn,
+    TokenType::Escribe_,
+    TokenType::Coloca_,
+    TokenType::ColocaCoaxial_,
+    TokenType::ColocaCoaxialConcentrador_,
+    TokenType::UneMaquinaPuerto_,
+    TokenType::AsignaPuerto_,
+    TokenType::MaquinaCoaxial_,
+    TokenType::AsignaMaquinaCoaxial_,
+];
+
+pub(super) const DECLARATION_FIRST: &[TokenType] = &[
+    TokenType::Define_,
+    TokenType::KeywordFn,
+    TokenType::Modulo_,
+    TokenType::Inicio_,
+];
+
+const DEVICE_FIRST: &[TokenType] = &[
+    TokenType::Maquinas_,
+    TokenType::Concentradores_,
+    TokenType::Coaxial_,
+];
+
+const BLOCK_FIRST: &[TokenType] = &[TokenType::BraceLeft, TokenType::Inicio_];
+
+const TYPE_FIRST: &[TokenType] = &[
+    TokenType::Num_,
+    TokenType::Maquina_,
+    TokenType::Coaxial_,
+    TokenType::Identifier,
+];
+
+const DIRECTIONS: &[TokenType] = &[
+    TokenType::Arriba_,
+    TokenType::Abajo_,
+    TokenType::Izquierda_,
+    TokenType::Derecha_,
+];
+
+const ASSIGN_OPERATORS: &[(TokenType, AssignOp)] = &[
+    (TokenType::OperatorAssign, AssignOp::Assign),
+    (TokenType::OperatorPlusAssign, AssignOp::AddAssign),
+    (TokenType::OperatorMinusAssign, AssignOp::SubtractAssign),
+    (TokenType::OperatorMultiplyAssign, AssignOp::MultiplyAssign),
+    (TokenType::OperatorDivideAssign, AssignOp::DivideAssign),
+];
+
+// name, parameters and return type of a `fn`
+type FnHeader = ((Symbol, Span), Vec<Param>, Option<TypeName>);
+
+/// Tokens that can start a statement
+pub fn statement_first() -> Vec<TokenType> {
+    STATEMENT_KEYWORDS
+        .iter()
+        .chain(EXPRESSION_FIRST)
+        .cloned()
+        .collect()
+}
+
+impl<'src> Parser<'src> {
+    /// program := ('programa' IDENT ';')? declaration* 'inicio' statement* 'fin' EOF
+    pub fn parse_program(&mut self) -> Program {
+        let marker = self.start_node();
+        let start = self.peek().span;
+
+        let mut name = None;
+        if self.eat(&TokenType::Programa_).is_some() {
+            name = self
+                .expect(&TokenType::Identifier, " for the program name")
+                .and_then(|token| token.symbol.map(|symbol| (symbol, token.span)));
+            if name.is_some() {
+                self.expect(&TokenType::Semicolon, " after the program name");
+            }
+            if self.panicking() {
+                self.synchronize();
+            }
+        }
+
+        let mut declarations = Vec::new();
+        while !self.check(&TokenType::Inicio_) && !self.check(&TokenType::EndOfFile) {
+            declarations.push(self.parse_declaration());
+        }
+
+        let body = match self.peek().type_ {
+            TokenType::Inicio_ => self.parse_block(),
+            _ => {
+                self.expected(DECLARATION_FIRST, "");
+                Block {
+                    statements: Vec::new(),
+                    span: self.peek().span,
+                }
+            }
+        };
+
+        if !self.check(&TokenType::EndOfFile) {
+            // the program is over, anything after it is reported once
+            self.expected(&[TokenType::EndOfFile], " after the program");
+        }
+        let span = start.to(self.last_span());
+
+        // the CST keeps whatever follows, so it still spells out the whole file
+        while !self.check(&TokenType::EndOfFile) {
+            self.advance();
+        }
+        self.finish_node(marker, SyntaxKind::Program);
+
+        Program {
+            name,
+            declarations,
+            body,
+            span,
+        }
+    }
+
+    fn parse_declaration(&mut self) -> Decl {
+        let marker = self.start_node();
+        let start = self.peek().span;
+
+        let decl = match self.peek().type_ {
+            TokenType::Define_ => self.parse_define().map(Decl::Define),
+            TokenType::KeywordFn => self.parse_function().map(Decl::Function),
+            TokenType::Modulo_ => self.parse_module().map(Decl::Module),
+            _ => {
+                self.expected(DECLARATION_FIRST, "");
+                self.advance();
+                None
+            }
+        };
+
+        let decl = match decl {
+            Some(decl) if !self.panicking() => decl,
+            _ => {
+                if self.panicking() {
+                    self.synchronize();
+                }
+                Decl::Error(start.to(self.last_span()))
+            }
+        };
+        let kind = match decl {
+            Decl::Define(_) => SyntaxKind::DefineDecl,
+            Decl::Function(_) => SyntaxKind::FnDecl,
+            Decl::Module(_) => SyntaxKind::ModuleDecl,
+            Decl::Error(_) => SyntaxKind::Error,
+        };
+        self.finish_node(marker, kind);
+        decl
+    }
+
+    // 'define' ('maquinas' | 'concentradores' | 'coaxial') item (',' item)* ';'
+    fn parse_define(&mut self) -> Option<DefineDecl> {
+        let define = self.advance();
+
+        let kind = match self.peek().type_ {
+            TokenType::Maquinas_ => DeviceKind::Machine,
+            TokenType::Concentradores_ => DeviceKind::Concentrator,
+            TokenType::Coaxial_ => DeviceKind::Coaxial,
+            _ => {
+                self.expected(DEVICE_FIRST, &format!(" after '{}'", define.lexeme));
+                return None;
+            }
+        };
+        self.advance();
+
+        let mut items = vec![self.parse_define_item()?];
+        while self.eat(&TokenType::Comma).is_some() {
+            items.push(self.parse_define_item()?);
+        }
+        self.expect(&TokenType::Semicolon, " after the definition")?;
+
+        Some(DefineDecl {
+            kind,
+            items,
+            span: define.span.to(self.last_span()),
+        })
+    }
+
+    // IDENT ('=' expression)?
+    fn parse_define_item(&mut self) -> Option<DefineItem> {
+        let marker = self.start_node();
+        let item = self.expect_name(" in the definition").map(|(name, span)| {
+            let size = self
+                .eat(&TokenType::OperatorAssign)
+                .map(|_| self.parse_expression());
+            DefineItem { name, span, size }
+        });
+        self.finish_node(marker, node_kind(&item, SyntaxKind::DefineItem));
+        item
+    }
+
+    // 'fn' IDENT '(' (param (',' param)*)? ')' ('->' type)? block
+    fn parse_function(&mut self) -> Option<FnDecl> {
+        let keyword = self.advance();
+        let header = self.parse_function_header();
+
+        // a broken header still has a body worth checking
+        if header.is_none() {
+            while !matches!(
+                self.peek().type_,
+                TokenType::BraceLeft
+                    | TokenType::Inicio_
+                    | TokenType::Define_
+                    | TokenType::KeywordFn
+                    | TokenType::Modulo_
+                    | TokenType::EndOfFile
+            ) {
+                self.advance();
+            }
+            if !BLOCK_FIRST.contains(&self.peek().type_) {
+                return None;
+            }
+            self.end_panic();
+        }
+        let body = self.parse_body(" for the function body");
+
+        let ((name, name_span), params, return_type) = header?;
+        Some(FnDecl {
+            name,
+            name_span,
+            params,
+            return_type,
+            body,
+            span: keyword.span.to(self.last_span()),
+        })
+    }
+
+    // 'modulo' IDENT ('{' function* '}' | 'inicio' function* 'fin')
+    fn parse_module(&mut self) -> Option<ModuleDecl> {
+        let keyword = self.advance();
+        let (name, name_span) = self.expect_name(&format!(" after '{}'", keyword.lexeme))?;
+        if !BLOCK_FIRST.contains(&self.peek().type_) {
+            self.expected(BLOCK_FIRST, " for the module body");
+            return None;
+        }
+        let open = self.advance();
+        let close = match open.type_ {
+            TokenType::BraceLeft => TokenType::BraceRight,
+            _ => TokenType::Fin_,
+        };
+
+        // a broken function is skipped, the rest of the module is still read
+        let mut functions = Vec::new();
+        while self.check(&TokenType::KeywordFn) {
+            let marker = self.start_node();
+            let function = self.parse_function().filter(|_| !self.panicking());
+            if self.panicking() {
+                self.synchronize();
+            }
+            self.finish_node(marker, node_kind(&function, SyntaxKind::FnDecl));
+            functions.extend(function);
+        }
+        if !self.check(&close) {
+            let context = format!(" in module '{}'", self.lexer().interner().resolve(name));
+            self.expected(&[TokenType::KeywordFn, close.clone()], &context);
+            return None;
+        }
+        self.expect_block_end(&close, &open);
+
+        Some(ModuleDecl {
+            name,
+            name_span,
+            functions,
+            span: keyword.span.to(self.last_span()),
+        })
+    }
+
+    fn parse_function_header(&mut self) -> Option<FnHeader> {
+        let name = self.expect_name(" for the function name")?;
+        self.expect(&TokenType::ParenthesisLeft, " after the function name")?;
+
+        let mut params = Vec::new();
+        if !self.check(&TokenType::ParenthesisRight) {
+            params.push(self.parse_param()?);
+            while self.eat(&TokenType::Comma).is_some() {
+                params.push(self.parse_param()?);
+            }
+        }
+        self.expect(&TokenType::ParenthesisRight, " after the parameters")?;
+
+        let mut return_type = None;
+        if self.eat(&TokenType::Arrow).is_some() {
+            return_type = Some(self.parse_type(" after '->'")?);
+        }
+        Some((name, params, return_type))
+    }
+
+    // IDENT ':' type
+    fn parse_param(&mut self) -> Option<Param> {
+        let marker = self.start_node();
+        let param = self.parse_param_inner();
+        self.finish_node(marker, node_kind(&param, SyntaxKind::Param));
+        param
+    }
+
+    fn parse_param_inner(&mut self) -> Option<Param> {
+        let (name, span) = self.expect_name(" for the parameter")?;
+        self.expect(&TokenType::Colon, " after the parameter name")?;
+        let ty = self.parse_type(" for the parameter")?;
+        Some(Param { name, span, ty })
+    }
+
+    fn parse_type(&mut self, context: &str) -> Option<TypeName> {
+        if !TYPE_FIRST.contains(&self.peek().type_) {
+            self.expected(TYPE_FIRST, context);
+            return None;
+        }
+        let marker = self.start_node();
+        let token = self.advance();
+        self.finish_node(marker, SyntaxKind::TypeName);
+        token.symbol.map(|name| TypeName {
+            name,
+            span: token.span,
+        })
+    }
+
+    fn expect_name(&mut self, context: &str) -> Option<(Symbol, Span)> {
+        let token = self.expect(&TokenType::Identifier, context)?;
+        token.symbol.map(|symbol| (symbol, token.span))
+    }
+
+    /// block := '{' statement* '}' | 'inicio' statement* 'fin'
+    pub fn parse_block(&mut self) -> Block {
+        let marker = self.start_node();
+        let open = self.advance();
+        let close = match open.type_ {
+            TokenType::BraceLeft => TokenType::BraceRight,
+            _ => TokenType::Fin_,
+        };
+
+        let mut statements = Vec::new();
+        while !matches!(
+            self.peek().type_,
+            TokenType::BraceRight | TokenType::Fin_ | TokenType::EndOfFile
+        ) {
+            statements.push(self.parse_statement());
+        }
+        self.expect_block_end(&close, &open);
+        self.finish_node(marker, SyntaxKind::Block);
+
+        Block {
+            statements,
+            span: open.span.to(self.last_span()),
+        }
+    }
+
+    fn expect_block_end(&mut self, close: &TokenType, open: &Token) {
+        if self.eat(close).is_some() {
+            return;
+        }
+        let found = self.peek().clone();
+        let expected = self.expected_list(std::slice::from_ref(close));
+        self.error(
+            format!(
+                "expected {} to close '{}' from line {}:{}, found {}",
+                expected,
+                open.lexeme,
+                open.span.line,
+                open.span.column,
+                describe(&found)
+            ),
+            found.span,
+        );
+        // `inicio ... }` and `{ ... fin` are taken as typos for the right closer
+        if matches!(found.type_, TokenType::BraceRight | TokenType::Fin_) {
+            self.advance();
+        }
+    }
+
+    fn parse_statement(&mut self) -> Stmt {
+        let marker = self.start_node();
+        let start = self.peek().span;
+
+        let kind = match self.peek().type_.clone() {
+            TokenType::KeywordLet => self.parse_let(),
+            TokenType::Si_ | TokenType::KeywordIf => self.parse_if(),
+            TokenType::KeywordWhile => self.parse_while(),
+            TokenType::KeywordFor => self.parse_for(),
+            TokenType::KeywordBreak => self.parse_jump(StmtKind::Break),
+            TokenType::KeywordContinue => self.parse_jump(StmtKind::Continue),
+            TokenType::KeywordReturn => self.parse_return(),
+            TokenType::Escribe_ => self.parse_write(),
+            type_ => match NetworkCommand::from_token(&type_) {
+                Some(command) => self.parse_network(command),
+                None if starts_expression(&type_) => self.parse_expression_statement(),
+                None => {
+                    self.expected(&statement_first(), "");
+                    self.advance();
+                    StmtKind::Error
+                }
+            },
+        };
+
+        if self.panicking() {
+            // the skipped tokens end up in the Error node
+            self.synchronize();
+            self.finish_node(marker, SyntaxKind::Error);
+            return Stmt::new(StmtKind::Error, start.to(self.last_span()));
+        }
+        self.finish_node(marker, statement_kind(&kind));
+        Stmt::new(kind, start.to(self.last_span()))
+    }
+
+    // 'let' IDENT (':' type)? '=' expression ';'
+    fn parse_let(&mut self) -> StmtKind {
+        let keyword = self.advance();
+        let Some((name, name_span)) = self.expect_name(&format!(" after '{}'", keyword.lexeme))
+        else {
+            return StmtKind::Error;
+        };
+
+        let mut ty = None;
+        if self.eat(&TokenType::Colon).is_some() {
+            ty = self.parse_type(" after ':'");
+            if ty.is_none() {
+                return StmtKind::Error;
+            }
+        }
+
+        if self
+            .expect(&TokenType::OperatorAssign, " in the declaration")
+            .is_none()
+        {
+            return StmtKind::Error;
+        }
+        let value = self.parse_expression();
+        self.expect(&TokenType::Semicolon, " after the declaration");
+
+        StmtKind::Let {
+            name,
+            name_span,
+            ty,
+            value,
+        }
+    }
+
+    // ('si' | 'if') expression block (('sino' | 'else') (if | block))?
+    fn parse_if(&mut self) -> StmtKind {
+        self.advance();
+        let condition = self.parse_condition();
+        let then_block = self.parse_body("");
+
+        let mut else_block = None;
+        if self.eat(&TokenType::Sino_).is_some() || self.eat(&TokenType::KeywordElse).is_some() {
+            else_block = Some(match self.peek().type_ {
+                // sino si ... is a block holding just the nested si
+                TokenType::Si_ | TokenType::KeywordIf => {
+                    let marker = self.start_node();
+                    let start = self.peek().span;
+                    let kind = self.parse_if();
+                    self.finish_node(marker, SyntaxKind::IfStmt);
+                    let span = start.to(self.last_span());
+                    Block {
+                        statements: vec![Stmt::new(kind, span)],
+                        span,
+                    }
+                }
+                _ => self.parse_body(""),
+            });
+        }
+
+        StmtKind::If {
+            condition,
+            then_block,
+            else_block,
+        }
+    }
+
+    // 'while' expression block
+    fn parse_while(&mut self) -> StmtKind {
+        self.advance();
+        let condition = self.parse_condition();
+        let body = self.parse_body("");
+        StmtKind::While { condition, body }
+    }
+
+    // 'for' IDENT 'in' (expression | 'maquinas' | 'concentradores' | 'coaxial') block
+    fn parse_for(&mut self) -> StmtKind {
+        let keyword = self.advance();
+        let Some((var, var_span)) = self.expect_name(&format!(" after '{}'", keyword.lexeme))
+        else {
+            return StmtKind::Error;
+        };
+        if self
+            .expect(&TokenType::KeywordIn, " after the loop variable")
+            .is_none()
+        {
+            return StmtKind::Error;
+        }
+
+        let iterable = match self.peek().type_ {
+            TokenType::Maquinas_ => Iterable::Devices(DeviceKind::Machine, self.advance().span),
+            TokenType::Concentradores_ => {
+                Iterable::Devices(DeviceKind::Concentrator, self.advance().span)
+            }
+            TokenType::Coaxial_ => Iterable::Devices(DeviceKind::Coaxial, self.advance().span),
+            ref type_ if starts_expression(type_) => Iterable::Expr(self.parse_expression()),
+            _ => {
+                let expected: Vec<TokenType> = EXPRESSION_FIRST
+                    .iter()
+                    .chain(DEVICE_FIRST)
+                    .cloned()
+                    .collect();
+                self.expected(&expected, " to loop over");
+                return StmtKind::Error;
+            }
+        };
+        let body = self.parse_body("");
+
+        StmtKind::For {
+            var,
+            var_span,
+            iterable,
+            body,
+        }
+    }
+
+    // 'break' ';' | 'continue' ';'
+    fn parse_jump(&mut self, kind: StmtKind) -> StmtKind {
+        let keyword = self.advance();
+        self.expect(
+            &TokenType::Semicolon,
+            &format!(" after '{}'", keyword.lexeme),
+        );
+        kind
+    }
+
+    fn parse_condition(&mut self) -> Expr {
+        if !starts_expression(&self.peek().type_) {
+            self.expected(EXPRESSION_FIRST, " for the condition");
+            return Expr::error(self.peek().span);
+        }
+        self.parse_expression()
+    }
+
+    fn parse_body(&mut self, context: &str) -> Block {
+        if BLOCK_FIRST.contains(&self.peek().type_) {
+            return self.parse_block();
+        }
+        self.expected(BLOCK_FIRST, context);
+        Block {
+            statements: Vec::new(),
+            span: self.peek().span,
+        }
+    }
+
+    // 'return' expression? ';'
+    fn parse_return(&mut self) -> StmtKind {
+        self.advance();
+        let value = if self.check(&TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.parse_expression())
+        };
+        self.expect(&TokenType::Semicolon, " after the return value");
+        StmtKind::Return(value)
+    }
+
+    // 'escribe' '(' (expression (',' expression)*)? ')' ';'
+    fn parse_write(&mut self) -> StmtKind {
+        let keyword = self.advance();
+        let Some(open) = self.expect(
+            &TokenType::ParenthesisLeft,
+            &format!(" after '{}'", keyword.lexeme),
+        ) else {
+            return StmtKind::Error;
+        };
+
+        let mut args = Vec::new();
+        if !self.check(&TokenType::ParenthesisRight) {
+            args.push(self.parse_expression());
+            while self.eat(&TokenType::Comma).is_some() {
+                args.push(self.parse_expression());
+            }
+        }
+        self.expect_closing(&TokenType::ParenthesisRight, &open);
+        self.expect(&TokenType::Semicolon, " after the statement");
+        StmtKind::Write(args)
+    }
+
+    // command '(' arg (',' arg)* ')' ';' where arg is a direction or an expression
+    fn parse_network(&mut self, command: NetworkCommand) -> StmtKind {
+        let keyword = self.advance();
+        let Some(open) = self.expect(
+            &TokenType::ParenthesisLeft,
+            &format!(" after '{}'", keyword.lexeme),
+        ) else {
+            return StmtKind::Error;
+        };
+
+        let mut args = Vec::new();
+        if !self.check(&TokenType::ParenthesisRight) {
+            args.push(self.parse_network_arg());
+            while self.eat(&TokenType::Comma).is_some() {
+                args.push(self.parse_network_arg());
+            }
+        }
+        self.expect_closing(&TokenType::ParenthesisRight, &open);
+
+        if !self.panicking() && args.len() != command.arity() {
+            self.error(
+                format!(
+                    "'{}' takes {} arguments, found {}",
+                    keyword.lexeme,
+                    command.arity(),
+                    args.len()
+                ),
+                keyword.span.to(self.last_span()),
+            );
+        }
+        self.expect(&TokenType::Semicolon, " after the statement");
+        StmtKind::Network { command, args }
+    }
+
+    fn parse_network_arg(&mut self) -> NetworkArg {
+        let type_ = self.peek().type_.clone();
+        if let Some(direction) = Direction::from_token(&type_) {
+            let token = self.advance();
+            return NetworkArg::Direction(direction, token.span);
+        }
+        if !starts_expression(&type_) {
+            let expected: Vec<TokenType> =
+                DIRECTIONS.iter().chain(EXPRESSION_FIRST).cloned().collect();
+            self.expected(&expected, "");
+            return NetworkArg::Expr(Expr::error(self.peek().span));
+        }
+        NetworkArg::Expr(self.parse_expression())
+    }
+
+    // expression (assign_op expression)? ';'
+    fn parse_expression_statement(&mut self) -> StmtKind {
+        let target = self.parse_expression();
+
+        let type_ = self.peek().type_.clone();
+        let kind = match ASSIGN_OPERATORS.iter().find(|(token, _)| *token == type_) {
+            Some(&(_, op)) => {
+                let operator = self.advance();
+                if !matches!(
+                    target.kind,
+                    ExprKind::Identifier(_)
+                        | ExprKind::Member { .. }
+                        | ExprKind::Index { .. }
+                        | ExprKind::Error
+                ) {
+                    self.error(
+                        format!(
+                            "cannot assign to this expression with '{}'",
+                            operator.lexeme
+                        ),
+                        target.span,
+                    );
+                }
+                let value = self.parse_expression();
+                StmtKind::Assign { target, op, value }
+            }
+            None => StmtKind::Expr(target),
+        };
+
+        self.expect(&TokenType::Semicolon, " after the statement");
+        kind
+    }
+}
+
+fn node_kind<T>(parsed: &Option<T>, kind: SyntaxKind) -> SyntaxKind {
+    match parsed {
+        Some(_) => kind,
+        None => SyntaxKind::Error,
+    }
+}
+
+fn statement_kind(kind: &StmtKind) -> SyntaxKind {
+    match kind {
+        StmtKind::Let { .. } => SyntaxKind::LetStmt,
+        StmtKind::Assign { .. } => SyntaxKind::AssignStmt,
+        StmtKind::Expr(_) => SyntaxKind::ExprStmt,
+        StmtKind::Write(_) => SyntaxKind::WriteStmt,
+        StmtKind::If { .. } => SyntaxKind::IfStmt,
+        StmtKind::While { .. } => SyntaxKind::WhileStmt,
+        StmtKind::For { .. } => SyntaxKind::ForStmt,
+        StmtKind::Break => SyntaxKind::BreakStmt,
+        StmtKind::Continue => SyntaxKind::ContinueStmt,
+        StmtKind::Return(_) => SyntaxKind::ReturnStmt,
+        StmtKind::Network { .. } => SyntaxKind::NetworkStmt,
+        StmtKind::Error => SyntaxKind::Error,
+    }
+}
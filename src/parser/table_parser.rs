@@ -0,0 +1,165 @@
+use crate::lexer::lexer_ana::{Lexer, Token, TokenType};
+use crate::parser::grammar::{Grammar, GrammarSymbol};
+use crate::parser::ll1::Analysis;
+use crate::parser::parser_ana::{ParseError, describe, expected_list};
+
+/// Concrete parse tree, one node per expanded production
+#[derive(Debug, Clone)]
+pub enum ParseTree<'src> {
+    Leaf(Token<'src>),
+    Node {
+        nonterminal: usize,
+        production: usize,
+        children: Vec<ParseTree<'src>>,
+    },
+}
+
+impl<'src> ParseTree<'src> {
+    pub fn first_token(&self) -> Option<&Token<'src>> {
+        match self {
+            ParseTree::Leaf(token) => Some(token),
+            ParseTree::Node { children, .. } => {
+                children.iter().find_map(|child| child.first_token())
+            }
+        }
+    }
+
+    pub fn last_token(&self) -> Option<&Token<'src>> {
+        match self {
+            ParseTree::Leaf(token) => Some(token),
+            ParseTree::Node { children, .. } => {
+                children.iter().rev().find_map(|child| child.last_token())
+            }
+        }
+    }
+
+    /// The tree as indented text, nonterminals by name and tokens by lexeme
+    pub fn dump(&self, grammar: &Grammar) -> String {
+        let mut out = String::new();
+        self.dump_into(grammar, 0, &mut out);
+        out
+    }
+
+    fn dump_into(&self, grammar: &Grammar, depth: usize, out: &mut String) {
+        let indent = "  ".repeat(depth);
+        match self {
+            ParseTree::Leaf(token) => out.push_str(&format!("{}{}\n", indent, describe(token))),
+            ParseTree::Node {
+                nonterminal,
+                children,
+                ..
+            } => {
+                out.push_str(&format!(
+                    "{}{}\n",
+                    indent, grammar.nonterminals[*nonterminal]
+                ));
+                for child in children {
+                    child.dump_into(grammar, depth + 1, out);
+                }
+            }
+        }
+    }
+}
+
+enum StackItem {
+    Symbol(GrammarSymbol),
+    // all symbols of the production are matched, build its node
+    Reduce(usize),
+}
+
+/// Predictive parser driven by the LL(1) table of the grammar
+pub struct TableParser<'a, 'src> {
+    analysis: &'a Analysis<'a>,
+    lexer: Lexer<'src>,
+}
+
+impl<'a, 'src> TableParser<'a, 'src> {
+    pub fn new(analysis: &'a Analysis<'a>, lexer: Lexer<'src>) -> Self {
+        TableParser { analysis, lexer }
+    }
+
+    pub fn lexer(&self) -> &Lexer<'src> {
+        &self.lexer
+    }
+
+    pub fn lexer_mut(&mut self) -> &mut Lexer<'src> {
+        &mut self.lexer
+    }
+
+    pub fn into_lexer(self) -> Lexer<'src> {
+        self.lexer
+    }
+
+    // Unknown tokens were already reported by the lexer, like in the hand written parser
+    fn next_token(&mut self) -> Token<'src> {
+        loop {
+            let token = self.lexer.next_token();
+            if token.type_ != TokenType::Unknown {
+                return token;
+            }
+        }
+    }
+
+    /// Parses the whole input, stopping at the first syntax error
+    pub fn parse(&mut self) -> Result<ParseTree<'src>, ParseError> {
+        let grammar = self.analysis.grammar;
+        let mut stack = vec![StackItem::Symbol(GrammarSymbol::NonTerminal(grammar.start))];
+        let mut values: Vec<ParseTree<'src>> = Vec::new();
+        let mut token = self.next_token();
+
+        while let Some(item) = stack.pop() {
+            match item {
+                StackItem::Symbol(GrammarSymbol::Terminal(terminal)) => {
+                    if grammar.terminals[terminal] != token.type_ {
+                        return Err(self.unexpected(&[grammar.terminals[terminal].clone()], &token));
+                    }
+                    values.push(ParseTree::Leaf(token));
+                    token = self.next_token();
+                }
+                StackItem::Symbol(GrammarSymbol::NonTerminal(nonterminal)) => {
+                    let production = grammar
+                        .terminal_index(&token.type_)
+                        .and_then(|terminal| self.analysis.predict(nonterminal, terminal));
+                    let Some(production) = production else {
+                        let expected: Vec<TokenType> = self.analysis.table[nonterminal]
+                            .keys()
+                            .map(|terminal| grammar.terminals[*terminal].clone())
+                            .collect();
+                        return Err(self.unexpected(&expected, &token));
+                    };
+
+                    stack.push(StackItem::Reduce(production));
+                    for symbol in grammar.productions[production].rhs.iter().rev() {
+                        stack.push(StackItem::Symbol(*symbol));
+                    }
+                }
+                StackItem::Reduce(production) => {
+                    let production_ref = &grammar.productions[production];
+                    let children = values.split_off(values.len() - production_ref.rhs.len());
+                    values.push(ParseTree::Node {
+                        nonterminal: production_ref.lhs,
+                        production,
+                        children,
+                    });
+                }
+            }
+        }
+
+        if token.type_ != TokenType::EndOfFile {
+            return Err(self.unexpected(&[TokenType::EndOfFile], &token));
+        }
+        // the start symbol reduced last, it is the only value left
+        Ok(values.pop().expect("the start symbol always builds a node"))
+    }
+
+    fn unexpected(&self, expected: &[TokenType], token: &Token) -> ParseError {
+        ParseError::new(
+            format!(
+                "expected {}, found {}",
+                expected_list(expected, self.lexer.profiles()),
+                describe(token)
+            ),
+            token.span,
+        )
+    }
+}
@@ -0,0 +1,1035 @@
+use crate::lexer::keywords::{self, KEYWORDS, KeywordProfile};
+use crate::lexer::lexer_ana::{Span, TokenType};
+use crate::parser::ast::{
+    AssignOp, BinaryOp, Block, Decl, DefineDecl, DeviceKind, Expr, ExprKind, FnDecl, Iterable,
+    ModuleDecl, NetworkArg, NetworkCommand, Program, Stmt, StmtKind, TypeName, UnaryOp,
+};
+use crate::semantic::types::Type;
+use crate::util::interner::{Interner, Symbol};
+use crate::util::symboltable::{SymbolEntry, SymbolKind, SymbolTable};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct TypeError {
+    pub message: String,
+    pub span: Span,
+    // the other end of a mismatch, e.g. the annotation a value was checked against
+    pub note: Option<(String, Span)>,
+}
+
+impl TypeError {
+    pub fn new(message: String, span: Span) -> Self {
+        TypeError {
+            message,
+            span,
+            note: None,
+        }
+    }
+
+    pub fn with_note(mut self, message: String, span: Span) -> Self {
+        self.note = Some((message, span));
+        self
+    }
+
+    pub fn display(&self, source: &str) {
+        eprintln!(
+            "\n Type error on line {}:{}",
+            self.span.line, self.span.column
+        );
+        eprintln!("     {}", self.message);
+        show_span(source, self.span);
+
+        if let Some((message, span)) = &self.note {
+            eprintln!(
+                "\n   note on line {}:{}: {}",
+                span.line, span.column, message
+            );
+            show_span(source, *span);
+        }
+    }
+}
+
+pub(crate) fn show_span(source: &str, span: Span) {
+    if let Some((line_text, carets)) = quote(source, span) {
+        eprintln!("\n   {}", line_text);
+        eprintln!("   {}", carets);
+    }
+}
+
+/// The line `span` starts on and the carets that go under it. The carets stay on that line even
+/// when the span runs past it or the source changed since the span was made, as a make.ob
+/// outliving an edit of main.kin does
+pub fn quote(source: &str, span: Span) -> Option<(&str, String)> {
+    let line_text = source.lines().nth(span.line.checked_sub(1)?)?;
+    let line_len = line_text.chars().count();
+    // one past the last char is where a missing `;` is pointed at
+    let column = span.column.clamp(1, line_len + 1);
+    let width = source
+        .get(span.start..span.end)
+        .map_or(line_len, |text| text.chars().count())
+        .min(line_len + 1 - column)
+        .max(1);
+    Some((
+        line_text,
+        format!("{}{}", " ".repeat(column - 1), "^".repeat(width)),
+    ))
+}
+
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub params: Vec<(Type, Span)>,
+    pub ret: Type,
+    // where `-> type` was written, None for functions returning nothing
+    pub ret_span: Option<Span>,
+}
+
+#[derive(Debug, Clone)]
+struct Binding {
+    ty: Type,
+    span: Span,
+}
+
+// what a `return` is checked against
+struct ReturnContext {
+    ty: Type,
+    span: Option<Span>,
+}
+
+/// Infers and checks types over a parsed program, collecting every error
+pub struct Checker<'a> {
+    interner: &'a Interner,
+    profiles: &'a [KeywordProfile],
+    globals: SymbolTable,
+    // keyed by (module, name), top level functions have no module
+    functions: HashMap<(Option<Symbol>, Symbol), Signature>,
+    // the module whose functions are being checked, its functions are called unqualified
+    current_module: Option<Symbol>,
+    // innermost block last
+    scopes: Vec<HashMap<Symbol, Binding>>,
+    // None in the main body
+    returns: Option<ReturnContext>,
+    // loops around the statement being checked, for break and continue
+    loops: usize,
+    errors: Vec<TypeError>,
+    // what each expression, variable and parameter turned out to be, by span
+    types: HashMap<Span, Type>,
+}
+
+pub fn check_program(
+    program: &Program,
+    interner: &Interner,
+    profiles: &[KeywordProfile],
+) -> Vec<TypeError> {
+    let mut checker = Checker::new(interner, profiles);
+    checker.check(program);
+    checker.errors
+}
+
+impl<'a> Checker<'a> {
+    pub fn new(interner: &'a Interner, profiles: &'a [KeywordProfile]) -> Self {
+        Checker {
+            interner,
+            profiles,
+            globals: SymbolTable::new(),
+            functions: HashMap::new(),
+            current_module: None,
+            scopes: Vec::new(),
+            returns: None,
+            loops: 0,
+            errors: Vec::new(),
+            types: HashMap::new(),
+        }
+    }
+
+    pub fn errors(&self) -> &[TypeError] {
+        &self.errors
+    }
+
+    /// The type of the expression at `span`, or of the variable or parameter declared there
+    pub fn type_at(&self, span: Span) -> Option<&Type> {
+        self.types.get(&span)
+    }
+
+    /// What `name` takes and returns, `module` is None for top level functions
+    pub fn signature(&self, module: Option<Symbol>, name: Symbol) -> Option<&Signature> {
+        self.functions.get(&(module, name))
+    }
+
+    pub fn check(&mut self, program: &Program) {
+        // every define and fn is visible everywhere, so they are collected first
+        for decl in &program.declarations {
+            match decl {
+                Decl::Define(define) => self.declare_devices(define),
+                Decl::Function(function) => self.declare_function(function, None),
+                Decl::Module(module) => self.declare_module(module),
+                Decl::Error(_) => {}
+            }
+        }
+
+        for decl in &program.declarations {
+            match decl {
+                Decl::Define(define) => self.check_sizes(define),
+                Decl::Function(function) => self.check_function(function),
+                Decl::Module(module) => {
+                    self.current_module = Some(module.name);
+                    for function in &module.functions {
+                        self.check_function(function);
+                    }
+                    self.current_module = None;
+                }
+                Decl::Error(_) => {}
+            }
+        }
+
+        self.returns = None;
+        self.check_block(&program.body, &[]);
+    }
+
+    fn name(&self, symbol: Symbol) -> &str {
+        self.interner.resolve(symbol)
+    }
+
+    // the keyword a symbol spells in the active profiles, `puertos` or `ports` alike
+    fn keyword(&self, symbol: Symbol) -> Option<&'static TokenType> {
+        let keyword = KEYWORDS.get(symbol.index())?;
+        keyword
+            .profiles
+            .iter()
+            .any(|profile| self.profiles.contains(profile))
+            .then_some(&keyword.token)
+    }
+
+    fn error(&mut self, error: TypeError) {
+        self.errors.push(error);
+    }
+
+    fn declare(&mut self, name: Symbol, kind: SymbolKind, span: Span) {
+        let entry = SymbolEntry { name, kind, span };
+        if let Err(previous) = self.globals.insert(entry) {
+            let previous = previous.span;
+            let error = TypeError::new(format!("'{}' is already defined", self.name(name)), span)
+                .with_note("first defined here".to_string(), previous);
+            self.error(error);
+        }
+    }
+
+    fn declare_devices(&mut self, define: &DefineDecl) {
+        let kind = match define.kind {
+            DeviceKind::Machine => SymbolKind::Machine,
+            DeviceKind::Concentrator => SymbolKind::Concentrator,
+            DeviceKind::Coaxial => SymbolKind::Coaxial,
+        };
+        for item in &define.items {
+            self.declare(item.name, kind, item.span);
+        }
+    }
+
+    fn declare_module(&mut self, module: &ModuleDecl) {
+        self.declare(module.name, SymbolKind::Module, module.name_span);
+        let mut seen: HashMap<Symbol, Span> = HashMap::new();
+        for function in &module.functions {
+            if let Some(&previous) = seen.get(&function.name) {
+                let error = TypeError::new(
+                    format!(
+                        "'{}' is already defined in module '{}'",
+                        self.name(function.name),
+                        self.name(module.name)
+                    ),
+                    function.name_span,
+                )
+                .with_note("first defined here".to_string(), previous);
+                self.error(error);
+                continue;
+            }
+            seen.insert(function.name, function.name_span);
+            self.declare_function(function, Some(module.name));
+        }
+    }
+
+    fn declare_function(&mut self, function: &FnDecl, module: Option<Symbol>) {
+        if module.is_none() {
+            self.declare(function.name, SymbolKind::Function, function.name_span);
+        }
+        let params = function
+            .params
+            .iter()
+            .map(|param| (self.resolve_type(&param.ty), param.ty.span))
+            .collect();
+        let ret = match &function.return_type {
+            Some(ty) => self.resolve_type(ty),
+            None => Type::Nothing,
+        };
+        self.functions
+            .entry((module, function.name))
+            .or_insert(Signature {
+                params,
+                ret,
+                ret_span: function.return_type.map(|ty| ty.span),
+            });
+    }
+
+    fn resolve_type(&mut self, ty: &TypeName) -> Type {
+        let resolved = match self.keyword(ty.name) {
+            Some(keyword) => Type::from_keyword(keyword),
+            None => Type::from_name(self.name(ty.name)),
+        };
+        resolved.unwrap_or_else(|| {
+            let name = self.name(ty.name).to_string();
+            self.error(TypeError::new(format!("unknown type '{}'", name), ty.span));
+            Type::Error
+        })
+    }
+
+    // concentrators are sized by port count, coaxial segments by length
+    fn check_sizes(&mut self, define: &DefineDecl) {
+        for item in &define.items {
+            let Some(size) = &item.size else {
+                continue;
+            };
+            if define.kind == DeviceKind::Machine {
+                self.error(TypeError::new(
+                    format!("machine '{}' cannot be given a size", self.name(item.name)),
+                    size.span,
+                ));
+                continue;
+            }
+            let ty = self.infer(size);
+            self.expect_type(&ty, &Type::Num, size.span);
+        }
+    }
+
+    fn check_function(&mut self, function: &FnDecl) {
+        let key = (self.current_module, function.name);
+        let Some(signature) = self.functions.get(&key).cloned() else {
+            return;
+        };
+        let params: Vec<(Symbol, Binding)> = function
+            .params
+            .iter()
+            .zip(&signature.params)
+            .map(|(param, (ty, _))| {
+                self.types.insert(param.span, ty.clone());
+                (
+                    param.name,
+                    Binding {
+                        ty: ty.clone(),
+                        span: param.span,
+                    },
+                )
+            })
+            .collect();
+
+        self.returns = Some(ReturnContext {
+            ty: signature.ret.clone(),
+            span: signature.ret_span,
+        });
+        self.loops = 0;
+        self.check_block(&function.body, &params);
+        self.returns = None;
+
+        if signature.ret != Type::Nothing && !always_returns(&function.body) {
+            let mut error = TypeError::new(
+                format!(
+                    "function '{}' can end without returning {}",
+                    self.function_label(self.current_module, function.name),
+                    signature.ret
+                ),
+                function.name_span,
+            );
+            if let Some(span) = signature.ret_span {
+                error = error.with_note("return type declared here".to_string(), span);
+            }
+            self.error(error);
+        }
+    }
+
+    fn check_block(&mut self, block: &Block, bindings: &[(Symbol, Binding)]) {
+        let mut scope = HashMap::new();
+        for (name, binding) in bindings {
+            if let Some(previous) = scope.insert(*name, binding.clone()) {
+                let error = TypeError::new(
+                    format!("parameter '{}' is declared twice", self.name(*name)),
+                    binding.span,
+                )
+                .with_note("first declared here".to_string(), previous.span);
+                self.error(error);
+            }
+        }
+        self.scopes.push(scope);
+        for stmt in &block.statements {
+            self.check_stmt(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    // `f` or `modulo::f`
+    fn function_label(&self, module: Option<Symbol>, name: Symbol) -> String {
+        match module {
+            Some(module) => format!("{}::{}", self.name(module), self.name(name)),
+            None => self.name(name).to_string(),
+        }
+    }
+
+    // an unqualified call inside a module finds the module's own functions first
+    fn function(&self, name: Symbol) -> Option<(Option<Symbol>, &Signature)> {
+        if let Some(module) = self.current_module
+            && let Some(signature) = self.functions.get(&(Some(module), name))
+        {
+            return Some((Some(module), signature));
+        }
+        self.functions
+            .get(&(None, name))
+            .map(|signature| (None, signature))
+    }
+
+    // `modulo::f`, reporting an unknown module or function
+    fn path(&mut self, module: (Symbol, Span), name: (Symbol, Span)) -> Option<Signature> {
+        let is_module = self
+            .globals
+            .lookup(module.0)
+            .is_some_and(|entry| entry.kind == SymbolKind::Module);
+        if !is_module {
+            let error = TypeError::new(
+                format!("unknown module '{}'", self.name(module.0)),
+                module.1,
+            );
+            self.error(error);
+            return None;
+        }
+        if let Some(signature) = self.functions.get(&(Some(module.0), name.0)) {
+            return Some(signature.clone());
+        }
+        let error = TypeError::new(
+            format!(
+                "module '{}' has no function '{}'",
+                self.name(module.0),
+                self.name(name.0)
+            ),
+            name.1,
+        );
+        self.error(error);
+        None
+    }
+
+    fn lookup(&self, name: Symbol) -> Option<Type> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(binding) = scope.get(&name) {
+                return Some(binding.ty.clone());
+            }
+        }
+        symbol_type(self.globals.lookup(name)?.kind)
+    }
+
+    fn is_local(&self, name: Symbol) -> bool {
+        self.scopes.iter().any(|scope| scope.contains_key(&name))
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Let {
+                name,
+                name_span,
+                ty,
+                value,
+            } => self.check_let(*name, *name_span, ty.as_ref(), value),
+            StmtKind::Assign { target, op, value } => self.check_assign(target, *op, value),
+            StmtKind::Expr(expr) => {
+                self.infer(expr);
+            }
+            StmtKind::Write(args) => {
+                for arg in args {
+                    let ty = self.infer(arg);
+                    self.expect_value(&ty, arg.span);
+                }
+            }
+            StmtKind::If {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                self.check_condition(condition);
+                self.check_block(then_block, &[]);
+                if let Some(else_block) = else_block {
+                    self.check_block(else_block, &[]);
+                }
+            }
+            StmtKind::While { condition, body } => {
+                self.check_condition(condition);
+                self.loops += 1;
+                self.check_block(body, &[]);
+                self.loops -= 1;
+            }
+            StmtKind::For {
+                var,
+                var_span,
+                iterable,
+                body,
+            } => {
+                let binding = Binding {
+                    ty: self.element_type(iterable),
+                    span: *var_span,
+                };
+                self.types.insert(*var_span, binding.ty.clone());
+                self.loops += 1;
+                self.check_block(body, &[(*var, binding)]);
+                self.loops -= 1;
+            }
+            StmtKind::Break | StmtKind::Continue if self.loops == 0 => {
+                let keyword = match stmt.kind {
+                    StmtKind::Break => "break",
+                    _ => "continue",
+                };
+                self.error(TypeError::new(
+                    format!("'{}' can only be used inside a loop", keyword),
+                    stmt.span,
+                ));
+            }
+            StmtKind::Break | StmtKind::Continue => {}
+            StmtKind::Return(value) => self.check_return(value.as_ref(), stmt.span),
+            StmtKind::Network { command, args } => self.check_network(*command, args),
+            StmtKind::Error => {}
+        }
+    }
+
+    // the loop variable of `for x in ...`
+    fn element_type(&mut self, iterable: &Iterable) -> Type {
+        let expr = match iterable {
+            Iterable::Devices(DeviceKind::Machine, _) => return Type::Machine,
+            Iterable::Devices(DeviceKind::Concentrator, _) => return Type::Concentrator,
+            Iterable::Devices(DeviceKind::Coaxial, _) => return Type::Coaxial,
+            Iterable::Expr(expr) => expr,
+        };
+        match self.infer(expr) {
+            Type::Range => Type::Num,
+            Type::List(element) => *element,
+            Type::Error => Type::Error,
+            other => {
+                self.error(TypeError::new(
+                    format!("cannot loop over {}, expected a range or a list", other),
+                    expr.span,
+                ));
+                Type::Error
+            }
+        }
+    }
+
+    fn check_let(&mut self, name: Symbol, span: Span, ty: Option<&TypeName>, value: &Expr) {
+        let found = self.infer(value);
+        self.expect_value(&found, value.span);
+
+        let ty = match ty {
+            Some(annotation) => {
+                let expected = self.resolve_type(annotation);
+                if !found.fits(&expected) {
+                    let error = mismatch(&expected, &found, value.span).with_note(
+                        format!("'{}' is declared {}", self.name(name), expected),
+                        annotation.span,
+                    );
+                    self.error(error);
+                }
+                expected
+            }
+            None if found == Type::Nothing => Type::Error,
+            None => found,
+        };
+        self.types.insert(span, ty.clone());
+
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("statements are checked inside a block");
+        if let Some(previous) = scope.insert(name, Binding { ty, span }) {
+            let error = TypeError::new(
+                format!("'{}' is already declared in this block", self.name(name)),
+                span,
+            )
+            .with_note("first declared here".to_string(), previous.span);
+            self.error(error);
+        }
+    }
+
+    fn check_assign(&mut self, target: &Expr, op: AssignOp, value: &Expr) {
+        let target_ty = match &target.kind {
+            ExprKind::Identifier(name) if !self.is_local(*name) => {
+                if let Some(entry) = self.globals.lookup(*name) {
+                    let kind = match entry.kind {
+                        SymbolKind::Function => "function".to_string(),
+                        SymbolKind::Module => "module".to_string(),
+                        kind => symbol_type(kind).map_or("it".to_string(), |ty| ty.to_string()),
+                    };
+                    let declared = entry.span;
+                    let error = TypeError::new(
+                        format!("cannot assign to {} '{}'", kind, self.name(*name)),
+                        target.span,
+                    )
+                    .with_note("declared here".to_string(), declared);
+                    self.error(error);
+                    Type::Error
+                } else {
+                    self.infer(target)
+                }
+            }
+            ExprKind::Member { member, .. } => {
+                self.infer(target);
+                let member = self.member_name(*member);
+                self.error(TypeError::new(
+                    format!("'{}' is read-only", member),
+                    target.span,
+                ));
+                Type::Error
+            }
+            _ => self.infer(target),
+        };
+
+        let value_ty = self.infer(value);
+        self.expect_value(&value_ty, value.span);
+
+        let Some(binary) = op.binary_op() else {
+            if !value_ty.fits(&target_ty) {
+                let error = mismatch(&target_ty, &value_ty, value.span)
+                    .with_note(format!("this is {}", target_ty), target.span);
+                self.error(error);
+            }
+            return;
+        };
+        // `x += 1` checks like `x = x + 1`
+        let result = self.binary(binary, (&target_ty, target.span), (&value_ty, value.span));
+        if !result.fits(&target_ty) {
+            let error = mismatch(&target_ty, &result, value.span)
+                .with_note(format!("this is {}", target_ty), target.span);
+            self.error(error);
+        }
+    }
+
+    fn check_condition(&mut self, condition: &Expr) {
+        let ty = self.infer(condition);
+        self.expect_type(&ty, &Type::Bool, condition.span);
+    }
+
+    fn check_return(&mut self, value: Option<&Expr>, span: Span) {
+        let found = match value {
+            Some(value) => {
+                let ty = self.infer(value);
+                self.expect_value(&ty, value.span);
+                ty
+            }
+            None => Type::Nothing,
+        };
+        let value_span = value.map_or(span, |value| value.span);
+
+        let Some(context) = &self.returns else {
+            if value.is_some() {
+                self.error(TypeError::new(
+                    "the main program cannot return a value".to_string(),
+                    value_span,
+                ));
+            }
+            return;
+        };
+        if found.fits(&context.ty) {
+            return;
+        }
+
+        let mut error = mismatch(&context.ty, &found, value_span);
+        if let Some(ret_span) = context.span {
+            error = error.with_note("return type declared here".to_string(), ret_span);
+        }
+        self.error(error);
+    }
+
+    fn check_network(&mut self, command: NetworkCommand, args: &[NetworkArg]) {
+        // a wrong argument count was already a syntax error
+        if args.len() != command.arity() {
+            return;
+        }
+        let device = [Type::Machine, Type::Concentrator, Type::Coaxial];
+        let expected: &[&[Type]] = match command {
+            // coloca(m1, x, y) or coloca(m1, derecha, c1)
+            NetworkCommand::Place => match args[1] {
+                NetworkArg::Direction(..) => &[&device[..2], &[], &device],
+                NetworkArg::Expr(_) => &[&device[..2], &[Type::Num], &[Type::Num]],
+            },
+            NetworkCommand::PlaceCoaxial => &[&[Type::Coaxial], &[Type::Num], &[Type::Num], &[]],
+            NetworkCommand::PlaceCoaxialConcentrator => &[&[Type::Coaxial], &[Type::Concentrator]],
+            NetworkCommand::ConnectPort => &[&[Type::Machine], &[Type::Concentrator], &[Type::Num]],
+            NetworkCommand::AssignPort => &[&[Type::Machine], &[Type::Concentrator]],
+            NetworkCommand::MachineCoaxial => &[&[Type::Machine], &[Type::Coaxial], &[Type::Num]],
+            NetworkCommand::AssignMachineCoaxial => &[&[Type::Machine], &[Type::Coaxial]],
+        };
+
+        for (arg, expected) in args.iter().zip(expected) {
+            match arg {
+                // an empty list is where a direction goes
+                NetworkArg::Direction(_, span) if !expected.is_empty() => {
+                    self.error(TypeError::new(
+                        format!("expected {}, found a direction", one_of(expected)),
+                        *span,
+                    ));
+                }
+                NetworkArg::Direction(..) => {}
+                NetworkArg::Expr(expr) if expected.is_empty() => {
+                    self.infer(expr);
+                    self.error(TypeError::new(
+                        "expected a direction (arriba, abajo, izquierda or derecha)".to_string(),
+                        expr.span,
+                    ));
+                }
+                NetworkArg::Expr(expr) => {
+                    let found = self.infer(expr);
+                    if !expected.iter().any(|ty| found.fits(ty)) {
+                        self.error(TypeError::new(
+                            format!("expected {}, found {}", one_of(expected), found),
+                            expr.span,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    fn expect_type(&mut self, found: &Type, expected: &Type, span: Span) {
+        if !found.fits(expected) {
+            self.error(mismatch(expected, found, span));
+        }
+    }
+
+    // a call to a function without `->` used where a value is needed
+    fn expect_value(&mut self, found: &Type, span: Span) {
+        if *found == Type::Nothing {
+            self.error(TypeError::new(
+                "expected a value, found nothing".to_string(),
+                span,
+            ));
+        }
+    }
+
+    pub fn infer(&mut self, expr: &Expr) -> Type {
+        let ty = self.infer_kind(expr);
+        self.types.insert(expr.span, ty.clone());
+        ty
+    }
+
+    fn infer_kind(&mut self, expr: &Expr) -> Type {
+        match &expr.kind {
+            ExprKind::Integer(_) | ExprKind::Float(_) => Type::Num,
+            ExprKind::String(_) => Type::Text,
+            ExprKind::Identifier(name) => self.infer_name(*name, expr.span),
+            ExprKind::Path {
+                module,
+                module_span,
+                name,
+                name_span,
+            } => {
+                if self
+                    .path((*module, *module_span), (*name, *name_span))
+                    .is_some()
+                {
+                    let label = self.function_label(Some(*module), *name);
+                    self.error(TypeError::new(
+                        format!("'{}' is a function, call it with ( )", label),
+                        expr.span,
+                    ));
+                }
+                Type::Error
+            }
+            ExprKind::Unary { op, operand } => {
+                let ty = self.infer(operand);
+                let expected = match op {
+                    UnaryOp::Negate => Type::Num,
+                    UnaryOp::Not => Type::Bool,
+                };
+                self.expect_type(&ty, &expected, operand.span);
+                expected
+            }
+            ExprKind::Binary { op, left, right } => {
+                let left_ty = self.infer(left);
+                let right_ty = self.infer(right);
+                self.binary(*op, (&left_ty, left.span), (&right_ty, right.span))
+            }
+            ExprKind::Member {
+                object,
+                member,
+                member_span,
+            } => {
+                let object_ty = self.infer(object);
+                self.infer_member(&object_ty, *member, *member_span)
+            }
+            ExprKind::Index { object, index } => {
+                let object_ty = self.infer(object);
+                let index_ty = self.infer(index);
+                self.expect_type(&index_ty, &Type::Num, index.span);
+                match object_ty {
+                    Type::List(element) => *element,
+                    Type::Error => Type::Error,
+                    other => {
+                        self.error(TypeError::new(
+                            format!("{} cannot be indexed", other),
+                            object.span,
+                        ));
+                        Type::Error
+                    }
+                }
+            }
+            ExprKind::Call { callee, args } => self.infer_call(callee, args),
+            ExprKind::Error => Type::Error,
+        }
+    }
+
+    fn infer_name(&mut self, name: Symbol, span: Span) -> Type {
+        if let Some(ty) = self.lookup(name) {
+            return ty;
+        }
+        let is_module = self
+            .globals
+            .lookup(name)
+            .is_some_and(|entry| entry.kind == SymbolKind::Module);
+        let message = if self.function(name).is_some() || self.builtin(name).is_some() {
+            format!("'{}' is a function, call it with ( )", self.name(name))
+        } else if is_module {
+            format!(
+                "'{}' is a module, call its functions as {}::name( )",
+                self.name(name),
+                self.name(name)
+            )
+        } else {
+            format!("unknown name '{}'", self.name(name))
+        };
+        self.error(TypeError::new(message, span));
+        Type::Error
+    }
+
+    fn binary(&mut self, op: BinaryOp, left: (&Type, Span), right: (&Type, Span)) -> Type {
+        let (operands, result) = match op {
+            // `+` also joins text
+            BinaryOp::Add if *left.0 == Type::Text => (Type::Text, Type::Text),
+            BinaryOp::Add
+            | BinaryOp::Subtract
+            | BinaryOp::Multiply
+            | BinaryOp::Divide
+            | BinaryOp::Modulo
+            | BinaryOp::Power => (Type::Num, Type::Num),
+            BinaryOp::Less | BinaryOp::Greater | BinaryOp::LessEq | BinaryOp::GreaterEq => {
+                (Type::Num, Type::Bool)
+            }
+            BinaryOp::And | BinaryOp::Or => (Type::Bool, Type::Bool),
+            BinaryOp::Range => (Type::Num, Type::Range),
+            // any two values of the same type compare
+            BinaryOp::Equal | BinaryOp::NotEqual => {
+                self.expect_value(left.0, left.1);
+                self.expect_value(right.0, right.1);
+                if !right.0.fits(left.0) {
+                    let error = TypeError::new(
+                        format!("cannot compare {} with {}", left.0, right.0),
+                        right.1,
+                    )
+                    .with_note(format!("this is {}", left.0), left.1);
+                    self.error(error);
+                }
+                return Type::Bool;
+            }
+        };
+
+        if !left.0.fits(&operands) {
+            let error = TypeError::new(
+                format!("expected {} for '{}', found {}", operands, op, left.0),
+                left.1,
+            );
+            self.error(error);
+        } else if !right.0.fits(&operands) {
+            let error = mismatch(&operands, right.0, right.1)
+                .with_note(format!("left side of '{}' is {}", op, left.0), left.1);
+            self.error(error);
+        }
+        result
+    }
+
+    // members spelled by keywords are named by their school spelling
+    fn member_name(&self, member: Symbol) -> String {
+        match self.keyword(member) {
+            Some(token) => keywords::spelling(token, &[KeywordProfile::School])
+                .unwrap_or_else(|| self.name(member))
+                .to_string(),
+            None => self.name(member).to_string(),
+        }
+    }
+
+    fn infer_member(&mut self, object: &Type, member: Symbol, span: Span) -> Type {
+        let name = self.member_name(member);
+        let ty = match (object, name.as_str()) {
+            (Type::Error, _) => Some(Type::Error),
+            (Type::Concentrator, "puertos") => Some(Type::List(Box::new(Type::Port))),
+            (Type::Concentrator, "disponibles") => Some(Type::Num),
+            (Type::Concentrator | Type::Coaxial, "completo") => Some(Type::Bool),
+            (Type::Coaxial, "longitud") => Some(Type::Num),
+            (Type::Machine | Type::Port, "presente") => Some(Type::Bool),
+            (device, "pos") if device.is_device() => Some(Type::Position),
+            (Type::Position, "x" | "y") => Some(Type::Num),
+            (Type::Port, "numero") => Some(Type::Num),
+            (Type::Port, "maquina") => Some(Type::Machine),
+            (Type::List(_), "longitud") => Some(Type::Num),
+            _ => None,
+        };
+        ty.unwrap_or_else(|| {
+            self.error(TypeError::new(
+                format!("{} has no member '{}'", object, name),
+                span,
+            ));
+            Type::Error
+        })
+    }
+
+    // builtin queries, `pos(m1)`, `disponibles(c1)`, ...
+    // the types each argument accepts and what the query gives back
+    fn builtin(&self, name: Symbol) -> Option<(Vec<Vec<Type>>, Type)> {
+        let device = vec![Type::Machine, Type::Concentrator, Type::Coaxial];
+        let builtin = match self.keyword(name)? {
+            TokenType::Pos_ => (vec![device], Type::Position),
+            TokenType::Presente_ => (vec![device], Type::Bool),
+            TokenType::Completo_ => (vec![vec![Type::Concentrator, Type::Coaxial]], Type::Bool),
+            TokenType::Disponibles_ | TokenType::Puertos_ => {
+                (vec![vec![Type::Concentrator]], Type::Num)
+            }
+            TokenType::Longitud_ => (vec![vec![Type::Coaxial]], Type::Num),
+            TokenType::Alcanza_ => (vec![device.clone(), device], Type::Bool),
+            TokenType::Saltos_ => (vec![device.clone(), device], Type::Num),
+            TokenType::Plano_ => (vec![], Type::Text),
+            _ => return None,
+        };
+        Some(builtin)
+    }
+
+    fn infer_call(&mut self, callee: &Expr, args: &[Expr]) -> Type {
+        let arg_types: Vec<Type> = args.iter().map(|arg| self.infer(arg)).collect();
+
+        if let ExprKind::Path {
+            module,
+            module_span,
+            name,
+            name_span,
+        } = callee.kind
+        {
+            let Some(signature) = self.path((module, module_span), (name, name_span)) else {
+                return Type::Error;
+            };
+            let label = self.function_label(Some(module), name);
+            return self.check_args(&label, &signature, callee.span, args, &arg_types);
+        }
+
+        let ExprKind::Identifier(name) = callee.kind else {
+            let ty = self.infer(callee);
+            if ty != Type::Error {
+                self.error(TypeError::new(
+                    format!("{} is not a function", ty),
+                    callee.span,
+                ));
+            }
+            return Type::Error;
+        };
+
+        if let Some((params, result)) = self.builtin(name) {
+            if args.len() != params.len() {
+                let error = arity_error(self.name(name), params.len(), args.len(), callee.span);
+                self.error(error);
+                return result;
+            }
+            for ((accepted, arg), ty) in params.iter().zip(args).zip(&arg_types) {
+                if !accepted.iter().any(|param| ty.fits(param)) {
+                    self.error(TypeError::new(
+                        format!("expected {}, found {}", one_of(accepted), ty),
+                        arg.span,
+                    ));
+                }
+            }
+            return result;
+        }
+
+        let (module, signature) = match self.function(name) {
+            Some((module, signature)) if !self.is_local(name) => (module, signature.clone()),
+            _ => {
+                let ty = self.infer(callee);
+                if ty != Type::Error {
+                    self.error(TypeError::new(
+                        format!("'{}' is {}, not a function", self.name(name), ty),
+                        callee.span,
+                    ));
+                }
+                return Type::Error;
+            }
+        };
+
+        let label = self.function_label(module, name);
+        self.check_args(&label, &signature, callee.span, args, &arg_types)
+    }
+
+    fn check_args(
+        &mut self,
+        label: &str,
+        signature: &Signature,
+        span: Span,
+        args: &[Expr],
+        arg_types: &[Type],
+    ) -> Type {
+        if args.len() != signature.params.len() {
+            let error = arity_error(label, signature.params.len(), args.len(), span);
+            self.error(error);
+            return signature.ret.clone();
+        }
+        for ((arg, found), (expected, declared)) in
+            args.iter().zip(arg_types).zip(&signature.params)
+        {
+            self.expect_value(found, arg.span);
+            if *found != Type::Nothing && !found.fits(expected) {
+                let error = mismatch(expected, found, arg.span)
+                    .with_note("parameter declared here".to_string(), *declared);
+                self.error(error);
+            }
+        }
+        signature.ret.clone()
+    }
+}
+
+fn mismatch(expected: &Type, found: &Type, span: Span) -> TypeError {
+    TypeError::new(format!("expected {}, found {}", expected, found), span)
+}
+
+fn arity_error(name: &str, expected: usize, found: usize, span: Span) -> TypeError {
+    let plural = if expected == 1 { "" } else { "s" };
+    TypeError::new(
+        format!(
+            "'{}' takes {} argument{}, found {}",
+            name, expected, plural, found
+        ),
+        span,
+    )
+}
+
+fn one_of(types: &[Type]) -> String {
+    let names: Vec<String> = types.iter().map(Type::to_string).collect();
+    match names.as_slice() {
+        [single] => single.clone(),
+        [rest @ .., last] => format!("{} or {}", rest.join(", "), last),
+        [] => "nothing".to_string(),
+    }
+}
+
+fn symbol_type(kind: SymbolKind) -> Option<Type> {
+    match kind {
+        SymbolKind::Machine => Some(Type::Machine),
+        SymbolKind::Concentrator => Some(Type::Concentrator),
+        SymbolKind::Coaxial => Some(Type::Coaxial),
+        _ => None,
+    }
+}
+
+// whether every path through the block ends in a `return`
+fn always_returns(block: &Block) -> bool {
+    block.statements.iter().any(|stmt| match &stmt.kind {
+        StmtKind::Return(_) => true,
+        StmtKind::If {
+            then_block,
+            else_block: Some(else_block),
+            ..
+        } => always_returns(then_block) && always_returns(else_block),
+        _ => false,
+    })
+}
@@ -0,0 +1,250 @@
+use crate::lexer::lexer_ana::Span;
+use crate::network::DEFAULT_COAXIAL_LENGTH;
+use crate::network::device::{DeviceId, MachineId, SegmentId};
+use crate::network::rules::{CoaxialRules, Problem, Rule, Severity};
+use crate::network::topology::Topology;
+use crate::parser::ast::{
+    Decl, DeviceKind, Expr, ExprKind, NetworkArg, NetworkCommand, Program, StmtKind, UnaryOp,
+};
+use crate::semantic::checker::show_span;
+use crate::util::interner::{Interner, Symbol};
+
+/// A coaxial rule broken by a statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleViolation {
+    // the cable and the rule, one broken rule is reported once per cable
+    pub segment: String,
+    pub rule: Rule,
+    pub message: String,
+    pub span: Span,
+    pub severity: Severity,
+}
+
+impl RuleViolation {
+    pub fn display(&self, source: &str) {
+        let kind = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        eprintln!(
+            "\n Coaxial {} on line {}:{}",
+            kind, self.span.line, self.span.column
+        );
+        eprintln!("     {}", self.message);
+        show_span(source, self.span);
+    }
+}
+
+/// Checks the coaxial cabling against `rules`. Only the network commands written straight in
+/// `inicio` are followed, the ones every run goes through, with their devices named and offsets
+/// written as numbers
+pub fn check_coaxial(
+    program: &Program,
+    interner: &Interner,
+    rules: &CoaxialRules,
+) -> Vec<RuleViolation> {
+    let mut replay = Replay {
+        topology: Topology::new(),
+        interner,
+        rules,
+        violations: Vec::new(),
+    };
+
+    for decl in &program.declarations {
+        let Decl::Define(define) = decl else {
+            continue;
+        };
+        for item in &define.items {
+            let name = interner.resolve(item.name);
+            // a device that fails to be added is reported when the program runs
+            let _ = match define.kind {
+                DeviceKind::Machine => replay.topology.add_machine(name).map(|_| ()),
+                DeviceKind::Concentrator => replay.topology.add_concentrator(name, 1).map(|_| ()),
+                DeviceKind::Coaxial => {
+                    let length = match &item.size {
+                        Some(size) => literal(size),
+                        None => Some(DEFAULT_COAXIAL_LENGTH),
+                    };
+                    if let Some(problem) =
+                        length.and_then(|length| rules.check_length(name, length))
+                    {
+                        let span = item
+                            .size
+                            .as_ref()
+                            .map_or(item.span, |size| item.span.to(size.span));
+                        replay.report(name.to_string(), problem, span);
+                    }
+                    let length = length.unwrap_or(DEFAULT_COAXIAL_LENGTH);
+                    replay.topology.add_coaxial(name, length).map(|_| ())
+                }
+            };
+        }
+    }
+
+    for stmt in &program.body.statements {
+        if let StmtKind::Network { command, args } = &stmt.kind {
+            replay.command(*command, args, stmt.span);
+        }
+    }
+    replay.violations
+}
+
+/// Checks the network a run built against `rules`. It holds the cabling `check_coaxial` can't
+/// follow: taps made in loops, branches or functions, or at computed offsets. A rule `reported`
+/// already broke on a cable is left out, the others point at where their cable is defined
+pub fn check_built(
+    program: &Program,
+    interner: &Interner,
+    topology: &Topology,
+    rules: &CoaxialRules,
+    reported: &[RuleViolation],
+) -> Vec<RuleViolation> {
+    rules
+        .check(topology)
+        .into_iter()
+        .map(|(segment, problem)| (topology.segment(segment).name.as_str(), problem))
+        .filter(|(segment, problem)| {
+            !reported
+                .iter()
+                .any(|violation| violation.segment == *segment && violation.rule == problem.rule)
+        })
+        .map(|(segment, problem)| RuleViolation {
+            segment: segment.to_string(),
+            rule: problem.rule,
+            message: problem.message,
+            span: definition(program, interner, segment),
+            severity: rules.severity,
+        })
+        .collect()
+}
+
+// `x1 = 20` in `define coaxial x1 = 20;`
+fn definition(program: &Program, interner: &Interner, name: &str) -> Span {
+    program
+        .declarations
+        .iter()
+        .filter_map(|decl| match decl {
+            Decl::Define(define) if define.kind == DeviceKind::Coaxial => Some(&define.items),
+            _ => None,
+        })
+        .flatten()
+        .find(|item| interner.resolve(item.name) == name)
+        .map_or(program.span, |item| {
+            item.size
+                .as_ref()
+                .map_or(item.span, |size| item.span.to(size.span))
+        })
+}
+
+struct Replay<'a> {
+    topology: Topology,
+    interner: &'a Interner,
+    rules: &'a CoaxialRules,
+    violations: Vec<RuleViolation>,
+}
+
+impl Replay<'_> {
+    fn report(&mut self, segment: String, problem: Problem, span: Span) {
+        self.violations.push(RuleViolation {
+            segment,
+            rule: problem.rule,
+            message: problem.message,
+            span,
+            severity: self.rules.severity,
+        });
+    }
+
+    fn command(&mut self, command: NetworkCommand, args: &[NetworkArg], span: Span) {
+        match (command, args) {
+            (NetworkCommand::MachineCoaxial, [machine, segment, NetworkArg::Expr(offset)]) => {
+                let (Some(machine), Some(segment), Some(offset)) = (
+                    self.machine(machine),
+                    self.segment(segment),
+                    literal(offset),
+                ) else {
+                    return;
+                };
+                let cable = self.topology.segment(segment);
+                if let Some(problem) = self.rules.check_tap(cable, offset) {
+                    self.report(cable.name.clone(), problem, span);
+                }
+                let _ = self.topology.attach_coaxial(machine, segment, offset);
+            }
+            (NetworkCommand::AssignMachineCoaxial, [machine, segment]) => {
+                let (Some(machine), Some(segment)) = (self.machine(machine), self.segment(segment))
+                else {
+                    return;
+                };
+                let cable = self.topology.segment(segment);
+                if let Some(problem) = self.rules.check_tap(cable, cable.next_offset()) {
+                    self.report(cable.name.clone(), problem, span);
+                }
+                let _ = self.topology.assign_coaxial(machine, segment);
+            }
+            (NetworkCommand::PlaceCoaxialConcentrator, [segment, concentrator]) => {
+                let Some(segment) = self.segment(segment) else {
+                    return;
+                };
+                let Some(DeviceId::Concentrator(concentrator)) = self.device(concentrator) else {
+                    return;
+                };
+                if self
+                    .topology
+                    .segment(segment)
+                    .concentrators
+                    .contains(&concentrator)
+                {
+                    return;
+                }
+                let cable = self.topology.segment(segment);
+                if let Some(problem) = self.rules.check_attach(cable) {
+                    self.report(cable.name.clone(), problem, span);
+                }
+                let _ = self.topology.attach_segment(segment, concentrator);
+            }
+            _ => {}
+        }
+    }
+
+    fn device(&self, arg: &NetworkArg) -> Option<DeviceId> {
+        let NetworkArg::Expr(Expr {
+            kind: ExprKind::Identifier(name),
+            ..
+        }) = arg
+        else {
+            return None;
+        };
+        self.topology.device(self.name(*name)).ok()
+    }
+
+    fn machine(&self, arg: &NetworkArg) -> Option<MachineId> {
+        match self.device(arg)? {
+            DeviceId::Machine(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    fn segment(&self, arg: &NetworkArg) -> Option<SegmentId> {
+        match self.device(arg)? {
+            DeviceId::Coaxial(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    fn name(&self, symbol: Symbol) -> &str {
+        self.interner.resolve(symbol)
+    }
+}
+
+// a number written in the source, `10`, `2.5` or `-1`
+fn literal(expr: &Expr) -> Option<f64> {
+    match &expr.kind {
+        ExprKind::Integer(value) => Some(*value as f64),
+        ExprKind::Float(value) => Some(*value),
+        ExprKind::Unary {
+            op: UnaryOp::Negate,
+            operand,
+        } => literal(operand).map(|value| -value),
+        _ => None,
+    }
+}
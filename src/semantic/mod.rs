@@ -0,0 +1,57 @@
+pub mod checker;
+pub mod coaxial;
+pub mod types;
+
+use crate::network::rules::{CoaxialRules, Severity};
+use crate::network::topology::Topology;
+use crate::parser::parser_ana::Parsed;
+use crate::semantic::coaxial::RuleViolation;
+use std::error::Error;
+
+/// Type checks a parsed program and its coaxial cabling against `rules`, printing every error
+/// and warning found. Gives back the coaxial warnings, so `check_network` leaves them out
+pub fn semantic(
+    parsed: &Parsed,
+    rules: &CoaxialRules,
+) -> Result<Vec<RuleViolation>, Box<dyn Error>> {
+    let errors = checker::check_program(&parsed.program, &parsed.interner, &parsed.profiles);
+
+    if !errors.is_empty() {
+        eprintln!("\ntype error {} ", errors.len());
+        for error in &errors {
+            error.display(&parsed.source);
+        }
+        return Err("type errors found".into());
+    }
+
+    let violations = coaxial::check_coaxial(&parsed.program, &parsed.interner, rules);
+    report(&violations, &parsed.source)?;
+    Ok(violations)
+}
+
+/// Checks the coaxial rules on the network the program built once it has run, printing what
+/// `semantic` couldn't see before the run. `reported` is what `semantic` gave back
+pub fn check_network(
+    parsed: &Parsed,
+    topology: &Topology,
+    rules: &CoaxialRules,
+    reported: &[RuleViolation],
+) -> Result<(), Box<dyn Error>> {
+    let violations =
+        coaxial::check_built(&parsed.program, &parsed.interner, topology, rules, reported);
+    report(&violations, &parsed.source)
+}
+
+fn report(violations: &[RuleViolation], source: &str) -> Result<(), Box<dyn Error>> {
+    for violation in violations {
+        violation.display(source);
+    }
+    if violations
+        .iter()
+        .any(|violation| violation.severity == Severity::Error)
+    {
+        return Err("coaxial rules broken".into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use crate::lexer::lexer_ana::TokenType;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    // integers and floats alike
+    Num,
+    Text,
+    Bool,
+    Machine,
+    Concentrator,
+    Coaxial,
+    Port,
+    Position,
+    // a..b
+    Range,
+    List(Box<Type>),
+    // what a `fn` without `->` returns
+    Nothing,
+    // already reported, matches anything so one mistake is reported once
+    Error,
+}
+
+impl Type {
+    /// Type named by a type keyword, `num`, `maquina` or `coaxial`
+    pub fn from_keyword(type_: &TokenType) -> Option<Type> {
+        match type_ {
+            TokenType::Num_ => Some(Type::Num),
+            TokenType::Maquina_ => Some(Type::Machine),
+            TokenType::Coaxial_ => Some(Type::Coaxial),
+            _ => None,
+        }
+    }
+
+    /// Type named by an identifier, e.g. `Concentrador` or `posicion`
+    pub fn from_name(name: &str) -> Option<Type> {
+        let ty = match name {
+            "num" => Type::Num,
+            "texto" | "Texto" => Type::Text,
+            "bool" | "Bool" => Type::Bool,
+            "maquina" | "Maquina" => Type::Machine,
+            "concentrador" | "Concentrador" => Type::Concentrator,
+            "coaxial" | "Coaxial" => Type::Coaxial,
+            "puerto" | "Puerto" => Type::Port,
+            "posicion" | "Posicion" => Type::Position,
+            "rango" | "Rango" => Type::Range,
+            _ => return None,
+        };
+        Some(ty)
+    }
+
+    pub fn is_device(&self) -> bool {
+        matches!(self, Type::Machine | Type::Concentrator | Type::Coaxial)
+    }
+
+    /// Whether a value of type `self` can go where `expected` is wanted
+    pub fn fits(&self, expected: &Type) -> bool {
+        match (self, expected) {
+            (Type::Error, _) | (_, Type::Error) => true,
+            (Type::List(found), Type::List(expected)) => found.fits(expected),
+            _ => self == expected,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Num => write!(f, "num"),
+            Type::Text => write!(f, "texto"),
+            Type::Bool => write!(f, "bool"),
+            Type::Machine => write!(f, "maquina"),
+            Type::Concentrator => write!(f, "concentrador"),
+            Type::Coaxial => write!(f, "coaxial"),
+            Type::Port => write!(f, "puerto"),
+            Type::Position => write!(f, "posicion"),
+            Type::Range => write!(f, "rango"),
+            Type::List(element) => write!(f, "[{}]", element),
+            Type::Nothing => write!(f, "nothing"),
+            Type::Error => write!(f, "{{error}}"),
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use std::fmt::Write;
+
+/// A JSON or YAML document, objects keep their keys in order
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Null,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+    List(Vec<Node>),
+    Object(Vec<(String, Node)>),
+}
+
+impl Node {
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        match self {
+            Node::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Node::Null => "null",
+            Node::Bool(_) => "a bool",
+            Node::Num(_) => "a number",
+            Node::Str(_) => "a string",
+            Node::List(_) => "a list",
+            Node::Object(_) => "an object",
+        }
+    }
+}
+
+/// Pretty printed JSON, two spaces per level
+pub fn to_json(node: &Node) -> String {
+    let mut out = String::new();
+    write_json(node, 0, &mut out);
+    out.push('\n');
+    out
+}
+
+fn write_json(node: &Node, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth + 1);
+    match node {
+        Node::Null => out.push_str("null"),
+        Node::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+        Node::Num(value) => {
+            let _ = write!(out, "{}", value);
+        }
+        Node::Str(text) => write_string(text, out),
+        Node::List(items) if items.is_empty() => out.push_str("[]"),
+        Node::Object(fields) if fields.is_empty() => out.push_str("{}"),
+        // short lists of plain values fit on one line
+        Node::List(items) if items.iter().all(is_scalar) => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.push_str(", ");
+                }
+                write_json(item, depth, out);
+            }
+            out.push(']');
+        }
+        Node::List(items) => {
+            out.push_str("[\n");
+            for (index, item) in items.iter().enumerate() {
+                out.push_str(&indent);
+                write_json(item, depth + 1, out);
+                out.push_str(if index + 1 < items.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&"  ".repeat(depth));
+            out.push(']');
+        }
+        Node::Object(fields) => {
+            out.push_str("{\n");
+            for (index, (key, value)) in fields.iter().enumerate() {
+                out.push_str(&indent);
+                write_string(key, out);
+                out.push_str(": ");
+                write_json(value, depth + 1, out);
+                out.push_str(if index + 1 < fields.len() {
+                    ",\n"
+                } else {
+                    "\n"
+                });
+            }
+            out.push_str(&"  ".repeat(depth));
+            out.push('}');
+        }
+    }
+}
+
+pub(crate) fn is_scalar(node: &Node) -> bool {
+    !matches!(node, Node::List(_) | Node::Object(_))
+}
+
+pub(crate) fn write_string(text: &str, out: &mut String) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// How deep lists and objects may nest, a deeper document is refused before reading it runs out
+/// of stack
+pub const MAX_DEPTH: usize = 128;
+
+/// Parses a JSON document, errors name the line and column
+pub fn parse_json(text: &str) -> Result<Node, String> {
+    let mut reader = JsonReader {
+        chars: text.chars().collect(),
+        at: 0,
+        depth: 0,
+    };
+    let node = reader.value()?;
+    reader.skip_space();
+    if reader.at < reader.chars.len() {
+        return Err(reader.error("expected the end of the document"));
+    }
+    Ok(node)
+}
+
+struct JsonReader {
+    chars: Vec<char>,
+    at: usize,
+    // lists and objects open around the value being read
+    depth: usize,
+}
+
+impl JsonReader {
+    fn error(&self, message: &str) -> String {
+        let before = &self.chars[..self.at.min(self.chars.len())];
+        let line = before.iter().filter(|c| **c == '\n').count() + 1;
+        let column = before.iter().rev().take_while(|c| **c != '\n').count() + 1;
+        format!("{} at {}:{}", message, line, column)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).copied()
+    }
+
+    fn skip_space(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.at += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_space();
+        if self.peek() != Some(c) {
+            return Err(self.error(&format!("expected '{}'", c)));
+        }
+        self.at += 1;
+        Ok(())
+    }
+
+    fn word(&mut self, word: &str, node: Node) -> Result<Node, String> {
+        let end = self.at + word.chars().count();
+        if end <= self.chars.len() && self.chars[self.at..end].iter().copied().eq(word.chars()) {
+            self.at = end;
+            Ok(node)
+        } else {
+            Err(self.error("expected a value"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Node, String> {
+        self.skip_space();
+        match self.peek() {
+            Some(open @ ('{' | '[')) => {
+                if self.depth == MAX_DEPTH {
+                    return Err(self.error(&format!("nested deeper than {} levels", MAX_DEPTH)));
+                }
+                self.depth += 1;
+                let node = if open == '{' {
+                    self.object()
+                } else {
+                    self.list()
+                };
+                self.depth -= 1;
+                node
+            }
+            Some('"') => self.string().map(Node::Str),
+            Some('t') => self.word("true", Node::Bool(true)),
+            Some('f') => self.word("false", Node::Bool(false)),
+            Some('n') => self.word("null", Node::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Node, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_space();
+        if self.peek() == Some('}') {
+            self.at += 1;
+            return Ok(Node::Object(fields));
+        }
+        loop {
+            self.skip_space();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a key in quotes"));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_space();
+            match self.peek() {
+                Some(',') => self.at += 1,
+                Some('}') => {
+                    self.at += 1;
+                    return Ok(Node::Object(fields));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn list(&mut self) -> Result<Node, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_space();
+        if self.peek() == Some(']') {
+            self.at += 1;
+            return Ok(Node::List(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_space();
+            match self.peek() {
+                Some(',') => self.at += 1,
+                Some(']') => {
+                    self.at += 1;
+                    return Ok(Node::List(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.at += 1;
+        let mut text = String::new();
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.at += 1;
+            match c {
+                '"' => return Ok(text),
+                '\\' => {
+                    let escaped = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.at += 1;
+                    match escaped {
+                        'n' => text.push('\n'),
+                        't' => text.push('\t'),
+                        'r' => text.push('\r'),
+                        'b' => text.push('\u{8}'),
+                        'f' => text.push('\u{c}'),
+                        'u' => {
+                            let end = self.at + 4;
+                            let hex: String =
+                                self.chars.get(self.at..end).unwrap_or(&[]).iter().collect();
+                            let code = u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("bad \\u escape"))?;
+                            self.at = end;
+                            text.push(code);
+                        }
+                        other => text.push(other),
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Node, String> {
+        let start = self.at;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.at += 1;
+        }
+        let text: String = self.chars[start..self.at].iter().collect();
+        text.parse()
+            .map(Node::Num)
+            .map_err(|_| self.error(&format!("bad number '{}'", text)))
+    }
+}
@@ -0,0 +1,68 @@
+use std::{collections::HashMap, fmt, rc::Rc};
+
+/// Handle to a string stored in an `Interner`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+// every distinct spelling is stored once, symbols are handed out in insertion order
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    names: HashMap<Rc<str>, Symbol>,
+    strings: Vec<Rc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Interner::default()
+    }
+
+    /// Interns `words` in order, so the first one gets index 0
+    pub fn with_words(words: &[&str]) -> Self {
+        let mut interner = Interner::new();
+        for word in words {
+            interner.intern(word);
+        }
+        interner
+    }
+
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(&symbol) = self.names.get(name) {
+            return symbol;
+        }
+
+        let symbol = Symbol(self.strings.len() as u32);
+        let name: Rc<str> = Rc::from(name);
+        self.strings.push(Rc::clone(&name));
+        self.names.insert(name, symbol);
+        symbol
+    }
+
+    /// Looks a spelling up without interning it
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.names.get(name).copied()
+    }
+
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
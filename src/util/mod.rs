@@ -1,5 +1,8 @@
 
+pub mod document;
+pub mod interner;
 pub mod symboltable;
+pub mod yaml;
 pub fn run_helper(){
 
 }
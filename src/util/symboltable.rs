@@ -1,18 +1,54 @@
-// use std::collections::HashMap;
-//
-// pub struct SymbolEntry {
-//     pub name: String,
-//     pub kind: String,
-// }
-// pub struct SymbolTable {
-//     entries: Vec<SymbolEntry>,
-//     name_index: HashMap<String, usize>,
-// }
-// impl SymbolTable {
-//     pub fn new() -> Self {
-//         SymbolTable {
-//             symbols: Vec::new(),
-//             name_to_index: HashMap::new(),
-//         }
-//     }
-// }
+use crate::lexer::lexer_ana::Span;
+use crate::util::interner::Symbol;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    Machine,
+    Concentrator,
+    Coaxial,
+    Function,
+    Module,
+}
+
+#[derive(Debug, Clone)]
+pub struct SymbolEntry {
+    pub name: Symbol,
+    pub kind: SymbolKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    entries: Vec<SymbolEntry>,
+    name_index: HashMap<Symbol, usize>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        SymbolTable::default()
+    }
+
+    /// Adds a new entry, returning the previous declaration if the name is taken
+    pub fn insert(&mut self, entry: SymbolEntry) -> Result<usize, &SymbolEntry> {
+        if let Some(&index) = self.name_index.get(&entry.name) {
+            return Err(&self.entries[index]);
+        }
+
+        let index = self.entries.len();
+        self.name_index.insert(entry.name, index);
+        self.entries.push(entry);
+        Ok(index)
+    }
+
+    pub fn lookup(&self, name: Symbol) -> Option<&SymbolEntry> {
+        self.name_index
+            .get(&name)
+            .map(|&index| &self.entries[index])
+    }
+
+    pub fn entries(&self) -> &[SymbolEntry] {
+        &self.entries
+    }
+}
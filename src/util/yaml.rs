@@ -0,0 +1,324 @@
+use crate::util::document::{MAX_DEPTH, Node, is_scalar, parse_json, to_json, write_string};
+
+/// Block style YAML, lists of plain values are written inline the JSON way
+pub fn to_yaml(node: &Node) -> String {
+    let mut out = String::new();
+    match node {
+        Node::Object(fields) if !fields.is_empty() => write_fields(fields, 0, false, &mut out),
+        Node::List(items) if !items.iter().all(is_scalar) => write_items(items, 0, &mut out),
+        other => {
+            out.push_str(&inline(other));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+// the first field of a list item follows its dash
+fn write_fields(fields: &[(String, Node)], depth: usize, after_dash: bool, out: &mut String) {
+    for (index, (key, value)) in fields.iter().enumerate() {
+        if index > 0 || !after_dash {
+            out.push_str(&"  ".repeat(depth));
+        }
+        out.push_str(&plain_or_quoted(key));
+        out.push(':');
+        write_value(value, depth, out);
+    }
+}
+
+fn write_items(items: &[Node], depth: usize, out: &mut String) {
+    for item in items {
+        out.push_str(&"  ".repeat(depth));
+        out.push('-');
+        match item {
+            Node::Object(fields) if !fields.is_empty() => {
+                out.push(' ');
+                write_fields(fields, depth + 1, true, out);
+            }
+            Node::List(items) if !items.iter().all(is_scalar) => {
+                out.push('\n');
+                write_items(items, depth + 1, out);
+            }
+            other => {
+                out.push(' ');
+                out.push_str(&inline(other));
+                out.push('\n');
+            }
+        }
+    }
+}
+
+// what follows `key:`
+fn write_value(value: &Node, depth: usize, out: &mut String) {
+    match value {
+        Node::Object(fields) if !fields.is_empty() => {
+            out.push('\n');
+            write_fields(fields, depth + 1, false, out);
+        }
+        Node::List(items) if !items.iter().all(is_scalar) => {
+            out.push('\n');
+            write_items(items, depth + 1, out);
+        }
+        other => {
+            out.push(' ');
+            out.push_str(&inline(other));
+            out.push('\n');
+        }
+    }
+}
+
+fn inline(node: &Node) -> String {
+    match node {
+        Node::Str(text) => plain_or_quoted(text),
+        // `[1, "a"]` and `{}` are valid YAML as they are in JSON
+        other => to_json(other).trim_end().to_string(),
+    }
+}
+
+// names go without quotes, anything YAML could read as something else gets them
+fn plain_or_quoted(text: &str) -> String {
+    let reserved = matches!(
+        text.to_ascii_lowercase().as_str(),
+        "null" | "true" | "false" | "yes" | "no" | "on" | "off" | "~"
+    );
+    let plain = text
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && text
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if plain && !reserved {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    write_string(text, &mut out);
+    out
+}
+
+/// Reads the YAML `to_yaml` writes: block mappings and lists, plain or quoted scalars and
+/// JSON style inline collections
+pub fn parse_yaml(text: &str) -> Result<Node, String> {
+    let lines: Vec<Line> = text
+        .lines()
+        .enumerate()
+        .filter_map(|(index, raw)| {
+            let content = strip_comment(raw).trim_end();
+            let trimmed = content.trim_start();
+            if trimmed.is_empty() || trimmed == "---" {
+                return None;
+            }
+            Some(Line {
+                number: index + 1,
+                indent: content.len() - trimmed.len(),
+                text: trimmed.to_string(),
+            })
+        })
+        .collect();
+
+    let Some(first) = lines.first() else {
+        return Ok(Node::Null);
+    };
+    let mut reader = YamlReader {
+        lines: &lines,
+        at: 0,
+        depth: 0,
+    };
+    let node = reader.block(first.indent)?;
+    if let Some(line) = lines.get(reader.at) {
+        return Err(format!("unexpected indentation on line {}", line.number));
+    }
+    Ok(node)
+}
+
+struct Line {
+    number: usize,
+    indent: usize,
+    text: String,
+}
+
+struct YamlReader<'a> {
+    lines: &'a [Line],
+    at: usize,
+    // lists and mappings open around the line being read
+    depth: usize,
+}
+
+impl YamlReader<'_> {
+    fn block(&mut self, indent: usize) -> Result<Node, String> {
+        let line = &self.lines[self.at];
+        if is_item(&line.text) {
+            self.list(indent)
+        } else if split_key(&line.text).is_some() {
+            self.mapping(indent, None)
+        } else {
+            self.at += 1;
+            scalar(&line.text, line.number)
+        }
+    }
+
+    // every list and mapping sits one level deeper than the one holding it
+    fn deeper(&mut self) -> Result<(), String> {
+        if self.depth == MAX_DEPTH {
+            let line = self.lines.get(self.at).or(self.lines.last());
+            return Err(format!(
+                "nested deeper than {} levels on line {}",
+                MAX_DEPTH,
+                line.map_or(1, |line| line.number)
+            ));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn list(&mut self, indent: usize) -> Result<Node, String> {
+        self.deeper()?;
+        let mut items = Vec::new();
+        while let Some(line) = self.lines.get(self.at) {
+            if line.indent != indent || !is_item(&line.text) {
+                break;
+            }
+            let rest = line.text[1..].trim_start();
+            let number = line.number;
+            // where the item's own content starts
+            let inner = indent + (line.text.len() - rest.len());
+            self.at += 1;
+            let item = if rest.is_empty() {
+                self.nested(indent)?
+            } else if let Some((key, value)) = split_key(rest) {
+                self.mapping(inner, Some((key, value, number)))?
+            } else {
+                scalar(rest, number)?
+            };
+            items.push(item);
+        }
+        self.depth -= 1;
+        Ok(Node::List(items))
+    }
+
+    // `first` is a field already read after a list dash
+    fn mapping(
+        &mut self,
+        indent: usize,
+        first: Option<(String, String, usize)>,
+    ) -> Result<Node, String> {
+        self.deeper()?;
+        let mut fields = Vec::new();
+        if let Some((key, value, number)) = first {
+            let value = self.field_value(indent, &value, number)?;
+            fields.push((key, value));
+        }
+        while let Some(line) = self.lines.get(self.at) {
+            if line.indent != indent || is_item(&line.text) {
+                break;
+            }
+            let number = line.number;
+            let Some((key, value)) = split_key(&line.text) else {
+                return Err(format!("expected 'key: value' on line {}", number));
+            };
+            self.at += 1;
+            let value = self.field_value(indent, &value, number)?;
+            fields.push((key, value));
+        }
+        self.depth -= 1;
+        Ok(Node::Object(fields))
+    }
+
+    fn field_value(&mut self, indent: usize, value: &str, number: usize) -> Result<Node, String> {
+        if !value.is_empty() {
+            return scalar(value, number);
+        }
+        // a list under a key may sit at the key's own indentation
+        match self.lines.get(self.at) {
+            Some(next) if next.indent == indent && is_item(&next.text) => self.list(indent),
+            _ => self.nested(indent),
+        }
+    }
+
+    // the block under a line, or null when nothing is indented under it
+    fn nested(&mut self, indent: usize) -> Result<Node, String> {
+        match self.lines.get(self.at) {
+            Some(next) if next.indent > indent => self.block(next.indent),
+            _ => Ok(Node::Null),
+        }
+    }
+}
+
+fn is_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+// `key: value` or `key:`, the key may be quoted
+fn split_key(text: &str) -> Option<(String, String)> {
+    if text.starts_with('"') {
+        let end = closing_quote(text)?;
+        let key = match parse_json(&text[..=end]).ok()? {
+            Node::Str(key) => key,
+            _ => return None,
+        };
+        let rest = text[end + 1..].strip_prefix(':')?;
+        return (rest.is_empty() || rest.starts_with(' ')).then(|| (key, rest.trim().to_string()));
+    }
+    if text.starts_with(['[', '{', '\'']) {
+        return None;
+    }
+    let colon = text
+        .char_indices()
+        .find(|(index, c)| *c == ':' && text[index + 1..].chars().next().is_none_or(|c| c == ' '))?
+        .0;
+    Some((
+        text[..colon].trim().to_string(),
+        text[colon + 1..].trim().to_string(),
+    ))
+}
+
+fn closing_quote(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = None;
+    for (index, c) in line.char_indices() {
+        match (quoted, c) {
+            (None, '"' | '\'') => quoted = Some(c),
+            (Some(open), c) if c == open => quoted = None,
+            (None, '#') if index == 0 || line[..index].ends_with(' ') => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn scalar(text: &str, number: usize) -> Result<Node, String> {
+    let error = |message: String| format!("{} on line {}", message, number);
+    match text {
+        "null" | "~" => return Ok(Node::Null),
+        "true" => return Ok(Node::Bool(true)),
+        "false" => return Ok(Node::Bool(false)),
+        _ => {}
+    }
+    if text.starts_with(['"', '[', '{']) {
+        return parse_json(text).map_err(error);
+    }
+    if let Some(inner) = text
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+    {
+        return Ok(Node::Str(inner.replace("''", "'")));
+    }
+    if let Ok(value) = text.parse::<f64>()
+        && text.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.')
+    {
+        return Ok(Node::Num(value));
+    }
+    Ok(Node::Str(text.to_string()))
+}
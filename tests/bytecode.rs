@@ -0,0 +1,234 @@
+mod common;
+
+use kindred::bytecode::disasm::disassemble;
+use kindred::bytecode::{DecodeError, Object, Op, compile::compile, vm};
+use kindred::interpreter::eval::RuntimeError;
+use kindred::ir::lower::lower;
+use kindred::ir::verify::verify;
+use kindred::network::topology::Topology;
+
+// compiled as `make` does, then read back from the bytes it would write
+fn object(source: &str) -> Object {
+    let (program, lexer) = common::parse(source);
+    let lowered = lower(&program, lexer.interner(), lexer.profiles()).unwrap();
+    assert!(verify(&lowered).is_empty());
+    Object::decode(&compile(&lowered, "main.kin", source.len()).encode()).unwrap()
+}
+
+fn run(object: &Object) -> Result<(String, Topology), RuntimeError> {
+    let mut out = Vec::new();
+    let topology = vm::run(object, &mut out)?;
+    Ok((String::from_utf8(out).unwrap(), topology))
+}
+
+fn interpret(source: &str) -> Result<(String, Topology), RuntimeError> {
+    let (program, lexer) = common::parse(source);
+    common::interpret(&program, &lexer)
+}
+
+const NETWORK: &str = r#"
+define maquinas m1, m2, m3;
+define concentradores c1 = 4;
+define coaxial x1 = 20;
+
+fn raiz(x: num) -> num {
+    for i in 0..100 {
+        si i * i >= x { return i; }
+    }
+    return -1;
+}
+
+modulo red {
+    fn doble(n: num) -> num { return n * 2; }
+    fn libres(c: concentrador) -> num { return doble(disponibles(c)) / 2; }
+}
+
+inicio
+    coloca(c1, 2, 2);
+    coloca(m1, derecha, c1);
+    colocaCoaxial(x1, 0, 5, derecha);
+    colocaCoaxialConcentrador(x1, c1);
+    uneMaquinaPuerto(m1, c1, raiz(4));
+    asignaPuerto(m2, c1);
+    coloca(m2, 9, 9);
+    coloca(m3, 1, 9);
+    maquinaCoaxial(m3, x1, 2.5);
+    let nombre = "red";
+    nombre += " local";
+    for p in c1.puertos {
+        si p.presente && p.numero < 3 { escribe(p.maquina, p.numero); }
+    }
+    for m in maquinas {
+        escribe(m, m.pos.x, alcanza(m, c1), completo(x1), x1.longitud);
+    }
+    escribe(red::libres(c1), puertos(c1), c1.puertos[0], 2 ** 3 % 5, saltos(m1, m2));
+    escribe(plano(), nombre, (1..4) == (1..4), -(2 - 5), !presente(m1));
+fin
+"#;
+
+#[test]
+fn compiled_programs_do_what_the_interpreter_does() {
+    let recursive = r#"
+fn fib(n: num) -> num {
+    si n < 2 { return n; }
+    return fib(n - 1) + fib(n - 2);
+}
+fn saluda(nombre: texto) {
+    escribe("hola " + nombre);
+}
+inicio
+    let i = 0;
+    while i < 10 || i == 10 {
+        i += 1;
+        si i % 2 == 0 { continue; }
+        si i > 7 { break; }
+        escribe(i, fib(i));
+    }
+    saluda("red");
+fin
+"#;
+    for source in [NETWORK, recursive] {
+        let (printed, topology) = run(&object(source)).unwrap();
+        let (expected, built) = interpret(source).unwrap();
+        assert_eq!(printed, expected);
+        assert_eq!(topology, built);
+    }
+    let (printed, _) = run(&object(NETWORK)).unwrap();
+    assert!(printed.starts_with("m2 1\nm1 2\nm1 3 verdadero falso 20\n"));
+    assert!(printed.ends_with("links:\n  m1 - port 2 of 'c1'\n  m2 - port 1 of 'c1'\n  m3 - 'x1' at 2.5\n  x1 - 'c1' red local verdadero 3 falso\n"));
+}
+
+#[test]
+fn object_files_round_trip_and_refuse_what_they_cannot_run() {
+    let object = object(NETWORK);
+    let bytes = object.encode();
+    assert_eq!(&bytes[..4], b"KNOB");
+    assert_eq!(Object::decode(&bytes).unwrap(), object);
+
+    let mut newer = bytes.clone();
+    newer[4] = 2;
+    let error = Object::decode(&newer).unwrap_err();
+    assert_eq!(error, DecodeError::Version(2));
+    assert_eq!(
+        error.to_string(),
+        "the object file is version 2, this kindred runs version 1, make it again"
+    );
+    assert_eq!(
+        Object::decode(b"fn main() {}"),
+        Err(DecodeError::NotAnObject)
+    );
+    assert_eq!(
+        Object::decode(&bytes[..bytes.len() - 3]),
+        Err(DecodeError::Truncated)
+    );
+
+    // code that would jump out of its function or pop an empty stack is refused, not run
+    let mut broken = object.clone();
+    broken.functions[1].code[0] = Op::Jump(500);
+    assert!(matches!(
+        Object::decode(&broken.encode()),
+        Err(DecodeError::Malformed(message)) if message == "raiz: op 0 names something that isn't there"
+    ));
+    let mut broken = object.clone();
+    let raiz = &mut broken.functions[1];
+    raiz.code.insert(0, Op::Add);
+    raiz.spans.insert(0, raiz.spans[0]);
+    assert!(matches!(
+        Object::decode(&broken.encode()),
+        Err(DecodeError::Malformed(message)) if message == "raiz: op 0 takes 2 values from a stack of 0"
+    ));
+
+    // a line entry past the end of the program it was compiled from is refused too
+    let mut broken = object.clone();
+    broken.source_len = broken.functions[0].spans[0].end - 1;
+    assert!(matches!(
+        Object::decode(&broken.encode()),
+        Err(DecodeError::Malformed(message)) if message == "broken line entry for op 0"
+    ));
+}
+
+#[test]
+fn runtime_errors_point_at_the_source() {
+    let source = r#"
+modulo mate {
+    fn divide(a: num, b: num) -> num { return a / b; }
+}
+fn media(total: num, cuenta: num) -> num {
+    return mate::divide(total, cuenta);
+}
+inicio
+    escribe(media(10, 2));
+    escribe(media(10, 0));
+fin
+"#;
+    let error = run(&object(source)).unwrap_err();
+    let expected = interpret(source).unwrap_err();
+    assert_eq!(error.message, "division by zero");
+    assert_eq!(error.span, expected.span);
+    assert_eq!(
+        error.trace_lines(),
+        [
+            "in mate::divide called at 6:12",
+            "in media called at 10:13",
+            "in inicio"
+        ]
+    );
+
+    let source = "fn f(n: num) -> num { return f(n + 1); } inicio escribe(f(0)); fin";
+    let error = run(&object(source)).unwrap_err();
+    assert_eq!(error.message, "too many nested calls, more than 100");
+    assert_eq!(error.trace.len(), 100);
+
+    // network errors are the interpreter's, on the command's line
+    let source = "define maquinas m1; define concentradores c1 = 2;
+inicio
+    uneMaquinaPuerto(m1, c1, 1);
+    uneMaquinaPuerto(m1, c1, 7);
+fin";
+    let error = run(&object(source)).unwrap_err();
+    assert_eq!(error.message, interpret(source).unwrap_err().message);
+    assert_eq!((error.span.line, error.span.column), (4, 5));
+}
+
+#[test]
+fn the_disassembler_lists_the_code_under_its_source_lines() {
+    let source = "define maquinas m1;
+fn doble(n: num) -> num { return n * 2; }
+inicio
+    escribe(doble(3), m1);
+fin
+";
+    let listing = disassemble(&object(source), Some(source));
+    assert_eq!(
+        listing,
+        "\
+; kindred object version 1, compiled from main.kin
+
+constants:
+    #0    @m1
+    #1    num 3
+    #2    num 2
+
+devices:
+    d0    maquina m1
+
+fn inicio(), 1 slots
+  ; 1: define maquinas m1;
+    0000  const #0                 ; @m1
+    0001  define_machine
+  ; 4: escribe(doble(3), m1);
+    0002  const #1                 ; num 3
+    0003  call 1                   ; doble
+    0004  const #0                 ; @m1
+    0005  write 2
+    0006  ret
+
+fn doble(n) -> value, 2 slots
+  ; 2: fn doble(n: num) -> num { return n * 2; }
+    0000  load 0                   ; n
+    0001  const #2                 ; num 2
+    0002  mul
+    0003  retv
+"
+    );
+}
@@ -0,0 +1,55 @@
+// shared by the test binaries, each one uses only part of it
+#![allow(dead_code)]
+
+use kindred::interpreter::eval::{Interpreter, RuntimeError};
+use kindred::lexer::lexer_ana::Lexer;
+use kindred::network::topology::Topology;
+use kindred::parser::ast::Program;
+use kindred::parser::parser_ana::Parser;
+use kindred::semantic::checker::{TypeError, check_program};
+
+// small xorshift generator so the properties run on many inputs without extra crates
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Parses `source`, which must have no syntax errors. The lexer names what the program holds
+pub fn parse(source: &str) -> (Program, Lexer<'_>) {
+    let mut parser = Parser::new(Lexer::new(source).unwrap());
+    let program = parser.parse_program();
+    assert!(!parser.has_errors(), "syntax errors in {}", source);
+    (program, parser.into_lexer())
+}
+
+pub fn type_errors(source: &str) -> Vec<TypeError> {
+    let (program, lexer) = parse(source);
+    check_program(&program, lexer.interner(), lexer.profiles())
+}
+
+/// Runs a parsed program, returning what it printed and the network it built
+pub fn interpret(program: &Program, lexer: &Lexer) -> Result<(String, Topology), RuntimeError> {
+    let mut out = Vec::new();
+    let mut interpreter = Interpreter::new(program, lexer.interner(), lexer.profiles(), &mut out);
+    interpreter.run(program)?;
+    let topology = interpreter.topology().clone();
+    Ok((String::from_utf8(out).unwrap(), topology))
+}
+
+/// Checks and runs `source`, which must be well typed
+pub fn run(source: &str) -> Result<(String, Topology), RuntimeError> {
+    let (program, lexer) = parse(source);
+    let errors = check_program(&program, lexer.interner(), lexer.profiles());
+    assert!(errors.is_empty(), "type errors: {:?}", errors);
+    interpret(&program, &lexer)
+}
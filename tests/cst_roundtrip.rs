@@ -0,0 +1,202 @@
+mod common;
+
+use common::Rng;
+use kindred::lexer::incremental::TextEdit;
+use kindred::lexer::lexer_ana::LexerOptions;
+use kindred::parser::cst::red::SyntaxNodePtr;
+use kindred::parser::cst::reparse::reparse;
+use kindred::parser::cst::view::{Expr, Program, Stmt};
+use kindred::parser::cst::{self, SyntaxKind};
+use std::rc::Rc;
+
+const SOURCES: &[&str] = &[
+    "",
+    "inicio fin",
+    "  // solo un comentario\n\n",
+    r#"//@keywords school
+programa red;   // nombre
+define maquinas m1,m2 ,  m3 ;
+define concentradores c1 = 4;
+
+fn distancia(a: num, b: num) -> num {
+    let d: num = a - b; // puede ser negativa
+    si d < 0 { return -d; } sino si d == 0 inicio return 0; fin
+    return d;
+}
+
+inicio
+	coloca(m1, 2, 3);
+    coloca( m2 , derecha , m1 );
+    escribe("listo", c1.puertos[1], f(1)(2));
+    while i < 10 { i += 1; }
+fin
+// al final
+"#,
+    // broken programs keep every byte as well
+    "inicio let = 1; x + ; @@ coloca m1; fin fin extra",
+    "fn f(a {} define maquinas; programa",
+    "inicio escribe(\"sin cerrar); fin",
+    "inicio ñ € 🙂 \u{a0} x = 1; fin",
+];
+
+fn parse(source: &str) -> cst::Parse {
+    cst::parse(source, LexerOptions::default()).unwrap()
+}
+
+#[test]
+fn tree_spells_out_the_source_exactly() {
+    for source in SOURCES {
+        let parse = parse(source);
+        assert_eq!(parse.root.kind(), SyntaxKind::Program);
+        assert_eq!(parse.root.text(), *source);
+        assert_eq!(parse.root.text_range(), 0..source.len());
+
+        // tokens are contiguous, offsets add up
+        let mut offset = 0;
+        for token in parse.root.descendant_tokens() {
+            assert_eq!(
+                token.text_range().start,
+                offset,
+                "{:?} in {:?}",
+                token,
+                source
+            );
+            assert_eq!(&source[token.text_range()], token.text());
+            offset = token.text_range().end;
+        }
+        assert_eq!(offset, source.len());
+    }
+}
+
+const FRAGMENTS: &[&str] = &[
+    "inicio", "fin", "{", "}", "(", ")", "[", "]", ";", ",", ".", "=", "+=", "-", "**", "si",
+    "sino", "while", "let", "fn", "define", "maquinas", "coloca", "escribe", "x", "1", "2.5",
+    "\"s\"", "\"", "//c\n", " ", "\n", "\t", "@", "ñ", "\u{a0}", "->", ":", "num", "modulo", "::",
+    "for", "in", "break",
+];
+
+#[test]
+fn arbitrary_text_round_trips() {
+    let mut rng = Rng(0x0c57_cafe_f00d_0001);
+
+    for _ in 0..1000 {
+        let mut source = String::new();
+        for _ in 0..rng.below(40) {
+            source.push_str(FRAGMENTS[rng.below(FRAGMENTS.len())]);
+        }
+        assert_eq!(parse(&source).root.text(), source);
+    }
+}
+
+#[test]
+fn typed_view_reads_the_tree() {
+    let parse = parse(SOURCES[3]);
+    let program = Program::cast(parse.root.clone()).unwrap();
+    assert_eq!(program.name().unwrap().text(), "red");
+
+    let defines = program.defines();
+    assert_eq!(defines.len(), 2);
+    let names: Vec<String> = defines[0]
+        .items()
+        .iter()
+        .map(|item| item.name().unwrap().text().to_string())
+        .collect();
+    assert_eq!(names, ["m1", "m2", "m3"]);
+    assert!(defines[1].items()[0].size().is_some());
+
+    let function = &program.functions()[0];
+    assert_eq!(function.name().unwrap().text(), "distancia");
+    assert_eq!(function.params().len(), 2);
+    assert_eq!(
+        function.return_type().unwrap().token().unwrap().text(),
+        "num"
+    );
+
+    let statements = function.body().unwrap().statements();
+    assert_eq!(statements.len(), 3);
+    let Stmt::If(branch) = &statements[1] else {
+        panic!("expected si, found {:?}", statements[1]);
+    };
+    assert!(matches!(branch.condition(), Some(Expr::Binary(_))));
+    assert_eq!(branch.else_branch().unwrap().kind(), SyntaxKind::IfStmt);
+
+    let body = program.body().unwrap().statements();
+    assert_eq!(body.len(), 4);
+    let Stmt::Network(place) = &body[1] else {
+        panic!("expected coloca, found {:?}", body[1]);
+    };
+    assert_eq!(place.args().len(), 3);
+    let Stmt::Write(write) = &body[2] else {
+        panic!("expected escribe, found {:?}", body[2]);
+    };
+    let args = write.args();
+    assert!(matches!(args[1], Expr::Index(_)));
+    let Expr::Call(call) = &args[2] else {
+        panic!("expected a call, found {:?}", args[2]);
+    };
+    assert!(matches!(call.callee(), Some(Expr::Call(_))));
+}
+
+fn apply(source: &str, range: std::ops::Range<usize>, replacement: &str) -> (String, TextEdit) {
+    let mut edited = source.to_string();
+    edited.replace_range(range.clone(), replacement);
+    let edit = TextEdit {
+        range,
+        replacement: replacement.to_string(),
+    };
+    (edited, edit)
+}
+
+#[test]
+fn reparsing_a_block_matches_a_full_parse() {
+    let source = SOURCES[3];
+    let old = parse(source);
+    let at = source.find("return d;").unwrap();
+    let edits = [
+        (at..at + 6, "escribe(d);"),
+        (at..at, "let e = d * 2;\n    "),
+        (at + 7..at + 8, "(d + 1) ** 2"),
+    ];
+
+    for (range, replacement) in edits {
+        let (edited, edit) = apply(source, range, replacement);
+        let incremental = reparse(&old, &edited, &edit, LexerOptions::default())
+            .unwrap_or_else(|| panic!("no incremental reparse for {:?}", edit));
+        let full = parse(&edited);
+        assert_eq!(incremental.root.text(), edited);
+        assert_eq!(incremental.root.green(), full.root.green());
+    }
+}
+
+#[test]
+fn reparsing_falls_back_when_the_block_breaks() {
+    let source = SOURCES[3];
+    let old = parse(source);
+    let at = source.find("return d;").unwrap();
+
+    // an extra closer ends the block early, a syntax error is not patched in
+    for (len, replacement) in [(6, "}"), (9, "return d")] {
+        let (edited, edit) = apply(source, at..at + len, replacement);
+        assert!(reparse(&old, &edited, &edit, LexerOptions::default()).is_none());
+    }
+    // outside every block
+    let (edited, edit) = apply(source, 0..0, " ");
+    assert!(reparse(&old, &edited, &edit, LexerOptions::default()).is_none());
+}
+
+#[test]
+fn untouched_nodes_keep_their_identity() {
+    let source = SOURCES[3];
+    let old = parse(source);
+    let function = Program::cast(old.root.clone()).unwrap().functions()[0].clone();
+    let pointer = SyntaxNodePtr::new(function.syntax());
+
+    // an edit in the main block, after the function
+    let at = source.find("i += 1").unwrap();
+    let (edited, edit) = apply(source, at..at + 6, "i = i + 1");
+    let new = reparse(&old, &edited, &edit, LexerOptions::default()).unwrap();
+
+    let found = pointer.to_node(&new.root).unwrap();
+    assert_eq!(found.kind(), SyntaxKind::FnDecl);
+    assert!(Rc::ptr_eq(found.green(), function.syntax().green()));
+}
@@ -0,0 +1,259 @@
+mod common;
+
+use kindred::lexer::keywords::KeywordProfile;
+use kindred::network::decompile::{DecompileError, decompile};
+use kindred::network::device::{DeviceId, Position};
+use kindred::network::export::{self, ImportError};
+use kindred::network::topology::{NetworkError, Topology};
+use kindred::network::{DEFAULT_COAXIAL_LENGTH, DEFAULT_PORTS};
+use kindred::parser::ast::Direction;
+use kindred::util::document::{Node, parse_json};
+use kindred::util::yaml::{parse_yaml, to_yaml};
+
+fn campus() -> Topology {
+    let mut net = Topology::with_grid(20, 10);
+    let m1 = net.add_machine("m1").unwrap();
+    let m2 = net.add_machine("m2").unwrap();
+    let m3 = net.add_machine("m3").unwrap();
+    net.add_machine("spare").unwrap();
+    let c1 = net.add_concentrator("c1", 4).unwrap();
+    let c2 = net.add_concentrator("c2", 2).unwrap();
+    let x1 = net.add_coaxial("x1", 12.5).unwrap();
+    net.add_coaxial("loose", 3.0).unwrap();
+
+    net.place(DeviceId::Machine(m1), Position::new(0, 0))
+        .unwrap();
+    net.place(DeviceId::Concentrator(c1), Position::new(1, 0))
+        .unwrap();
+    net.place(DeviceId::Concentrator(c2), Position::new(5, 5))
+        .unwrap();
+    net.place_coaxial(x1, Position::new(2, 2), Direction::Right)
+        .unwrap();
+
+    net.connect_port(m1, c1, 3).unwrap();
+    net.connect_port(m2, c2, 1).unwrap();
+    // taps out of order, the cable keeps them sorted
+    net.attach_coaxial(m3, x1, 7.5).unwrap();
+    net.attach_segment(x1, c2).unwrap();
+    net.attach_segment(x1, c1).unwrap();
+    net
+}
+
+#[test]
+fn exported_topologies_import_back_unchanged() {
+    let net = campus();
+
+    let json = export::to_json(&net);
+    assert!(json.starts_with("{\n  \"schema\": \"kindred-topology/1\",\n"));
+    assert!(json.contains("\"concentrators\": [\"c2\", \"c1\"]"));
+    assert_eq!(export::from_json(&json), Ok(net.clone()));
+
+    let yaml = export::to_yaml(&net);
+    assert!(yaml.contains(
+        "- name: m3\n    position: null\n    link:\n      coaxial: x1\n      offset: 7.5\n"
+    ));
+    assert_eq!(export::from_yaml(&yaml), Ok(net.clone()));
+
+    // the two formats describe the same document
+    assert_eq!(parse_json(&json), parse_yaml(&yaml));
+    assert_eq!(
+        export::from_json(&export::to_json(&Topology::new())),
+        Ok(Topology::new())
+    );
+}
+
+#[test]
+fn broken_documents_are_rejected_with_a_path() {
+    let json = export::to_json(&campus());
+
+    assert_eq!(
+        export::from_json(&json.replace("kindred-topology/1", "kindred-topology/9"))
+            .unwrap_err()
+            .to_string(),
+        "schema: expected schema 'kindred-topology/1', found 'kindred-topology/9'"
+    );
+    assert_eq!(
+        export::from_json(&json.replace("\"port\": 3", "\"port\": \"3\""))
+            .unwrap_err()
+            .to_string(),
+        "machines[0].link.port: expected a number, found a string"
+    );
+    assert_eq!(
+        export::from_json(&json.replace("\"ports\": 4", "\"sockets\": 4"))
+            .unwrap_err()
+            .to_string(),
+        "concentrators[0].ports: missing"
+    );
+    assert_eq!(
+        export::from_json(&json.replace("\"direction\": \"right\"", "\"direction\": \"north\""))
+            .unwrap_err()
+            .to_string(),
+        "coaxial[0].direction: expected up, down, left or right, found 'north'"
+    );
+
+    // well formed, but two machines on one port
+    let taken = json
+        .replace("\"port\": 1", "\"port\": 3")
+        .replace("\"c2\",\n        \"port\"", "\"c1\",\n        \"port\"");
+    assert!(matches!(
+        export::from_json(&taken),
+        Err(ImportError::Network(NetworkError::PortTaken { .. }))
+    ));
+
+    let error = export::from_json("{\n  \"schema\": }").unwrap_err();
+    assert_eq!(error.to_string(), "expected a value at 2:13");
+
+    // nesting is bounded, a deep document is a syntax error and not a stack overflow
+    let error = export::from_json(&"[".repeat(200_000)).unwrap_err();
+    assert_eq!(error.to_string(), "nested deeper than 128 levels at 1:129");
+    let deep: String = (0..1000)
+        .map(|level| format!("{}-\n", "  ".repeat(level)))
+        .collect();
+    assert_eq!(
+        export::from_yaml(&deep).unwrap_err().to_string(),
+        "nested deeper than 128 levels on line 129"
+    );
+    let deep: String = (0..1000)
+        .map(|level| format!("{}k:\n", " ".repeat(level)))
+        .collect();
+    assert!(
+        parse_yaml(&deep)
+            .unwrap_err()
+            .starts_with("nested deeper than 128 levels")
+    );
+    let shallow = format!("{}{}", "[".repeat(128), "]".repeat(128));
+    assert!(parse_json(&shallow).is_ok());
+}
+
+#[test]
+fn yaml_reads_what_people_write() {
+    let text = "\
+# hand written
+schema: 'kindred-topology/1'
+grid: {\"width\": 4, \"height\": 4}
+machines:
+- name: \"m 1\"   # quoted, it has a space
+  position:
+    x: 0
+    y: -1
+  link: ~
+empty:
+";
+    let node = parse_yaml(text).unwrap();
+    assert_eq!(
+        node.get("schema"),
+        Some(&Node::Str("kindred-topology/1".to_string()))
+    );
+    assert_eq!(
+        node.get("grid").and_then(|grid| grid.get("height")),
+        Some(&Node::Num(4.0))
+    );
+    let Some(Node::List(machines)) = node.get("machines") else {
+        panic!("machines should be a list");
+    };
+    assert_eq!(machines[0].get("name"), Some(&Node::Str("m 1".to_string())));
+    assert_eq!(
+        machines[0].get("position").and_then(|at| at.get("y")),
+        Some(&Node::Num(-1.0))
+    );
+    assert_eq!(machines[0].get("link"), Some(&Node::Null));
+    assert_eq!(node.get("empty"), Some(&Node::Null));
+    assert!(parse_yaml("tags: [a, 1]").unwrap_err().contains("line 1"));
+
+    // anything that would read back as another value is quoted
+    let tricky = Node::Object(vec![
+        ("no".to_string(), Node::Str("yes".to_string())),
+        ("n".to_string(), Node::Str("12".to_string())),
+        ("s".to_string(), Node::Str("a: b".to_string())),
+    ]);
+    let written = to_yaml(&tricky);
+    assert_eq!(written, "\"no\": \"yes\"\nn: \"12\"\ns: \"a: b\"\n");
+    assert_eq!(parse_yaml(&written), Ok(tricky));
+}
+
+// parses, checks and runs a program, returning the network it built
+fn build(source: &str) -> Topology {
+    common::run(source).unwrap().1
+}
+
+#[test]
+fn decompiled_programs_build_the_same_topology() {
+    let mut net = Topology::new();
+    let m1 = net.add_machine("m1").unwrap();
+    let m2 = net.add_machine("m2").unwrap();
+    let m3 = net.add_machine("m3").unwrap();
+    let m4 = net.add_machine("m4").unwrap();
+    net.add_machine("spare").unwrap();
+    let c1 = net.add_concentrator("c1", 4).unwrap();
+    let c2 = net.add_concentrator("c2", DEFAULT_PORTS).unwrap();
+    let x1 = net.add_coaxial("x1", 12.5).unwrap();
+    net.add_coaxial("x2", DEFAULT_COAXIAL_LENGTH).unwrap();
+    net.place(DeviceId::Machine(m1), Position::new(0, 0))
+        .unwrap();
+    net.place(DeviceId::Concentrator(c1), Position::new(1, 0))
+        .unwrap();
+    net.place_coaxial(x1, Position::new(2, 5), Direction::Down)
+        .unwrap();
+    net.connect_port(m1, c1, 3).unwrap();
+    net.connect_port(m2, c2, 1).unwrap();
+    net.attach_coaxial(m3, x1, 0.0).unwrap();
+    net.attach_coaxial(m4, x1, 7.5).unwrap();
+    net.attach_segment(x1, c2).unwrap();
+    net.attach_segment(x1, c1).unwrap();
+
+    let program = decompile(&net, KeywordProfile::School).unwrap();
+    assert_eq!(
+        program,
+        "\
+//@keywords school
+
+define maquinas m1, m2, m3, m4, spare;
+define concentradores c1 = 4, c2;
+define coaxial x1 = 12.5, x2;
+
+inicio
+    coloca(m1, 0, 0);
+    coloca(c1, 1, 0);
+    colocaCoaxial(x1, 2, 5, abajo);
+
+    colocaCoaxialConcentrador(x1, c2);
+    colocaCoaxialConcentrador(x1, c1);
+
+    uneMaquinaPuerto(m1, c1, 3);
+    uneMaquinaPuerto(m2, c2, 1);
+
+    asignaMaquinaCoaxial(m3, x1);
+    maquinaCoaxial(m4, x1, 7.5);
+fin
+"
+    );
+    assert_eq!(build(&program), net);
+
+    let english = decompile(&net, KeywordProfile::En).unwrap();
+    assert!(english.contains("placeCoaxial(x1, 2, 5, down);"));
+    assert_eq!(build(&english), net);
+
+    // what can't be written as a program
+    let mut wide = Topology::with_grid(20, 10);
+    assert_eq!(
+        decompile(&wide, KeywordProfile::School)
+            .unwrap_err()
+            .to_string(),
+        "the grid is 20x10, a program can only build on the 200x200 one"
+    );
+    wide = Topology::new();
+    wide.add_machine("pc_01").unwrap();
+    assert!(matches!(
+        decompile(&wide, KeywordProfile::School),
+        Err(DecompileError::BadName(_))
+    ));
+    let mut keyword = Topology::new();
+    keyword.add_machine("fin").unwrap();
+    assert_eq!(
+        decompile(&keyword, KeywordProfile::School)
+            .unwrap_err()
+            .to_string(),
+        "'fin' is a keyword in the school profile"
+    );
+    assert!(decompile(&keyword, KeywordProfile::En).is_ok());
+}
@@ -0,0 +1,73 @@
+use kindred::lexer::keywords::KEYWORDS;
+use kindred::lexer::lexer_ana::{Lexer, LexerOptions};
+use kindred::util::interner::Interner;
+
+#[test]
+fn one_spelling_is_one_symbol_handed_out_in_order() {
+    let mut interner = Interner::new();
+    assert!(interner.is_empty());
+    let red = interner.intern("red");
+    let m1 = interner.intern("m1");
+
+    assert_eq!(interner.intern("red"), red);
+    assert_eq!((red.index(), m1.index()), (0, 1));
+    assert!(red < m1);
+    assert_eq!(interner.len(), 2);
+    assert_eq!(interner.resolve(red), "red");
+    assert_eq!(interner.resolve(m1), "m1");
+
+    // looking a name up doesn't intern it
+    assert_eq!(interner.get("m1"), Some(m1));
+    assert_eq!(interner.get("m2"), None);
+    assert_eq!(interner.len(), 2);
+}
+
+#[test]
+fn keywords_are_seeded_at_their_place_in_the_table() {
+    let interner = Lexer::keyword_interner();
+    assert_eq!(interner.len(), KEYWORDS.len());
+    for (index, keyword) in KEYWORDS.iter().enumerate() {
+        let symbol = interner.get(keyword.spelling).unwrap();
+        assert_eq!(symbol.index(), index);
+        assert_eq!(interner.resolve(symbol), keyword.spelling);
+    }
+
+    // the names of a file come after them
+    let mut lexer =
+        Lexer::with_interner("let red = inicio;", interner, LexerOptions::default()).unwrap();
+    let symbols: Vec<_> = lexer.by_ref().filter_map(|token| token.symbol).collect();
+    let interner = lexer.interner();
+    assert_eq!(
+        symbols,
+        [
+            interner.get("let").unwrap(),
+            interner.get("red").unwrap(),
+            interner.get("inicio").unwrap()
+        ]
+    );
+    assert_eq!(symbols[1].index(), KEYWORDS.len());
+    assert_eq!(interner.resolve(symbols[1]), "red");
+}
+
+#[test]
+fn lexing_needs_an_interner_seeded_with_the_keywords() {
+    let refused =
+        |interner: Interner| match Lexer::with_interner("red", interner, LexerOptions::default()) {
+            Ok(_) => None,
+            Err(error) => Some(error.to_string()),
+        };
+    assert_eq!(
+        refused(Interner::new()),
+        Some("interner was not seeded with the keyword table".to_string())
+    );
+
+    // every keyword, but out of order
+    let mut words: Vec<&str> = KEYWORDS.iter().map(|keyword| keyword.spelling).collect();
+    words.swap(0, 1);
+    assert!(refused(Interner::with_words(&words)).is_some());
+
+    // names after the keywords are fine, that is how files share one
+    let mut shared = Lexer::keyword_interner();
+    shared.intern("red");
+    assert_eq!(refused(shared), None);
+}
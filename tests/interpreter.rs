@@ -0,0 +1,331 @@
+mod common;
+
+use kindred::interpreter::eval::RuntimeError;
+
+// checks and runs `source`, returning what it printed
+fn run(source: &str) -> Result<String, RuntimeError> {
+    common::run(source).map(|(printed, _)| printed)
+}
+
+fn type_errors(source: &str) -> Vec<String> {
+    common::type_errors(source)
+        .into_iter()
+        .map(|error| error.message)
+        .collect()
+}
+
+#[test]
+fn recursive_functions_return_values() {
+    let source = r#"
+fn fib(n: num) -> num {
+    si n < 2 { return n; }
+    return fib(n - 1) + fib(n - 2);
+}
+fn factorial(n: num) -> num {
+    si n <= 1 { return 1; }
+    return n * factorial(n - 1);
+}
+fn saluda(nombre: texto) {
+    escribe("hola " + nombre);
+}
+inicio
+    escribe(fib(15), factorial(10), 7 / 2);
+    saluda("red");
+fin
+"#;
+    assert_eq!(run(source).unwrap(), "610 3628800 3.5\nhola red\n");
+}
+
+#[test]
+fn modules_are_called_by_path() {
+    let source = r#"
+fn doble(n: num) -> num { return n * 100; }
+modulo mate {
+    fn doble(n: num) -> num { return n * 2; }
+    // inside the module its own doble wins
+    fn cuadruple(n: num) -> num { return doble(doble(n)); }
+}
+modulo texto_util inicio
+    fn entre(a: texto, b: texto) -> texto { return a + " y " + b; }
+fin
+inicio
+    escribe(mate::cuadruple(3), doble(3), mate::doble(3));
+    escribe(texto_util::entre("a", "b"));
+fin
+"#;
+    assert_eq!(run(source).unwrap(), "12 300 6\na y b\n");
+}
+
+#[test]
+fn runtime_errors_carry_a_stack_trace() {
+    let source = r#"
+modulo mate {
+    fn divide(a: num, b: num) -> num { return a / b; }
+}
+fn media(total: num, cuenta: num) -> num {
+    return mate::divide(total, cuenta);
+}
+inicio
+    escribe(media(10, 2));
+    escribe(media(10, 0));
+fin
+"#;
+    let error = run(source).unwrap_err();
+    assert_eq!(error.message, "division by zero");
+    assert_eq!(error.span.line, 3);
+    assert_eq!(
+        error.trace_lines(),
+        [
+            "in mate::divide called at 6:12",
+            "in media called at 10:13",
+            "in inicio"
+        ]
+    );
+}
+
+#[test]
+fn runaway_recursion_is_stopped() {
+    let source = "fn f(n: num) -> num { return f(n + 1); } inicio escribe(f(0)); fin";
+    let error = run(source).unwrap_err();
+    assert!(error.message.starts_with("too many nested calls"));
+    assert_eq!(error.trace.last().unwrap().0, "f");
+}
+
+#[test]
+fn calls_are_resolved_and_counted() {
+    let source = r#"
+fn uno(a: num) -> num { return a; }
+modulo m { fn dos(a: num, b: num) -> num { return a + b; } }
+inicio
+    uno(1, 2);
+    m::dos(1);
+    m::tres(1);
+    n::uno(1);
+    escribe(m::dos, m);
+fin
+"#;
+    assert_eq!(
+        type_errors(source),
+        [
+            "'uno' takes 1 argument, found 2",
+            "'m::dos' takes 2 arguments, found 1",
+            "module 'm' has no function 'tres'",
+            "unknown module 'n'",
+            "'m::dos' is a function, call it with ( )",
+            "'m' is a module, call its functions as m::name( )",
+        ]
+    );
+}
+
+#[test]
+fn loops_count_and_walk_devices() {
+    let source = r#"
+define maquinas m1, m2, m3;
+define concentradores c1 = 4;
+
+fn raiz_entera(x: num) -> num {
+    for i in 0..100 {
+        si i * i >= x { return i; }
+    }
+    return -1;
+}
+
+inicio
+    let pares = 0;
+    for i in 0..10 {
+        si i % 2 == 1 { continue; }
+        si i > 6 { break; }
+        pares += i;
+    }
+    escribe(pares);
+    for m in maquinas { escribe(m); }
+    for c in concentradores inicio escribe(c); fin
+    let n = 0;
+    while n < 100 {
+        n += 1;
+        si n == 4 { break; }
+    }
+    escribe(n, raiz_entera(9));
+fin
+"#;
+    assert_eq!(run(source).unwrap(), "12\nm1\nm2\nm3\nc1\n4 3\n");
+}
+
+#[test]
+fn loops_are_checked() {
+    let source = r#"
+fn f() { break; }
+inicio
+    for i in 0..3 { let t: texto = i; }
+    for m in maquinas { escribe(m); }
+    for x in 5 { }
+    continue;
+fin
+"#;
+    assert_eq!(
+        type_errors(source),
+        [
+            "'break' can only be used inside a loop",
+            "expected texto, found num",
+            "cannot loop over num, expected a range or a list",
+            "'continue' can only be used inside a loop",
+        ]
+    );
+}
+
+#[test]
+fn network_commands_build_the_topology() {
+    let source = r#"
+define maquinas m1, m2, m3, m4;
+define concentradores c1 = 3;
+define coaxial x1 = 10;
+
+inicio
+    coloca(c1, 2, 3);
+    coloca(m1, derecha, c1);
+    escribe(pos(m1), m1.pos.x, presente(c1));
+    uneMaquinaPuerto(m1, c1, 2);
+    asignaPuerto(m2, c1);
+    escribe(disponibles(c1), puertos(c1), c1.puertos);
+    for p in c1.puertos {
+        si p.presente { escribe(p, p.maquina); }
+    }
+    asignaMaquinaCoaxial(m3, x1);
+    maquinaCoaxial(m4, x1, 5);
+    escribe(presente(x1), x1.longitud, completo(x1), c1.puertos[0]);
+fin
+"#;
+    assert_eq!(
+        run(source).unwrap(),
+        "(3, 3) 3 falso\n1 3 [c1:1, c1:2, c1:3]\nc1:1 m2\nc1:2 m1\nverdadero 10 falso c1:1\n"
+    );
+}
+
+#[test]
+fn plano_draws_the_grid_as_it_is() {
+    let source = r#"
+define maquinas m1;
+define concentradores c1 = 2;
+
+inicio
+    coloca(c1, 0, 0);
+    escribe(plano());
+    coloca(m1, derecha, c1);
+    uneMaquinaPuerto(m1, c1, 2);
+    escribe(plano());
+fin
+"#;
+    assert_eq!(
+        run(source).unwrap(),
+        "\
+grid (0, 0) to (0, 0)
+╔══╗
+║c1║
+╚══╝
+
+not placed: m1
+grid (0, 0) to (1, 0)
+╔══╗┌──┐
+║c1║│m1│
+╚══╝└2─┘
+
+links:
+  m1 - port 2 of 'c1'
+"
+    );
+    assert_eq!(
+        type_errors("define maquinas m1;\ninicio\n    escribe(plano(m1));\nfin\n"),
+        vec!["'plano' takes 0 arguments, found 1"]
+    );
+}
+
+#[test]
+fn network_errors_stop_the_program() {
+    let source = r#"
+define maquinas m1, m2;
+define concentradores c1 = 2;
+inicio
+    uneMaquinaPuerto(m1, c1, 1);
+    uneMaquinaPuerto(m2, c1, 1);
+fin
+"#;
+    let error = run(source).unwrap_err();
+    assert_eq!(error.message, "port 1 of 'c1' is already taken by 'm1'");
+    assert_eq!(error.span.line, 6);
+
+    let source = "define maquinas m1, m2; inicio coloca(m1, abajo, m2); fin";
+    assert_eq!(
+        run(source).unwrap_err().message,
+        "'m2' has not been placed yet"
+    );
+}
+
+#[test]
+fn relative_placements_are_resolved() {
+    let source = r#"
+define maquinas m1, m2, m3;
+define concentradores c1;
+define coaxial x1 = 4;
+inicio
+    coloca(c1, 5, 5);
+    coloca(m1, izquierda, c1);
+    coloca(m2, izquierda, c1);
+    coloca(m3, abajo, m2);
+    escribe(pos(m1), pos(m2), pos(m3));
+fin
+"#;
+    assert_eq!(run(source).unwrap(), "(4, 5) (3, 5) (3, 6)\n");
+
+    let source = r#"
+define maquinas m1;
+define coaxial x1 = 4;
+inicio
+    colocaCoaxial(x1, 0, 0, abajo);
+    coloca(m1, 0, 3);
+fin
+"#;
+    let error = run(source).unwrap_err();
+    assert_eq!(error.message, "'m1' would overlap 'x1' at (0, 3)");
+    assert_eq!(error.span.line, 6);
+}
+
+#[test]
+fn reachability_queries_follow_the_wiring() {
+    let source = r#"
+define maquinas m1, m2, m3;
+define concentradores c1;
+define coaxial x1;
+inicio
+    asignaPuerto(m1, c1);
+    asignaMaquinaCoaxial(m2, x1);
+    escribe(alcanza(m1, m2), alcanza(m1, c1));
+    colocaCoaxialConcentrador(x1, c1);
+    si alcanza(m1, m2) { escribe(saltos(m1, m2)); }
+    escribe(saltos(m1, m3));
+fin
+"#;
+    let error = run(source).unwrap_err();
+    assert_eq!(
+        error.message,
+        "'m1' cannot reach 'm3', check with alcanza first"
+    );
+
+    let source = source.replace("    escribe(saltos(m1, m3));\n", "");
+    assert_eq!(run(&source).unwrap(), "falso verdadero\n3\n");
+
+    let source = r#"
+define maquinas m1;
+inicio
+    escribe(alcanza(m1));
+    let s: texto = saltos(m1, 2);
+fin
+"#;
+    assert_eq!(
+        type_errors(source),
+        [
+            "'alcanza' takes 2 arguments, found 1",
+            "expected maquina, concentrador or coaxial, found num",
+            "expected texto, found num",
+        ]
+    );
+}
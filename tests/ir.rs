@@ -0,0 +1,231 @@
+mod common;
+
+use kindred::ir::lower::lower;
+use kindred::ir::verify::verify;
+use kindred::ir::{BlockId, Const, Inst, Operand, Program, Terminator};
+
+fn lowered(source: &str) -> Program {
+    let (program, lexer) = common::parse(source);
+    lower(&program, lexer.interner(), lexer.profiles()).unwrap()
+}
+
+fn errors(program: &Program) -> Vec<String> {
+    verify(program)
+        .iter()
+        .map(|error| error.to_string())
+        .collect()
+}
+
+const PROGRAM: &str = r#"
+define maquinas m1;
+define concentradores c1 = 2;
+
+fn signo(n: num) -> texto {
+    si n < 0 { return "negativo"; } sino { return "positivo"; }
+}
+
+inicio
+    let i = 0;
+    while i < 3 && !presente(m1) {
+        i += 1;
+        si i == 2 { continue; }
+        uneMaquinaPuerto(m1, c1, i);
+    }
+    escribe(signo(-i) + "!");
+fin
+"#;
+
+#[test]
+fn programs_lower_to_blocks_of_typed_instructions() {
+    let program = lowered(PROGRAM);
+    assert_eq!(
+        program.to_string(),
+        "\
+fn inicio() {
+    var i: num
+    var _and: bool
+b0:
+    define_machine @m1
+    define_concentrator @c1, 2
+    i = 0
+    jmp b1
+b1:
+    %0: bool = lt i, 3
+    _and = %0
+    br %0, b2, b3
+b2:
+    %1: bool = field.presente @m1
+    %2: bool = not %1
+    _and = %2
+    jmp b3
+b3:
+    br _and, b4, b7
+b4:
+    i = add i, 1
+    %3: bool = eq i, 2
+    br %3, b5, b6
+b5:
+    jmp b1
+b6:
+    connect_port @m1, @c1, i
+    jmp b1
+b7:
+    %4: num = neg i
+    %5: texto = call signo(%4)
+    %6: texto = concat %5, \"!\"
+    write %6
+    ret
+}
+
+fn signo(n: num) -> texto {
+b0:
+    %0: bool = lt n, 0
+    br %0, b1, b2
+b1:
+    ret \"negativo\"
+b2:
+    ret \"positivo\"
+}
+"
+    );
+    assert!(verify(&program).is_empty());
+}
+
+#[test]
+fn everything_the_language_has_lowers_and_verifies() {
+    let program = lowered(
+        r#"
+define maquinas m1, m2, m3;
+define concentradores c1 = 4;
+define coaxial x1 = 20;
+
+fn raiz(x: num) -> num {
+    for i in 0..100 {
+        si i * i >= x { return i; }
+    }
+    return -1;
+}
+
+modulo red {
+    fn doble(n: num) -> num { return n * 2; }
+    fn libres(c: Concentrador) -> num { return doble(disponibles(c)) / 2; }
+}
+
+inicio
+    coloca(c1, 2, 2);
+    coloca(m1, derecha, c1);
+    colocaCoaxial(x1, 0, 5, derecha);
+    colocaCoaxialConcentrador(x1, c1);
+    uneMaquinaPuerto(m1, c1, raiz(4));
+    asignaPuerto(m2, c1);
+    maquinaCoaxial(m3, x1, 2.5);
+    let nombre = "red";
+    nombre += " local";
+    for p in c1.puertos {
+        si p.presente || p.numero > 3 { escribe(p.maquina, p.numero); }
+    }
+    for m in maquinas {
+        escribe(m, m.pos.x, alcanza(m, c1), completo(x1), x1.longitud);
+        si m != m1 {
+            let m = c1;
+            escribe(m);
+        }
+    }
+    escribe(red::libres(c1), puertos(c1), c1.puertos[0], 2 ** 3 % 5, saltos(m1, m2));
+    escribe(plano(), nombre, (1..4) == (1..4));
+fin
+"#,
+    );
+    assert_eq!(errors(&program), Vec::<String>::new());
+
+    let text = program.to_string();
+    for expected in [
+        "place_next_to.right @m1, @c1",
+        "place_coax.right @x1, 0, 5",
+        "attach_segment @x1, @c1",
+        "assign_port @m2, @c1",
+        "attach_coax @m3, @x1, 2.5",
+        "devices.machines",
+        "call red::libres(@c1)",
+        "query_ports @c1",
+        "query_layout",
+        "nombre = concat nombre, \" local\"",
+        // the inner m shadows the loop's
+        "var m.1: concentrador",
+        "fn red::libres(c: concentrador) -> num",
+        // a module's own functions first
+        "call red::doble(",
+    ] {
+        assert!(text.contains(expected), "no {:?} in\n{}", expected, text);
+    }
+}
+
+#[test]
+fn the_verifier_catches_broken_programs() {
+    let program = lowered(PROGRAM);
+
+    let mut broken = program.clone();
+    broken.functions[0].blocks[5].terminator = Terminator::Jump(BlockId(99));
+    assert_eq!(
+        errors(&broken),
+        ["inicio, b5: jumps to b99, which doesn't exist"]
+    );
+
+    // the else branch goes nowhere
+    let mut broken = program.clone();
+    broken.functions[1].blocks[0].terminator = Terminator::Branch {
+        condition: Operand::Const(Const::Num(1.0)),
+        then: BlockId(1),
+        otherwise: BlockId(1),
+    };
+    assert_eq!(errors(&broken), ["signo, b2: is unreachable"]);
+    broken.functions[1].blocks.pop();
+    assert_eq!(
+        errors(&broken),
+        ["signo, b0: the condition is num, expected bool"]
+    );
+
+    let mut broken = program.clone();
+    let inst = broken.functions[1].blocks[0].insts[0].clone();
+    broken.functions[1].blocks[0].insts.push(inst);
+    assert_eq!(
+        errors(&broken),
+        ["signo, b0: has 2 instructions but 1 spans"]
+    );
+    let span = broken.functions[1].blocks[0].spans[0];
+    broken.functions[1].blocks[0].spans.push(span);
+    assert_eq!(errors(&broken), ["signo: %0 is assigned 2 times"]);
+
+    // without `i = 0` the loop reads i before anything is in it
+    let mut broken = program.clone();
+    broken.functions[0].blocks[0].insts.remove(2);
+    broken.functions[0].blocks[0].spans.remove(2);
+    assert_eq!(
+        errors(&broken)[0],
+        "inicio, b1: i is read before it is assigned"
+    );
+
+    let mut broken = program.clone();
+    let Inst::Binary { right, .. } = &mut broken.functions[0].blocks[7].insts[2] else {
+        panic!("the third instruction of b7 is the concat");
+    };
+    *right = Operand::Const(Const::Num(1.0));
+    broken.functions[1].blocks[1].terminator = Terminator::Return(None);
+    assert_eq!(
+        errors(&broken),
+        [
+            "inicio, b7: the right side of concat is num, expected texto",
+            "signo, b1: returns nothing, expected texto",
+        ]
+    );
+}
+
+#[test]
+fn programs_with_type_errors_are_not_lowered() {
+    let (program, lexer) = common::parse("inicio let x: texto = 1; fin");
+    let error = lower(&program, lexer.interner(), lexer.profiles()).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "cannot lower a program with type errors: expected texto, found num at 1:23"
+    );
+}
@@ -0,0 +1,393 @@
+mod common;
+
+use common::Rng;
+use kindred::lexer::incremental::TextEdit;
+use kindred::lexer::keywords::KeywordProfile;
+use kindred::lexer::lexer_ana::{ErrorType, Lexer, LexerOptions, Span, Token, TokenType};
+use kindred::parser::parser_ana::Parser;
+
+const FRAGMENTS: &[&str] = &[
+    "&", "|", "&&", "||", "\"", "\\", "//", "\n", "\r\n", "\t", " ", "1", "1.", "1.5", ".5", "x",
+    "_a", "si", "coloca", "ñ", "é", "€", "🙂", "\u{a0}", "@", "#", "$", "~", "`", "?", "'", "<>",
+    "!=", "\\n", "\\q", "\0",
+];
+
+fn arbitrary_source(rng: &mut Rng) -> String {
+    let mut source = String::new();
+    for _ in 0..rng.below(64) {
+        if rng.below(3) == 0 {
+            // any char at all, surrogates aside
+            let c = char::from_u32(rng.below(0x11_0000) as u32).unwrap_or('\u{fffd}');
+            source.push(c);
+        } else {
+            source.push_str(FRAGMENTS[rng.below(FRAGMENTS.len())]);
+        }
+    }
+    source
+}
+
+// lexes with a hard bound on the number of tokens, so a stalled lexer fails instead of hanging
+fn lex_bounded<'a>(lexer: &mut Lexer<'a>, source: &str) -> Vec<Token<'a>> {
+    let bound = source.len() + 1;
+    let tokens: Vec<Token> = lexer.by_ref().take(bound + 1).collect();
+    assert!(
+        tokens.len() <= bound,
+        "lexer did not terminate on {:?}",
+        source
+    );
+    tokens
+}
+
+#[test]
+fn lexer_terminates_and_always_makes_progress() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+
+    for _ in 0..2000 {
+        let source = arbitrary_source(&mut rng);
+        let mut lexer = Lexer::new(&source).unwrap();
+        let tokens = lex_bounded(&mut lexer, &source);
+
+        let (eof, rest) = tokens.split_last().unwrap();
+        assert_eq!(eof.type_, TokenType::EndOfFile, "no EOF for {:?}", source);
+
+        let mut previous_end = 0;
+        for token in rest {
+            assert!(token.span.start >= previous_end, "{:?}", source);
+            assert!(
+                token.span.end > token.span.start,
+                "empty token in {:?}",
+                source
+            );
+            assert!(source.is_char_boundary(token.span.end), "{:?}", source);
+            previous_end = token.span.end;
+        }
+    }
+}
+
+#[test]
+fn consecutive_invalid_characters_form_one_token() {
+    let mut rng = Rng(0xdead_beef_cafe_f00d);
+
+    for _ in 0..2000 {
+        let source = arbitrary_source(&mut rng);
+        let mut lexer = Lexer::new(&source).unwrap();
+        let tokens = lex_bounded(&mut lexer, &source);
+
+        for pair in tokens.windows(2) {
+            let adjacent = pair[0].span.end == pair[1].span.start;
+            let both_unknown =
+                pair[0].type_ == TokenType::Unknown && pair[1].type_ == TokenType::Unknown;
+            // an unterminated string may directly follow a bad run
+            let string_start = source[pair[1].span.start..].starts_with('"');
+            assert!(
+                !(adjacent && both_unknown && !string_start),
+                "unmerged invalid run in {:?}",
+                source
+            );
+        }
+
+        let unknown = tokens
+            .iter()
+            .filter(|token| token.type_ == TokenType::Unknown)
+            .count();
+        assert!(unknown <= lexer.error_count(), "{:?}", source);
+    }
+}
+
+#[test]
+fn error_cap_limits_reported_errors() {
+    let mut rng = Rng(0x0123_4567_89ab_cdef);
+
+    for _ in 0..500 {
+        let source = arbitrary_source(&mut rng);
+        let options = LexerOptions {
+            max_errors: Some(3),
+            ..LexerOptions::default()
+        };
+        let mut uncapped = Lexer::with_options(
+            &source,
+            LexerOptions {
+                max_errors: None,
+                ..LexerOptions::default()
+            },
+        )
+        .unwrap();
+        let mut capped = Lexer::with_options(&source, options).unwrap();
+        lex_bounded(&mut uncapped, &source);
+        lex_bounded(&mut capped, &source);
+
+        assert!(capped.get_errors().len() <= 3);
+        assert_eq!(capped.error_count(), uncapped.error_count());
+    }
+}
+
+#[test]
+fn bad_runs_and_lone_operators_report_once() {
+    let source = "@#$ & | ñandú";
+    let mut lexer = Lexer::new(source).unwrap();
+    let lexemes: Vec<(TokenType, String)> = lexer
+        .by_ref()
+        .map(|token| (token.type_, token.lexeme.into_owned()))
+        .collect();
+
+    assert_eq!(
+        lexemes,
+        vec![
+            (TokenType::Unknown, "@#$".to_string()),
+            (TokenType::Unknown, "&".to_string()),
+            (TokenType::Unknown, "|".to_string()),
+            (TokenType::Unknown, "ñ".to_string()),
+            (TokenType::Identifier, "and".to_string()),
+            (TokenType::Unknown, "ú".to_string()),
+            (TokenType::EndOfFile, String::new()),
+        ]
+    );
+    assert_eq!(lexer.error_count(), 5);
+}
+
+// fewer pieces than `arbitrary_source`, so strings, escapes and line ends meet often
+const LINE_FRAGMENTS: &[&str] = &[
+    "\"", "\\", "\\\n", "\n", " ", "x", "si", "1.5", "// c", "\"a\"", "@", "(",
+];
+
+fn line_source(rng: &mut Rng) -> String {
+    (0..rng.below(48))
+        .map(|_| LINE_FRAGMENTS[rng.below(LINE_FRAGMENTS.len())])
+        .collect()
+}
+
+// picks a char boundary of `source`, all of them equally likely
+fn boundary(rng: &mut Rng, source: &str) -> usize {
+    let boundaries: Vec<usize> = (0..=source.len())
+        .filter(|index| source.is_char_boundary(*index))
+        .collect();
+    boundaries[rng.below(boundaries.len())]
+}
+
+fn described(tokens: &[Token]) -> Vec<(TokenType, String, Span)> {
+    tokens
+        .iter()
+        .map(|token| (token.type_.clone(), token.lexeme.to_string(), token.span))
+        .collect()
+}
+
+#[test]
+fn relexing_an_edit_gives_the_tokens_of_a_full_lex() {
+    let mut rng = Rng(0x5851_f42d_4c95_7f2d);
+
+    for _ in 0..2000 {
+        let old = line_source(&mut rng);
+        let start = boundary(&mut rng, &old);
+        let end = start + boundary(&mut rng, &old[start..]);
+        let replacement = line_source(&mut rng).chars().take(rng.below(8)).collect();
+        let new = format!("{}{}{}", &old[..start], replacement, &old[end..]);
+        let edit = TextEdit {
+            range: start..end,
+            replacement,
+        };
+
+        let mut lexer =
+            Lexer::with_interner(&old, Lexer::keyword_interner(), LexerOptions::default()).unwrap();
+        let old_tokens = lex_bounded(&mut lexer, &old);
+
+        let mut lexer =
+            Lexer::with_interner(&new, lexer.into_interner(), LexerOptions::default()).unwrap();
+        let relexed = lexer.relex(&old_tokens, &edit).unwrap();
+        let mut full =
+            Lexer::with_interner(&new, lexer.into_interner(), LexerOptions::default()).unwrap();
+        let expected = lex_bounded(&mut full, &new);
+
+        assert_eq!(
+            described(&relexed.tokens),
+            described(&expected),
+            "{:?} edited to {:?}",
+            old,
+            new
+        );
+    }
+}
+
+#[test]
+fn keyword_hints_are_only_kept_for_names_nothing_declares() {
+    let source = "define maquinas maquina1, maquina2;
+fn signo(n: num) -> num { return n; }
+inicio
+    let fine = signo(1);
+    coloka(maquina1, 1, 1);
+fin";
+    let mut parser = Parser::new(Lexer::new(source).unwrap());
+    let program = parser.parse_program();
+    let hinted = |lexer: &Lexer| -> Vec<(String, Option<&str>)> {
+        lexer
+            .get_warnings()
+            .iter()
+            .map(|warning| (warning.message.clone(), warning.suggestion))
+            .collect()
+    };
+    assert_eq!(hinted(parser.lexer()).len(), 5);
+
+    parser
+        .lexer_mut()
+        .forget_declared(&program.declared_names());
+    assert_eq!(
+        hinted(parser.lexer()),
+        [(
+            "'coloka' is an identifier, not a keyword".to_string(),
+            Some("coloca")
+        )]
+    );
+}
+
+#[test]
+fn keyword_case_and_typos_get_a_suggestion() {
+    let lex = |source: &str, case_insensitive_keywords| {
+        let options = LexerOptions {
+            case_insensitive_keywords,
+            ..LexerOptions::default()
+        };
+        let mut lexer = Lexer::with_options(source, options).unwrap();
+        let tokens: Vec<TokenType> = lexer.by_ref().map(|token| token.type_).collect();
+        let warnings: Vec<(String, Option<&str>)> = lexer
+            .get_warnings()
+            .iter()
+            .map(|warning| (warning.message.clone(), warning.suggestion))
+            .collect();
+        (tokens, warnings)
+    };
+
+    let (tokens, warnings) = lex("colocacoaxialconcentrador", true);
+    assert_eq!(
+        tokens,
+        [TokenType::ColocaCoaxialConcentrador_, TokenType::EndOfFile]
+    );
+    assert_eq!(
+        warnings,
+        [(
+            "Keyword 'colocacoaxialconcentrador' should be written 'colocaCoaxialConcentrador'"
+                .to_string(),
+            Some("colocaCoaxialConcentrador")
+        )]
+    );
+
+    // keywords are case sensitive unless asked, the wrong case is only a hint then
+    let (tokens, warnings) = lex("colocacoaxialconcentrador", false);
+    assert_eq!(tokens, [TokenType::Identifier, TokenType::EndOfFile]);
+    assert_eq!(warnings[0].1, Some("colocaCoaxialConcentrador"));
+
+    // one edit away from `escribe`, hinted once however often it is written
+    let (tokens, warnings) = lex("escrib escrib", false);
+    assert_eq!(tokens[..2], [TokenType::Identifier, TokenType::Identifier]);
+    assert_eq!(
+        warnings,
+        [(
+            "'escrib' is an identifier, not a keyword".to_string(),
+            Some("escribe")
+        )]
+    );
+
+    // too far from every keyword, or too short to guess at
+    let (_, warnings) = lex("impresora si2 x", false);
+    assert_eq!(warnings, []);
+}
+
+#[test]
+fn keyword_profiles_pick_the_spellings() {
+    // the tokens, the profiles in effect and the kinds of error
+    let lex = |source: &str, profiles: &[KeywordProfile]| {
+        let options = LexerOptions {
+            profiles: profiles.to_vec(),
+            ..LexerOptions::default()
+        };
+        let mut lexer = Lexer::with_options(source, options).unwrap();
+        let tokens: Vec<TokenType> = lexer.by_ref().map(|token| token.type_).collect();
+        let errors: Vec<ErrorType> = lexer
+            .get_errors()
+            .iter()
+            .map(|error| error.error_type.clone())
+            .collect();
+        (tokens, lexer.profiles().to_vec(), errors)
+    };
+    let types = |source: &str, profiles: &[KeywordProfile]| lex(source, profiles).0;
+
+    assert_eq!(
+        types("place", &[KeywordProfile::En]),
+        [TokenType::Coloca_, TokenType::EndOfFile]
+    );
+    assert_eq!(
+        types("coloca", &[KeywordProfile::School]),
+        [TokenType::Coloca_, TokenType::EndOfFile]
+    );
+    assert_eq!(
+        types("sea mientras", &[KeywordProfile::Es]),
+        [
+            TokenType::KeywordLet,
+            TokenType::KeywordWhile,
+            TokenType::EndOfFile
+        ]
+    );
+    // a spelling outside the profiles in effect is a plain name
+    assert_eq!(
+        types("place", &[KeywordProfile::School]),
+        [TokenType::Identifier, TokenType::EndOfFile]
+    );
+
+    // the pragma of the file wins over the profiles it is lexed with
+    assert_eq!(
+        lex("//@keywords en\nplace coloca", &[KeywordProfile::School]),
+        (
+            vec![
+                TokenType::Coloca_,
+                TokenType::Identifier,
+                TokenType::EndOfFile
+            ],
+            vec![KeywordProfile::En],
+            vec![]
+        )
+    );
+
+    // an unknown profile in the pragma is an error, the given profiles are used instead
+    assert_eq!(
+        lex("//@keywords klingon\ncoloca", &[KeywordProfile::School]),
+        (
+            vec![TokenType::Coloca_, TokenType::EndOfFile],
+            vec![KeywordProfile::School],
+            vec![ErrorType::InvalidPragma]
+        )
+    );
+}
+
+#[test]
+fn punctuation_takes_the_longest_spelling() {
+    let source = "a**b*c *** x+=1 m::f ::: -> - > x..y.z ... a<>b <>= <";
+    let lexemes: Vec<(TokenType, String)> = Lexer::new(source)
+        .unwrap()
+        .map(|token| (token.type_, token.lexeme.into_owned()))
+        .filter(|(type_, _)| *type_ != TokenType::Identifier && *type_ != TokenType::Integer)
+        .collect();
+    let expected = [
+        (TokenType::OperatorPower, "**"),
+        (TokenType::OperatorMultiply, "*"),
+        (TokenType::OperatorPower, "**"),
+        (TokenType::OperatorMultiply, "*"),
+        (TokenType::OperatorPlusAssign, "+="),
+        (TokenType::DoubleColon, "::"),
+        (TokenType::DoubleColon, "::"),
+        (TokenType::Colon, ":"),
+        (TokenType::Arrow, "->"),
+        (TokenType::OperatorMinus, "-"),
+        (TokenType::OperatorGreater, ">"),
+        (TokenType::DotDot, ".."),
+        (TokenType::Dot, "."),
+        (TokenType::DotDot, ".."),
+        (TokenType::Dot, "."),
+        (TokenType::OperatorNotEqual, "<>"),
+        (TokenType::OperatorNotEqual, "<>"),
+        (TokenType::OperatorAssign, "="),
+        (TokenType::OperatorLess, "<"),
+        (TokenType::EndOfFile, ""),
+    ];
+    assert_eq!(
+        lexemes,
+        expected.map(|(type_, lexeme)| (type_, lexeme.to_string()))
+    );
+}
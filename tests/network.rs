@@ -0,0 +1,476 @@
+use kindred::network::analysis::{Graph, report};
+use kindred::network::device::{DeviceId, Link, PortState, Position};
+use kindred::network::render::render;
+use kindred::network::rules::{CoaxialRules, Problem, Rule};
+use kindred::network::simulation::{self, FrameReport, Outcome, Scenario, simulate};
+use kindred::network::topology::{NetworkError, Topology};
+use kindred::parser::ast::Direction;
+
+#[test]
+fn ports_are_connected_and_assigned() {
+    let mut net = Topology::new();
+    let m1 = net.add_machine("m1").unwrap();
+    let m2 = net.add_machine("m2").unwrap();
+    let m3 = net.add_machine("m3").unwrap();
+    let c1 = net.add_concentrator("c1", 2).unwrap();
+
+    net.connect_port(m1, c1, 2).unwrap();
+    assert_eq!(net.port(c1, 2), Ok(PortState::Connected(m1)));
+    assert_eq!(net.machine(m1).link, Some(Link::Port(c1, 2)));
+
+    assert_eq!(
+        net.connect_port(m2, c1, 2).unwrap_err().to_string(),
+        "port 2 of 'c1' is already taken by 'm1'"
+    );
+    assert!(matches!(
+        net.connect_port(m2, c1, 3),
+        Err(NetworkError::PortOutOfRange {
+            port: 3,
+            ports: 2,
+            ..
+        })
+    ));
+    assert!(matches!(
+        net.port(c1, 0),
+        Err(NetworkError::PortOutOfRange { .. })
+    ));
+    assert_eq!(
+        net.assign_port(m1, c1).unwrap_err().to_string(),
+        "machine 'm1' is already connected to port 2 of 'c1'"
+    );
+
+    assert_eq!(net.assign_port(m2, c1), Ok(1));
+    assert!(net.concentrator(c1).is_complete());
+    assert_eq!(net.concentrator(c1).available(), 0);
+    assert_eq!(
+        net.assign_port(m3, c1),
+        Err(NetworkError::NoFreePort("c1".to_string()))
+    );
+    // a failed command leaves the machine free
+    assert_eq!(net.machine(m3).link, None);
+}
+
+#[test]
+fn coaxial_taps_stay_sorted_until_the_cable_is_full() {
+    let mut net = Topology::new();
+    let machines: Vec<_> = (1..=4)
+        .map(|n| net.add_machine(&format!("m{}", n)).unwrap())
+        .collect();
+    let x1 = net.add_coaxial("x1", 2.0).unwrap();
+
+    net.attach_coaxial(machines[0], x1, 1.0).unwrap();
+    assert_eq!(
+        net.attach_coaxial(machines[1], x1, 1.0)
+            .unwrap_err()
+            .to_string(),
+        "'m1' already taps coaxial 'x1' at 1"
+    );
+    assert!(matches!(
+        net.attach_coaxial(machines[1], x1, 2.5),
+        Err(NetworkError::OffsetOutOfRange { .. })
+    ));
+    net.attach_coaxial(machines[1], x1, 0.0).unwrap();
+    let offsets: Vec<f64> = net.segment(x1).taps.iter().map(|tap| tap.offset).collect();
+    assert_eq!(offsets, [0.0, 1.0]);
+
+    assert_eq!(net.assign_coaxial(machines[2], x1), Ok(2.0));
+    assert!(net.segment(x1).is_complete());
+    assert_eq!(
+        net.assign_coaxial(machines[3], x1),
+        Err(NetworkError::SegmentFull("x1".to_string()))
+    );
+}
+
+#[test]
+fn names_are_unique_and_typed() {
+    let mut net = Topology::new();
+    let m1 = net.add_machine("m1").unwrap();
+    let c1 = net.add_concentrator("c1", 4).unwrap();
+    let x1 = net.add_coaxial("x1", 185.0).unwrap();
+
+    assert_eq!(
+        net.add_concentrator("m1", 4),
+        Err(NetworkError::DuplicateName("m1".to_string()))
+    );
+    assert_eq!(
+        net.add_concentrator("c2", 0),
+        Err(NetworkError::NoPorts("c2".to_string()))
+    );
+    assert!(net.add_coaxial("x2", -1.0).is_err());
+    assert!(net.add_coaxial("x3", f64::NAN).is_err());
+
+    assert_eq!(net.device("c1"), Ok(DeviceId::Concentrator(c1)));
+    assert_eq!(
+        net.device("nada"),
+        Err(NetworkError::UnknownDevice("nada".to_string()))
+    );
+    assert_eq!(
+        net.concentrator_id("m1").unwrap_err().to_string(),
+        "'m1' is not a concentrator"
+    );
+
+    net.attach_segment(x1, c1).unwrap();
+    assert_eq!(
+        net.attach_segment(x1, c1).unwrap_err().to_string(),
+        "coaxial 'x1' is already attached to 'c1'"
+    );
+    assert_eq!(net.segments_of(c1), [x1]);
+    assert_eq!(net.devices().count(), 3);
+    assert_eq!(net.name(DeviceId::Machine(m1)), "m1");
+}
+
+#[test]
+fn devices_are_placed_on_the_grid() {
+    let mut net = Topology::new();
+    let m1 = DeviceId::Machine(net.add_machine("m1").unwrap());
+    let x1 = net.add_coaxial("x1", 10.0).unwrap();
+
+    assert_eq!(net.position(m1), None);
+    net.place(m1, Position::new(2, 3)).unwrap();
+    assert_eq!(net.position(m1), Some(Position::new(2, 3)));
+    assert_eq!(
+        Position::new(2, 3).step(Direction::Up, 1),
+        Position::new(2, 2)
+    );
+    assert_eq!(
+        Position::new(2, 3).step(Direction::Left, 2).to_string(),
+        "(0, 3)"
+    );
+
+    net.place_coaxial(x1, Position::new(0, 0), Direction::Right)
+        .unwrap();
+    assert_eq!(net.segment(x1).direction, Some(Direction::Right));
+    assert_eq!(
+        net.position(DeviceId::Coaxial(x1)),
+        Some(Position::new(0, 0))
+    );
+}
+
+#[test]
+fn placements_collide_and_stay_on_the_grid() {
+    let mut net = Topology::with_grid(10, 5);
+    let m1 = DeviceId::Machine(net.add_machine("m1").unwrap());
+    let m2 = DeviceId::Machine(net.add_machine("m2").unwrap());
+    let m3 = DeviceId::Machine(net.add_machine("m3").unwrap());
+    let c1 = DeviceId::Concentrator(net.add_concentrator("c1", 4).unwrap());
+    let x1 = net.add_coaxial("x1", 3.0).unwrap();
+
+    assert_eq!(
+        net.place_next_to(m1, Direction::Right, c1),
+        Err(NetworkError::NotPlaced("c1".to_string()))
+    );
+    net.place(c1, Position::new(2, 2)).unwrap();
+    assert_eq!(
+        net.place(m1, Position::new(2, 2)).unwrap_err().to_string(),
+        "'m1' would overlap 'c1' at (2, 2)"
+    );
+    assert_eq!(
+        net.place(m1, Position::new(10, 0)).unwrap_err().to_string(),
+        "'m1' would leave the grid at (10, 0), it goes from (0, 0) to (9, 4)"
+    );
+
+    // relative placements skip over what is already there
+    assert_eq!(
+        net.place_next_to(m1, Direction::Right, c1),
+        Ok(Position::new(3, 2))
+    );
+    assert_eq!(
+        net.place_next_to(m2, Direction::Right, c1),
+        Ok(Position::new(4, 2))
+    );
+    assert_eq!(
+        net.place_next_to(m3, Direction::Up, c1),
+        Ok(Position::new(2, 1))
+    );
+
+    // the cable covers a cell for every unit of its length
+    assert!(matches!(
+        net.place_coaxial(x1, Position::new(2, 0), Direction::Down),
+        Err(NetworkError::Overlap { .. })
+    ));
+    assert!(matches!(
+        net.place_coaxial(x1, Position::new(7, 0), Direction::Right),
+        Err(NetworkError::OutOfBounds { .. })
+    ));
+    net.place_coaxial(x1, Position::new(5, 4), Direction::Right)
+        .unwrap();
+    let cable = DeviceId::Coaxial(x1);
+    assert_eq!(net.layout.at(Position::new(8, 4)), Some(cable));
+    assert_eq!(net.layout.covered_by(cable).count(), 4);
+
+    // moving a device frees the cells it left
+    net.place(m1, Position::new(0, 0)).unwrap();
+    assert_eq!(net.layout.at(Position::new(3, 2)), None);
+    assert_eq!(net.layout.cells().count(), 8);
+
+    // a cable far longer than the grid is refused at its far end, before any cell is made
+    let long = net.add_coaxial("x2", 1e17).unwrap();
+    assert_eq!(
+        net.place_coaxial(long, Position::new(0, 0), Direction::Right)
+            .unwrap_err()
+            .to_string(),
+        "'x2' would leave the grid at (100000000000000000, 0), it goes from (0, 0) to (9, 4)"
+    );
+    let endless = DeviceId::Coaxial(net.add_coaxial("x3", f64::MAX).unwrap());
+    assert_eq!(
+        net.place(endless, Position::new(3, 0)),
+        Err(NetworkError::OutOfBounds {
+            device: "x3".to_string(),
+            position: Position::new(i64::MAX, 0),
+            width: 10,
+            height: 5,
+        })
+    );
+    assert_eq!(net.layout.cells().count(), 8);
+}
+
+#[test]
+fn graph_finds_paths_groups_and_loops() {
+    let mut net = Topology::new();
+    let m: Vec<_> = (1..=5)
+        .map(|n| net.add_machine(&format!("m{}", n)).unwrap())
+        .collect();
+    let c1 = net.add_concentrator("c1", 4).unwrap();
+    let c2 = net.add_concentrator("c2", 4).unwrap();
+    let x1 = net.add_coaxial("x1", 10.0).unwrap();
+    let x2 = net.add_coaxial("x2", 10.0).unwrap();
+
+    net.assign_port(m[0], c1).unwrap();
+    net.assign_port(m[1], c1).unwrap();
+    net.assign_port(m[2], c2).unwrap();
+    net.assign_coaxial(m[3], x1).unwrap();
+    net.attach_segment(x1, c1).unwrap();
+    net.attach_segment(x1, c2).unwrap();
+
+    let machine = |index: usize| DeviceId::Machine(m[index]);
+    let graph = Graph::new(&net);
+    assert_eq!(
+        graph.path(machine(0), machine(2)),
+        Some(vec![
+            machine(0),
+            DeviceId::Concentrator(c1),
+            DeviceId::Coaxial(x1),
+            DeviceId::Concentrator(c2),
+            machine(2),
+        ])
+    );
+    assert_eq!(graph.hops(machine(0), machine(1)), Some(2));
+    assert_eq!(graph.hops(machine(3), machine(3)), Some(0));
+    assert!(!graph.reaches(machine(0), machine(4)));
+    assert_eq!(graph.isolated(), [machine(4)]);
+    assert_eq!(graph.components().len(), 3);
+    assert_eq!(graph.loops(), []);
+
+    // a second cable between the same concentrators
+    net.attach_segment(x2, c2).unwrap();
+    net.attach_segment(x2, c1).unwrap();
+    let graph = Graph::new(&net);
+    assert_eq!(
+        graph.loops(),
+        [(DeviceId::Coaxial(x2), DeviceId::Concentrator(c1))]
+    );
+    assert_eq!(graph.components().len(), 2);
+
+    let report = report(&net);
+    assert!(report.contains("  m1: m2 (2 hops), m3 (4 hops), m4 (3 hops)\n"));
+    assert!(report.contains("isolated: m5\n"));
+    assert!(report.contains("  x2 - c1 closes a loop\n"));
+}
+
+#[test]
+fn finished_topologies_are_checked_against_coaxial_rules() {
+    let mut net = Topology::new();
+    let m1 = net.add_machine("m1").unwrap();
+    let m2 = net.add_machine("m2").unwrap();
+    let x1 = net.add_coaxial("x1", 600.0).unwrap();
+    net.attach_coaxial(m1, x1, 10.0).unwrap();
+    net.attach_coaxial(m2, x1, 12.0).unwrap();
+
+    assert_eq!(
+        CoaxialRules::THIN.check(&net),
+        [(
+            x1,
+            Problem {
+                rule: Rule::Length,
+                message: "coaxial 'x1' is 600 m long, 10BASE2 segments go up to 185 m".to_string()
+            }
+        )]
+    );
+    let problems: Vec<String> = CoaxialRules::THICK
+        .check(&net)
+        .into_iter()
+        .map(|(_, problem)| problem.message)
+        .collect();
+    assert_eq!(
+        problems,
+        [
+            "coaxial 'x1' is 600 m long, 10BASE5 segments go up to 500 m",
+            "tap on coaxial 'x1' at 12 is 2 m from the next one, 10BASE5 needs 2.5 m",
+        ]
+    );
+}
+
+#[test]
+fn frames_share_the_medium_with_csma_cd() {
+    let mut net = Topology::new();
+    let m1 = net.add_machine("m1").unwrap();
+    let m2 = net.add_machine("m2").unwrap();
+    let m3 = net.add_machine("m3").unwrap();
+    let m4 = net.add_machine("m4").unwrap();
+    net.add_machine("m5").unwrap();
+    let c1 = net.add_concentrator("c1", 4).unwrap();
+    let x1 = net.add_coaxial("x1", 100.0).unwrap();
+    net.attach_coaxial(m1, x1, 0.0).unwrap();
+    net.attach_coaxial(m2, x1, 100.0).unwrap();
+    net.connect_port(m3, c1, 1).unwrap();
+    net.connect_port(m4, c1, 2).unwrap();
+
+    // 5 ns a metre on the cable, 500 ns through a concentrator
+    let delays = simulation::delays(&net);
+    assert_eq!(delays[m1.0][m2.0], Some(500));
+    assert_eq!(delays[m3.0][m4.0], Some(500));
+    assert_eq!(delays[m1.0][m3.0], None);
+    net.attach_segment(x1, c1).unwrap();
+    assert_eq!(simulation::delays(&net)[m2.0][m3.0], Some(1000));
+
+    // m2 hears m1 before it starts and waits for the frame and the gap
+    let scenario = Scenario::from_yaml(
+        "frames:\n  - from: m1\n    to: m2\n    at: 0\n  - from: m2\n    to: m1\n    at: 10\n",
+        &net,
+    )
+    .unwrap();
+    let run = simulate(&net, &scenario);
+    assert_eq!(
+        run.frames,
+        vec![
+            FrameReport {
+                collisions: 0,
+                outcome: Outcome::Delivered {
+                    at: 58_100,
+                    latency: 58_100
+                },
+            },
+            FrameReport {
+                collisions: 0,
+                outcome: Outcome::Delivered {
+                    at: 125_800,
+                    latency: 115_800
+                },
+            },
+        ]
+    );
+    assert_eq!(
+        run.timeline[1],
+        (10_000, "m2 defers frame 2, the medium is busy".to_string())
+    );
+
+    // starting together, both hear the other half a microsecond later and back off
+    let scenario = Scenario::from_json(
+        r#"{"seed": 3, "frames": [
+            {"from": "m1", "to": "m3", "at": 0},
+            {"from": "m2", "to": "m4", "at": 0, "bytes": 1500},
+            {"from": "m5", "to": "m1", "at": 1}
+        ]}"#,
+        &net,
+    )
+    .unwrap();
+    let run = simulate(&net, &scenario);
+    assert_eq!(
+        run.timeline[2],
+        (
+            500,
+            "m1 detects a collision on frame 1, jams and backs off 1 slot".to_string()
+        )
+    );
+    assert!(run.frames[0].collisions > 0 && run.frames[1].collisions > 0);
+    assert!(matches!(run.frames[0].outcome, Outcome::Delivered { .. }));
+    assert!(matches!(run.frames[1].outcome, Outcome::Delivered { .. }));
+    assert_eq!(
+        run.frames[2].outcome,
+        Outcome::Dropped("'m5' is not connected to anything".to_string())
+    );
+    // the seed decides the backoff, the same seed gives the same run
+    assert_eq!(simulate(&net, &scenario), run);
+    let summary = simulation::report(&net, &scenario, &run);
+    assert!(summary.contains("3 frames, 2 delivered, 1 dropped, 2 collided\n"));
+
+    for (document, error) in [
+        (
+            r#"{"frames": [{"from": "m1", "to": "m1", "at": 0}]}"#,
+            "frames[0]: a machine can't send a frame to itself",
+        ),
+        (
+            r#"{"frames": [{"from": "m1", "to": "m9", "at": 0}]}"#,
+            "unknown device 'm9'",
+        ),
+        (
+            r#"{"frames": [{"from": "m1", "to": "m2", "at": 0, "bytes": 9000}]}"#,
+            "frames[0].bytes: frames go up to 1518 bytes",
+        ),
+        (
+            r#"{"bitrate": 0, "frames": []}"#,
+            "bitrate: expected a bitrate above 0",
+        ),
+    ] {
+        assert_eq!(
+            Scenario::from_json(document, &net).unwrap_err().to_string(),
+            error
+        );
+    }
+}
+
+#[test]
+fn the_grid_is_drawn_with_box_characters() {
+    let mut net = Topology::new();
+    assert_eq!(render(&net), "nothing is placed on the grid\n");
+
+    let m1 = net.add_machine("m1").unwrap();
+    let m2 = net.add_machine("m2").unwrap();
+    let m3 = net.add_machine("m3").unwrap();
+    let c1 = net.add_concentrator("c1", 4).unwrap();
+    let x1 = net.add_coaxial("x1", 12.0).unwrap();
+    let x2 = net.add_coaxial("x2", 2.0).unwrap();
+    net.place(DeviceId::Concentrator(c1), Position::new(1, 1))
+        .unwrap();
+    net.place_next_to(
+        DeviceId::Machine(m1),
+        Direction::Left,
+        DeviceId::Concentrator(c1),
+    )
+    .unwrap();
+    net.place_coaxial(x1, Position::new(2, 1), Direction::Right)
+        .unwrap();
+    net.place_coaxial(x2, Position::new(4, 3), Direction::Down)
+        .unwrap();
+    net.place(DeviceId::Machine(m2), Position::new(8, 2))
+        .unwrap();
+    net.connect_port(m1, c1, 3).unwrap();
+    net.attach_coaxial(m2, x1, 6.0).unwrap();
+    net.attach_coaxial(m3, x2, 1.0).unwrap();
+    net.attach_segment(x1, c1).unwrap();
+
+    // the bare cable after the tap is shortened, x2 crossing below keeps the rest
+    assert_eq!(
+        render(&net),
+        "\
+grid (0, 1) to (14, 5)
+┌──┐╔══╗
+│m1│║c1║━━━━━━━━┳━━┄━
+└3─┘╚══╝
+              ┌──┐
+              │m2│
+              └──┘
+          ┃
+          ┣
+          ┃
+
+links:
+  m1 - port 3 of 'c1'
+  m2 - 'x1' at 6
+  m3 - 'x2' at 1
+  x1 - 'c1'
+
+not placed: m3
+"
+    );
+}
@@ -0,0 +1,233 @@
+mod common;
+
+use common::Rng;
+use kindred::lexer::lexer_ana::Lexer;
+use kindred::parser::actions::AstBuilder;
+use kindred::parser::ast::Program;
+use kindred::parser::grammar::Grammar;
+use kindred::parser::ll1::Analysis;
+use kindred::parser::parser_ana::Parser;
+use kindred::parser::table_parser::TableParser;
+
+const CORPUS: &[&str] = &[
+    "inicio fin",
+    "programa vacio; inicio fin",
+    r#"
+programa red;
+define maquinas m1, m2, m3;
+define concentradores c1 = 4, c2 = 2 * 4;
+define coaxial x1 = 50;
+
+inicio
+    coloca(m1, 2, 3);
+    coloca(m2, derecha, m1);
+    colocaCoaxial(x1, 0, 0, abajo);
+    colocaCoaxialConcentrador(x1, c1);
+    uneMaquinaPuerto(m1, c1, 1);
+    asignaPuerto(m2, c1);
+    maquinaCoaxial(m3, x1, 10);
+    asignaMaquinaCoaxial(m3, x1);
+    escribe("listo", m1, c1.puertos);
+fin
+"#,
+    r#"
+fn distancia(a: num, b: num) -> num {
+    let d: num = a - b;
+    si d < 0 {
+        return -d;
+    }
+    return d;
+}
+
+fn saluda() inicio
+    escribe();
+    return;
+fin
+
+fn usa(m: maquina, x: coaxial, c: Concentrador) {
+    escribe(pos(m), longitud(x), disponibles(c));
+}
+
+inicio
+    let total = distancia(1, 2) + distancia(3, 4) * 2;
+    total += 1;
+    total -= 2;
+    total *= 3;
+    total /= 4;
+    lista[0] = total;
+    c1.puertos[2] = m1;
+    saluda();
+fin
+"#,
+    r#"
+define maquinas m1, m2;
+inicio
+    for i in 0..10 {
+        si i == 3 { continue; }
+        while i > 5 { break; }
+    }
+    for m in maquinas { escribe(m); }
+    for p in c1.puertos inicio escribe(p.numero); fin
+fin
+"#,
+    r#"
+modulo mate {
+    fn doble(n: num) -> num { return n * 2; }
+    fn cuadruple(n: num) -> num { return doble(doble(n)); }
+}
+modulo vacio inicio fin
+inicio
+    escribe(mate::cuadruple(3) + mate::doble(1), mate::doble);
+fin
+"#,
+    r#"
+inicio
+    si a && b || !c inicio
+        escribe(1);
+    fin sino si a == b {
+        escribe(2);
+    } sino inicio
+        escribe(3);
+    fin
+    if x != y { x = y; } else if x <> y { } else { y = x; }
+    while i < 10 { i += 1; while j { j = j - 1; } }
+    let r = 0..n;
+    let p = -2 ** 2 ** -x % 3;
+    let q = ((1 + 2) * (3 - 4)) / 5.5;
+    let s = f(g(1), h[2].k)(3)[4];
+    let t = 1 <= 2 == 3 >= 4 != (5 > 6);
+    "solo una expresion";
+    presente(m1);
+    completo(c1).x;
+fin
+"#,
+];
+
+// syntax errors both parsers must reject
+const REJECTED: &[&str] = &[
+    "",
+    "inicio",
+    "programa; inicio fin",
+    "define inicio fin",
+    "define maquinas m1 m2; inicio fin",
+    "fn f(a) {} inicio fin",
+    "inicio let = 1; fin",
+    "inicio let x 1; fin",
+    "inicio x = ; fin",
+    "inicio x + 1 = 2; fin",
+    "inicio si x fin",
+    "inicio coloca m1; fin",
+    "inicio escribe(1,); fin",
+    "inicio f(; fin",
+    "inicio (1 + 2; fin",
+    "inicio fin fin",
+    "inicio 99999999999999999999; fin",
+];
+
+fn hand_written(source: &str) -> Option<Program> {
+    let mut parser = Parser::new(Lexer::new(source).unwrap());
+    let program = parser.parse_program();
+    (!parser.has_errors()).then_some(program)
+}
+
+fn table_driven(grammar: &Grammar, analysis: &Analysis, source: &str) -> Option<Program> {
+    let mut parser = TableParser::new(analysis, Lexer::new(source).unwrap());
+    let tree = parser.parse().ok()?;
+    AstBuilder::new(grammar).program(&tree).ok()
+}
+
+#[test]
+fn grammar_is_ll1() {
+    let grammar = Grammar::load().unwrap();
+    let analysis = Analysis::new(&grammar);
+    assert!(analysis.conflicts.is_empty());
+    assert!(analysis.unreachable().is_empty());
+}
+
+#[test]
+fn parsers_build_the_same_ast_for_the_corpus() {
+    let grammar = Grammar::load().unwrap();
+    let analysis = Analysis::new(&grammar);
+
+    for source in CORPUS {
+        let hand = hand_written(source);
+        let table = table_driven(&grammar, &analysis, source);
+        assert!(hand.is_some(), "hand written parser rejected {}", source);
+        assert_eq!(hand, table, "parsers disagree on {}", source);
+    }
+}
+
+#[test]
+fn parsers_reject_the_same_programs() {
+    let grammar = Grammar::load().unwrap();
+    let analysis = Analysis::new(&grammar);
+
+    for source in REJECTED {
+        assert!(
+            hand_written(source).is_none(),
+            "hand written parser accepted {:?}",
+            source
+        );
+        assert!(
+            table_driven(&grammar, &analysis, source).is_none(),
+            "table parser accepted {:?}",
+            source
+        );
+    }
+}
+
+const BINARY: &[&str] = &[
+    "..", "||", "&&", "==", "!=", "<", ">", "<=", ">=", "+", "-", "*", "/", "%", "**",
+];
+const OPERANDS: &[&str] = &["1", "2.5", "\"s\"", "a", "b", "pos", "puertos"];
+
+fn arbitrary_expression(rng: &mut Rng, depth: usize) -> String {
+    if depth == 0 {
+        return OPERANDS[rng.below(OPERANDS.len())].to_string();
+    }
+    match rng.below(8) {
+        0 => format!("-{}", arbitrary_expression(rng, depth - 1)),
+        1 => format!("!{}", arbitrary_expression(rng, depth - 1)),
+        2 => format!("({})", arbitrary_expression(rng, depth - 1)),
+        3 => format!(
+            "{}({}, {})",
+            arbitrary_expression(rng, 0),
+            arbitrary_expression(rng, depth - 1),
+            arbitrary_expression(rng, depth - 1)
+        ),
+        4 => format!(
+            "{}[{}].x",
+            arbitrary_expression(rng, 0),
+            arbitrary_expression(rng, depth - 1)
+        ),
+        _ => format!(
+            "{} {} {}",
+            arbitrary_expression(rng, depth - 1),
+            BINARY[rng.below(BINARY.len())],
+            arbitrary_expression(rng, depth - 1)
+        ),
+    }
+}
+
+#[test]
+fn parsers_agree_on_precedence_and_spans_of_random_expressions() {
+    let grammar = Grammar::load().unwrap();
+    let analysis = Analysis::new(&grammar);
+    let mut rng = Rng(0x5eed_1234_abcd_0042);
+
+    for _ in 0..500 {
+        let depth = 1 + rng.below(4);
+        let source = format!(
+            "inicio let x = {}; fin",
+            arbitrary_expression(&mut rng, depth)
+        );
+        let hand = hand_written(&source);
+        assert!(hand.is_some(), "hand written parser rejected {}", source);
+        assert_eq!(
+            hand,
+            table_driven(&grammar, &analysis, &source),
+            "{}",
+            source
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use kindred::lexer::lexer_ana::Lexer;
+use kindred::parser::ast::{Expr, ExprKind};
+use kindred::parser::parser_ana::Parser;
+use kindred::util::interner::Interner;
+
+// the tree with every operator bracketed, `a + b * c` is `(+ a (* b c))`
+fn tree(expr: &Expr, interner: &Interner) -> String {
+    match &expr.kind {
+        ExprKind::Integer(value) => value.to_string(),
+        ExprKind::Float(value) => value.to_string(),
+        ExprKind::Identifier(name) => interner.resolve(*name).to_string(),
+        ExprKind::Unary { op, operand } => format!("({} {})", op, tree(operand, interner)),
+        ExprKind::Binary { op, left, right } => format!(
+            "({} {} {})",
+            op,
+            tree(left, interner),
+            tree(right, interner)
+        ),
+        ExprKind::Call { callee, args } => {
+            let mut text = format!("(call {}", tree(callee, interner));
+            for arg in args {
+                text.push(' ');
+                text.push_str(&tree(arg, interner));
+            }
+            text + ")"
+        }
+        ExprKind::Member { object, member, .. } => {
+            format!(
+                "(. {} {})",
+                tree(object, interner),
+                interner.resolve(*member)
+            )
+        }
+        other => panic!("no tree for {:?}", other),
+    }
+}
+
+fn parsed(source: &str) -> String {
+    let mut parser = Parser::new(Lexer::new(source).unwrap());
+    let expr = parser.parse_expression();
+    assert!(!parser.has_errors(), "syntax errors in {}", source);
+    tree(&expr, parser.lexer().interner())
+}
+
+#[test]
+fn power_binds_tighter_than_a_sign_and_to_the_right() {
+    assert_eq!(parsed("-2 ** 2"), "(- (** 2 2))");
+    assert_eq!(parsed("2 ** 3 ** 2"), "(** 2 (** 3 2))");
+    assert_eq!(parsed("(-2) ** 2"), "(** (- 2) 2)");
+    assert_eq!(parsed("-a * b"), "(* (- a) b)");
+}
+
+#[test]
+fn arithmetic_groups_by_precedence_then_from_the_left() {
+    assert_eq!(parsed("1 + 2 * 3"), "(+ 1 (* 2 3))");
+    assert_eq!(parsed("1 - 2 - 3"), "(- (- 1 2) 3)");
+    assert_eq!(parsed("8 / 4 % 3 * 2"), "(* (% (/ 8 4) 3) 2)");
+}
+
+#[test]
+fn a_range_takes_whole_sums_on_both_sides() {
+    assert_eq!(parsed("a .. b + 1"), "(.. a (+ b 1))");
+    assert_eq!(
+        parsed("0 .. c1.puertos.longitud - 1"),
+        "(.. 0 (- (. (. c1 puertos) longitud) 1))"
+    );
+}
+
+#[test]
+fn comparisons_chain_left_to_right_below_logic() {
+    assert_eq!(parsed("a < b < c"), "(< (< a b) c)");
+    assert_eq!(parsed("a == b != c"), "(!= (== a b) c)");
+    assert_eq!(parsed("a < b == c >= d"), "(== (< a b) (>= c d))");
+    assert_eq!(
+        parsed("!a && b || c == 1 + f(x, 2)"),
+        "(|| (&& (! a) b) (== c (+ 1 (call f x 2))))"
+    );
+}
@@ -0,0 +1,80 @@
+use kindred::lexer::lexer_ana::Lexer;
+use kindred::parser::parser_ana::Parser;
+
+fn errors(source: &str) -> Vec<String> {
+    let mut parser = Parser::new(Lexer::new(source).unwrap());
+    parser.parse_program();
+    parser
+        .get_errors()
+        .iter()
+        .map(|error| {
+            format!(
+                "{}:{} {}",
+                error.span.line, error.span.column, error.message
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn each_broken_statement_is_reported_once() {
+    let source = "define maquinas m1;
+inicio
+    let x = 1 2 3 4;
+    coloca(m1, 2 3);
+    escribe(a b c);
+    si x { escribe(x) }
+    let y = 2;
+fin";
+    assert_eq!(
+        errors(source),
+        [
+            "3:14 expected ';' after the declaration, found '2'",
+            "4:18 expected ')' to close '(' from line 4:11, found '3'",
+            "5:15 expected ')' to close '(' from line 5:12, found 'b'",
+            "6:22 expected ';' after the statement, found '}'",
+        ]
+    );
+}
+
+#[test]
+fn a_statement_that_cannot_start_lists_what_could() {
+    let source = "inicio
+    ) let y = 2;
+    while { }
+    let z = 3;
+fin";
+    assert_eq!(
+        errors(source),
+        [
+            "2:5 expected one of 'let', 'si', 'if', 'while', 'for', 'break', 'continue', \
+             'return', 'escribe', 'coloca', 'colocaCoaxial', 'colocaCoaxialConcentrador', \
+             'uneMaquinaPuerto', 'asignaPuerto', 'maquinaCoaxial', 'asignaMaquinaCoaxial', \
+             integer, float, string, identifier, '(', '-', '!', 'pos', 'presente', 'completo', \
+             'disponibles', 'longitud', 'puertos', 'alcanza', 'saltos', 'plano', found ')'",
+            "3:11 expected one of integer, float, string, identifier, '(', '-', '!', 'pos', \
+             'presente', 'completo', 'disponibles', 'longitud', 'puertos', 'alcanza', 'saltos', \
+             'plano' for the condition, found '{'",
+        ]
+    );
+}
+
+#[test]
+fn an_operator_missing_its_right_side_is_reported_at_the_operator() {
+    let source = "inicio
+    let x = 1 +;
+    escribe(2 ** , x);
+    let y = x;
+fin";
+    assert_eq!(
+        errors(source),
+        [
+            "2:15 expected one of integer, float, string, identifier, '(', '-', '!', 'pos', \
+             'presente', 'completo', 'disponibles', 'longitud', 'puertos', 'alcanza', 'saltos', \
+             'plano' after '+', found ';'",
+            "3:15 expected one of integer, float, string, identifier, '(', '-', '!', 'pos', \
+             'presente', 'completo', 'disponibles', 'longitud', 'puertos', 'alcanza', 'saltos', \
+             'plano' after '**', found ','",
+        ]
+    );
+}
@@ -0,0 +1,330 @@
+mod common;
+
+use kindred::lexer::lexer_ana::Span;
+use kindred::network::rules::{CoaxialRules, Rule, Severity};
+use kindred::semantic::checker::{TypeError, quote};
+use kindred::semantic::coaxial::{RuleViolation, check_built, check_coaxial};
+
+fn check(source: &str) -> Vec<TypeError> {
+    common::type_errors(source)
+}
+
+fn messages(source: &str) -> Vec<String> {
+    check(source)
+        .into_iter()
+        .map(|error| error.message)
+        .collect()
+}
+
+// the text under a span
+fn text(source: &str, span: Span) -> &str {
+    &source[span.start..span.end]
+}
+
+#[test]
+fn well_typed_program_passes() {
+    let source = r#"
+define maquinas m1, m2;
+define concentradores c1 = 4;
+define coaxial x1 = 2 * 25;
+
+fn libre(c: Concentrador) -> bool {
+    return disponibles(c) > 0 && !completo(c);
+}
+
+fn mayor(a: num, b: num) -> num {
+    si a > b { return a; } sino { return b; }
+}
+
+fn conecta(m: maquina, c: concentrador, puerto: num) {
+    uneMaquinaPuerto(m, c, puerto);
+}
+
+inicio
+    let n = mayor(1, 2.5) ** 2 % 3;
+    let nombre: texto = "red " + "local";
+    let p: Puerto = c1.puertos[n];
+    let dentro = 0..c1.puertos.longitud;
+    si libre(c1) && p.maquina == m1 {
+        conecta(m1, c1, 1);
+    }
+    while n < 10 { n += 1; }
+    coloca(m1, 2, 3);
+    coloca(m2, derecha, m1);
+    colocaCoaxial(x1, 0, 0, abajo);
+    maquinaCoaxial(m2, x1, longitud(x1) / 2);
+    escribe(nombre, pos(m1).x, dentro, presente(m2));
+fin
+"#;
+    assert_eq!(check(source).len(), 0, "{:?}", messages(source));
+}
+
+#[test]
+fn mismatch_points_at_value_and_annotation() {
+    let source = "define maquinas m1;\ninicio let a: num = m1; fin";
+    let errors = check(source);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "expected num, found maquina");
+    assert_eq!(text(source, errors[0].span), "m1");
+    let (note, span) = errors[0].note.clone().unwrap();
+    assert_eq!(note, "'a' is declared num");
+    assert_eq!(text(source, span), "num");
+}
+
+#[test]
+fn arguments_and_returns_are_checked_against_the_signature() {
+    let source = r#"
+define maquinas m1;
+fn doble(n: num) -> num { return n * 2; }
+fn nada() { }
+fn mal() -> Puerto { return 1; }
+inicio
+    doble(m1);
+    doble(1, 2);
+    let x = nada();
+    escribe(doble);
+fin
+"#;
+    let errors = check(source);
+    let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        [
+            "expected puerto, found num",
+            "expected num, found maquina",
+            "'doble' takes 1 argument, found 2",
+            "expected a value, found nothing",
+            "'doble' is a function, call it with ( )",
+        ]
+    );
+    assert_eq!(text(source, errors[0].note.as_ref().unwrap().1), "Puerto");
+    assert_eq!(text(source, errors[1].note.as_ref().unwrap().1), "num");
+}
+
+#[test]
+fn conditions_must_be_bool() {
+    assert_eq!(
+        messages("inicio si 1 { } while \"x\" { } fin"),
+        ["expected bool, found num", "expected bool, found texto"]
+    );
+}
+
+#[test]
+fn missing_return_is_reported() {
+    assert_eq!(
+        messages("fn f(a: num) -> num { si a > 0 { return a; } } inicio fin"),
+        ["function 'f' can end without returning num"]
+    );
+}
+
+#[test]
+fn network_commands_take_devices() {
+    let source = r#"
+define maquinas m1;
+define concentradores c1 = 4;
+define coaxial x1 = 10;
+inicio
+    uneMaquinaPuerto(c1, m1, 1);
+    colocaCoaxial(x1, 0, 0, 5);
+    coloca(m1, arriba, 2);
+    asignaMaquinaCoaxial(m1, c1);
+fin
+"#;
+    assert_eq!(
+        messages(source),
+        [
+            "expected maquina, found concentrador",
+            "expected concentrador, found maquina",
+            "expected a direction (arriba, abajo, izquierda or derecha)",
+            "expected maquina, concentrador or coaxial, found num",
+            "expected coaxial, found concentrador",
+        ]
+    );
+}
+
+#[test]
+fn names_and_members_are_resolved() {
+    let source = r#"
+define maquinas m1, m1;
+define concentradores c1 = "cuatro";
+inicio
+    m1 = 2;
+    escribe(y, c1.colores, c1.disponibles[0]);
+    let t: tabla = 1;
+fin
+"#;
+    assert_eq!(
+        messages(source),
+        [
+            "'m1' is already defined",
+            "expected num, found texto",
+            "cannot assign to maquina 'm1'",
+            "unknown name 'y'",
+            "concentrador has no member 'colores'",
+            "num cannot be indexed",
+            "unknown type 'tabla'",
+        ]
+    );
+}
+
+#[test]
+fn quoted_spans_stay_on_their_line() {
+    let source = "let añade = 1;\nfin";
+    // carets count chars, `ñ` takes two bytes
+    assert_eq!(
+        quote(source, Span::new(4, 10, 1, 5)),
+        Some(("let añade = 1;", "    ^^^^^".to_string()))
+    );
+    // a span made before the source was edited may run past its line or the whole source
+    assert_eq!(
+        quote(source, Span::new(4, 40, 1, 5)),
+        Some(("let añade = 1;", "    ^^^^^^^^^^".to_string()))
+    );
+    assert_eq!(
+        quote(source, Span::new(0, 2, 2, 9)),
+        Some(("fin", "   ^".to_string()))
+    );
+    assert_eq!(quote(source, Span::new(0, 1, 3, 1)), None);
+    assert_eq!(quote(source, Span::new(0, 1, 0, 1)), None);
+}
+
+#[test]
+fn one_mistake_is_reported_once() {
+    // `y` is unknown, nothing built on it is reported again
+    assert_eq!(
+        messages("inicio let a = y + 1; let b: num = a * 2; si a > b { } fin"),
+        ["unknown name 'y'"]
+    );
+}
+
+#[test]
+fn coaxial_cabling_is_checked_against_the_standard() {
+    let source = r#"
+define maquinas m1, m2, m3, m4;
+define concentradores c1, c2, c3;
+define coaxial x1 = 200, x2 = 10, x3 = 2 * 300;
+inicio
+    maquinaCoaxial(m1, x2, 1);
+    maquinaCoaxial(m2, x2, 1.2);
+    asignaMaquinaCoaxial(m3, x2);
+    si verdadero { maquinaCoaxial(m4, x2, 1.1); }
+    colocaCoaxialConcentrador(x1, c1);
+    colocaCoaxialConcentrador(x1, c2);
+    colocaCoaxialConcentrador(x1, c3);
+fin
+"#;
+    let (program, lexer) = common::parse(source);
+    let check = |rules: &CoaxialRules| {
+        check_coaxial(&program, lexer.interner(), rules)
+            .into_iter()
+            .map(|violation| (text(source, violation.span), violation.message))
+            .collect::<Vec<_>>()
+    };
+
+    // the size of x3 is not a literal, and the tap inside `si` may never happen
+    assert_eq!(
+        check(&CoaxialRules::THIN),
+        [
+            (
+                "x1 = 200",
+                "coaxial 'x1' is 200 m long, 10BASE2 segments go up to 185 m".to_string()
+            ),
+            (
+                "maquinaCoaxial(m2, x2, 1.2);",
+                "tap on coaxial 'x2' at 1.2 is 0.2 m from the next one, 10BASE2 needs 0.5 m"
+                    .to_string()
+            ),
+            (
+                "colocaCoaxialConcentrador(x1, c3);",
+                "coaxial 'x1' has only 2 ends and they are taken, the cable can't be terminated"
+                    .to_string()
+            ),
+        ]
+    );
+
+    let thick = CoaxialRules {
+        max_taps: 2,
+        severity: Severity::Error,
+        ..CoaxialRules::THICK
+    };
+    let violations = check_coaxial(&program, lexer.interner(), &thick);
+    assert!(
+        violations
+            .iter()
+            .all(|violation| violation.severity == Severity::Error)
+    );
+    assert_eq!(
+        violations[1].message,
+        "coaxial 'x2' already has 2 machines, 10BASE5 allows 2"
+    );
+    assert_eq!(violations.len(), 3);
+    assert!(CoaxialRules::parse("10BASE5").is_ok());
+    assert!(CoaxialRules::parse("10base-t").is_err());
+}
+
+#[test]
+fn cabling_made_at_run_time_is_checked_on_the_built_network() {
+    let machines: Vec<String> = (1..=40).map(|index| format!("m{}", index)).collect();
+    let source = format!(
+        "define maquinas {};
+define maquinas a, b;
+define coaxial x1 = 100, x2 = 10, x3 = 200;
+fn une(m: maquina, offset: num) {{ maquinaCoaxial(m, x2, offset); }}
+inicio
+    maquinaCoaxial(a, x2, 1);
+    for m in maquinas {{
+        si m != a && m != b {{ asignaMaquinaCoaxial(m, x1); }}
+    }}
+    si 1 < 2 {{ une(b, 1 + 0.25); }}
+fin
+",
+        machines.join(", ")
+    );
+    let (program, lexer) = common::parse(&source);
+    // only the length of x3 is written where it can be seen without running
+    let reported = check_coaxial(&program, lexer.interner(), &CoaxialRules::THIN);
+    assert_eq!(reported.len(), 1);
+    assert_eq!(
+        (reported[0].segment.as_str(), reported[0].rule),
+        ("x3", Rule::Length)
+    );
+
+    let (_, topology) = common::interpret(&program, &lexer).unwrap();
+    let built = |reported: &[RuleViolation]| {
+        check_built(
+            &program,
+            lexer.interner(),
+            &topology,
+            &CoaxialRules::THIN,
+            reported,
+        )
+    };
+    let violations = built(&reported);
+    let found: Vec<(&str, &str)> = violations
+        .iter()
+        .map(|violation| (text(&source, violation.span), violation.message.as_str()))
+        .collect();
+    assert_eq!(found.len(), 11);
+    assert_eq!(
+        found[0],
+        (
+            "x1 = 100",
+            "coaxial 'x1' already has 30 machines, 10BASE2 allows 30"
+        )
+    );
+    assert_eq!(
+        found[10],
+        (
+            "x2 = 10",
+            "tap on coaxial 'x2' at 1.25 is 0.25 m from the next one, 10BASE2 needs 0.5 m"
+        )
+    );
+
+    // a rule already broken on a cable is left out whatever its message said
+    assert_eq!(built(&[]).len(), 12);
+    let taps_on_x1 = RuleViolation {
+        message: String::new(),
+        ..violations[0].clone()
+    };
+    assert_eq!(built(&[taps_on_x1]).len(), 2);
+}